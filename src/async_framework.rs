@@ -0,0 +1,180 @@
+//! Async API for SafeBank framework
+//! Wraps `SafeBankFramework` so storage-touching calls run on tokio's blocking pool instead of the caller's task
+//!
+//! ```
+//! use safebank::{DeviceInfo, TransactionType, async_framework::AsyncSafeBankFramework, config::SafeBankConfig};
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let framework = AsyncSafeBankFramework::new(SafeBankConfig::default());
+//! let device = DeviceInfo {
+//!     device_id: "phone-1".to_string(),
+//!     device_type: "feature_phone".to_string(),
+//!     os_version: None,
+//!     app_version: "1.0.0".to_string(),
+//!     is_trusted: true,
+//!     registered_at: chrono::Utc::now(),
+//!     capabilities: None,
+//!     mfa_method: safebank::auth::MfaMethod::Otp,
+//!     attestation_key: None,
+//!     trusted_at: None,
+//! };
+//!
+//! let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), device).await?;
+//! framework.authenticate_user("+254712345678", "4821", "phone-1").await?;
+//! framework.process_transaction(user.user_id, 500.0, "Cash agent".to_string(), TransactionType::Deposit).await?;
+//! let before = framework.get_balance(user.user_id).await?;
+//!
+//! let transaction = framework.process_transaction(user.user_id, 50.0, "Village Shop".to_string(), TransactionType::Payment).await?;
+//! let charged = transaction.amount + transaction.fee + transaction.levy;
+//! assert!((framework.get_balance(user.user_id).await? - (before - charged)).abs() < 1e-9);
+//! # Ok::<(), safebank::errors::SafeBankError>(())
+//! # }).unwrap();
+//! ```
+
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::{
+    DeviceInfo, SafeBankFramework, Transaction, TransactionRequest, TransactionType, TransferChallenge, UserProfile,
+    auth::{AuthOutcome, EntryMetrics},
+    clock::SharedClock,
+    config::SafeBankConfig,
+    errors::{Result, SafeBankError},
+    storage::SharedStorage,
+    transaction::{TransactionFilter, TransactionPage},
+    utils::DeviceCapabilities,
+};
+
+/// Cloneable async handle to a framework instance.
+/// Calls are serialized on the framework and executed with `spawn_blocking`, so a slow
+/// storage backend never stalls the async executor. The sync `SafeBankFramework` is unchanged.
+#[derive(Debug, Clone)]
+pub struct AsyncSafeBankFramework {
+    inner: Arc<Mutex<SafeBankFramework>>,
+}
+
+impl AsyncSafeBankFramework {
+    pub fn new(config: SafeBankConfig) -> Self {
+        Self::from_framework(SafeBankFramework::new(config))
+    }
+
+    /// Create an async framework driven by the given clock
+    pub fn with_clock(config: SafeBankConfig, clock: SharedClock) -> Self {
+        Self::from_framework(SafeBankFramework::with_clock(config, clock))
+    }
+
+    /// Create an async framework that persists state in the given storage backend
+    pub fn with_storage(config: SafeBankConfig, storage: SharedStorage, clock: SharedClock) -> Self {
+        Self::from_framework(SafeBankFramework::with_storage(config, storage, clock))
+    }
+
+    /// Wrap an already configured framework
+    pub fn from_framework(framework: SafeBankFramework) -> Self {
+        Self { inner: Arc::new(Mutex::new(framework)) }
+    }
+
+    pub async fn register_user(&self, phone_number: String, pin: String, device_info: DeviceInfo) -> Result<UserProfile> {
+        self.run(move |framework| framework.register_user(phone_number, pin, device_info)).await
+    }
+
+    pub async fn authenticate_user(&self, phone_number: &str, pin: &str, device_id: &str) -> Result<UserProfile> {
+        let (phone_number, pin, device_id) = (phone_number.to_string(), pin.to_string(), device_id.to_string());
+        self.run(move |framework| framework.authenticate_user(&phone_number, &pin, &device_id)).await
+    }
+
+    pub async fn authenticate_ex(&self, phone_number: &str, pin: &str, device_id: &str, entry_metrics: Option<EntryMetrics>) -> Result<AuthOutcome> {
+        let (phone_number, pin, device_id) = (phone_number.to_string(), pin.to_string(), device_id.to_string());
+        self.run(move |framework| framework.authenticate_ex(&phone_number, &pin, &device_id, entry_metrics)).await
+    }
+
+    pub async fn complete_mfa(&self, challenge_id: Uuid, code: &str) -> Result<UserProfile> {
+        let code = code.to_string();
+        self.run(move |framework| framework.complete_mfa(challenge_id, &code)).await
+    }
+
+    pub async fn update_device_capabilities(&self, phone_number: &str, pin: &str, device_id: &str, capabilities: DeviceCapabilities) -> Result<()> {
+        let (phone_number, pin, device_id) = (phone_number.to_string(), pin.to_string(), device_id.to_string());
+        self.run(move |framework| framework.update_device_capabilities(&phone_number, &pin, &device_id, capabilities)).await
+    }
+
+    pub async fn enroll_device_attestation(&self, phone_number: &str, pin: &str, device_id: &str) -> Result<String> {
+        let (phone_number, pin, device_id) = (phone_number.to_string(), pin.to_string(), device_id.to_string());
+        self.run(move |framework| framework.enroll_device_attestation(&phone_number, &pin, &device_id)).await
+    }
+
+    pub async fn process_transaction(&self, user_id: Uuid, amount: f64, recipient: String, transaction_type: TransactionType) -> Result<Transaction> {
+        self.run(move |framework| framework.process_transaction(user_id, amount, recipient, transaction_type)).await
+    }
+
+    pub async fn process_request(&self, request: TransactionRequest) -> Result<Transaction> {
+        self.run(move |framework| framework.process_request(request)).await
+    }
+
+    pub async fn initiate_transfer(&self, user_id: Uuid, amount: f64, recipient: String) -> Result<(Transaction, Option<TransferChallenge>)> {
+        self.run(move |framework| framework.initiate_transfer(user_id, amount, recipient)).await
+    }
+
+    pub async fn confirm_transfer(&self, transaction_id: Uuid, code: String) -> Result<Transaction> {
+        self.run(move |framework| framework.confirm_transfer(transaction_id, &code)).await
+    }
+
+    pub async fn reverse_transaction(&self, transaction_id: Uuid, reason: String) -> Result<Transaction> {
+        self.run(move |framework| framework.reverse_transaction(transaction_id, reason)).await
+    }
+
+    pub async fn query_transactions(&self, filter: TransactionFilter, page: usize, page_size: usize) -> Result<TransactionPage> {
+        self.run(move |framework| framework.query_transactions(filter, page, page_size)).await
+    }
+
+    pub async fn get_balance(&self, user_id: Uuid) -> Result<f64> {
+        self.run(move |framework| framework.get_balance(user_id)).await
+    }
+
+    /// Run a closure against the sync framework for operations without an async wrapper
+    pub async fn with_framework<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut SafeBankFramework) -> Result<T> + Send + 'static,
+    {
+        self.run(f).await
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut SafeBankFramework) -> Result<T> + Send + 'static,
+    {
+        let mut framework = self.inner.clone().lock_owned().await;
+        tokio::task::spawn_blocking(move || f(&mut framework))
+            .await
+            .map_err(|e| SafeBankError::StorageError {
+                message: format!("Blocking task failed: {}", e),
+            })?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_framework_shares_state_across_clones() {
+        let framework = AsyncSafeBankFramework::new(SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).await.unwrap();
+
+        let handle = framework.clone();
+        let task = tokio::spawn(async move {
+            handle.authenticate_user("+254712345678", "4821", "test-device").await
+        });
+        assert_eq!(task.await.unwrap().unwrap().user_id, user.user_id);
+
+        let deposit = framework.process_transaction(user.user_id, 200.0, "Cash agent".to_string(), TransactionType::Deposit).await.unwrap();
+        let credited = deposit.amount - deposit.fee - deposit.levy;
+        assert!((framework.get_balance(user.user_id).await.unwrap() - credited).abs() < 1e-9);
+        assert!(matches!(
+            framework.authenticate_user("+254712345678", "0000", "test-device").await,
+            Err(SafeBankError::AuthenticationFailed { .. })
+        ));
+    }
+}
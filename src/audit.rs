@@ -0,0 +1,109 @@
+//! Security audit log for SafeBank framework
+//! Append-only record of authentication and account security events for compliance and incident response
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditEventType {
+    Registration,
+    LoginSuccess,
+    LoginFailure,
+    Lockout,
+    DeviceRegistered,
+    DeviceTrusted,
+    DeviceRevoked,
+    AttestationKeyEnrolled,
+    PinChanged,
+    PinReset,
+    AccountFrozen,
+    AccountUnfrozen,
+    DuressCleared,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+/// A single recorded security event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: DateTime<Utc>,
+    pub user_id: Option<Uuid>,
+    /// Phone number as passed through `utils::sanitize_for_logging`
+    pub phone_number: String,
+    pub event_type: AuditEventType,
+    pub outcome: AuditOutcome,
+    pub metadata: HashMap<String, String>,
+}
+
+/// Criteria for reading back audit events; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub user_id: Option<Uuid>,
+    pub event_type: Option<AuditEventType>,
+    pub outcome: Option<AuditOutcome>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditFilter {
+    fn matches(&self, event: &AuditEvent) -> bool {
+        self.user_id.is_none_or(|user_id| event.user_id == Some(user_id))
+            && self.event_type.is_none_or(|event_type| event.event_type == event_type)
+            && self.outcome.is_none_or(|outcome| event.outcome == outcome)
+            && self.from.is_none_or(|from| event.timestamp >= from)
+            && self.to.is_none_or(|to| event.timestamp <= to)
+    }
+}
+
+/// Append-only event store; entries can be read but never modified or removed
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    events: Vec<AuditEvent>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an event, masking the phone number before it is stored
+    pub fn record(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        user_id: Option<Uuid>,
+        phone_number: &str,
+        event_type: AuditEventType,
+        outcome: AuditOutcome,
+        metadata: HashMap<String, String>,
+    ) {
+        self.events.push(AuditEvent {
+            timestamp,
+            user_id,
+            phone_number: utils::sanitize_for_logging(phone_number),
+            event_type,
+            outcome,
+            metadata,
+        });
+    }
+
+    /// Events matching the filter, oldest first
+    pub fn query(&self, filter: &AuditFilter) -> Vec<AuditEvent> {
+        self.events.iter().filter(|event| filter.matches(event)).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
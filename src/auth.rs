@@ -2,35 +2,207 @@
 //! Provides secure user authentication optimized for rural environments
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use chrono::{DateTime, Utc, Duration};
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
 use argon2::password_hash::SaltString;
-use rand_core::OsRng;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{UserProfile, DeviceInfo, BehavioralProfile, config::SafeBankConfig, errors::{SafeBankError, Result}};
+use crate::{UserProfile, DeviceInfo, BehavioralProfile, logging, utils, audit::{AuditEvent, AuditEventType, AuditFilter, AuditLog, AuditOutcome}, clock::{self, SharedClock}, config::SafeBankConfig, errors::{PinRejection, SafeBankError, Result}, otp::OtpManager, storage::{self, SharedStorage}, system::{SharedSystemState, SystemState}};
 
 #[derive(Debug)]
 pub struct AuthManager {
     config: SafeBankConfig,
-    users: HashMap<String, UserProfile>, // phone_number -> UserProfile
-    user_by_id: HashMap<Uuid, UserProfile>, // user_id -> UserProfile
-    failed_attempts: HashMap<String, (u32, DateTime<Utc>)>, // phone_number -> (count, last_attempt)
+    storage: SharedStorage,
+    offline_credentials: HashMap<String, CachedCredential>, // phone_number -> locally cached credential
+    pending_offline_logins: Vec<OfflineSession>, // offline logins awaiting sync
+    offline_sessions: HashMap<Uuid, OfflineSession>, // session_id -> session issued by an offline login
+    otp_manager: OtpManager,
+    mfa_challenges: HashMap<Uuid, MfaChallenge>, // challenge_id -> login awaiting its second factor
+    system: SharedSystemState, // login throttle shared with other instances
+    audit_log: AuditLog,
+    clock: SharedClock,
+}
+
+/// Second factor a client must supply to finish an `MfaRequired` login or confirm a transfer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MfaMethod {
+    /// One-time code texted to the user's phone
+    #[default]
+    Otp,
+    /// Fingerprint or face match on the device, proven by `sign_attestation` over the challenge
+    /// with the key enrolled for the device
+    Biometric,
+}
+
+impl MfaMethod {
+    /// Biometric confirmation for devices that can do it and hold an attestation key, a texted code otherwise
+    pub fn for_device(device: &DeviceInfo) -> Self {
+        let capable = device.capabilities.as_ref().is_some_and(|capabilities| capabilities.supports_advanced_auth());
+        if capable && device.attestation_key.is_some() {
+            MfaMethod::Biometric
+        } else {
+            MfaMethod::Otp
+        }
+    }
+}
+
+/// Signature the device's secure hardware makes over a challenge once the user's fingerprint or
+/// face matches, keyed by the attestation key enrolled for the device (hex HMAC-SHA256)
+pub fn sign_attestation(attestation_key: &str, challenge_id: Uuid) -> String {
+    hex::encode(attestation_mac(attestation_key, challenge_id).finalize().into_bytes())
+}
+
+fn attestation_mac(attestation_key: &str, challenge_id: Uuid) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(attestation_key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(challenge_id.as_bytes());
+    mac
+}
+
+/// Result of a login attempt whose PIN was accepted
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)] // short-lived; callers match on it and move the profile out
+pub enum AuthOutcome {
+    Authenticated(UserProfile),
+    /// Finish the login with `complete_mfa` and this challenge
+    MfaRequired { challenge_id: Uuid, method: MfaMethod },
+    /// The device must be trusted on the account before it can log in
+    DeviceVerificationRequired { device_id: String },
+}
+
+/// How the PIN was keyed in, as measured by the client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryMetrics {
+    /// From the first keypress to submission
+    pub total_ms: u32,
+    /// Gaps between consecutive keypresses
+    #[serde(default)]
+    pub keystroke_intervals_ms: Vec<u32>,
+}
+
+/// Rolling average and spread of a user's PIN entry time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryBaseline {
+    pub mean_ms: f64,
+    pub std_dev_ms: f64,
+    pub samples: u32,
+}
+
+/// Entries a baseline needs before deviations from it count
+const MIN_ENTRY_SAMPLES: u32 = 5;
+
+/// Keypresses closer together than this are faster than a person types on a phone
+const MIN_HUMAN_KEYSTROKE_MS: u32 = 40;
+
+/// Storage records holding `LoginFailures`, keyed by phone number after this prefix
+const LOGIN_FAILURES_PREFIX: &str = "login_failures/";
+
+/// Storage records flagging a user as under duress, keyed by user id after this prefix. The flag
+/// holds the time of the duress login and stays until staff clear it, across restarts and instances.
+const DURESS_PREFIX: &str = "duress/";
+
+/// Failed logins against a phone number since its last successful one. Kept as a storage record
+/// so lockouts survive a restart and hold on every instance sharing the backend.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LoginFailures {
+    count: u32,
+    last_attempt: Option<DateTime<Utc>>,
+    /// Lockouts since the last successful login
+    lockout_level: u32,
+}
+
+/// Login that passed the PIN check and is waiting for its second factor
+#[derive(Debug, Clone)]
+struct MfaChallenge {
+    user_id: Uuid,
+    phone_number: String,
+    device_id: String,
+    /// Chosen when the challenge opened, so later device changes can't swap the factor asked for
+    method: MfaMethod,
+    used_duress_pin: bool,
+    expires_at: DateTime<Utc>,
+    /// Ordinary PIN entry timing, learned into the baseline once the challenge is passed
+    entry_metrics: Option<EntryMetrics>,
+}
+
+/// Credential cached on the device so the user can log in without connectivity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedCredential {
+    pub user_id: Uuid,
+    pub phone_number: String,
+    pub pin_hash: String,
+    /// The duress PIN, so it raises the alarm offline too
+    #[serde(default)]
+    pub duress_pin_hash: Option<String>,
+    pub device_id: String,
+    pub cached_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Result of an offline login; only offline-limited operations are permitted with it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineSession {
+    /// Identifies the session among those this manager issued; the other fields are only a copy
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub device_id: String,
+    pub authenticated_at: DateTime<Utc>,
+    /// The session ends with the cached credential it was issued against
+    pub expires_at: DateTime<Utc>,
+    /// The configured offline limit, for display; enforcement always reads the config
+    pub transaction_limit: f64,
+}
+
+/// Result of `import_users`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Profiles whose user id or phone number is already registered
+    pub duplicates: usize,
+    /// Profiles with a phone number that can't be normalized
+    pub invalid: usize,
 }
 
 impl AuthManager {
     pub fn new(config: &SafeBankConfig) -> Self {
+        Self::with_clock(config, clock::system_clock())
+    }
+
+    /// Create an auth manager driven by the given clock
+    pub fn with_clock(config: &SafeBankConfig, clock: SharedClock) -> Self {
+        Self::with_storage(config, storage::in_memory_storage(), clock)
+    }
+
+    /// Create an auth manager that persists users in the given storage backend
+    pub fn with_storage(config: &SafeBankConfig, storage: SharedStorage, clock: SharedClock) -> Self {
+        Self::with_system_state(config, storage, clock, Arc::new(SystemState::new(config)))
+    }
+
+    /// Create an auth manager that throttles logins in state shared with other instances
+    pub fn with_system_state(config: &SafeBankConfig, storage: SharedStorage, clock: SharedClock, system: SharedSystemState) -> Self {
         Self {
             config: config.clone(),
-            users: HashMap::new(),
-            user_by_id: HashMap::new(),
-            failed_attempts: HashMap::new(),
+            storage: storage.clone(),
+            offline_credentials: HashMap::new(),
+            pending_offline_logins: Vec::new(),
+            offline_sessions: HashMap::new(),
+            otp_manager: OtpManager::new(config.otp_length, config.otp_ttl_seconds, config.otp_max_attempts, storage.clone(), "login_otp/", clock.clone()),
+            mfa_challenges: HashMap::new(),
+            system,
+            audit_log: AuditLog::new(),
+            clock,
         }
     }
 
     /// Register a new user with phone number and PIN
-    pub fn register_user(&mut self, phone_number: String, pin: String, device_info: DeviceInfo) -> Result<UserProfile> {
-        // Validate phone number format (basic validation)
+    pub fn register_user(&mut self, phone_number: String, pin: String, mut device_info: DeviceInfo) -> Result<UserProfile> {
+        // Store numbers in E.164 so differently formatted entries resolve to one account
+        let phone_number = utils::normalize_phone_number(&phone_number, &self.config.default_phone_region)?;
         if !self.is_valid_phone_number(&phone_number) {
             return Err(SafeBankError::AuthenticationFailed {
                 message: "Invalid phone number format".to_string(),
@@ -38,155 +210,992 @@ impl AuthManager {
         }
 
         // Check if user already exists
-        if self.users.contains_key(&phone_number) {
+        if self.storage.load_user_by_phone(&phone_number)?.is_some() {
             return Err(SafeBankError::AuthenticationFailed {
                 message: "User already exists".to_string(),
             });
         }
 
         // Validate PIN
-        if !self.is_valid_pin(&pin) {
-            return Err(SafeBankError::InvalidPin);
-        }
+        self.check_pin(&pin)?;
 
         // Hash PIN using Argon2 (memory-hard function suitable for low-end devices)
-        let pin_hash = self.hash_pin(&pin)?;
+        let pin_hash = self.hash_pin(&pin, device_info.capabilities.as_ref(), None)?;
+        device_info.trusted_at = None;
+        // Attestation keys are only issued by `enroll_attestation_key`
+        device_info.attestation_key = None;
+        device_info.mfa_method = MfaMethod::for_device(&device_info);
 
         // Create user profile
         let user_profile = UserProfile {
             user_id: Uuid::new_v4(),
-            phone_number: phone_number.clone(),
+            phone_number,
             pin_hash,
-            device_info,
+            primary_device_id: Some(device_info.device_id.clone()),
+            devices: vec![device_info],
             behavioral_profile: BehavioralProfile {
                 typical_transaction_amount: 0.0,
                 typical_transaction_times: vec![],
                 common_recipients: vec![],
                 geographic_patterns: vec![],
+                usual_coordinates: vec![],
                 usage_frequency: 0.0,
+                amount_std_dev: 0.0,
+                amount_sample_count: 0,
+                last_updated: None,
             },
-            created_at: Utc::now(),
+            created_at: self.clock.now(),
             last_login: None,
+            last_login_device: None,
             failed_attempts: 0,
             is_locked: false,
+            frozen_reason: None,
+            last_confirmed_fraud: None,
+            timezone_offset_minutes: None,
+            duress_pin_hash: None,
+            pin_history: Vec::new(),
+            pin_entry_baseline: None,
         };
 
         // Store user
-        self.user_by_id.insert(user_profile.user_id, user_profile.clone());
-        self.users.insert(phone_number, user_profile.clone());
+        self.storage.save_user(&user_profile)?;
+        logging::info_event!(user_id = %user_profile.user_id, device_id = %user_profile.devices[0].device_id, "user registered");
+        self.audit(AuditEventType::Registration, Some(user_profile.user_id), &user_profile.phone_number, AuditOutcome::Success, &[
+            ("device_id", &user_profile.devices[0].device_id),
+        ]);
 
         Ok(user_profile)
     }
 
-    /// Authenticate user with phone number, PIN, and device verification
+    /// Authenticate user with phone number, PIN, and device verification.
+    /// Logins that need a second factor or an unverified device fail with `OtpRequired` or `UnrecognizedDevice`.
     pub fn authenticate(&mut self, phone_number: &str, pin: &str, device_id: &str) -> Result<UserProfile> {
+        self.authenticate_checked(phone_number, pin, device_id, None, None).map(|(user, _)| user)
+    }
+
+    /// `authenticate` for a request from a known network address (e.g. a USSD gateway or agent IP),
+    /// so failures are also throttled per address
+    pub fn authenticate_from(&mut self, phone_number: &str, pin: &str, device_id: &str, client_address: &str) -> Result<UserProfile> {
+        self.authenticate_checked(phone_number, pin, device_id, Some(client_address), None).map(|(user, _)| user)
+    }
+
+    /// Authenticate with PIN and a one-time code issued by `issue_otp`
+    pub fn authenticate_with_otp(&mut self, phone_number: &str, pin: &str, device_id: &str, otp: &str) -> Result<UserProfile> {
+        self.authenticate_checked(phone_number, pin, device_id, None, Some(otp)).map(|(user, _)| user)
+    }
+
+    /// Authenticate with phone number and PIN, reporting what else the client must do to finish the login.
+    /// PIN entry timing far from the user's usual asks for a one-time code; logins without metrics are unaffected.
+    pub fn authenticate_ex(&mut self, phone_number: &str, pin: &str, device_id: &str, entry_metrics: Option<&EntryMetrics>) -> Result<AuthOutcome> {
+        self.authenticate_ex_checked(phone_number, pin, device_id, entry_metrics).map(|(outcome, _)| outcome)
+    }
+
+    /// Finish an `MfaRequired` login with the one-time code issued for the user's phone, or for a
+    /// `Biometric` challenge the device's `sign_attestation` over the challenge id
+    pub fn complete_mfa(&mut self, challenge_id: Uuid, response: &str) -> Result<UserProfile> {
+        self.complete_mfa_checked(challenge_id, response).map(|(user, _)| user)
+    }
+
+    /// Issue a one-time code for a phone number; the caller delivers it out of band
+    pub fn issue_otp(&mut self, phone_number: &str) -> Result<String> {
+        let phone_number = self.canonical_phone(phone_number);
+        self.otp_manager.issue_otp(&phone_number)
+    }
+
+    /// Verify and consume a one-time code
+    pub fn verify_otp(&mut self, phone_number: &str, code: &str) -> Result<()> {
+        let phone_number = self.canonical_phone(phone_number);
+        self.otp_manager.verify_otp(&phone_number, code)
+    }
+
+    /// Authenticate and report whether the duress PIN was used.
+    /// A duress login looks identical to a normal one from the caller's side of `authenticate`.
+    /// When `require_otp` is enabled a valid one-time code must accompany the PIN.
+    pub(crate) fn authenticate_checked(&mut self, phone_number: &str, pin: &str, device_id: &str, client_address: Option<&str>, otp: Option<&str>) -> Result<(UserProfile, bool)> {
+        let _span = logging::enter_span!("authenticate", device_id);
+        let phone_number = &self.canonical_phone(phone_number);
+        let result = self.throttled_login(phone_number, pin, device_id, client_address, otp, None)
+            .and_then(|(outcome, used_duress_pin)| match outcome {
+                AuthOutcome::Authenticated(user) => Ok((user, used_duress_pin)),
+                AuthOutcome::MfaRequired { challenge_id, .. } => {
+                    self.mfa_challenges.remove(&challenge_id);
+                    Err(SafeBankError::OtpRequired)
+                }
+                AuthOutcome::DeviceVerificationRequired { device_id } => Err(SafeBankError::UnrecognizedDevice { device_id }),
+            });
+        self.audit_login(phone_number, device_id, "online", result.as_ref().map(|(user, _)| user.user_id));
+        result
+    }
+
+    /// `authenticate_ex` that also reports whether the duress PIN was used
+    pub(crate) fn authenticate_ex_checked(&mut self, phone_number: &str, pin: &str, device_id: &str, entry_metrics: Option<&EntryMetrics>) -> Result<(AuthOutcome, bool)> {
+        let _span = logging::enter_span!("authenticate", device_id);
+        let phone_number = &self.canonical_phone(phone_number);
+        let result = self.throttled_login(phone_number, pin, device_id, None, None, entry_metrics);
+        match &result {
+            Ok((AuthOutcome::Authenticated(user), _)) => self.audit_login(phone_number, device_id, "online", Ok(user.user_id)),
+            Err(e) => self.audit_login(phone_number, device_id, "online", Err(e)),
+            Ok(_) => {}
+        }
+        result
+    }
+
+    /// Whether this manager opened the login challenge and is still waiting on it
+    pub(crate) fn has_mfa_challenge(&self, challenge_id: Uuid) -> bool {
+        self.mfa_challenges.contains_key(&challenge_id)
+    }
+
+    /// `complete_mfa` that also reports whether the duress PIN was used for the first factor.
+    /// `response` is the texted code, or the device's `sign_attestation` for a `Biometric` challenge.
+    pub(crate) fn complete_mfa_checked(&mut self, challenge_id: Uuid, response: &str) -> Result<(UserProfile, bool)> {
+        let challenge = self.mfa_challenges.get(&challenge_id).cloned()
+            .ok_or_else(|| SafeBankError::AuthenticationFailed {
+                message: "Unknown login challenge".to_string(),
+            })?;
+
+        let result = self.verify_mfa(challenge_id, &challenge, response);
+        self.audit_login(&challenge.phone_number, &challenge.device_id, "mfa", result.as_ref().map(|(user, _)| user.user_id));
+        result
+    }
+
+    fn verify_mfa(&mut self, challenge_id: Uuid, challenge: &MfaChallenge, response: &str) -> Result<(UserProfile, bool)> {
+        if self.clock.now() > challenge.expires_at {
+            self.mfa_challenges.remove(&challenge_id);
+            return Err(SafeBankError::OtpExpired);
+        }
+
+        if self.is_account_locked(&challenge.phone_number) {
+            self.mfa_challenges.remove(&challenge_id);
+            return Err(SafeBankError::AccountLocked);
+        }
+
+        let mut user = self.get_user_by_id(challenge.user_id)?;
+        match challenge.method {
+            MfaMethod::Otp => self.check_otp(&challenge.phone_number, response)?,
+            MfaMethod::Biometric => self.check_attestation(&user, &challenge.device_id, challenge_id, response)?,
+        }
+        self.mfa_challenges.remove(&challenge_id);
+
+        if user.is_locked {
+            return Err(SafeBankError::AccountFrozen);
+        }
+        if let Some(metrics) = &challenge.entry_metrics {
+            self.learn_entry_timing(&mut user, metrics);
+        }
+        let user = self.finish_login(user, &challenge.device_id, challenge.used_duress_pin)?;
+        Ok((user, challenge.used_duress_pin))
+    }
+
+    /// `evaluate_login` behind the per-device and per-address throttle: sources with too many
+    /// recent failures are refused before any account is looked at, and new failures are counted
+    fn throttled_login(&mut self, phone_number: &str, pin: &str, device_id: &str, client_address: Option<&str>, otp: Option<&str>, entry_metrics: Option<&EntryMetrics>) -> Result<(AuthOutcome, bool)> {
+        let now = self.clock.now();
+        let sources: Vec<String> = std::iter::once(format!("device:{}", device_id))
+            .chain(client_address.map(|address| format!("address:{}", address)))
+            .collect();
+
+        let retry_after = sources.iter()
+            .filter_map(|source| self.system.login_retry_after(source, now))
+            .max();
+        if let Some(retry_after) = retry_after {
+            logging::warn_event!(device_id, client_address, "login attempts throttled");
+            return Err(SafeBankError::RateLimited {
+                retry_after_seconds: retry_after.num_seconds().max(1) as u64,
+            });
+        }
+
+        let result = self.evaluate_login(phone_number, pin, device_id, otp, entry_metrics);
+        // Authentication failures (1xxx codes) count; storage and other internal errors don't
+        if let Err(e) = &result {
+            if (1000..2000).contains(&e.numeric_code()) {
+                for source in &sources {
+                    self.system.record_login_failure(source, now);
+                }
+            }
+        }
+        result
+    }
+
+    /// Run the login checks; a supplied one-time code satisfies the second factor immediately,
+    /// otherwise a challenge is opened for `complete_mfa`
+    fn evaluate_login(&mut self, phone_number: &str, pin: &str, device_id: &str, otp: Option<&str>, entry_metrics: Option<&EntryMetrics>) -> Result<(AuthOutcome, bool)> {
         // Check if account is temporarily locked due to failed attempts
         if self.is_account_locked(phone_number) {
             return Err(SafeBankError::AccountLocked);
         }
 
         // Get user profile
-        let mut user = self.users.get(phone_number)
+        let mut user = self.storage.load_user_by_phone(phone_number)?
             .ok_or_else(|| SafeBankError::UserNotFound {
                 user_id: phone_number.to_string(),
-            })?.clone();
+            })?;
 
-        // Check if account is locked
+        // Operator freezes hold until lifted
         if user.is_locked {
-            return Err(SafeBankError::AccountLocked);
+            return Err(SafeBankError::AccountFrozen);
         }
 
-        // Verify PIN
-        if !self.verify_pin(pin, &user.pin_hash)? {
+        // Verify PIN, falling back to the duress PIN which authenticates normally
+        let used_duress_pin = if self.verify_pin(pin, &user.pin_hash)? {
+            false
+        } else if self.verify_duress_pin(pin, &user)? {
+            true
+        } else {
             self.record_failed_attempt(phone_number);
             return Err(SafeBankError::AuthenticationFailed {
                 message: "Invalid PIN".to_string(),
             });
-        }
+        };
 
         // Device verification (if enabled)
-        if self.config.require_device_verification {
-            if user.device_info.device_id != device_id {
-                // For rural banking, we might want to allow device changes but flag them
-                if !user.device_info.is_trusted {
-                    return Err(SafeBankError::UnrecognizedDevice {
-                        device_id: device_id.to_string(),
-                    });
+        if self.config.require_device_verification && !Self::is_device_accepted(&user, device_id) {
+            return Ok((AuthOutcome::DeviceVerificationRequired {
+                device_id: device_id.to_string(),
+            }, used_duress_pin));
+        }
+
+        // Unusual PIN entry timing is a soft signal: it asks for a second factor rather than failing the login
+        let unusual_timing = self.is_unusual_entry(&user, entry_metrics);
+        if unusual_timing {
+            logging::warn_event!(user_id = %user.user_id, device_id, "unusual PIN entry timing, stepping up");
+        }
+
+        // Second factor (if enabled, or stepped up)
+        if self.config.require_otp || unusual_timing {
+            match otp {
+                Some(code) => self.check_otp(phone_number, code)?,
+                None => {
+                    let learnable = entry_metrics.filter(|_| !unusual_timing).cloned();
+                    let (challenge_id, method) = self.open_mfa_challenge(&user, device_id, used_duress_pin, learnable);
+                    return Ok((AuthOutcome::MfaRequired { challenge_id, method }, used_duress_pin));
                 }
             }
         }
 
-        // Update successful login
-        user.last_login = Some(Utc::now());
+        if let Some(metrics) = entry_metrics.filter(|_| !unusual_timing) {
+            self.learn_entry_timing(&mut user, metrics);
+        }
+        let user = self.finish_login(user, device_id, used_duress_pin)?;
+        Ok((AuthOutcome::Authenticated(user), used_duress_pin))
+    }
+
+    /// Whether PIN entry looks scripted or far outside the user's usual time.
+    /// Missing timing is neutral until a baseline exists, then it counts as unusual.
+    fn is_unusual_entry(&self, user: &UserProfile, metrics: Option<&EntryMetrics>) -> bool {
+        let Some(threshold) = self.config.pin_timing_step_up_threshold else {
+            return false;
+        };
+        let Some(metrics) = metrics else {
+            return user.pin_entry_baseline.as_ref().is_some_and(|baseline| baseline.samples >= MIN_ENTRY_SAMPLES);
+        };
+        if metrics.keystroke_intervals_ms.len() >= 2
+            && metrics.keystroke_intervals_ms.iter().all(|interval| *interval < MIN_HUMAN_KEYSTROKE_MS)
+        {
+            return true;
+        }
+
+        match &user.pin_entry_baseline {
+            Some(baseline) if baseline.samples >= MIN_ENTRY_SAMPLES => {
+                // Floor the spread so a very consistent user isn't stepped up over small wobbles
+                let spread = baseline.std_dev_ms.max(baseline.mean_ms * 0.1).max(1.0);
+                (metrics.total_ms as f64 - baseline.mean_ms).abs() / spread > threshold
+            }
+            _ => false,
+        }
+    }
+
+    /// Fold an ordinary PIN entry into the user's timing baseline (exponential moving average)
+    fn learn_entry_timing(&self, user: &mut UserProfile, metrics: &EntryMetrics) {
+        let alpha = self.config.profile_ema_alpha;
+        let total = metrics.total_ms as f64;
+        user.pin_entry_baseline = Some(match user.pin_entry_baseline.take() {
+            None => EntryBaseline { mean_ms: total, std_dev_ms: 0.0, samples: 1 },
+            Some(baseline) => {
+                let deviation = total - baseline.mean_ms;
+                let variance = (1.0 - alpha) * (baseline.std_dev_ms.powi(2) + alpha * deviation.powi(2));
+                EntryBaseline {
+                    mean_ms: baseline.mean_ms + alpha * deviation,
+                    std_dev_ms: variance.sqrt(),
+                    samples: baseline.samples + 1,
+                }
+            }
+        });
+    }
+
+    /// Verify a one-time code; a wrong code counts towards lockout like a wrong PIN
+    fn check_otp(&mut self, phone_number: &str, code: &str) -> Result<()> {
+        if self.is_account_locked(phone_number) {
+            return Err(SafeBankError::AccountLocked);
+        }
+        let result = self.otp_manager.verify_otp(phone_number, code);
+        if matches!(result, Err(SafeBankError::InvalidOtp)) {
+            self.record_failed_attempt(phone_number);
+        }
+        result
+    }
+
+    /// Open a second-factor challenge that lives as long as a one-time code, asking for
+    /// the method stored for the device
+    fn open_mfa_challenge(&mut self, user: &UserProfile, device_id: &str, used_duress_pin: bool, entry_metrics: Option<EntryMetrics>) -> (Uuid, MfaMethod) {
+        let now = self.clock.now();
+        self.mfa_challenges.retain(|_, challenge| challenge.expires_at >= now);
+
+        let challenge_id = Uuid::new_v4();
+        let method = Self::mfa_method(user, device_id);
+        self.mfa_challenges.insert(challenge_id, MfaChallenge {
+            user_id: user.user_id,
+            phone_number: user.phone_number.clone(),
+            device_id: device_id.to_string(),
+            method,
+            used_duress_pin,
+            expires_at: now + Duration::seconds(self.config.otp_ttl_seconds as i64),
+            entry_metrics,
+        });
+        (challenge_id, method)
+    }
+
+    /// The second factor stored for a user's device; unregistered devices get a texted code
+    pub(crate) fn mfa_method(user: &UserProfile, device_id: &str) -> MfaMethod {
+        user.device(device_id).map(|device| device.mfa_method).unwrap_or_default()
+    }
+
+    /// Check a device's `sign_attestation` over a challenge. A bad signature counts towards lockout
+    /// like a wrong PIN, as does one from a device with no key enrolled.
+    pub(crate) fn check_attestation(&mut self, user: &UserProfile, device_id: &str, challenge_id: Uuid, signature: &str) -> Result<()> {
+        if self.is_account_locked(&user.phone_number) {
+            return Err(SafeBankError::AccountLocked);
+        }
+        let valid = user.device(device_id)
+            .and_then(|device| device.attestation_key.as_deref())
+            .is_some_and(|key| hex::decode(signature)
+                .is_ok_and(|signature| attestation_mac(key, challenge_id).verify_slice(&signature).is_ok()));
+        if !valid {
+            self.record_failed_attempt(&user.phone_number);
+            return Err(SafeBankError::AuthenticationFailed {
+                message: "Invalid device attestation".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Record a successful login
+    fn finish_login(&mut self, mut user: UserProfile, device_id: &str, used_duress_pin: bool) -> Result<UserProfile> {
+        user.last_login = Some(self.clock.now());
         user.failed_attempts = 0;
+        // Only a registered device is remembered; others leave transactions on the primary device
+        user.last_login_device = user.device(device_id).map(|device| device.device_id.clone());
         
-        // Clear failed attempts
-        self.failed_attempts.remove(phone_number);
+        // Clear failed attempts and lockout escalation
+        self.clear_failed_attempts(&user.phone_number);
+
+        if used_duress_pin {
+            self.flag_duress(user.user_id)?;
+        }
 
         // Update stored user
-        self.users.insert(phone_number.to_string(), user.clone());
-        self.user_by_id.insert(user.user_id, user.clone());
+        self.storage.save_user(&user)?;
+
+        Ok(user)
+    }
+
+    /// Cache a user's credential locally so they can authenticate while offline
+    pub fn cache_credentials(&mut self, user_id: Uuid) -> Result<CachedCredential> {
+        let user = self.active_user(user_id)?;
+        let device_id = user.primary_device()
+            .map(|device| device.device_id.clone())
+            .ok_or_else(|| SafeBankError::UnrecognizedDevice { device_id: String::new() })?;
+        let now = self.clock.now();
+
+        let credential = CachedCredential {
+            user_id,
+            phone_number: user.phone_number.clone(),
+            pin_hash: user.pin_hash.clone(),
+            duress_pin_hash: user.duress_pin_hash.clone(),
+            device_id,
+            cached_at: now,
+            expires_at: now + Duration::hours(self.config.offline_cache_duration_hours as i64),
+        };
+
+        self.offline_credentials.insert(user.phone_number, credential.clone());
+        Ok(credential)
+    }
+
+    /// Authenticate against the locally cached credential without consulting the user store
+    pub fn authenticate_offline(&mut self, phone_number: &str, pin: &str, device_id: &str) -> Result<OfflineSession> {
+        self.authenticate_offline_checked(phone_number, pin, device_id).map(|(session, _)| session)
+    }
+
+    /// `authenticate_offline` that also reports whether the duress PIN was used
+    pub(crate) fn authenticate_offline_checked(&mut self, phone_number: &str, pin: &str, device_id: &str) -> Result<(OfflineSession, bool)> {
+        let phone_number = &self.canonical_phone(phone_number);
+        let result = self.verify_offline_login(phone_number, pin, device_id);
+        self.audit_login(phone_number, device_id, "offline", result.as_ref().map(|(session, _)| session.user_id));
+        result
+    }
+
+    fn verify_offline_login(&mut self, phone_number: &str, pin: &str, device_id: &str) -> Result<(OfflineSession, bool)> {
+        if self.is_account_locked(phone_number) {
+            return Err(SafeBankError::AccountLocked);
+        }
+
+        let credential = self.offline_credentials.get(phone_number)
+            .cloned()
+            .ok_or(SafeBankError::OfflineModeRestriction)?;
+
+        let now = self.clock.now();
+        if now > credential.expires_at {
+            return Err(SafeBankError::OfflineModeRestriction);
+        }
+
+        // The duress PIN authenticates like the real one, as it does online
+        let used_duress_pin = if self.verify_pin(pin, &credential.pin_hash)? {
+            false
+        } else if self.verify_cached_duress_pin(pin, &credential)? {
+            true
+        } else {
+            self.record_failed_attempt(phone_number);
+            return Err(SafeBankError::AuthenticationFailed {
+                message: "Invalid PIN".to_string(),
+            });
+        };
+
+        if self.config.require_device_verification && credential.device_id != device_id {
+            return Err(SafeBankError::UnrecognizedDevice {
+                device_id: device_id.to_string(),
+            });
+        }
+
+        self.clear_failed_attempts(phone_number);
+        if used_duress_pin {
+            self.flag_duress(credential.user_id)?;
+        }
+
+        let session = OfflineSession {
+            session_id: Uuid::new_v4(),
+            user_id: credential.user_id,
+            device_id: device_id.to_string(),
+            authenticated_at: now,
+            expires_at: credential.expires_at,
+            transaction_limit: self.config.offline_transaction_limit,
+        };
+        self.offline_sessions.retain(|_, issued| issued.expires_at >= now);
+        self.offline_sessions.insert(session.session_id, session.clone());
+        self.pending_offline_logins.push(session.clone());
+
+        Ok((session, used_duress_pin))
+    }
+
+    /// The session this manager issued under `session`'s id, rejecting unknown, altered or expired ones
+    pub fn issued_offline_session(&self, session: &OfflineSession) -> Result<OfflineSession> {
+        let issued = self.offline_sessions.get(&session.session_id)
+            .filter(|issued| issued.user_id == session.user_id && issued.device_id == session.device_id)
+            .ok_or_else(|| SafeBankError::AuthenticationFailed {
+                message: "Unknown offline session".to_string(),
+            })?;
+        if self.clock.now() > issued.expires_at {
+            return Err(SafeBankError::OfflineModeRestriction);
+        }
+        Ok(issued.clone())
+    }
+
+    /// Apply offline logins to the user store once connectivity returns.
+    /// Returns the number of logins synced.
+    pub fn sync_offline_logins(&mut self) -> Result<usize> {
+        let sessions = std::mem::take(&mut self.pending_offline_logins);
+
+        for session in &sessions {
+            if let Some(mut user) = self.storage.load_user_by_id(session.user_id)? {
+                if user.last_login.is_none_or(|last| last < session.authenticated_at) {
+                    user.last_login = Some(session.authenticated_at);
+                }
+                user.failed_attempts = 0;
+                self.storage.save_user(&user)?;
+            }
+        }
+
+        Ok(sessions.len())
+    }
+
+    /// Set a secondary duress PIN that authenticates normally but silently flags the session
+    pub fn set_duress_pin(&mut self, user_id: Uuid, duress_pin: &str) -> Result<()> {
+        let mut user = self.get_user_by_id(user_id)?;
+
+        self.check_pin(duress_pin)?;
+        if self.verify_pin(duress_pin, &user.pin_hash)? {
+            return Err(invalid_pin(PinRejection::MatchesOtherPin, "Duress PIN must differ from the regular PIN"));
+        }
+
+        user.duress_pin_hash = Some(self.hash_pin(duress_pin, Self::primary_capabilities(&user), Some(&user.pin_hash))?);
+        if let Some(credential) = self.offline_credentials.get_mut(&user.phone_number) {
+            credential.duress_pin_hash = user.duress_pin_hash.clone();
+        }
+        self.storage.save_user(&user)
+    }
+
+    /// Change a user's PIN after verifying the current one.
+    /// A wrong current PIN counts towards lockout; any offline credential cached with the old PIN is dropped.
+    pub fn change_pin(&mut self, user_id: Uuid, old_pin: &str, new_pin: &str) -> Result<()> {
+        let user = self.get_user_by_id(user_id)?;
+
+        if user.is_locked {
+            return Err(SafeBankError::AccountFrozen);
+        }
+        if self.is_account_locked(&user.phone_number) {
+            return Err(SafeBankError::AccountLocked);
+        }
+
+        if !self.verify_pin(old_pin, &user.pin_hash)? {
+            self.record_failed_attempt(&user.phone_number);
+            return Err(SafeBankError::AuthenticationFailed {
+                message: "Invalid PIN".to_string(),
+            });
+        }
+
+        if old_pin == new_pin {
+            return Err(invalid_pin(PinRejection::Unchanged, "New PIN must differ from the current PIN"));
+        }
+
+        self.replace_pin(user, new_pin, AuditEventType::PinChanged)
+    }
+
+    /// Set a new PIN without the current one (staff-assisted recovery).
+    /// The account must not be locked.
+    pub fn reset_pin(&mut self, user_id: Uuid, new_pin: &str) -> Result<()> {
+        let user = self.get_user_by_id(user_id)?;
+
+        if user.is_locked {
+            return Err(SafeBankError::AccountFrozen);
+        }
+        if self.is_account_locked(&user.phone_number) {
+            return Err(SafeBankError::AccountLocked);
+        }
+
+        self.replace_pin(user, new_pin, AuditEventType::PinReset)
+    }
+
+    /// Set the timezone a user's habits are tracked in, in minutes east of UTC
+    pub fn set_timezone(&mut self, user_id: Uuid, offset_minutes: i32) -> Result<()> {
+        if offset_minutes.abs() > 14 * 60 {
+            return Err(SafeBankError::ConfigError {
+                message: "Timezone offset must be within 14 hours of UTC".to_string(),
+            });
+        }
+        let mut user = self.get_user_by_id(user_id)?;
+        user.timezone_offset_minutes = Some(offset_minutes);
+        self.storage.save_user(&user)
+    }
+
+    /// Administratively freeze an account, e.g. when it is suspected compromised.
+    /// Logins and transactions are refused until `unfreeze_account`, and any offline credential is dropped.
+    pub fn freeze_account(&mut self, user_id: Uuid, reason: &str) -> Result<()> {
+        let mut user = self.get_user_by_id(user_id)?;
+        user.is_locked = true;
+        user.frozen_reason = Some(reason.to_string());
+        self.offline_credentials.remove(&user.phone_number);
+        self.storage.save_user(&user)?;
+        logging::warn_event!(%user_id, "account frozen");
+        self.audit(AuditEventType::AccountFrozen, Some(user_id), &user.phone_number, AuditOutcome::Success, &[
+            ("reason", reason),
+        ]);
+        Ok(())
+    }
 
+    /// Lift an administrative freeze
+    pub fn unfreeze_account(&mut self, user_id: Uuid) -> Result<()> {
+        let mut user = self.get_user_by_id(user_id)?;
+        user.is_locked = false;
+        user.frozen_reason = None;
+        self.storage.save_user(&user)?;
+        logging::info_event!(%user_id, "account unfrozen");
+        self.audit(AuditEventType::AccountUnfrozen, Some(user_id), &user.phone_number, AuditOutcome::Success, &[]);
+        Ok(())
+    }
+
+    /// Note confirmed fraud on an account, which resets its daily limit growth
+    pub fn record_confirmed_fraud(&mut self, user_id: Uuid) -> Result<()> {
+        let mut user = self.get_user_by_id(user_id)?;
+        user.last_confirmed_fraud = Some(self.clock.now());
+        self.storage.save_user(&user)
+    }
+
+    /// A user who may transact: registered and not frozen
+    pub fn active_user(&self, user_id: Uuid) -> Result<UserProfile> {
+        let user = self.get_user_by_id(user_id)?;
+        if user.is_locked {
+            return Err(SafeBankError::AccountFrozen);
+        }
         Ok(user)
     }
 
+    /// Validate and store a new PIN hashed with a fresh salt, remembering the old hash
+    fn replace_pin(&mut self, mut user: UserProfile, new_pin: &str, event_type: AuditEventType) -> Result<()> {
+        self.check_pin(new_pin)?;
+        if self.verify_duress_pin(new_pin, &user)? {
+            return Err(invalid_pin(PinRejection::MatchesOtherPin, "PIN matches the duress PIN"));
+        }
+        if self.is_recent_pin(new_pin, &user)? {
+            return Err(invalid_pin(PinRejection::RecentlyUsed, "PIN was used recently"));
+        }
+
+        let new_hash = self.hash_pin(new_pin, Self::primary_capabilities(&user), Some(&user.pin_hash))?;
+        let previous_hash = std::mem::replace(&mut user.pin_hash, new_hash);
+        user.pin_history.insert(0, previous_hash);
+        user.pin_history.truncate(self.config.pin_history_size);
+        logging::info_event!(user_id = %user.user_id, event = ?event_type, "PIN replaced");
+        self.offline_credentials.remove(&user.phone_number);
+        self.storage.save_user(&user)?;
+        self.audit(event_type, Some(user.user_id), &user.phone_number, AuditOutcome::Success, &[]);
+        Ok(())
+    }
+
+    /// Check whether the user is flagged as under duress. A flag that can't be read counts as set,
+    /// so a storage fault never releases a hold.
+    pub fn is_under_duress(&self, user_id: Uuid) -> bool {
+        self.storage.load_record(&duress_key(user_id)).map_or(true, |flag| flag.is_some())
+    }
+
+    /// Flag the user as under duress until staff clear it
+    fn flag_duress(&self, user_id: Uuid) -> Result<()> {
+        storage::save_json(self.storage.as_ref(), &duress_key(user_id), &self.clock.now())
+    }
+
+    /// Get authentication statistics for monitoring
+    pub fn get_statistics(&self) -> HashMap<String, f64> {
+        let now = self.clock.now();
+        let records = self.storage.load_all_records().unwrap_or_default();
+        let duress_flags = records.keys().filter(|key| key.starts_with(DURESS_PREFIX)).count();
+        let failures: Vec<LoginFailures> = records
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(LOGIN_FAILURES_PREFIX))
+            .filter_map(|(_, value)| serde_json::from_str(&value).ok())
+            .collect();
+        let active_lockouts = failures.iter()
+            .filter(|failures| self.is_locked_out(failures))
+            .count();
+        let failed_attempts: u32 = failures.iter().map(|failures| failures.count).sum();
+        let pending_challenges = self.mfa_challenges.values()
+            .filter(|challenge| challenge.expires_at >= now)
+            .count();
+
+        let mut stats = HashMap::new();
+        stats.insert("active_lockouts".to_string(), active_lockouts as f64);
+        stats.insert("failed_attempts".to_string(), failed_attempts as f64);
+        stats.insert("duress_sessions".to_string(), duress_flags as f64);
+        stats.insert("pending_mfa_challenges".to_string(), pending_challenges as f64);
+        stats
+    }
+
+    /// Clear the duress flag once bank staff have resolved the situation
+    pub fn clear_duress(&mut self, user_id: Uuid) -> Result<()> {
+        let user = self.get_user_by_id(user_id)?;
+        self.storage.remove_record(&duress_key(user_id))?;
+        logging::info_event!(%user_id, "duress flag cleared");
+        self.audit(AuditEventType::DuressCleared, Some(user_id), &user.phone_number, AuditOutcome::Success, &[]);
+        Ok(())
+    }
+
     /// Get user by ID
     pub fn get_user_by_id(&self, user_id: Uuid) -> Result<UserProfile> {
-        self.user_by_id.get(&user_id)
-            .cloned()
+        self.storage.load_user_by_id(user_id)?
             .ok_or_else(|| SafeBankError::UserNotFound {
                 user_id: user_id.to_string(),
             })
     }
 
+    /// All registered users
+    pub fn list_users(&self) -> Result<Vec<UserProfile>> {
+        let mut users = self.storage.load_all_users()?;
+        users.sort_by_key(|user| user.created_at);
+        Ok(users)
+    }
+
+    /// Every user profile, including PIN hashes, for backup or migration to another deployment
+    pub fn export_users(&self) -> Result<Vec<UserProfile>> {
+        self.list_users()
+    }
+
+    /// Bulk-load exported profiles, e.g. when onboarding an existing co-op.
+    /// PIN hashes are kept as they are; users already registered here are skipped, and profiles
+    /// without a PIN hash or a registered device are counted as invalid.
+    pub fn import_users(&mut self, users: Vec<UserProfile>) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let mut accepted: Vec<UserProfile> = Vec::new();
+        for mut user in users {
+            match utils::normalize_phone_number(&user.phone_number, &self.config.default_phone_region) {
+                Ok(phone_number) if self.is_valid_phone_number(&phone_number) => user.phone_number = phone_number,
+                _ => {
+                    report.invalid += 1;
+                    continue;
+                }
+            }
+            if user.pin_hash.is_empty() || user.devices.is_empty() {
+                report.invalid += 1;
+                continue;
+            }
+            let seen = accepted.iter().any(|other| other.user_id == user.user_id || other.phone_number == user.phone_number);
+            if seen || self.storage.load_user_by_id(user.user_id)?.is_some() || self.storage.load_user_by_phone(&user.phone_number)?.is_some() {
+                report.duplicates += 1;
+                continue;
+            }
+            accepted.push(user);
+        }
+
+        self.restore_users(&accepted)?;
+        for user in &accepted {
+            self.audit(AuditEventType::Registration, Some(user.user_id), &user.phone_number, AuditOutcome::Success, &[
+                ("source", "import"),
+            ]);
+        }
+        report.imported = accepted.len();
+        logging::info_event!(imported = report.imported, duplicates = report.duplicates, invalid = report.invalid, "users imported");
+        Ok(report)
+    }
+
+    /// Reload previously exported user profiles into storage
+    pub fn restore_users(&mut self, users: &[UserProfile]) -> Result<()> {
+        for user in users {
+            let mut user = user.clone();
+            user.pin_primary_device();
+            self.storage.save_user(&user)?;
+        }
+        Ok(())
+    }
+
     /// Update user's behavioral profile
     pub fn update_user_profile(&mut self, user_id: Uuid, behavioral_profile: BehavioralProfile) -> Result<()> {
-        if let Some(user) = self.user_by_id.get_mut(&user_id) {
-            user.behavioral_profile = behavioral_profile;
-            // Also update in phone number map
-            self.users.insert(user.phone_number.clone(), user.clone());
-            Ok(())
-        } else {
-            Err(SafeBankError::UserNotFound {
-                user_id: user_id.to_string(),
+        let mut user = self.get_user_by_id(user_id)?;
+        user.behavioral_profile = behavioral_profile;
+        self.storage.save_user(&user)
+    }
+
+    /// Trust a registered device for a user
+    pub fn trust_device(&mut self, user_id: Uuid, device_id: String) -> Result<()> {
+        let mut user = self.get_user_by_id(user_id)?;
+        if let Some(device) = user.devices.iter_mut().find(|device| device.device_id == device_id) {
+            device.is_trusted = true;
+            device.trusted_at = Some(self.clock.now());
+            self.storage.save_user(&user)?;
+            self.audit(AuditEventType::DeviceTrusted, Some(user_id), &user.phone_number, AuditOutcome::Success, &[
+                ("device_id", &device_id),
+            ]);
+        }
+        Ok(())
+    }
+
+    /// Add a device to a user's account. New devices start untrusted and
+    /// cannot be used to log in until `trust_device` is called for them.
+    pub fn register_device(&mut self, user_id: Uuid, mut device_info: DeviceInfo) -> Result<()> {
+        let mut user = self.get_user_by_id(user_id)?;
+        if user.device(&device_info.device_id).is_some() {
+            return Err(SafeBankError::AuthenticationFailed {
+                message: "Device already registered".to_string(),
+            });
+        }
+
+        device_info.is_trusted = false;
+        device_info.trusted_at = None;
+        device_info.attestation_key = None;
+        device_info.mfa_method = MfaMethod::for_device(&device_info);
+        self.audit(AuditEventType::DeviceRegistered, Some(user_id), &user.phone_number, AuditOutcome::Success, &[
+            ("device_id", &device_info.device_id),
+        ]);
+        user.devices.push(device_info);
+        self.storage.save_user(&user)
+    }
+
+    /// Record what a registered device can do, which picks its second factor. The primary
+    /// device's capabilities set the hashing cost of PINs changed later.
+    pub fn set_device_capabilities(&mut self, user_id: Uuid, device_id: &str, capabilities: utils::DeviceCapabilities) -> Result<()> {
+        let mut user = self.get_user_by_id(user_id)?;
+        let device = Self::registered_device(&mut user, device_id)?;
+        device.capabilities = Some(capabilities);
+        device.mfa_method = MfaMethod::for_device(device);
+        self.storage.save_user(&user)
+    }
+
+    /// Issue a new attestation key for the device's secure hardware, replacing any earlier one.
+    /// The key is returned once for provisioning; devices that can match a fingerprint or face
+    /// then confirm logins and transfers with `sign_attestation` instead of a texted code.
+    pub fn enroll_attestation_key(&mut self, user_id: Uuid, device_id: &str) -> Result<String> {
+        let mut user = self.get_user_by_id(user_id)?;
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        let key = hex::encode(key);
+
+        let device = Self::registered_device(&mut user, device_id)?;
+        device.attestation_key = Some(key.clone());
+        device.mfa_method = MfaMethod::for_device(device);
+        self.storage.save_user(&user)?;
+        self.audit(AuditEventType::AttestationKeyEnrolled, Some(user_id), &user.phone_number, AuditOutcome::Success, &[
+            ("device_id", device_id),
+        ]);
+        Ok(key)
+    }
+
+    fn registered_device<'a>(user: &'a mut UserProfile, device_id: &str) -> Result<&'a mut DeviceInfo> {
+        user.devices.iter_mut()
+            .find(|device| device.device_id == device_id)
+            .ok_or_else(|| SafeBankError::UnrecognizedDevice {
+                device_id: device_id.to_string(),
             })
+    }
+
+    /// Remove a device from a user's account, e.g. when a phone is lost.
+    /// The last remaining device cannot be revoked.
+    pub fn revoke_device(&mut self, user_id: Uuid, device_id: &str) -> Result<()> {
+        let mut user = self.get_user_by_id(user_id)?;
+        let index = user.devices.iter()
+            .position(|device| device.device_id == device_id)
+            .ok_or_else(|| SafeBankError::UnrecognizedDevice {
+                device_id: device_id.to_string(),
+            })?;
+
+        if user.devices.len() == 1 {
+            return Err(SafeBankError::AuthenticationFailed {
+                message: "Cannot revoke the only registered device".to_string(),
+            });
+        }
+
+        // Revoking the primary leaves none; the remaining devices must be trusted to log in
+        user.pin_primary_device();
+
+        user.devices.remove(index);
+        if let Some(credential) = self.offline_credentials.get(&user.phone_number) {
+            if credential.device_id == device_id {
+                self.offline_credentials.remove(&user.phone_number);
+            }
         }
+        self.storage.save_user(&user)?;
+        self.audit(AuditEventType::DeviceRevoked, Some(user_id), &user.phone_number, AuditOutcome::Success, &[
+            ("device_id", device_id),
+        ]);
+        Ok(())
     }
 
-    /// Trust a device for a user
-    pub fn trust_device(&mut self, user_id: Uuid, device_id: String) -> Result<()> {
-        if let Some(user) = self.user_by_id.get_mut(&user_id) {
-            if user.device_info.device_id == device_id {
-                user.device_info.is_trusted = true;
-                self.users.insert(user.phone_number.clone(), user.clone());
+    /// Devices registered to a user
+    pub fn list_devices(&self, user_id: Uuid) -> Result<Vec<DeviceInfo>> {
+        Ok(self.get_user_by_id(user_id)?.devices)
+    }
+
+    /// The primary (sign-up) device is always accepted; devices added later must be trusted first
+    fn is_device_accepted(user: &UserProfile, device_id: &str) -> bool {
+        let is_primary = user.primary_device().is_some_and(|primary| primary.device_id == device_id);
+        is_primary || user.device(device_id).is_some_and(|device| device.is_trusted)
+    }
+
+    /// Security events matching the filter, oldest first
+    pub fn query_audit(&self, filter: &AuditFilter) -> Vec<AuditEvent> {
+        self.audit_log.query(filter)
+    }
+
+    /// Append a security event to the audit log
+    fn audit(&mut self, event_type: AuditEventType, user_id: Option<Uuid>, phone_number: &str, outcome: AuditOutcome, metadata: &[(&str, &str)]) {
+        let metadata = metadata.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        self.audit_log.record(self.clock.now(), user_id, phone_number, event_type, outcome, metadata);
+    }
+
+    /// Record the outcome of a login attempt; failures carry the reason
+    fn audit_login(&mut self, phone_number: &str, device_id: &str, mode: &str, outcome: std::result::Result<Uuid, &SafeBankError>) {
+        match outcome {
+            Ok(user_id) => {
+                logging::info_event!(%user_id, device_id, mode, "login succeeded");
+                self.audit(AuditEventType::LoginSuccess, Some(user_id), phone_number, AuditOutcome::Success, &[
+                    ("device_id", device_id),
+                    ("mode", mode),
+                ]);
+            }
+            Err(e) => {
+                logging::warn_event!(
+                    phone = %utils::sanitize_for_logging(phone_number),
+                    device_id,
+                    mode,
+                    error_code = e.code(),
+                    "login failed"
+                );
+                let user_id = self.storage.load_user_by_phone(phone_number).ok().flatten().map(|user| user.user_id);
+                self.audit(AuditEventType::LoginFailure, user_id, phone_number, AuditOutcome::Failure, &[
+                    ("device_id", device_id),
+                    ("mode", mode),
+                    ("reason", &e.to_string()),
+                ]);
             }
-            Ok(())
-        } else {
-            Err(SafeBankError::UserNotFound {
-                user_id: user_id.to_string(),
-            })
         }
     }
 
     /// Check if account is temporarily locked due to failed attempts
-    fn is_account_locked(&self, phone_number: &str) -> bool {
-        if let Some((count, last_attempt)) = self.failed_attempts.get(phone_number) {
-            if *count >= self.config.max_failed_attempts {
-                let lockout_duration = Duration::minutes(self.config.lockout_duration_minutes as i64);
-                return Utc::now() - *last_attempt < lockout_duration;
+    pub(crate) fn is_account_locked(&self, phone_number: &str) -> bool {
+        self.is_locked_out(&self.login_failures(phone_number))
+    }
+
+    fn is_locked_out(&self, failures: &LoginFailures) -> bool {
+        match failures.last_attempt {
+            Some(last_attempt) if failures.count >= self.config.max_failed_attempts => {
+                self.clock.now() - last_attempt < self.lockout_duration(failures.lockout_level)
             }
+            _ => false,
         }
-        false
     }
 
-    /// Record a failed authentication attempt
-    fn record_failed_attempt(&mut self, phone_number: &str) {
-        let count = self.failed_attempts
-            .get(phone_number)
-            .map(|(count, _)| count + 1)
-            .unwrap_or(1);
-        
-        self.failed_attempts.insert(phone_number.to_string(), (count, Utc::now()));
+    /// Duration of the current lockout: the base duration grows by the backoff multiplier
+    /// with every repeated lockout, up to the configured maximum
+    fn lockout_duration(&self, lockout_level: u32) -> Duration {
+        let level = lockout_level.max(1);
+        let minutes = self.config.lockout_duration_minutes as f64
+            * self.config.lockout_backoff_multiplier.powi(level as i32 - 1);
+        let minutes = minutes.min(self.config.max_lockout_duration_minutes as f64);
+        Duration::minutes(minutes as i64)
+    }
+
+    fn login_failures(&self, phone_number: &str) -> LoginFailures {
+        storage::load_json(self.storage.as_ref(), &login_failures_key(phone_number))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    /// Record a failed authentication attempt.
+    /// Every failure at or beyond the attempt limit starts a new, longer lockout.
+    pub(crate) fn record_failed_attempt(&mut self, phone_number: &str) {
+        let mut failures = self.login_failures(phone_number);
+        failures.count += 1;
+        failures.last_attempt = Some(self.clock.now());
+        if failures.count >= self.config.max_failed_attempts {
+            failures.lockout_level += 1;
+        }
+        if storage::save_json(self.storage.as_ref(), &login_failures_key(phone_number), &failures).is_err() {
+            logging::warn_event!(phone = %utils::sanitize_for_logging(phone_number), "could not record failed login attempt");
+        }
+
+        if failures.count >= self.config.max_failed_attempts {
+            let level = failures.lockout_level.to_string();
+            let minutes = self.lockout_duration(failures.lockout_level).num_minutes().to_string();
+            let user_id = self.storage.load_user_by_phone(phone_number).ok().flatten().map(|user| user.user_id);
+            logging::warn_event!(
+                phone = %utils::sanitize_for_logging(phone_number),
+                lockout_level = %level,
+                duration_minutes = %minutes,
+                "account locked after repeated failed attempts"
+            );
+            self.audit(AuditEventType::Lockout, user_id, phone_number, AuditOutcome::Failure, &[
+                ("lockout_level", &level),
+                ("duration_minutes", &minutes),
+            ]);
+        }
+    }
+
+    /// Reset the failed-attempt counter and lockout escalation after a successful login
+    fn clear_failed_attempts(&mut self, phone_number: &str) {
+        if self.storage.remove_record(&login_failures_key(phone_number)).is_err() {
+            logging::warn_event!(phone = %utils::sanitize_for_logging(phone_number), "could not clear failed login attempts");
+        }
+    }
+
+    /// E.164 form of a phone number for lookups; unparseable input is kept as typed so it simply matches no user
+    fn canonical_phone(&self, phone_number: &str) -> String {
+        utils::normalize_phone_number(phone_number, &self.config.default_phone_region)
+            .unwrap_or_else(|_| phone_number.to_string())
     }
 
     /// Validate phone number format (basic validation for rural context)
@@ -196,53 +1205,29 @@ impl AuthManager {
         clean_number.len() >= 10 && clean_number.len() <= 15 && clean_number.chars().all(|c| c.is_ascii_digit())
     }
 
-    /// Validate PIN format
-    fn is_valid_pin(&self, pin: &str) -> bool {
+    /// Validate PIN format and, when configured, refuse easily guessed PINs
+    fn check_pin(&self, pin: &str) -> Result<()> {
         if self.config.pin_complexity_required {
-            // Complex PIN validation
-            pin.len() >= 6 && pin.chars().all(|c| c.is_ascii_digit()) && !self.is_sequential(pin)
-        } else {
-            // Simple PIN validation for rural users
-            pin.len() >= 4 && pin.len() <= 6 && pin.chars().all(|c| c.is_ascii_digit())
+            if pin.len() < 6 || !pin.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid_pin(PinRejection::TooShort, "PIN must be at least 6 digits"));
+            }
+        } else if pin.len() < 4 || pin.len() > 6 || !pin.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid_pin(PinRejection::Format, "PIN must be 4-6 digits"));
         }
-    }
 
-    /// Check if PIN is sequential (e.g., 1234, 9876)
-    fn is_sequential(&self, pin: &str) -> bool {
-        if pin.len() < 3 {
-            return false;
-        }
-        
-        let chars: Vec<char> = pin.chars().collect();
-        let mut is_ascending = true;
-        let mut is_descending = true;
-        
-        for i in 1..chars.len() {
-            let current = chars[i].to_digit(10).unwrap_or(0);
-            let previous = chars[i-1].to_digit(10).unwrap_or(0);
-            
-            if current != previous + 1 {
-                is_ascending = false;
-            }
-            if current != previous.saturating_sub(1) {
-                is_descending = false;
+        if self.config.reject_weak_pins || self.config.pin_complexity_required {
+            if let Some(weakness) = utils::pin_weakness(pin) {
+                return Err(invalid_pin(PinRejection::Weak, format!("PIN {}", weakness)));
             }
         }
-        
-        is_ascending || is_descending
+        Ok(())
     }
 
-    /// Hash PIN using Argon2
-    fn hash_pin(&self, pin: &str) -> Result<String> {
+    /// Hash PIN using Argon2, at a cost suited to the device it is set from but no lighter than
+    /// `current`, the account's stored hash, unless `allow_pin_cost_downgrade` is set
+    fn hash_pin(&self, pin: &str, capabilities: Option<&utils::DeviceCapabilities>, current: Option<&str>) -> Result<String> {
         let salt = SaltString::generate(&mut OsRng);
-        let argon2 = if self.config.enable_lightweight_encryption {
-            // Lighter parameters for low-end devices
-            Argon2::default()
-        } else {
-            Argon2::default()
-        };
-        
-        let password_hash = argon2
+        let password_hash = self.pin_hasher(capabilities, current)?
             .hash_password(pin.as_bytes(), &salt)
             .map_err(|e| SafeBankError::CryptographyError {
                 message: format!("Failed to hash PIN: {}", e),
@@ -251,6 +1236,65 @@ impl AuthManager {
         Ok(password_hash.to_string())
     }
 
+    /// Argon2 instance for new PIN hashes: the configured lighter cost on low-end devices (and devices
+    /// that don't report their capabilities), the library defaults otherwise. Capabilities are reported
+    /// by the client, so they never bring the cost below that of the `current` hash being replaced
+    /// unless the deployment allows it.
+    fn pin_hasher(&self, capabilities: Option<&utils::DeviceCapabilities>, current: Option<&str>) -> Result<Argon2<'static>> {
+        let low_end = capabilities.is_none_or(|capabilities| capabilities.is_low_end());
+        let params = if !self.config.enable_lightweight_encryption || !low_end {
+            Params::default()
+        } else {
+            Params::new(
+                self.config.argon2_memory_kib,
+                self.config.argon2_iterations,
+                self.config.argon2_parallelism,
+                None,
+            ).map_err(|e| SafeBankError::CryptographyError {
+                message: format!("Invalid Argon2 parameters: {}", e),
+            })?
+        };
+
+        let stored = current.filter(|_| !self.config.allow_pin_cost_downgrade)
+            .and_then(|hash| Params::try_from(&PasswordHash::new(hash).ok()?).ok());
+        let params = match stored {
+            Some(stored) if stored.m_cost() > params.m_cost() || stored.t_cost() > params.t_cost() => stored,
+            _ => params,
+        };
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    fn primary_capabilities(user: &UserProfile) -> Option<&utils::DeviceCapabilities> {
+        user.primary_device().and_then(|device| device.capabilities.as_ref())
+    }
+
+    /// Whether the PIN is the current one or among the remembered previous PINs.
+    /// Each hash has its own salt, so every entry goes through Argon2 verification.
+    fn is_recent_pin(&self, pin: &str, user: &UserProfile) -> Result<bool> {
+        for hash in std::iter::once(&user.pin_hash).chain(&user.pin_history) {
+            if self.verify_pin(pin, hash)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Verify PIN against the duress PIN cached for offline login, if one is set
+    fn verify_cached_duress_pin(&self, pin: &str, credential: &CachedCredential) -> Result<bool> {
+        match &credential.duress_pin_hash {
+            Some(hash) => self.verify_pin(pin, hash),
+            None => Ok(false),
+        }
+    }
+
+    /// Verify PIN against the user's duress PIN, if one is set
+    fn verify_duress_pin(&self, pin: &str, user: &UserProfile) -> Result<bool> {
+        match &user.duress_pin_hash {
+            Some(hash) => self.verify_pin(pin, hash),
+            None => Ok(false),
+        }
+    }
+
     /// Verify PIN against hash
     fn verify_pin(&self, pin: &str, hash: &str) -> Result<bool> {
         let parsed_hash = PasswordHash::new(hash)
@@ -258,15 +1302,29 @@ impl AuthManager {
                 message: format!("Failed to parse hash: {}", e),
             })?;
         
+        // The cost parameters are read from the hash itself, so either hashing mode verifies
         let argon2 = Argon2::default();
         Ok(argon2.verify_password(pin.as_bytes(), &parsed_hash).is_ok())
     }
 }
 
+fn invalid_pin(rejection: PinRejection, reason: impl Into<String>) -> SafeBankError {
+    SafeBankError::InvalidPin { reason: reason.into(), rejection }
+}
+
+fn login_failures_key(phone_number: &str) -> String {
+    format!("{}{}", LOGIN_FAILURES_PREFIX, phone_number)
+}
+
+fn duress_key(user_id: Uuid) -> String {
+    format!("{}{}", DURESS_PREFIX, user_id)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::SafeBankConfig;
+    use std::sync::Arc;
+    use crate::{clock::MockClock, config::SafeBankConfig};
 
     fn create_test_device_info() -> DeviceInfo {
         DeviceInfo {
@@ -276,6 +1334,10 @@ mod tests {
             app_version: "1.0.0".to_string(),
             is_trusted: false,
             registered_at: Utc::now(),
+            capabilities: None,
+            mfa_method: MfaMethod::Otp,
+            attestation_key: None,
+            trusted_at: None,
         }
     }
 
@@ -286,7 +1348,7 @@ mod tests {
         
         let result = auth_manager.register_user(
             "+1234567890".to_string(),
-            "1234".to_string(),
+            "5829".to_string(),
             create_test_device_info(),
         );
         
@@ -301,15 +1363,15 @@ mod tests {
         // Register user first
         let user = auth_manager.register_user(
             "+1234567890".to_string(),
-            "1234".to_string(),
+            "5829".to_string(),
             create_test_device_info(),
         ).unwrap();
         
         // Test authentication
         let auth_result = auth_manager.authenticate(
             "+1234567890",
-            "1234",
-            &user.device_info.device_id,
+            "5829",
+            &user.devices[0].device_id,
         );
         
         assert!(auth_result.is_ok());
@@ -320,21 +1382,669 @@ mod tests {
         let config = SafeBankConfig::default();
         let auth_manager = AuthManager::new(&config);
         
-        assert!(!auth_manager.is_valid_pin("123")); // Too short
-        assert!(!auth_manager.is_valid_pin("12345678")); // Too long
-        assert!(!auth_manager.is_valid_pin("12ab")); // Contains letters
-        assert!(auth_manager.is_valid_pin("1234")); // Valid
+        assert!(auth_manager.check_pin("358").is_err()); // Too short
+        assert!(auth_manager.check_pin("35829174").is_err()); // Too long
+        assert!(auth_manager.check_pin("58ab").is_err()); // Contains letters
+        assert!(auth_manager.check_pin("5829").is_ok()); // Valid
     }
 
     #[test]
-    fn test_failed_attempts_lockout() {
+    fn test_exported_users_import_into_another_manager() {
+        let config = SafeBankConfig::default();
+        let mut source = AuthManager::new(&config);
+        let mary = source.register_user("+254712345678".to_string(), "4821".to_string(), create_test_device_info()).unwrap();
+        source.register_user("+254787654321".to_string(), "7392".to_string(), create_test_device_info()).unwrap();
+
+        let mut exported = source.export_users().unwrap();
+        assert_eq!(exported.len(), 2);
+        let mut malformed = exported[0].clone();
+        malformed.user_id = Uuid::new_v4();
+        malformed.phone_number = "not a phone".to_string();
+        exported.push(malformed);
+        // A profile with no device could never transact
+        let mut deviceless = exported[0].clone();
+        deviceless.user_id = Uuid::new_v4();
+        deviceless.phone_number = "+254711111111".to_string();
+        deviceless.devices.clear();
+        exported.push(deviceless);
+
+        let mut target = AuthManager::new(&config);
+        let report = target.import_users(exported.clone()).unwrap();
+        assert_eq!((report.imported, report.duplicates, report.invalid), (2, 0, 2));
+        assert!(target.storage.load_user_by_phone("+254711111111").unwrap().is_none());
+        let again = target.import_users(exported).unwrap();
+        assert_eq!((again.imported, again.duplicates), (0, 2));
+
+        // The PIN hash carried over, so the member logs in with their existing PIN
+        let user = target.authenticate("+254712345678", "4821", &mary.devices[0].device_id).unwrap();
+        assert_eq!(user.user_id, mary.user_id);
+        assert_eq!(user.pin_hash, mary.pin_hash);
+    }
+
+    #[test]
+    fn test_weak_pins_are_rejected_with_reason() {
+        let config = SafeBankConfig::default();
+        let auth_manager = AuthManager::new(&config);
+
+        for (pin, reason) in [
+            ("0000", "single digit"),
+            ("1111", "single digit"),
+            ("1212", "short pattern"),
+            ("123123", "short pattern"),
+            ("1234", "consecutive"),
+            ("9876", "consecutive"),
+            ("1004", "commonly chosen"),
+            ("1990", "year"),
+        ] {
+            match auth_manager.check_pin(pin) {
+                Err(SafeBankError::InvalidPin { reason: actual, rejection: PinRejection::Weak }) => assert!(actual.contains(reason), "{}: {}", pin, actual),
+                other => panic!("{} should be weak, got {:?}", pin, other),
+            }
+        }
+        for pin in ["4821", "5930", "7392", "250418"] {
+            assert!(auth_manager.check_pin(pin).is_ok(), "{}", pin);
+        }
+
+        // Weak PINs are only refused when configured
+        let lenient = AuthManager::new(&SafeBankConfig { reject_weak_pins: false, ..config });
+        assert!(lenient.check_pin("1234").is_ok());
+        assert!(lenient.check_pin("123").is_err());
+    }
+
+    #[test]
+    fn test_lightweight_pin_hashing_uses_configured_params() {
+        let config = SafeBankConfig {
+            enable_lightweight_encryption: true,
+            argon2_memory_kib: 1024,
+            argon2_iterations: 1,
+            argon2_parallelism: 1,
+            ..SafeBankConfig::default()
+        };
+        let light = AuthManager::new(&config);
+        let hash = light.hash_pin("4821", None, None).unwrap();
+        let params = Params::try_from(&PasswordHash::new(&hash).unwrap()).unwrap();
+        assert_eq!((params.m_cost(), params.t_cost(), params.p_cost()), (1024, 1, 1));
+        assert!(light.verify_pin("4821", &hash).unwrap());
+        assert!(!light.verify_pin("4822", &hash).unwrap());
+
+        // Strong hashing keeps the library defaults, and each mode verifies the other's hashes
+        let strong = AuthManager::new(&SafeBankConfig { enable_lightweight_encryption: false, ..config });
+        let strong_hash = strong.hash_pin("4821", None, None).unwrap();
+        let params = Params::try_from(&PasswordHash::new(&strong_hash).unwrap()).unwrap();
+        assert_eq!(params.m_cost(), Params::DEFAULT_M_COST);
+        assert!(light.verify_pin("4821", &strong_hash).unwrap());
+        assert!(strong.verify_pin("4821", &hash).unwrap());
+
+        // Only low-end devices get the lighter cost
+        let capable = low_end_capabilities(true);
+        let capable = utils::DeviceCapabilities { ram_mb: Some(4096), storage_mb: Some(65536), screen_size: utils::DeviceScreenSize::Large, ..capable };
+        let capable_hash = light.hash_pin("4821", Some(&capable), None).unwrap();
+        let params = Params::try_from(&PasswordHash::new(&capable_hash).unwrap()).unwrap();
+        assert_eq!(params.m_cost(), Params::DEFAULT_M_COST);
+        let low_end_hash = light.hash_pin("4821", Some(&low_end_capabilities(false)), None).unwrap();
+        let params = Params::try_from(&PasswordHash::new(&low_end_hash).unwrap()).unwrap();
+        assert_eq!(params.m_cost(), 1024);
+    }
+
+    fn low_end_capabilities(has_fingerprint: bool) -> utils::DeviceCapabilities {
+        utils::DeviceCapabilities {
+            has_camera: false,
+            has_fingerprint,
+            has_sms: true,
+            has_internet: false,
+            ram_mb: Some(512),
+            storage_mb: Some(1024),
+            screen_size: utils::DeviceScreenSize::Small,
+        }
+    }
+
+    #[test]
+    fn test_device_capabilities_choose_second_factor_and_keep_hash_cost() {
+        let config = SafeBankConfig { require_otp: true, ..SafeBankConfig::default() };
+        let mut auth_manager = AuthManager::new(&config);
+
+        // Keys the client makes up at registration are discarded
+        let fingerprint = DeviceInfo {
+            device_id: "fingerprint-phone".to_string(),
+            capabilities: Some(low_end_capabilities(true)),
+            attestation_key: Some("chosen-by-the-client".to_string()),
+            ..create_test_device_info()
+        };
+        let sms_only = DeviceInfo {
+            device_id: "feature-phone".to_string(),
+            capabilities: Some(low_end_capabilities(false)),
+            ..create_test_device_info()
+        };
+        let alice = auth_manager.register_user("+1234567890".to_string(), "4821".to_string(), fingerprint).unwrap();
+        let bob = auth_manager.register_user("+1234567891".to_string(), "5829".to_string(), sms_only).unwrap();
+        assert!(alice.devices[0].attestation_key.is_none());
+        assert_eq!(alice.devices[0].mfa_method, MfaMethod::Otp);
+
+        // With an enrolled key the fingerprint phone confirms with a match; the feature phone still gets a text
+        let key = auth_manager.enroll_attestation_key(alice.user_id, "fingerprint-phone").unwrap();
+        auth_manager.enroll_attestation_key(bob.user_id, "feature-phone").unwrap();
+        let AuthOutcome::MfaRequired { challenge_id, method: MfaMethod::Biometric } = auth_manager.authenticate_ex("+1234567890", "4821", "fingerprint-phone", None).unwrap() else {
+            panic!("expected a biometric challenge");
+        };
+        assert!(matches!(
+            auth_manager.authenticate_ex("+1234567891", "5829", "feature-phone", None).unwrap(),
+            AuthOutcome::MfaRequired { method: MfaMethod::Otp, .. }
+        ));
+
+        // Only a signature with the enrolled key passes; the device id alone is no proof
+        assert!(auth_manager.complete_mfa(challenge_id, "fingerprint-phone").is_err());
+        assert!(auth_manager.complete_mfa(challenge_id, &sign_attestation(&key, challenge_id)).is_ok());
+
+        // Upgrading the phone raises the cost of the next PIN hash...
+        let capable = utils::DeviceCapabilities { ram_mb: Some(4096), storage_mb: Some(65536), screen_size: utils::DeviceScreenSize::Large, ..low_end_capabilities(true) };
+        auth_manager.set_device_capabilities(alice.user_id, "fingerprint-phone", capable).unwrap();
+        auth_manager.change_pin(alice.user_id, "4821", "5829").unwrap();
+        let m_cost = |manager: &AuthManager| {
+            let hash = manager.get_user_by_id(alice.user_id).unwrap().pin_hash;
+            Params::try_from(&PasswordHash::new(&hash).unwrap()).unwrap().m_cost()
+        };
+        assert_eq!(m_cost(&auth_manager), Params::DEFAULT_M_COST);
+
+        // ...but claiming to be low-end again doesn't bring it back down
+        auth_manager.set_device_capabilities(alice.user_id, "fingerprint-phone", low_end_capabilities(false)).unwrap();
+        auth_manager.change_pin(alice.user_id, "5829", "7302").unwrap();
+        assert_eq!(m_cost(&auth_manager), Params::DEFAULT_M_COST);
+
+        // unless the deployment allows it
+        let mut lenient = AuthManager::with_storage(&SafeBankConfig { allow_pin_cost_downgrade: true, ..config }, auth_manager.storage.clone(), clock::system_clock());
+        lenient.change_pin(alice.user_id, "7302", "9163").unwrap();
+        assert_eq!(m_cost(&lenient), SafeBankConfig::default().argon2_memory_kib);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_failed_authentication_emits_warning() {
+        use std::sync::Mutex;
+        use tracing::{Event, Level, Metadata, field::{Field, Visit}, span};
+
+        /// Subscriber that keeps the fields of every warn-level event
+        struct WarningCapture(Arc<Mutex<Vec<String>>>);
+
+        struct FieldText(String);
+
+        impl Visit for FieldText {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0.push_str(&format!("{}={:?} ", field.name(), value));
+            }
+        }
+
+        impl tracing::Subscriber for WarningCapture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id { span::Id::from_u64(1) }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &Event<'_>) {
+                if *event.metadata().level() == Level::WARN {
+                    let mut text = FieldText(String::new());
+                    event.record(&mut text);
+                    self.0.lock().unwrap().push(text.0);
+                }
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let config = SafeBankConfig::default();
+        let mut auth_manager = AuthManager::new(&config);
+        let user = auth_manager.register_user("+254712345678".to_string(), "4821".to_string(), create_test_device_info()).unwrap();
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(WarningCapture(warnings.clone()), || {
+            auth_manager.authenticate("+254712345678", "4821", &user.devices[0].device_id).unwrap();
+            assert!(auth_manager.authenticate("+254712345678", "0000", &user.devices[0].device_id).is_err());
+        });
+
+        let warnings = warnings.lock().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("login failed"));
+        assert!(warnings[0].contains("AUTH_FAILED"));
+        // The phone number is masked
+        assert!(!warnings[0].contains("712345678"));
+    }
+
+    #[test]
+    fn test_failures_across_accounts_throttle_the_device() {
+        let config = SafeBankConfig {
+            auth_rate_limit_attempts: 5,
+            auth_rate_limit_window_seconds: 600,
+            ..SafeBankConfig::default()
+        };
+        let clock = MockClock::new(Utc::now());
+        let mut auth_manager = AuthManager::with_clock(&config, Arc::new(clock.clone()));
+        let user = auth_manager.register_user("+254712345678".to_string(), "4821".to_string(), create_test_device_info()).unwrap();
+
+        // One wrong PIN per account never locks an account, but the device runs out of attempts
+        for suffix in 0..5 {
+            let phone = format!("+25471200000{}", suffix);
+            assert!(auth_manager.authenticate(&phone, "1234", "attacker-phone").is_err());
+        }
+        assert!(matches!(
+            auth_manager.authenticate("+254712000009", "1234", "attacker-phone"),
+            Err(SafeBankError::RateLimited { retry_after_seconds: 600 })
+        ));
+        // Even correct credentials are refused from the throttled device
+        assert!(matches!(
+            auth_manager.authenticate("+254712345678", "4821", "attacker-phone"),
+            Err(SafeBankError::RateLimited { .. })
+        ));
+        // Other devices are unaffected
+        auth_manager.authenticate("+254712345678", "4821", &user.devices[0].device_id).unwrap();
+
+        // A shared client address is throttled too, whichever device it reports
+        for suffix in 0..5 {
+            let device = format!("rotating-{}", suffix);
+            let _ = auth_manager.authenticate_from("+254712000001", "1234", &device, "10.0.0.7");
+        }
+        assert!(matches!(
+            auth_manager.authenticate_from("+254712345678", "4821", &user.devices[0].device_id, "10.0.0.7"),
+            Err(SafeBankError::RateLimited { .. })
+        ));
+
+        clock.advance(Duration::seconds(601));
+        assert!(auth_manager.authenticate("+254712345678", "4821", "attacker-phone").is_err_and(|e| !matches!(e, SafeBankError::RateLimited { .. })));
+    }
+
+    #[test]
+    fn test_duress_pin_authenticates_and_flags() {
+        let config = SafeBankConfig::default();
+        let mut auth_manager = AuthManager::new(&config);
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "4821".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+
+        // The duress PIN must differ from the normal PIN
+        assert!(auth_manager.set_duress_pin(user.user_id, "4821").is_err());
+        auth_manager.set_duress_pin(user.user_id, "9731").unwrap();
+
+        auth_manager.authenticate("+1234567890", "4821", &user.devices[0].device_id).unwrap();
+        assert!(!auth_manager.is_under_duress(user.user_id));
+
+        auth_manager.authenticate("+1234567890", "9731", &user.devices[0].device_id).unwrap();
+        assert!(auth_manager.is_under_duress(user.user_id));
+    }
+
+    #[test]
+    fn test_offline_authentication_with_cached_credential() {
+        let config = SafeBankConfig::default();
+        let mut auth_manager = AuthManager::new(&config);
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "4821".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+
+        // Nothing cached yet
+        assert!(auth_manager.authenticate_offline("+1234567890", "4821", &user.devices[0].device_id).is_err());
+
+        auth_manager.cache_credentials(user.user_id).unwrap();
+        assert!(auth_manager.authenticate_offline("+1234567890", "0000", &user.devices[0].device_id).is_err());
+
+        let session = auth_manager.authenticate_offline("+1234567890", "4821", &user.devices[0].device_id).unwrap();
+        assert_eq!(session.user_id, user.user_id);
+        assert_eq!(session.transaction_limit, config.offline_transaction_limit);
+
+        // Sync applies the login once connectivity returns
+        assert_eq!(auth_manager.sync_offline_logins().unwrap(), 1);
+        assert_eq!(auth_manager.get_user_by_id(user.user_id).unwrap().last_login, Some(session.authenticated_at));
+    }
+
+    #[test]
+    fn test_lockout_expires_with_mock_clock() {
+        let config = SafeBankConfig::default();
+        let clock = MockClock::new(Utc::now());
+        let mut auth_manager = AuthManager::with_clock(&config, Arc::new(clock.clone()));
+
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "5829".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+
+        for _ in 0..config.max_failed_attempts {
+            let _ = auth_manager.authenticate("+1234567890", "9999", &user.devices[0].device_id);
+        }
+        assert!(matches!(
+            auth_manager.authenticate("+1234567890", "5829", &user.devices[0].device_id),
+            Err(SafeBankError::AccountLocked)
+        ));
+
+        // Advance past the lockout window without sleeping
+        clock.advance(Duration::minutes(config.lockout_duration_minutes as i64 + 1));
+
+        assert!(auth_manager.authenticate("+1234567890", "5829", &user.devices[0].device_id).is_ok());
+    }
+
+    #[test]
+    fn test_repeated_lockouts_escalate() {
+        let config = SafeBankConfig::default();
+        let clock = MockClock::new(Utc::now());
+        let mut auth_manager = AuthManager::with_clock(&config, Arc::new(clock.clone()));
+
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "5829".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+        let device_id = user.devices[0].device_id.clone();
+        let base = config.lockout_duration_minutes as i64;
+
+        for _ in 0..config.max_failed_attempts {
+            let _ = auth_manager.authenticate("+1234567890", "9999", &device_id);
+        }
+        assert!(auth_manager.is_account_locked("+1234567890"));
+        clock.advance(Duration::minutes(base + 1));
+        assert!(!auth_manager.is_account_locked("+1234567890"));
+
+        // Failing again after the first lockout locks the account for twice as long
+        let _ = auth_manager.authenticate("+1234567890", "9999", &device_id);
+        clock.advance(Duration::minutes(base + 1));
+        assert!(auth_manager.is_account_locked("+1234567890"));
+        clock.advance(Duration::minutes(base));
+        assert!(!auth_manager.is_account_locked("+1234567890"));
+
+        // A successful login resets the escalation back to the base duration
+        assert!(auth_manager.authenticate("+1234567890", "5829", &device_id).is_ok());
+        for _ in 0..config.max_failed_attempts {
+            let _ = auth_manager.authenticate("+1234567890", "9999", &device_id);
+        }
+        clock.advance(Duration::minutes(base + 1));
+        assert!(!auth_manager.is_account_locked("+1234567890"));
+    }
+
+    #[test]
+    fn test_phone_formats_resolve_to_one_user() {
+        let config = SafeBankConfig::default();
+        let mut auth_manager = AuthManager::new(&config);
+
+        let user = auth_manager.register_user(
+            "0712 345 678".to_string(),
+            "5829".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+        assert_eq!(user.phone_number, "+254712345678");
+        let device_id = user.devices[0].device_id.clone();
+
+        for variant in ["+254712345678", "254-712-345-678", "+254 712 345 678", "0712345678"] {
+            assert_eq!(auth_manager.authenticate(variant, "5829", &device_id).unwrap().user_id, user.user_id, "{}", variant);
+        }
+
+        // Another formatting of the same number cannot open a second account
+        assert!(matches!(
+            auth_manager.register_user("254 712 345 678".to_string(), "5678".to_string(), create_test_device_info()),
+            Err(SafeBankError::AuthenticationFailed { message }) if message == "User already exists"
+        ));
+    }
+
+    #[test]
+    fn test_authenticate_ex_outcomes() {
+        let config = SafeBankConfig { require_otp: true, ..SafeBankConfig::default() };
+        let mut auth_manager = AuthManager::new(&config);
+
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "5829".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+        let device_id = user.devices[0].device_id.clone();
+
+        // A correct PIN opens a challenge instead of completing the login
+        let challenge_id = match auth_manager.authenticate_ex("+1234567890", "5829", &device_id, None).unwrap() {
+            AuthOutcome::MfaRequired { challenge_id, method } => {
+                assert_eq!(method, MfaMethod::Otp);
+                challenge_id
+            }
+            other => panic!("expected MfaRequired, got {:?}", other),
+        };
+        let code = auth_manager.issue_otp("+1234567890").unwrap();
+        assert!(matches!(auth_manager.complete_mfa(challenge_id, "000000x"), Err(SafeBankError::InvalidOtp)));
+        assert_eq!(auth_manager.complete_mfa(challenge_id, &code).unwrap().user_id, user.user_id);
+        // Challenges are single use
+        assert!(auth_manager.complete_mfa(challenge_id, &code).is_err());
+
+        // The legacy API maps the same state to the existing error
+        assert!(matches!(
+            auth_manager.authenticate("+1234567890", "5829", &device_id),
+            Err(SafeBankError::OtpRequired)
+        ));
+
+        // An unknown device is asked to verify itself before any second factor
+        assert!(matches!(
+            auth_manager.authenticate_ex("+1234567890", "5829", "other-device", None).unwrap(),
+            AuthOutcome::DeviceVerificationRequired { device_id } if device_id == "other-device"
+        ));
+        assert!(matches!(
+            auth_manager.authenticate("+1234567890", "5829", "other-device"),
+            Err(SafeBankError::UnrecognizedDevice { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unusual_pin_entry_timing_steps_up() {
+        let config = SafeBankConfig { require_otp: false, ..SafeBankConfig::default() };
+        let mut auth_manager = AuthManager::new(&config);
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "5829".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+        let device_id = user.devices[0].device_id.clone();
+
+        // Build up a baseline of ordinary, human-speed entries
+        for total_ms in [1900, 2100, 2000, 1950, 2050, 2000] {
+            let metrics = EntryMetrics { total_ms, keystroke_intervals_ms: vec![500, 450, 520] };
+            assert!(matches!(
+                auth_manager.authenticate_ex("+1234567890", "5829", &device_id, Some(&metrics)).unwrap(),
+                AuthOutcome::Authenticated(_)
+            ));
+        }
+
+        // Scripted entry is stepped up to a one-time code
+        let scripted = EntryMetrics { total_ms: 60, keystroke_intervals_ms: vec![10, 12, 9] };
+        assert!(matches!(
+            auth_manager.authenticate_ex("+1234567890", "5829", &device_id, Some(&scripted)).unwrap(),
+            AuthOutcome::MfaRequired { method: MfaMethod::Otp, .. }
+        ));
+        // So is a much slower entry than usual
+        let hesitant = EntryMetrics { total_ms: 9000, keystroke_intervals_ms: Vec::new() };
+        assert!(matches!(
+            auth_manager.authenticate_ex("+1234567890", "5829", &device_id, Some(&hesitant)).unwrap(),
+            AuthOutcome::MfaRequired { .. }
+        ));
+
+        // Leaving timing out doesn't dodge the signal once a baseline exists
+        assert!(matches!(
+            auth_manager.authenticate_ex("+1234567890", "5829", &device_id, None).unwrap(),
+            AuthOutcome::MfaRequired { .. }
+        ));
+        // Before that, clients that don't report timing log in as before
+        let newcomer = auth_manager.register_user("+1234567891".to_string(), "5829".to_string(), create_test_device_info()).unwrap();
+        assert!(matches!(
+            auth_manager.authenticate_ex("+1234567891", "5829", &newcomer.devices[0].device_id, None).unwrap(),
+            AuthOutcome::Authenticated(_)
+        ));
+
+        // When every login takes a one-time code, the baseline is learned as the challenge is passed
+        let mut auth_manager = AuthManager::new(&SafeBankConfig { require_otp: true, ..config });
+        let user = auth_manager.register_user("+1234567890".to_string(), "5829".to_string(), create_test_device_info()).unwrap();
+        for total_ms in [1900, 2100, 2000, 1950, 2050] {
+            let metrics = EntryMetrics { total_ms, keystroke_intervals_ms: vec![500, 450, 520] };
+            let AuthOutcome::MfaRequired { challenge_id, .. } = auth_manager.authenticate_ex("+1234567890", "5829", &device_id, Some(&metrics)).unwrap() else {
+                panic!("expected MfaRequired");
+            };
+            let code = auth_manager.issue_otp("+1234567890").unwrap();
+            auth_manager.complete_mfa(challenge_id, &code).unwrap();
+        }
+        let baseline = auth_manager.get_user_by_id(user.user_id).unwrap().pin_entry_baseline.unwrap();
+        assert_eq!(baseline.samples, 5);
+        assert!((baseline.mean_ms - 2000.0).abs() < 100.0);
+    }
+
+    #[test]
+    fn test_change_and_reset_pin() {
+        let config = SafeBankConfig::default();
+        let mut auth_manager = AuthManager::new(&config);
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "4821".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+        let device_id = user.devices[0].device_id.clone();
+
+        assert!(auth_manager.change_pin(user.user_id, "0000", "5930").is_err());
+        assert!(matches!(auth_manager.change_pin(user.user_id, "4821", "4821"), Err(SafeBankError::InvalidPin { .. })));
+        assert!(matches!(auth_manager.change_pin(user.user_id, "4821", "12"), Err(SafeBankError::InvalidPin { .. })));
+
+        auth_manager.change_pin(user.user_id, "4821", "5930").unwrap();
+        assert!(auth_manager.authenticate("+1234567890", "4821", &device_id).is_err());
+        assert!(auth_manager.authenticate("+1234567890", "5930", &device_id).is_ok());
+
+        auth_manager.reset_pin(user.user_id, "7162").unwrap();
+        assert!(auth_manager.authenticate("+1234567890", "7162", &device_id).is_ok());
+
+        // Recovery is refused while the account is locked out
+        for _ in 0..config.max_failed_attempts {
+            let _ = auth_manager.authenticate("+1234567890", "9999", &device_id);
+        }
+        assert!(matches!(auth_manager.reset_pin(user.user_id, "3817"), Err(SafeBankError::AccountLocked)));
+    }
+
+    #[test]
+    fn test_previous_pins_cannot_be_reused() {
+        let config = SafeBankConfig { pin_history_size: 2, ..SafeBankConfig::default() };
+        let mut auth_manager = AuthManager::new(&config);
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "4821".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+
+        auth_manager.change_pin(user.user_id, "4821", "5930").unwrap();
+        assert!(matches!(auth_manager.change_pin(user.user_id, "5930", "4821"), Err(SafeBankError::InvalidPin { .. })));
+        assert!(matches!(auth_manager.reset_pin(user.user_id, "4821"), Err(SafeBankError::InvalidPin { .. })));
+        auth_manager.change_pin(user.user_id, "5930", "7162").unwrap();
+
+        let stored = auth_manager.get_user_by_id(user.user_id).unwrap();
+        assert_eq!(stored.pin_history.len(), 2);
+        assert!(!stored.pin_history.contains(&stored.pin_hash));
+
+        // Once pushed out of the two-entry history, the original PIN is allowed again
+        auth_manager.change_pin(user.user_id, "7162", "3817").unwrap();
+        auth_manager.change_pin(user.user_id, "3817", "4821").unwrap();
+        assert_eq!(auth_manager.get_user_by_id(user.user_id).unwrap().pin_history.len(), 2);
+    }
+
+    #[test]
+    fn test_second_device_login_and_revocation() {
+        let config = SafeBankConfig::default();
+        let mut auth_manager = AuthManager::new(&config);
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "4821".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+
+        let feature_phone = DeviceInfo {
+            device_id: "feature-phone-456".to_string(),
+            device_type: "feature_phone".to_string(),
+            ..create_test_device_info()
+        };
+        auth_manager.register_device(user.user_id, feature_phone).unwrap();
+        assert!(auth_manager.register_device(user.user_id, create_test_device_info()).is_err());
+        assert_eq!(auth_manager.list_devices(user.user_id).unwrap().len(), 2);
+
+        // A newly added device needs to be trusted before it can log in
+        assert!(matches!(
+            auth_manager.authenticate("+1234567890", "4821", "feature-phone-456"),
+            Err(SafeBankError::UnrecognizedDevice { .. })
+        ));
+        auth_manager.trust_device(user.user_id, "feature-phone-456".to_string()).unwrap();
+        assert!(auth_manager.authenticate("+1234567890", "4821", "feature-phone-456").is_ok());
+        assert!(auth_manager.authenticate("+1234567890", "4821", "test-device-123").is_ok());
+
+        auth_manager.revoke_device(user.user_id, "feature-phone-456").unwrap();
+        assert!(matches!(
+            auth_manager.authenticate("+1234567890", "4821", "feature-phone-456"),
+            Err(SafeBankError::UnrecognizedDevice { .. })
+        ));
+        assert!(auth_manager.revoke_device(user.user_id, "test-device-123").is_err());
+    }
+
+    #[test]
+    fn test_revoking_primary_device_promotes_no_other_device() {
+        let config = SafeBankConfig::default();
+        let mut auth_manager = AuthManager::new(&config);
+        let mut legacy = auth_manager.register_user("+1234567890".to_string(), "4821".to_string(), create_test_device_info()).unwrap();
+        auth_manager.register_device(legacy.user_id, DeviceInfo {
+            device_id: "feature-phone-456".to_string(),
+            ..create_test_device_info()
+        }).unwrap();
+
+        // A record from before the primary device was stored still treats the first device as primary
+        legacy = auth_manager.get_user_by_id(legacy.user_id).unwrap();
+        legacy.primary_device_id = None;
+        auth_manager.storage.save_user(&legacy).unwrap();
+        assert!(auth_manager.authenticate("+1234567890", "4821", "test-device-123").is_ok());
+
+        // After the primary is revoked the untrusted device left in its slot still can't log in
+        auth_manager.revoke_device(legacy.user_id, "test-device-123").unwrap();
+        assert!(auth_manager.get_user_by_id(legacy.user_id).unwrap().primary_device().is_none());
+        assert!(matches!(
+            auth_manager.authenticate("+1234567890", "4821", "feature-phone-456"),
+            Err(SafeBankError::UnrecognizedDevice { .. })
+        ));
+    }
+
+    #[test]
+    fn test_audit_log_records_auth_events() {
         let config = SafeBankConfig::default();
         let mut auth_manager = AuthManager::new(&config);
+        let user = auth_manager.register_user(
+            "+1234567890".to_string(),
+            "4821".to_string(),
+            create_test_device_info(),
+        ).unwrap();
+        let device_id = user.devices[0].device_id.clone();
+
+        auth_manager.authenticate("+1234567890", "4821", &device_id).unwrap();
+        for _ in 0..config.max_failed_attempts {
+            let _ = auth_manager.authenticate("+1234567890", "0000", &device_id);
+        }
+        auth_manager.trust_device(user.user_id, device_id.clone()).unwrap();
+
+        let events = auth_manager.query_audit(&AuditFilter { user_id: Some(user.user_id), ..Default::default() });
+        let types: Vec<AuditEventType> = events.iter().map(|event| event.event_type).collect();
+        assert_eq!(types[0], AuditEventType::Registration);
+        assert!(events.iter().all(|event| event.phone_number == "+***-***-****"));
+        assert_eq!(types[1], AuditEventType::LoginSuccess);
+        assert!(types.contains(&AuditEventType::Lockout));
+        assert_eq!(types.last(), Some(&AuditEventType::DeviceTrusted));
+
+        let failures = auth_manager.query_audit(&AuditFilter {
+            event_type: Some(AuditEventType::LoginFailure),
+            ..Default::default()
+        });
+        assert_eq!(failures.len(), config.max_failed_attempts as usize);
+        assert!(failures.iter().all(|event| event.outcome == AuditOutcome::Failure));
+        assert_eq!(failures[0].metadata["reason"], "Authentication failed: Invalid PIN");
+    }
+
+    #[test]
+    fn test_failed_attempts_lockout() {
+        let config = SafeBankConfig::default();
+        let shared = storage::in_memory_storage();
+        let mut auth_manager = AuthManager::with_storage(&config, shared.clone(), clock::system_clock());
         
         // Register user
         let user = auth_manager.register_user(
             "+1234567890".to_string(),
-            "1234".to_string(),
+            "5829".to_string(),
             create_test_device_info(),
         ).unwrap();
         
@@ -343,11 +2053,18 @@ mod tests {
             let _ = auth_manager.authenticate(
                 "+1234567890",
                 "wrong",
-                &user.device_info.device_id,
+                &user.devices[0].device_id,
             );
         }
         
         // Account should be locked now
         assert!(auth_manager.is_account_locked("+1234567890"));
+
+        // The lockout is stored, so a restarted instance on the same storage still enforces it
+        let mut restarted = AuthManager::with_storage(&config, shared, clock::system_clock());
+        assert!(matches!(
+            restarted.authenticate("+1234567890", "5829", &user.devices[0].device_id),
+            Err(SafeBankError::AccountLocked)
+        ));
     }
 }
\ No newline at end of file
@@ -0,0 +1,83 @@
+//! Clock abstraction for SafeBank framework
+//! Lets time-dependent behavior (lockouts, daily limits, offline expiry) run against a controllable clock
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Duration, Utc};
+
+/// Source of the current time for the managers
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Real-time clock backed by the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Manually advanced clock for deterministic tests.
+/// Clones share the same underlying time, so a test can keep a handle while the managers hold another.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    current: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            current: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    /// Move the clock forward by the given duration
+    pub fn advance(&self, duration: Duration) {
+        let mut current = self.current.lock().expect("mock clock poisoned");
+        *current += duration;
+    }
+
+    /// Jump the clock to a specific instant
+    pub fn set(&self, instant: DateTime<Utc>) {
+        *self.current.lock().expect("mock clock poisoned") = instant;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.current.lock().expect("mock clock poisoned")
+    }
+}
+
+/// Shared clock handle used by the managers
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Default clock for production use
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_advances_shared_time() {
+        let clock = MockClock::new(Utc::now());
+        let handle = clock.clone();
+        let start = clock.now();
+
+        handle.advance(Duration::minutes(30));
+
+        assert_eq!(clock.now() - start, Duration::minutes(30));
+    }
+}
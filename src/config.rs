@@ -1,17 +1,148 @@
 //! Configuration module for SafeBank framework
 //! Optimized for rural banking environments with low resource constraints
 
+use std::collections::HashMap;
+use std::path::Path;
+use chrono::{DateTime, Duration, NaiveDate, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::{TransactionType, errors::SafeBankError, money::Money, rules::FraudRule, utils::ConnectivityStatus};
+
+/// Rounding rule applied when amounts are reduced to the currency's minor unit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundingMode {
+    /// Ties round away from zero (2.345 -> 2.35)
+    #[default]
+    HalfUp,
+    /// Banker's rounding: ties round to the nearest even digit (2.345 -> 2.34)
+    HalfEven,
+}
+
+/// Government levy charged on transactions, separate from the bank's own fee
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevySchedule {
+    /// Fraction of the amount charged as levy (0.015 = 1.5%)
+    pub rate: f64,
+    /// Fixed levy added per transaction
+    pub flat_amount: f64,
+    /// Amounts at or below this value are exempt
+    pub exempt_up_to: f64,
+    /// Transaction types the levy applies to
+    pub applies_to: Vec<TransactionType>,
+}
+
+impl LevySchedule {
+    /// Levy owed on a transaction of the given amount and type (unrounded)
+    pub fn levy_for(&self, amount: f64, transaction_type: &TransactionType) -> f64 {
+        if amount <= self.exempt_up_to || !self.applies_to.contains(transaction_type) {
+            return 0.0;
+        }
+        amount * self.rate + self.flat_amount
+    }
+}
+
+/// Bank fee schedule: a per-type rate in basis points, bounded by minimum and maximum charges
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeeSchedule {
+    pub transfer_bps: u32,
+    pub international_transfer_bps: u32,
+    pub payment_bps: u32,
+    pub withdrawal_bps: u32,
+    pub deposit_bps: u32,
+    /// Smallest and largest fee charged, in currency units
+    pub min_fee: f64,
+    pub max_fee: f64,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self {
+            transfer_bps: 100,
+            international_transfer_bps: 300,
+            payment_bps: 50,
+            withdrawal_bps: 200,
+            deposit_bps: 0,
+            min_fee: 0.10,
+            max_fee: 50.0,
+        }
+    }
+}
+
+impl FeeSchedule {
+    /// Rate in basis points for a transaction type name ("transfer", "payment", ...)
+    pub fn rate_bps(&self, transaction_type: &str, is_domestic: bool) -> u32 {
+        match transaction_type.to_lowercase().as_str() {
+            "transfer" if is_domestic => self.transfer_bps,
+            "transfer" => self.international_transfer_bps,
+            "payment" => self.payment_bps,
+            "withdrawal" => self.withdrawal_bps,
+            "deposit" => self.deposit_bps,
+            _ => self.transfer_bps,
+        }
+    }
+}
+
+/// Each risk factor's share of the statistical fraud score. Weights needn't sum to 1;
+/// the weighted sum is clamped to 0-1. Device anomalies use `device_anomaly_weight`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskWeights {
+    pub amount: f64,
+    pub time: f64,
+    pub frequency: f64,
+    pub recipient: f64,
+    pub location: f64,
+    /// Implausibly fast travel between located transactions
+    pub travel: f64,
+    /// Amounts at or above the single transaction limit
+    pub limit: f64,
+    /// Late-night and round-number heuristics; off unless given a weight
+    pub pattern: f64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            amount: 0.3,
+            time: 0.2,
+            frequency: 0.25,
+            recipient: 0.15,
+            location: 0.15,
+            travel: 0.3,
+            limit: 0.1,
+            pattern: 0.0,
+        }
+    }
+}
+
+/// Least and most one risk factor can add to the composite fraud score, after weighting.
+/// The floor only applies when the factor is present at all.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FactorBounds {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Fields missing from a config file take their values from `SafeBankConfig::default()`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SafeBankConfig {
     /// Maximum allowed failed authentication attempts before lockout
     pub max_failed_attempts: u32,
     
-    /// Account lockout duration in minutes
+    /// Account lockout duration in minutes for the first lockout
     pub lockout_duration_minutes: u32,
+    /// Each repeated lockout lasts this many times longer than the previous one, up to the cap
+    pub lockout_backoff_multiplier: f64,
+    pub max_lockout_duration_minutes: u32,
     
-    /// Fraud detection thresholds (0.0 to 1.0)
+    /// Failed logins allowed from one device or client address within the window, whichever account they target
+    pub auth_rate_limit_attempts: u32,
+    pub auth_rate_limit_window_seconds: u64,
+    
+    /// Fraud detection thresholds (0.0 to 1.0).
+    /// Scores above `low` but not `medium` require step-up authentication when the device isn't trusted.
     pub fraud_threshold_low: f64,
     pub fraud_threshold_medium: f64,
     pub fraud_threshold_high: f64,
@@ -19,25 +150,169 @@ pub struct SafeBankConfig {
     /// Transaction limits
     pub daily_transaction_limit: f64,
     pub single_transaction_limit: f64,
+    /// Highest daily limit a seasoned, trusted account can grow into; `None` keeps every user
+    /// on `daily_transaction_limit`
+    pub trusted_daily_limit_ceiling: Option<f64>,
+    
+    /// Maximum number of recipients in a single split transfer
+    pub max_split_recipients: usize,
+    /// Most transactions a user may make per day
+    pub daily_transaction_count_limit: Option<u32>,
+    /// Most a user may send to any single recipient per day
+    pub daily_per_recipient_limit: Option<f64>,
+    /// Transfers above this amount are held until the user confirms them with a texted code; `None` never holds them
+    pub transfer_confirmation_threshold: Option<f64>,
+    /// Longest memo a transaction may carry, in characters
+    pub max_memo_length: usize,
+    
+    /// Notify users once their daily usage crosses this percentage of the daily limit
+    pub daily_limit_warning_percent: Option<f64>,
+    
+    /// Escalate when combined system-wide volume for the day exceeds this amount
+    pub system_daily_volume_ceiling: Option<f64>,
+    
+    /// Transaction levy (e.g. a per-transfer tax); `None` disables it
+    pub levy_schedule: Option<LevySchedule>,
+    
+    /// Bank fees charged on each transaction
+    pub fee_schedule: FeeSchedule,
+    /// Count fees, not just amounts, towards the daily transaction limit
+    pub fees_count_toward_daily_limit: bool,
     
     /// Security settings
     pub require_device_verification: bool,
     pub enable_behavioral_analysis: bool,
     pub pin_complexity_required: bool,
+    /// Refuse easily guessed PINs: one repeated digit, short repeating patterns, sequences, years and common choices
+    pub reject_weak_pins: bool,
+    /// How many previous PINs a user may not switch back to
+    pub pin_history_size: usize,
+    /// Ask for a one-time code when PIN entry time is this many standard deviations from the
+    /// user's usual, or keyed faster than a person can type; `None` ignores entry timing
+    pub pin_timing_step_up_threshold: Option<f64>,
+    
+    /// Require a one-time code (sent by SMS) in addition to the PIN
+    pub require_otp: bool,
+    pub otp_length: usize,
+    pub otp_ttl_seconds: u64,
+    /// Wrong entries after which an outstanding code is discarded and a new one must be requested
+    pub otp_max_attempts: u32,
+    
+    /// Largest transaction allowed through while a user is under duress; larger ones are silently held
+    pub duress_transaction_cap: f64,
+    
+    /// Smoothing factor (0-1] for incremental profile learning; higher values adapt faster to recent behavior
+    pub profile_ema_alpha: f64,
+    /// When rebuilding a profile, a transaction this many days old counts half as much as one made today;
+    /// `None` weighs all history equally
+    pub profile_decay_half_life_days: Option<f64>,
+    /// Hours, recipients and locations not seen within this many days drop out of a rebuilt profile
+    pub profile_pattern_window_days: Option<u32>,
+    /// Profiles not learned for this many days count for half as much in the fraud score
+    pub profile_stale_after_days: Option<u32>,
+    /// New users are still being learned until they have made this many transactions or their
+    /// account is this many days old, whichever comes first
+    pub learning_grace_transactions: u32,
+    pub learning_grace_days: u32,
+    /// Multiplier on behavioral anomaly scores during the learning grace period (0 suppresses them)
+    pub learning_grace_damping: f64,
+    /// Behavioral shifts (amount, time, recipient, location, device) within this window count together
+    pub takeover_window_minutes: u32,
+    /// How many behaviors shifting together block as a likely account takeover; one fewer steps up
+    pub takeover_dimensions: usize,
+    /// Completed payments to a recipient after which they count as one of the user's usual
+    /// recipients; `None` leaves that to profile learning
+    pub trusted_recipient_after: Option<u32>,
+    /// Transactions matching the amount and recipient of one a reviewer approved within this many
+    /// hours score lower; `None` turns this off
+    pub approval_damping_hours: Option<u32>,
+    /// Multiplier on the amount and recipient anomalies of such repeat transactions (0 suppresses
+    /// them, 1 changes nothing); device, location and other signals still score in full
+    pub approval_damping: f64,
+    
+    /// Flag consecutive transactions whose locations imply travel faster than this (km/h)
+    pub max_travel_speed_kmh: f64,
+    /// Local hours `[start, end)` counted as late night; wraps past midnight when start > end
+    pub night_start_hour: u8,
+    pub night_end_hour: u8,
+    /// Amounts that are a multiple of the modulus and at least the minimum count as suspiciously round
+    pub round_amount_modulus: f64,
+    pub round_amount_minimum: f64,
+    /// Weight (0-1) of the device check in the fraud score; unrecognized devices score highest
+    pub device_anomaly_weight: f64,
+    /// Weights of the other statistical risk factors
+    pub risk_weights: RiskWeights,
+    /// Per-factor bounds on weighted contributions, keyed by the names in `risk_weight_map`
+    pub risk_factor_bounds: HashMap<String, FactorBounds>,
+    /// Factors, by the names in `risk_weight_map`, that block a transaction whenever present
+    pub terminal_risk_factors: Vec<String>,
+    /// How many reasons to give customers for a fraud rejection; 0 keeps the message generic
+    pub fraud_explanation_max_reasons: usize,
+    /// Operator fraud rules, evaluated in order after the statistical score
+    pub fraud_rules: Vec<FraudRule>,
+    /// Coordinates (latitude, longitude) for location names, extending the built-in town table
+    pub location_coordinates: HashMap<String, (f64, f64)>,
+    
+    /// Geofence: when non-empty, transactions must originate in one of these regions (or exact locations).
+    /// Locations are "Town, Region"; matching is case-insensitive.
+    pub allowed_regions: Vec<String>,
+    /// Under a geofence, reject transactions that carry no location instead of letting them through
+    pub require_transaction_location: bool,
+    
+    /// Privacy settings: store recipients as salted hashes instead of plaintext.
+    /// Hashing needs a salt that every instance sharing the storage agrees on.
+    pub hash_recipients: bool,
+    pub recipient_hash_salt: Option<String>,
+    
+    /// How long after a transaction it can still be reversed
+    pub reversal_window_hours: u32,
+    /// Transactions held for review longer than this are rejected by `expire_stale_transactions`;
+    /// `None` holds them until a reviewer acts
+    pub pending_expiry_hours: Option<u32>,
     
     /// Offline mode settings
     pub offline_transaction_limit: f64,
     pub offline_cache_duration_hours: u32,
+    pub max_pending_offline_transactions: usize,
     
     /// Performance optimizations for low-end devices
     pub enable_lightweight_encryption: bool,
+    /// Argon2 cost used for PIN hashing when lightweight encryption is enabled
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    /// Let a PIN set on an existing account whose primary device reports low-end capabilities
+    /// be hashed at the lighter cost, even when the account's stored hash is stronger
+    pub allow_pin_cost_downgrade: bool,
     pub cache_size_mb: u32,
     pub sync_interval_minutes: u32,
     
+    /// Reachability probe: `host:port` to connect to and how long to wait before calling the link limited
+    pub connectivity_probe_target: String,
+    pub connectivity_probe_timeout_ms: u64,
+    
+    /// Local timezone in minutes east of UTC (180 for Kenya, 330 for India);
+    /// daily limits roll over at local midnight
+    pub timezone_offset_minutes: i32,
+    
+    /// Text users the outcome of approved and rejected transactions; minimal mode turns this off
+    pub transaction_notifications: bool,
+    
     /// Rural-specific settings
     pub low_connectivity_mode: bool,
     pub simplified_interface: bool,
     pub local_currency: String,
+    /// Region (ISO 3166 alpha-2) assumed for phone numbers entered without a country code
+    pub default_phone_region: String,
+    
+    /// Mid-market rates: units of local currency per unit of each foreign currency
+    pub exchange_rates: HashMap<String, f64>,
+    /// Margin (percent) taken on top of the mid-market rate for conversions
+    pub fx_margin_percent: f64,
+    
+    /// Money rounding: decimal places of the minor unit and the tie-breaking rule
+    pub currency_decimal_places: u32,
+    pub rounding_mode: RoundingMode,
 }
 
 impl Default for SafeBankConfig {
@@ -45,22 +320,89 @@ impl Default for SafeBankConfig {
         Self {
             max_failed_attempts: 3,
             lockout_duration_minutes: 15,
+            lockout_backoff_multiplier: 2.0,
+            max_lockout_duration_minutes: 24 * 60,
+            auth_rate_limit_attempts: 10,
+            auth_rate_limit_window_seconds: 15 * 60,
             fraud_threshold_low: 0.3,
             fraud_threshold_medium: 0.6,
             fraud_threshold_high: 0.8,
             daily_transaction_limit: 10000.0, // Adjust based on local currency
+            trusted_daily_limit_ceiling: None,
             single_transaction_limit: 5000.0,
+            max_split_recipients: 10,
+            daily_transaction_count_limit: Some(100),
+            daily_per_recipient_limit: Some(5000.0),
+            transfer_confirmation_threshold: None,
+            max_memo_length: 80,
+            daily_limit_warning_percent: Some(80.0),
+            system_daily_volume_ceiling: Some(1_000_000.0),
+            levy_schedule: None,
+            fee_schedule: FeeSchedule::default(),
+            fees_count_toward_daily_limit: false,
             require_device_verification: true,
             enable_behavioral_analysis: true,
             pin_complexity_required: false, // Simplified for rural users
+            reject_weak_pins: true,
+            pin_history_size: 3,
+            pin_timing_step_up_threshold: Some(4.0),
+            require_otp: false,
+            otp_length: 6,
+            otp_ttl_seconds: 300,
+            otp_max_attempts: 3,
+            duress_transaction_cap: 100.0,
+            profile_ema_alpha: 0.2,
+            profile_decay_half_life_days: None,
+            profile_pattern_window_days: None,
+            profile_stale_after_days: Some(180),
+            learning_grace_transactions: 5,
+            learning_grace_days: 14,
+            learning_grace_damping: 0.25,
+            takeover_window_minutes: 60,
+            takeover_dimensions: 3,
+            trusted_recipient_after: Some(2),
+            approval_damping_hours: Some(24),
+            approval_damping: 0.5,
+            max_travel_speed_kmh: 300.0,
+            night_start_hour: 23,
+            night_end_hour: 6,
+            round_amount_modulus: 100.0,
+            round_amount_minimum: 1000.0,
+            device_anomaly_weight: 0.25,
+            risk_weights: RiskWeights::default(),
+            risk_factor_bounds: HashMap::new(),
+            terminal_risk_factors: Vec::new(),
+            fraud_explanation_max_reasons: 2,
+            fraud_rules: Vec::new(),
+            location_coordinates: HashMap::new(),
+            allowed_regions: Vec::new(),
+            require_transaction_location: false,
+            hash_recipients: false,
+            recipient_hash_salt: None, // Required once hash_recipients is enabled
+            reversal_window_hours: 72,
+            pending_expiry_hours: Some(72),
             offline_transaction_limit: 1000.0,
             offline_cache_duration_hours: 24,
+            max_pending_offline_transactions: 50,
             enable_lightweight_encryption: true,
+            argon2_memory_kib: 8192,
+            argon2_iterations: 2,
+            argon2_parallelism: 1,
+            allow_pin_cost_downgrade: false,
             cache_size_mb: 50, // Conservative for low-end devices
             sync_interval_minutes: 30,
+            connectivity_probe_target: crate::connectivity::DEFAULT_PROBE_TARGET.to_string(),
+            connectivity_probe_timeout_ms: crate::connectivity::DEFAULT_PROBE_TIMEOUT_MS,
+            timezone_offset_minutes: 0,
+            transaction_notifications: true,
             low_connectivity_mode: true,
             simplified_interface: true,
             local_currency: "USD".to_string(),
+            default_phone_region: "KE".to_string(),
+            exchange_rates: HashMap::new(),
+            fx_margin_percent: 1.5,
+            currency_decimal_places: 2,
+            rounding_mode: RoundingMode::HalfUp,
         }
     }
 }
@@ -71,25 +413,223 @@ impl SafeBankConfig {
         Self {
             max_failed_attempts: 3,
             lockout_duration_minutes: 10,
+            lockout_backoff_multiplier: 2.0,
+            max_lockout_duration_minutes: 12 * 60,
+            auth_rate_limit_attempts: 10,
+            auth_rate_limit_window_seconds: 15 * 60,
             fraud_threshold_low: 0.4,
             fraud_threshold_medium: 0.7,
             fraud_threshold_high: 0.9,
             daily_transaction_limit: 5000.0,
+            trusted_daily_limit_ceiling: None,
             single_transaction_limit: 2000.0,
+            max_split_recipients: 5,
+            daily_transaction_count_limit: Some(30),
+            daily_per_recipient_limit: Some(2000.0),
+            transfer_confirmation_threshold: None,
+            max_memo_length: 40,
+            daily_limit_warning_percent: Some(80.0),
+            system_daily_volume_ceiling: Some(250_000.0),
+            levy_schedule: None,
+            fee_schedule: FeeSchedule::default(),
+            fees_count_toward_daily_limit: false,
             require_device_verification: true,
             enable_behavioral_analysis: false, // Disable to save resources
             pin_complexity_required: false,
+            reject_weak_pins: true,
+            pin_history_size: 3,
+            pin_timing_step_up_threshold: Some(4.0),
+            require_otp: false,
+            otp_length: 4, // Easier to type on feature phones
+            otp_ttl_seconds: 600, // SMS delivery can be slow
+            otp_max_attempts: 3, // Short codes need a tight cap
+            duress_transaction_cap: 50.0,
+            profile_ema_alpha: 0.2,
+            profile_decay_half_life_days: None,
+            profile_pattern_window_days: None,
+            profile_stale_after_days: Some(180),
+            learning_grace_transactions: 5,
+            learning_grace_days: 14,
+            learning_grace_damping: 0.25,
+            takeover_window_minutes: 60,
+            takeover_dimensions: 3,
+            trusted_recipient_after: Some(2),
+            approval_damping_hours: Some(24),
+            approval_damping: 0.5,
+            max_travel_speed_kmh: 300.0,
+            night_start_hour: 23,
+            night_end_hour: 6,
+            round_amount_modulus: 100.0,
+            round_amount_minimum: 1000.0,
+            device_anomaly_weight: 0.25,
+            risk_weights: RiskWeights::default(),
+            risk_factor_bounds: HashMap::new(),
+            terminal_risk_factors: Vec::new(),
+            fraud_explanation_max_reasons: 2,
+            fraud_rules: Vec::new(),
+            location_coordinates: HashMap::new(),
+            allowed_regions: Vec::new(),
+            require_transaction_location: false,
+            hash_recipients: false,
+            recipient_hash_salt: None,
+            reversal_window_hours: 72,
+            pending_expiry_hours: Some(72),
             offline_transaction_limit: 500.0,
             offline_cache_duration_hours: 12,
+            max_pending_offline_transactions: 20,
             enable_lightweight_encryption: true,
+            argon2_memory_kib: 4096,
+            argon2_iterations: 2,
+            argon2_parallelism: 1,
+            allow_pin_cost_downgrade: false,
             cache_size_mb: 20,
             sync_interval_minutes: 60,
+            connectivity_probe_target: crate::connectivity::DEFAULT_PROBE_TARGET.to_string(),
+            connectivity_probe_timeout_ms: 1000, // Give up quickly rather than stall the UI
+            timezone_offset_minutes: 0,
+            transaction_notifications: false, // Every SMS costs airtime
             low_connectivity_mode: true,
             simplified_interface: true,
             local_currency: "USD".to_string(),
+            default_phone_region: "KE".to_string(),
+            exchange_rates: HashMap::new(),
+            fx_margin_percent: 1.5,
+            currency_decimal_places: 2,
+            rounding_mode: RoundingMode::HalfUp,
         }
     }
 
+    /// Builder starting from the default configuration
+    pub fn builder() -> SafeBankConfigBuilder {
+        SafeBankConfigBuilder::new()
+    }
+
+    /// Round a monetary amount to the configured minor unit using the configured rounding mode
+    pub fn round_money(&self, amount: f64) -> f64 {
+        crate::utils::round_money(amount, self.currency_decimal_places, self.rounding_mode)
+    }
+
+    /// Convert a decimal amount to minor units of the local currency
+    pub fn to_money(&self, amount: f64) -> Money {
+        Money::from_decimal(amount, self.currency_decimal_places, self.rounding_mode)
+    }
+
+    /// Convert minor units back to a decimal amount for the `f64` API boundary
+    pub fn from_money(&self, amount: Money) -> f64 {
+        amount.to_decimal(self.currency_decimal_places)
+    }
+
+    /// The banking day (local calendar date) a moment falls on
+    pub fn banking_day(&self, time: DateTime<Utc>) -> NaiveDate {
+        crate::utils::get_local_date(time, self.timezone_offset_minutes)
+    }
+
+    /// Local hour of day (0-23) a moment falls in
+    pub fn local_hour(&self, time: DateTime<Utc>) -> u8 {
+        self.user_local_hour(None, time)
+    }
+
+    /// Local hour of day for a user, whose own offset (if set) overrides the deployment's
+    pub fn user_local_hour(&self, user_offset_minutes: Option<i32>, time: DateTime<Utc>) -> u8 {
+        let offset = user_offset_minutes.unwrap_or(self.timezone_offset_minutes);
+        (time + Duration::minutes(offset as i64)).hour() as u8
+    }
+
+    /// Whether a local hour falls in the configured late-night window
+    pub fn is_night_hour(&self, hour: u8) -> bool {
+        if self.night_start_hour <= self.night_end_hour {
+            (self.night_start_hour..self.night_end_hour).contains(&hour)
+        } else {
+            hour >= self.night_start_hour || hour < self.night_end_hour
+        }
+    }
+
+    /// Whether an amount is large and round enough to be worth a small fraud bump
+    pub fn is_round_amount(&self, amount: f64) -> bool {
+        amount >= self.round_amount_minimum && amount % self.round_amount_modulus == 0.0
+    }
+
+    /// Whether the geofence admits a transaction from this location; always true without a geofence
+    pub fn is_location_allowed(&self, location: Option<&str>) -> bool {
+        if self.allowed_regions.is_empty() {
+            return true;
+        }
+        let Some(location) = location else {
+            return !self.require_transaction_location;
+        };
+
+        let normalize = |value: &str| value.trim().to_lowercase();
+        let location = normalize(location);
+        let region = normalize(location.rsplit(',').next().unwrap_or(&location));
+        self.allowed_regions.iter()
+            .map(|allowed| normalize(allowed))
+            .any(|allowed| allowed == region || allowed == location)
+    }
+
+    /// Coordinates configured for a location name in `location_coordinates`, compared case-insensitively
+    pub fn configured_coordinates(&self, location: &str) -> Option<(f64, f64)> {
+        let key = location.trim().to_lowercase();
+        self.location_coordinates
+            .iter()
+            .find(|(name, _)| name.trim().to_lowercase() == key)
+            .map(|(_, coordinates)| *coordinates)
+    }
+
+    /// How often to sync at the given connectivity, or `None` when syncing should not be attempted.
+    /// Limited links are used at half the frequency in low-connectivity mode and skipped otherwise.
+    pub fn sync_interval(&self, status: &ConnectivityStatus) -> Option<Duration> {
+        let interval = Duration::minutes(self.sync_interval_minutes as i64);
+        match status {
+            ConnectivityStatus::Online => Some(interval),
+            ConnectivityStatus::Limited if self.low_connectivity_mode => Some(interval * 2),
+            ConnectivityStatus::Limited | ConnectivityStatus::Offline => None,
+        }
+    }
+
+    /// Load configuration from a TOML or JSON file (chosen by extension, TOML otherwise) and validate it
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SafeBankError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| SafeBankError::ConfigError {
+                message: format!("Failed to read {}: {}", path.display(), e),
+            })?;
+
+        let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let config: SafeBankConfig = if is_json {
+            serde_json::from_str(&contents)
+                .map_err(|e| SafeBankError::ConfigError {
+                    message: format!("Invalid JSON in {}: {}", path.display(), e),
+                })?
+        } else {
+            toml::from_str(&contents)
+                .map_err(|e| SafeBankError::ConfigError {
+                    message: format!("Invalid TOML in {}: {}", path.display(), e),
+                })?
+        };
+
+        config.validate().map_err(|message| SafeBankError::ConfigError { message })?;
+        Ok(config)
+    }
+
+    /// Risk factor weights keyed by factor name, as taken by `utils::calculate_composite_risk_score`
+    pub fn risk_weight_map(&self) -> HashMap<String, f64> {
+        let weights = &self.risk_weights;
+        [
+            ("amount", weights.amount),
+            ("time", weights.time),
+            ("frequency", weights.frequency),
+            ("device", self.device_anomaly_weight),
+            ("recipient", weights.recipient),
+            ("location", weights.location),
+            ("travel", weights.travel),
+            ("limit", weights.limit),
+            ("pattern", weights.pattern),
+        ]
+        .into_iter()
+        .map(|(factor, weight)| (factor.to_string(), weight))
+        .collect()
+    }
+
     /// Validate configuration settings
     pub fn validate(&self) -> Result<(), String> {
         if self.fraud_threshold_low >= self.fraud_threshold_medium {
@@ -104,6 +644,202 @@ impl SafeBankConfig {
             return Err("Daily limit must be greater than or equal to single transaction limit".to_string());
         }
         
+        if self.trusted_daily_limit_ceiling.is_some_and(|ceiling| ceiling < self.daily_transaction_limit) {
+            return Err("Trusted daily limit ceiling must be at least the daily limit".to_string());
+        }
+
+        if self.hash_recipients && self.recipient_hash_salt.as_deref().is_none_or(|salt| salt.is_empty()) {
+            return Err("Recipient hashing requires a recipient hash salt".to_string());
+        }
+
+        if self.lockout_backoff_multiplier < 1.0 {
+            return Err("Lockout backoff multiplier must be at least 1".to_string());
+        }
+
+        if self.max_lockout_duration_minutes < self.lockout_duration_minutes {
+            return Err("Maximum lockout duration must be at least the base lockout duration".to_string());
+        }
+
+        if self.auth_rate_limit_attempts == 0 || self.auth_rate_limit_window_seconds == 0 {
+            return Err("Authentication rate limit attempts and window must be positive".to_string());
+        }
+
+        if !(4..=10).contains(&self.otp_length) {
+            return Err("OTP length must be between 4 and 10 digits".to_string());
+        }
+
+        // Every remembered PIN costs an Argon2 verification on each change
+        if self.pin_history_size > 24 {
+            return Err("PIN history size must be at most 24".to_string());
+        }
+        
+        if self.pin_timing_step_up_threshold.is_some_and(|threshold| threshold <= 0.0) {
+            return Err("PIN timing step-up threshold must be positive".to_string());
+        }
+        
+        if self.pending_expiry_hours == Some(0) {
+            return Err("Pending expiry must be at least one hour".to_string());
+        }
+        
+        if self.otp_ttl_seconds == 0 {
+            return Err("OTP lifetime must be positive".to_string());
+        }
+
+        if self.otp_max_attempts == 0 {
+            return Err("OTP attempts must be at least 1".to_string());
+        }
+
+        if self.max_split_recipients == 0 {
+            return Err("Split transfers must allow at least one recipient".to_string());
+        }
+        
+        if self.max_memo_length == 0 {
+            return Err("Maximum memo length must be positive".to_string());
+        }
+        
+        if self.daily_transaction_count_limit == Some(0) {
+            return Err("Daily transaction count limit must allow at least one transaction".to_string());
+        }
+        
+        if self.daily_per_recipient_limit.is_some_and(|limit| limit <= 0.0) {
+            return Err("Daily per-recipient limit must be positive".to_string());
+        }
+        
+        if self.transfer_confirmation_threshold.is_some_and(|threshold| threshold < 0.0) {
+            return Err("Transfer confirmation threshold must not be negative".to_string());
+        }
+        
+        if let Some(percent) = self.daily_limit_warning_percent {
+            if percent <= 0.0 || percent > 100.0 {
+                return Err("Daily limit warning percentage must be between 0 and 100".to_string());
+            }
+        }
+        
+        if let Some(levy) = &self.levy_schedule {
+            if levy.rate < 0.0 || levy.flat_amount < 0.0 {
+                return Err("Levy rate and flat amount must not be negative".to_string());
+            }
+        }
+        
+        let fees = &self.fee_schedule;
+        let rates = [fees.transfer_bps, fees.international_transfer_bps, fees.payment_bps, fees.withdrawal_bps, fees.deposit_bps];
+        if rates.iter().any(|bps| *bps > 10_000) {
+            return Err("Fee rates must not exceed 10000 basis points".to_string());
+        }
+        if fees.min_fee < 0.0 || fees.max_fee < fees.min_fee {
+            return Err("Fee caps must satisfy 0 <= min_fee <= max_fee".to_string());
+        }
+        
+        if self.fx_margin_percent < 0.0 || self.fx_margin_percent >= 100.0 {
+            return Err("FX margin must be between 0 and 100 percent".to_string());
+        }
+        
+        if self.exchange_rates.values().any(|rate| *rate <= 0.0) {
+            return Err("Exchange rates must be positive".to_string());
+        }
+        
+        if self.profile_ema_alpha <= 0.0 || self.profile_ema_alpha > 1.0 {
+            return Err("Profile EMA alpha must be in (0, 1]".to_string());
+        }
+        
+        if self.profile_decay_half_life_days.is_some_and(|days| days <= 0.0) {
+            return Err("Profile decay half-life must be positive".to_string());
+        }
+        
+        if self.profile_pattern_window_days == Some(0) || self.profile_stale_after_days == Some(0) {
+            return Err("Profile pattern window and staleness must be at least one day".to_string());
+        }
+        
+        if !(0.0..=1.0).contains(&self.learning_grace_damping) {
+            return Err("Learning grace damping must be between 0 and 1".to_string());
+        }
+        
+        if !(2..=5).contains(&self.takeover_dimensions) {
+            return Err("Takeover dimensions must be between 2 and 5".to_string());
+        }
+        
+        if self.trusted_recipient_after == Some(0) {
+            return Err("Trusted recipient promotion needs at least one completed payment".to_string());
+        }
+        
+        if self.approval_damping_hours == Some(0) || !(0.0..=1.0).contains(&self.approval_damping) {
+            return Err("Approval damping needs a window of at least one hour and a multiplier between 0 and 1".to_string());
+        }
+        
+        if self.max_travel_speed_kmh <= 0.0 {
+            return Err("Maximum travel speed must be positive".to_string());
+        }
+        
+        if self.night_start_hour > 23 || self.night_end_hour > 24 || self.night_start_hour == self.night_end_hour {
+            return Err("Night hours must be a non-empty window within 0-24".to_string());
+        }
+        
+        if self.round_amount_modulus <= 0.0 || self.round_amount_minimum < 0.0 {
+            return Err("Round amount modulus must be positive and its minimum not negative".to_string());
+        }
+        
+        if !(0.0..=1.0).contains(&self.device_anomaly_weight) {
+            return Err("Device anomaly weight must be between 0 and 1".to_string());
+        }
+        
+        let weights = self.risk_weight_map();
+        if weights.values().any(|weight| !(0.0..=1.0).contains(weight)) {
+            return Err("Risk factor weights must be between 0 and 1".to_string());
+        }
+        
+        for (factor, bounds) in &self.risk_factor_bounds {
+            if !weights.contains_key(factor) {
+                return Err(format!("Unknown risk factor '{}' in risk factor bounds", factor));
+            }
+            if !(0.0 <= bounds.min && bounds.min <= bounds.max && bounds.max <= 1.0) {
+                return Err(format!("Bounds for risk factor '{}' must satisfy 0 <= min <= max <= 1", factor));
+            }
+        }
+        
+        if let Some(factor) = self.terminal_risk_factors.iter().find(|factor| !weights.contains_key(*factor)) {
+            return Err(format!("Unknown terminal risk factor '{}'", factor));
+        }
+        
+        for rule in &self.fraud_rules {
+            rule.validate()?;
+        }
+        
+        if self.allowed_regions.iter().any(|region| region.trim().is_empty()) {
+            return Err("Allowed regions must not be blank".to_string());
+        }
+        
+        if self.location_coordinates.values().any(|(lat, lon)| lat.abs() > 90.0 || lon.abs() > 180.0) {
+            return Err("Location coordinates must be valid latitude/longitude".to_string());
+        }
+        
+        if self.timezone_offset_minutes.abs() > 14 * 60 {
+            return Err("Timezone offset must be within 14 hours of UTC".to_string());
+        }
+        
+        if self.connectivity_probe_target.trim().is_empty() {
+            return Err("Connectivity probe target must not be empty".to_string());
+        }
+        
+        if !(1..=10_000).contains(&self.connectivity_probe_timeout_ms) {
+            return Err("Connectivity probe timeout must be between 1 and 10000 ms".to_string());
+        }
+        
+        if self.currency_decimal_places > 6 {
+            return Err("Currency decimal places must be at most 6".to_string());
+        }
+        
+        if crate::utils::calling_code(&self.default_phone_region).is_none() {
+            return Err(format!("Unsupported default phone region: {}", self.default_phone_region));
+        }
+        
+        if self.argon2_iterations == 0 || !(1..=16).contains(&self.argon2_parallelism) {
+            return Err("Argon2 needs at least one iteration and 1-16 lanes".to_string());
+        }
+        
+        if self.argon2_memory_kib < 8 * self.argon2_parallelism {
+            return Err("Argon2 memory must be at least 8 KiB per lane".to_string());
+        }
+        
         if self.cache_size_mb == 0 {
             return Err("Cache size must be greater than 0".to_string());
         }
@@ -112,6 +848,129 @@ impl SafeBankConfig {
     }
 }
 
+/// Chainable construction of a `SafeBankConfig`, starting from `SafeBankConfig::default()`
+/// and validated on `build()`
+#[derive(Debug, Clone, Default)]
+pub struct SafeBankConfigBuilder {
+    config: SafeBankConfig,
+}
+
+macro_rules! builder_setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, value: $ty) -> Self {
+                self.config.$field = value;
+                self
+            }
+        )*
+    };
+}
+
+impl SafeBankConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from an existing configuration instead of the defaults
+    pub fn from_config(config: SafeBankConfig) -> Self {
+        Self { config }
+    }
+
+    builder_setters! {
+        max_failed_attempts: u32,
+        lockout_duration_minutes: u32,
+        lockout_backoff_multiplier: f64,
+        max_lockout_duration_minutes: u32,
+        auth_rate_limit_attempts: u32,
+        auth_rate_limit_window_seconds: u64,
+        fraud_threshold_low: f64,
+        fraud_threshold_medium: f64,
+        fraud_threshold_high: f64,
+        daily_transaction_limit: f64,
+        single_transaction_limit: f64,
+        trusted_daily_limit_ceiling: Option<f64>,
+        max_split_recipients: usize,
+        daily_transaction_count_limit: Option<u32>,
+        daily_per_recipient_limit: Option<f64>,
+        transfer_confirmation_threshold: Option<f64>,
+        max_memo_length: usize,
+        daily_limit_warning_percent: Option<f64>,
+        system_daily_volume_ceiling: Option<f64>,
+        levy_schedule: Option<LevySchedule>,
+        fee_schedule: FeeSchedule,
+        fees_count_toward_daily_limit: bool,
+        require_device_verification: bool,
+        enable_behavioral_analysis: bool,
+        pin_complexity_required: bool,
+        reject_weak_pins: bool,
+        pin_history_size: usize,
+        pin_timing_step_up_threshold: Option<f64>,
+        require_otp: bool,
+        otp_length: usize,
+        otp_ttl_seconds: u64,
+        otp_max_attempts: u32,
+        duress_transaction_cap: f64,
+        profile_ema_alpha: f64,
+        profile_decay_half_life_days: Option<f64>,
+        profile_pattern_window_days: Option<u32>,
+        profile_stale_after_days: Option<u32>,
+        learning_grace_transactions: u32,
+        learning_grace_days: u32,
+        learning_grace_damping: f64,
+        takeover_window_minutes: u32,
+        takeover_dimensions: usize,
+        trusted_recipient_after: Option<u32>,
+        approval_damping_hours: Option<u32>,
+        approval_damping: f64,
+        max_travel_speed_kmh: f64,
+        night_start_hour: u8,
+        night_end_hour: u8,
+        round_amount_modulus: f64,
+        round_amount_minimum: f64,
+        device_anomaly_weight: f64,
+        risk_weights: RiskWeights,
+        risk_factor_bounds: HashMap<String, FactorBounds>,
+        terminal_risk_factors: Vec<String>,
+        fraud_explanation_max_reasons: usize,
+        fraud_rules: Vec<FraudRule>,
+        location_coordinates: HashMap<String, (f64, f64)>,
+        allowed_regions: Vec<String>,
+        require_transaction_location: bool,
+        hash_recipients: bool,
+        recipient_hash_salt: Option<String>,
+        reversal_window_hours: u32,
+        pending_expiry_hours: Option<u32>,
+        offline_transaction_limit: f64,
+        offline_cache_duration_hours: u32,
+        max_pending_offline_transactions: usize,
+        enable_lightweight_encryption: bool,
+        argon2_memory_kib: u32,
+        argon2_iterations: u32,
+        argon2_parallelism: u32,
+        allow_pin_cost_downgrade: bool,
+        cache_size_mb: u32,
+        sync_interval_minutes: u32,
+        connectivity_probe_target: String,
+        connectivity_probe_timeout_ms: u64,
+        timezone_offset_minutes: i32,
+        transaction_notifications: bool,
+        low_connectivity_mode: bool,
+        simplified_interface: bool,
+        local_currency: String,
+        default_phone_region: String,
+        exchange_rates: HashMap<String, f64>,
+        fx_margin_percent: f64,
+        currency_decimal_places: u32,
+        rounding_mode: RoundingMode,
+    }
+
+    /// Validate and return the configuration
+    pub fn build(self) -> Result<SafeBankConfig, String> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,9 +989,87 @@ mod tests {
 
     #[test]
     fn test_invalid_thresholds() {
-        let mut config = SafeBankConfig::default();
-        config.fraud_threshold_low = 0.8;
-        config.fraud_threshold_medium = 0.5;
+        let config = SafeBankConfig {
+            fraud_threshold_low: 0.8,
+            fraud_threshold_medium: 0.5,
+            ..SafeBankConfig::default()
+        };
         assert!(config.validate().is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_recipient_hashing_requires_salt() {
+        let config = SafeBankConfig { hash_recipients: true, ..SafeBankConfig::default() };
+        assert!(config.validate().is_err());
+        let salted = SafeBankConfig { recipient_hash_salt: Some("branch-salt".to_string()), ..config };
+        assert!(salted.validate().is_ok());
+    }
+
+    #[test]
+    fn test_builder() {
+        let config = SafeBankConfig::builder()
+            .daily_transaction_limit(20_000.0)
+            .enable_behavioral_analysis(false)
+            .local_currency("KES".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(config.daily_transaction_limit, 20_000.0);
+        assert!(!config.enable_behavioral_analysis);
+        assert_eq!(config.local_currency, "KES");
+        assert_eq!(config.single_transaction_limit, SafeBankConfig::default().single_transaction_limit);
+
+        // Daily limit below the single-transaction limit is rejected
+        let invalid = SafeBankConfig::builder()
+            .daily_transaction_limit(100.0)
+            .build();
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_config_file_round_trip() {
+        let config = SafeBankConfig {
+            daily_transaction_limit: 7500.0,
+            local_currency: "KES".to_string(),
+            rounding_mode: RoundingMode::HalfEven,
+            levy_schedule: Some(LevySchedule {
+                rate: 0.015,
+                flat_amount: 0.0,
+                exempt_up_to: 100.0,
+                applies_to: vec![TransactionType::Transfer],
+            }),
+            exchange_rates: HashMap::from([("USD".to_string(), 129.5)]),
+            ..SafeBankConfig::minimal()
+        };
+
+        let dir = std::env::temp_dir().join(format!("safebank-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let toml_path = dir.join("bank.toml");
+        let json_path = dir.join("bank.json");
+        std::fs::write(&toml_path, toml::to_string(&config).unwrap()).unwrap();
+        std::fs::write(&json_path, serde_json::to_string(&config).unwrap()).unwrap();
+
+        for path in [&toml_path, &json_path] {
+            let loaded = SafeBankConfig::from_file(path).unwrap();
+            assert_eq!(loaded.daily_transaction_limit, 7500.0);
+            assert_eq!(loaded.local_currency, "KES");
+            assert_eq!(loaded.rounding_mode, RoundingMode::HalfEven);
+            assert_eq!(loaded.levy_schedule.unwrap().rate, 0.015);
+            assert_eq!(loaded.exchange_rates["USD"], 129.5);
+            assert_eq!(loaded.enable_behavioral_analysis, config.enable_behavioral_analysis);
+        }
+
+        // Parse failures and invalid values surface as configuration errors
+        std::fs::write(&toml_path, "daily_transaction_limit = \"lots\"").unwrap();
+        assert!(matches!(SafeBankConfig::from_file(&toml_path), Err(SafeBankError::ConfigError { .. })));
+        std::fs::write(&toml_path, "daily_transaction_limit = 10.0").unwrap();
+        assert!(matches!(SafeBankConfig::from_file(&toml_path), Err(SafeBankError::ConfigError { .. })));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_example_config_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("config/safebank.example.toml");
+        assert!(SafeBankConfig::from_file(path).is_ok());
+    }
+}
@@ -0,0 +1,104 @@
+//! Network reachability probing for SafeBank framework
+//! Decides between online and offline operation with a cheap, time-bounded check
+
+use std::fmt::Debug;
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use crate::utils::ConnectivityStatus;
+
+/// Default probe target: a public DNS resolver, given as an IP so no lookup is needed
+pub const DEFAULT_PROBE_TARGET: &str = "1.1.1.1:53";
+pub const DEFAULT_PROBE_TIMEOUT_MS: u64 = 1500;
+
+/// Source of the current connectivity status
+pub trait ConnectivityProbe: Debug + Send + Sync {
+    fn check(&self) -> ConnectivityStatus;
+}
+
+/// Shared probe handle used by the framework
+pub type SharedConnectivityProbe = Arc<dyn ConnectivityProbe>;
+
+/// Probe that opens (and immediately drops) a TCP connection to `host:port`
+#[derive(Debug, Clone)]
+pub struct TcpProbe {
+    target: String,
+    timeout: Duration,
+}
+
+impl TcpProbe {
+    pub fn new(target: impl Into<String>, timeout: Duration) -> Self {
+        Self { target: target.into(), timeout }
+    }
+}
+
+impl Default for TcpProbe {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROBE_TARGET, Duration::from_millis(DEFAULT_PROBE_TIMEOUT_MS))
+    }
+}
+
+impl ConnectivityProbe for TcpProbe {
+    fn check(&self) -> ConnectivityStatus {
+        status_from_result(connect(&self.target, self.timeout))
+    }
+}
+
+/// Probe that always reports the same status, for tests and forced offline operation
+#[derive(Debug, Clone)]
+pub struct FixedProbe(pub ConnectivityStatus);
+
+impl ConnectivityProbe for FixedProbe {
+    fn check(&self) -> ConnectivityStatus {
+        self.0.clone()
+    }
+}
+
+/// Map a probe attempt to a status: connected is online, a timeout means a
+/// network exists but is too slow to rely on, anything else is offline
+pub fn status_from_result(result: io::Result<()>) -> ConnectivityStatus {
+    match result {
+        Ok(()) => ConnectivityStatus::Online,
+        Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => ConnectivityStatus::Limited,
+        Err(_) => ConnectivityStatus::Offline,
+    }
+}
+
+fn connect(target: &str, timeout: Duration) -> io::Result<()> {
+    // Resolution failure (no DNS) is reported as an ordinary error, i.e. offline
+    let addr = target.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "probe target resolved to no addresses"))?;
+    TcpStream::connect_timeout(&addr, timeout).map(drop)
+}
+
+/// Default probe built from the configured target and timeout
+pub fn probe_from_config(config: &crate::config::SafeBankConfig) -> SharedConnectivityProbe {
+    Arc::new(TcpProbe::new(
+        config.connectivity_probe_target.clone(),
+        Duration::from_millis(config.connectivity_probe_timeout_ms),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_tcp_probe_against_local_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let probe = TcpProbe::new(addr.to_string(), Duration::from_millis(500));
+        assert_eq!(probe.check(), ConnectivityStatus::Online);
+
+        // Nothing listens once the listener is gone
+        drop(listener);
+        assert_eq!(probe.check(), ConnectivityStatus::Offline);
+        assert_eq!(TcpProbe::new("not a host", Duration::from_millis(100)).check(), ConnectivityStatus::Offline);
+
+        assert_eq!(status_from_result(Err(io::ErrorKind::TimedOut.into())), ConnectivityStatus::Limited);
+    }
+}
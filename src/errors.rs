@@ -1,5 +1,6 @@
 //! Error handling for SafeBank framework
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,21 +11,52 @@ pub enum SafeBankError {
     #[error("Account locked due to too many failed attempts")]
     AccountLocked,
     
+    #[error("Account frozen by an administrator")]
+    AccountFrozen,
+    
     #[error("User not found: {user_id}")]
     UserNotFound { user_id: String },
     
-    #[error("Invalid PIN format")]
-    InvalidPin,
+    #[error("Invalid PIN: {reason}")]
+    InvalidPin {
+        reason: String,
+        /// Which rule refused the PIN, choosing the message the user sees
+        rejection: PinRejection,
+    },
+    
+    #[error("One-time code required")]
+    OtpRequired,
+    
+    #[error("Invalid one-time code")]
+    InvalidOtp,
+    
+    #[error("One-time code expired")]
+    OtpExpired,
     
     #[error("Device not recognized: {device_id}")]
     UnrecognizedDevice { device_id: String },
     
+    #[error("Too many attempts; retry after {retry_after_seconds}s")]
+    RateLimited { retry_after_seconds: u64 },
+    
     #[error("Transaction rejected by fraud detection: score {fraud_score}")]
-    FraudDetected { fraud_score: f64 },
+    FraudDetected {
+        fraud_score: f64,
+        /// Main customer-safe reasons, most significant first; the score itself is never shown to users
+        reasons: Vec<FraudReason>,
+        /// Held for review rather than rejected outright
+        held: bool,
+    },
     
     #[error("Transaction limit exceeded: {amount} > {limit}")]
     TransactionLimitExceeded { amount: f64, limit: f64 },
     
+    #[error("Daily transaction count exceeded: {count} > {limit}")]
+    TransactionCountLimitExceeded { count: u32, limit: u32 },
+    
+    #[error("Transactions from {location} are not permitted")]
+    RegionNotAllowed { location: String },
+    
     #[error("Insufficient funds: balance {balance}, required {required}")]
     InsufficientFunds { balance: f64, required: f64 },
     
@@ -59,35 +91,229 @@ pub enum SafeBankError {
 impl SafeBankError {
     /// Convert error to user-friendly message appropriate for rural banking context
     pub fn to_user_message(&self) -> String {
+        self.to_user_message_localized("en")
+    }
+
+    /// User-friendly message in the requested language (same codes and names as
+    /// `utils::get_emergency_help_message`); unknown languages fall back to English
+    pub fn to_user_message_localized(&self, lang: &str) -> String {
+        use Language::*;
+        let language = Language::from_code(lang);
+
         match self {
-            SafeBankError::AuthenticationFailed { .. } => {
-                "Invalid phone number or PIN. Please try again.".to_string()
-            }
-            SafeBankError::AccountLocked => {
-                "Account temporarily locked for security. Please try again later.".to_string()
-            }
-            SafeBankError::InvalidPin => {
-                "PIN must be 4-6 digits. Please enter a valid PIN.".to_string()
-            }
-            SafeBankError::FraudDetected { .. } => {
-                "Transaction flagged for security review. Please contact support.".to_string()
-            }
-            SafeBankError::TransactionLimitExceeded { limit, .. } => {
-                format!("Transaction exceeds daily limit of ${:.2}", limit)
-            }
-            SafeBankError::InsufficientFunds { balance, .. } => {
-                format!("Insufficient balance. Available: ${:.2}", balance)
+            SafeBankError::AuthenticationFailed { .. } => match language {
+                English => "Invalid phone number or PIN. Please try again.",
+                Swahili => "Nambari ya simu au PIN si sahihi. Tafadhali jaribu tena.",
+                French => "Numéro de téléphone ou code PIN invalide. Veuillez réessayer.",
+                Spanish => "Número de teléfono o PIN no válido. Por favor, inténtelo de nuevo.",
+                Portuguese => "Número de telefone ou PIN inválido. Por favor, tente novamente.",
+            }.to_string(),
+            SafeBankError::AccountLocked => match language {
+                English => "Account temporarily locked for security. Please try again later.",
+                Swahili => "Akaunti imefungwa kwa muda kwa usalama. Tafadhali jaribu tena baadaye.",
+                French => "Compte temporairement bloqué par sécurité. Veuillez réessayer plus tard.",
+                Spanish => "Cuenta bloqueada temporalmente por seguridad. Inténtelo más tarde.",
+                Portuguese => "Conta bloqueada temporariamente por segurança. Tente novamente mais tarde.",
+            }.to_string(),
+            SafeBankError::AccountFrozen => match language {
+                English => "Your account has been frozen. Please contact support.",
+                Swahili => "Akaunti yako imesimamishwa. Tafadhali wasiliana na huduma kwa wateja.",
+                French => "Votre compte a été gelé. Veuillez contacter le support.",
+                Spanish => "Su cuenta ha sido congelada. Contacte con soporte.",
+                Portuguese => "A sua conta foi congelada. Contacte o apoio ao cliente.",
+            }.to_string(),
+            SafeBankError::InvalidPin { rejection, .. } => rejection.message(language).to_string(),
+            SafeBankError::OtpRequired => match language {
+                English => "Enter the code sent to your phone to continue.",
+                Swahili => "Weka nambari ya siri iliyotumwa kwenye simu yako ili kuendelea.",
+                French => "Saisissez le code envoyé sur votre téléphone pour continuer.",
+                Spanish => "Introduzca el código enviado a su teléfono para continuar.",
+                Portuguese => "Introduza o código enviado para o seu telefone para continuar.",
+            }.to_string(),
+            SafeBankError::InvalidOtp => match language {
+                English => "The code you entered is incorrect. Please try again.",
+                Swahili => "Nambari uliyoweka si sahihi. Tafadhali jaribu tena.",
+                French => "Le code saisi est incorrect. Veuillez réessayer.",
+                Spanish => "El código introducido es incorrecto. Inténtelo de nuevo.",
+                Portuguese => "O código introduzido está incorreto. Tente novamente.",
+            }.to_string(),
+            SafeBankError::OtpExpired => match language {
+                English => "Your code has expired. Please request a new one.",
+                Swahili => "Nambari yako imeisha muda. Tafadhali omba nyingine.",
+                French => "Votre code a expiré. Veuillez en demander un nouveau.",
+                Spanish => "Su código ha caducado. Solicite uno nuevo.",
+                Portuguese => "O seu código expirou. Solicite um novo.",
+            }.to_string(),
+            SafeBankError::RateLimited { retry_after_seconds } => {
+                let minutes = retry_after_seconds.div_ceil(60).max(1);
+                match language {
+                    English => format!("Too many attempts from this device. Please wait {} minute(s) and try again.", minutes),
+                    Swahili => format!("Majaribio mengi mno kutoka kifaa hiki. Tafadhali subiri dakika {} ujaribu tena.", minutes),
+                    French => format!("Trop de tentatives depuis cet appareil. Veuillez patienter {} minute(s) et réessayer.", minutes),
+                    Spanish => format!("Demasiados intentos desde este dispositivo. Espere {} minuto(s) e inténtelo de nuevo.", minutes),
+                    Portuguese => format!("Demasiadas tentativas a partir deste dispositivo. Aguarde {} minuto(s) e tente novamente.", minutes),
+                }
             }
-            SafeBankError::NetworkError { .. } => {
-                "Network connection issue. Please check your connection and try again.".to_string()
+            SafeBankError::FraudDetected { reasons, held: false, .. } if !reasons.is_empty() => {
+                let explanation = FraudReason::join(reasons, language);
+                match language {
+                    English => format!("This transaction {}. It has been declined for your security; please contact support.", explanation),
+                    Swahili => format!("Muamala huu {}. Umekataliwa kwa usalama wako; tafadhali wasiliana na huduma kwa wateja.", explanation),
+                    French => format!("Cette transaction {}. Elle a été refusée pour votre sécurité ; veuillez contacter le support.", explanation),
+                    Spanish => format!("Esta transacción {}. Se ha rechazado por su seguridad; contacte con soporte.", explanation),
+                    Portuguese => format!("Esta transação {}. Foi recusada para sua segurança; contacte o apoio ao cliente.", explanation),
+                }
             }
-            SafeBankError::OfflineModeRestriction => {
-                "This operation requires internet connection.".to_string()
+            SafeBankError::FraudDetected { reasons, .. } if !reasons.is_empty() => {
+                let explanation = FraudReason::join(reasons, language);
+                match language {
+                    English => format!("This transaction {}. It has been held for security review; please contact support.", explanation),
+                    Swahili => format!("Muamala huu {}. Umewekwa kwa ukaguzi wa usalama; tafadhali wasiliana na huduma kwa wateja.", explanation),
+                    French => format!("Cette transaction {}. Elle a été retenue pour un contrôle de sécurité ; veuillez contacter le support.", explanation),
+                    Spanish => format!("Esta transacción {}. Se ha retenido para revisión de seguridad; contacte con soporte.", explanation),
+                    Portuguese => format!("Esta transação {}. Foi retida para revisão de segurança; contacte o apoio ao cliente.", explanation),
+                }
             }
-            SafeBankError::TimeoutError { .. } => {
-                "Operation timed out. Please try again.".to_string()
-            }
-            _ => "An error occurred. Please try again or contact support.".to_string(),
+            SafeBankError::FraudDetected { held: false, .. } => match language {
+                English => "Transaction declined for security reasons. Please contact support.",
+                Swahili => "Muamala umekataliwa kwa sababu za usalama. Tafadhali wasiliana na huduma kwa wateja.",
+                French => "Transaction refusée pour des raisons de sécurité. Veuillez contacter le support.",
+                Spanish => "Transacción rechazada por motivos de seguridad. Contacte con soporte.",
+                Portuguese => "Transação recusada por motivos de segurança. Contacte o apoio ao cliente.",
+            }.to_string(),
+            SafeBankError::FraudDetected { .. } => match language {
+                English => "Transaction flagged for security review. Please contact support.",
+                Swahili => "Muamala umewekwa kwa ukaguzi wa usalama. Tafadhali wasiliana na huduma kwa wateja.",
+                French => "Transaction signalée pour un contrôle de sécurité. Veuillez contacter le support.",
+                Spanish => "Transacción marcada para revisión de seguridad. Contacte con soporte.",
+                Portuguese => "Transação sinalizada para revisão de segurança. Contacte o apoio ao cliente.",
+            }.to_string(),
+            SafeBankError::TransactionLimitExceeded { limit, .. } => match language {
+                English => format!("Transaction exceeds daily limit of ${:.2}", limit),
+                Swahili => format!("Muamala unazidi kikomo cha siku cha ${:.2}", limit),
+                French => format!("La transaction dépasse la limite quotidienne de ${:.2}", limit),
+                Spanish => format!("La transacción supera el límite diario de ${:.2}", limit),
+                Portuguese => format!("A transação excede o limite diário de ${:.2}", limit),
+            },
+            SafeBankError::TransactionCountLimitExceeded { limit, .. } => match language {
+                English => format!("You have reached the limit of {} transactions today. Please try again tomorrow.", limit),
+                Swahili => format!("Umefikia kikomo cha miamala {} leo. Tafadhali jaribu tena kesho.", limit),
+                French => format!("Vous avez atteint la limite de {} transactions aujourd'hui. Veuillez réessayer demain.", limit),
+                Spanish => format!("Ha alcanzado el límite de {} transacciones hoy. Inténtelo de nuevo mañana.", limit),
+                Portuguese => format!("Atingiu o limite de {} transações hoje. Tente novamente amanhã.", limit),
+            },
+            SafeBankError::RegionNotAllowed { .. } => match language {
+                English => "Transactions are not available in your current area. Please contact support.",
+                Swahili => "Miamala haipatikani katika eneo lako la sasa. Tafadhali wasiliana na huduma kwa wateja.",
+                French => "Les transactions ne sont pas disponibles dans votre zone actuelle. Veuillez contacter le support.",
+                Spanish => "Las transacciones no están disponibles en su zona actual. Contacte con soporte.",
+                Portuguese => "As transações não estão disponíveis na sua zona atual. Contacte o apoio ao cliente.",
+            }.to_string(),
+            SafeBankError::InsufficientFunds { balance, .. } => match language {
+                English => format!("Insufficient balance. Available: ${:.2}", balance),
+                Swahili => format!("Salio halitoshi. Kilichopo: ${:.2}", balance),
+                French => format!("Solde insuffisant. Disponible : ${:.2}", balance),
+                Spanish => format!("Saldo insuficiente. Disponible: ${:.2}", balance),
+                Portuguese => format!("Saldo insuficiente. Disponível: ${:.2}", balance),
+            },
+            SafeBankError::NetworkError { .. } => match language {
+                English => "Network connection issue. Please check your connection and try again.",
+                Swahili => "Tatizo la mtandao. Tafadhali angalia muunganisho wako na ujaribu tena.",
+                French => "Problème de connexion réseau. Vérifiez votre connexion et réessayez.",
+                Spanish => "Problema de conexión de red. Compruebe su conexión e inténtelo de nuevo.",
+                Portuguese => "Problema de ligação à rede. Verifique a sua ligação e tente novamente.",
+            }.to_string(),
+            SafeBankError::ResourceLimitExceeded { resource } => match language {
+                English => format!("Limit reached for {}. Please connect and sync before continuing.", resource),
+                Swahili => format!("Kikomo kimefikiwa kwa {}. Tafadhali unganisha na usawazishe kabla ya kuendelea.", resource),
+                French => format!("Limite atteinte pour {}. Connectez-vous et synchronisez avant de continuer.", resource),
+                Spanish => format!("Límite alcanzado para {}. Conéctese y sincronice antes de continuar.", resource),
+                Portuguese => format!("Limite atingido para {}. Ligue-se e sincronize antes de continuar.", resource),
+            },
+            SafeBankError::OfflineModeRestriction => match language {
+                English => "This operation requires internet connection.",
+                Swahili => "Huduma hii inahitaji muunganisho wa intaneti.",
+                French => "Cette opération nécessite une connexion Internet.",
+                Spanish => "Esta operación requiere conexión a internet.",
+                Portuguese => "Esta operação requer ligação à internet.",
+            }.to_string(),
+            SafeBankError::TimeoutError { .. } => match language {
+                English => "Operation timed out. Please try again.",
+                Swahili => "Muda wa huduma umeisha. Tafadhali jaribu tena.",
+                French => "L'opération a expiré. Veuillez réessayer.",
+                Spanish => "La operación ha excedido el tiempo de espera. Inténtelo de nuevo.",
+                Portuguese => "A operação excedeu o tempo limite. Tente novamente.",
+            }.to_string(),
+            _ => match language {
+                English => "An error occurred. Please try again or contact support.",
+                Swahili => "Hitilafu imetokea. Tafadhali jaribu tena au wasiliana na huduma kwa wateja.",
+                French => "Une erreur s'est produite. Veuillez réessayer ou contacter le support.",
+                Spanish => "Se ha producido un error. Inténtelo de nuevo o contacte con soporte.",
+                Portuguese => "Ocorreu um erro. Tente novamente ou contacte o apoio ao cliente.",
+            }.to_string(),
+        }
+    }
+
+    /// Stable machine-readable code for clients to branch on.
+    /// Codes are part of the public API: existing codes never change or get reused.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SafeBankError::AuthenticationFailed { .. } => "AUTH_FAILED",
+            SafeBankError::AccountLocked => "ACCOUNT_LOCKED",
+            SafeBankError::AccountFrozen => "ACCOUNT_FROZEN",
+            SafeBankError::UserNotFound { .. } => "USER_NOT_FOUND",
+            SafeBankError::InvalidPin { .. } => "INVALID_PIN",
+            SafeBankError::OtpRequired => "OTP_REQUIRED",
+            SafeBankError::InvalidOtp => "OTP_INVALID",
+            SafeBankError::OtpExpired => "OTP_EXPIRED",
+            SafeBankError::UnrecognizedDevice { .. } => "DEVICE_UNRECOGNIZED",
+            SafeBankError::RateLimited { .. } => "RATE_LIMITED",
+            SafeBankError::FraudDetected { .. } => "FRAUD_DETECTED",
+            SafeBankError::TransactionLimitExceeded { .. } => "TXN_LIMIT",
+            SafeBankError::TransactionCountLimitExceeded { .. } => "TXN_COUNT_LIMIT",
+            SafeBankError::RegionNotAllowed { .. } => "REGION_NOT_ALLOWED",
+            SafeBankError::InsufficientFunds { .. } => "INSUFFICIENT_FUNDS",
+            SafeBankError::NetworkError { .. } => "NETWORK_ERROR",
+            SafeBankError::SerializationError { .. } => "SERIALIZATION_ERROR",
+            SafeBankError::ConfigError { .. } => "CONFIG_ERROR",
+            SafeBankError::CryptographyError { .. } => "CRYPTO_ERROR",
+            SafeBankError::StorageError { .. } => "STORAGE_ERROR",
+            SafeBankError::ResourceLimitExceeded { .. } => "RESOURCE_LIMIT",
+            SafeBankError::OfflineModeRestriction => "OFFLINE_RESTRICTED",
+            SafeBankError::TimeoutError { .. } => "TIMEOUT",
+            SafeBankError::InvalidTransactionState { .. } => "TXN_INVALID_STATE",
+        }
+    }
+
+    /// Stable numeric form of `code()`, grouped by area:
+    /// 1xxx authentication, 2xxx transactions and fraud, 3xxx connectivity, 4xxx internal.
+    /// Like `code()`, numbers never change or get reused.
+    pub fn numeric_code(&self) -> u16 {
+        match self {
+            SafeBankError::AuthenticationFailed { .. } => 1001,
+            SafeBankError::AccountLocked => 1002,
+            SafeBankError::UserNotFound { .. } => 1003,
+            SafeBankError::InvalidPin { .. } => 1004,
+            SafeBankError::OtpRequired => 1005,
+            SafeBankError::InvalidOtp => 1006,
+            SafeBankError::OtpExpired => 1007,
+            SafeBankError::UnrecognizedDevice { .. } => 1008,
+            SafeBankError::RateLimited { .. } => 1009,
+            SafeBankError::AccountFrozen => 1010,
+            SafeBankError::FraudDetected { .. } => 2001,
+            SafeBankError::TransactionLimitExceeded { .. } => 2002,
+            SafeBankError::InsufficientFunds { .. } => 2003,
+            SafeBankError::InvalidTransactionState { .. } => 2004,
+            SafeBankError::TransactionCountLimitExceeded { .. } => 2005,
+            SafeBankError::RegionNotAllowed { .. } => 2006,
+            SafeBankError::NetworkError { .. } => 3001,
+            SafeBankError::OfflineModeRestriction => 3002,
+            SafeBankError::TimeoutError { .. } => 3003,
+            SafeBankError::ResourceLimitExceeded { .. } => 3004,
+            SafeBankError::SerializationError { .. } => 4001,
+            SafeBankError::ConfigError { .. } => 4002,
+            SafeBankError::CryptographyError { .. } => 4003,
+            SafeBankError::StorageError { .. } => 4004,
         }
     }
 
@@ -96,11 +322,17 @@ impl SafeBankError {
         match self {
             SafeBankError::NetworkError { .. } 
             | SafeBankError::TimeoutError { .. }
-            | SafeBankError::AuthenticationFailed { .. } => true,
+            | SafeBankError::AuthenticationFailed { .. }
+            | SafeBankError::InvalidOtp
+            | SafeBankError::OtpExpired
+            | SafeBankError::RateLimited { .. } => true,
             
             SafeBankError::AccountLocked 
+            | SafeBankError::AccountFrozen
             | SafeBankError::FraudDetected { .. }
             | SafeBankError::TransactionLimitExceeded { .. }
+            | SafeBankError::TransactionCountLimitExceeded { .. }
+            | SafeBankError::RegionNotAllowed { .. }
             | SafeBankError::InsufficientFunds { .. } => false,
             
             _ => false,
@@ -114,10 +346,14 @@ impl SafeBankError {
             | SafeBankError::CryptographyError { .. } => ErrorSeverity::Critical,
             
             SafeBankError::AccountLocked 
+            | SafeBankError::AccountFrozen
             | SafeBankError::TransactionLimitExceeded { .. }
+            | SafeBankError::TransactionCountLimitExceeded { .. }
+            | SafeBankError::RegionNotAllowed { .. }
             | SafeBankError::InsufficientFunds { .. } => ErrorSeverity::High,
             
             SafeBankError::AuthenticationFailed { .. }
+            | SafeBankError::RateLimited { .. }
             | SafeBankError::NetworkError { .. } => ErrorSeverity::Medium,
             
             _ => ErrorSeverity::Low,
@@ -125,7 +361,175 @@ impl SafeBankError {
     }
 }
 
+/// Customer-safe reason a transaction was stopped, phrased without revealing scores or operator rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FraudReason {
+    UnusualAmount,
+    NewRecipient,
+    UnusualTime,
+    UnfamiliarLocation,
+    UnfamiliarDevice,
+    UnusualActivity,
+}
+
+impl FraudReason {
+    /// Predicate completing "This transaction ..."
+    fn phrase(&self, language: Language) -> &'static str {
+        use Language::*;
+        match self {
+            FraudReason::UnusualAmount => match language {
+                English => "is larger than your usual amount",
+                Swahili => "ni kubwa kuliko kiasi chako cha kawaida",
+                French => "est plus élevée que votre montant habituel",
+                Spanish => "es mayor que su importe habitual",
+                Portuguese => "é superior ao seu montante habitual",
+            },
+            FraudReason::NewRecipient => match language {
+                English => "goes to a new recipient",
+                Swahili => "inaenda kwa mpokeaji mpya",
+                French => "est destinée à un nouveau bénéficiaire",
+                Spanish => "va a un nuevo destinatario",
+                Portuguese => "destina-se a um novo destinatário",
+            },
+            FraudReason::UnusualTime => match language {
+                English => "was made at an unusual time for you",
+                Swahili => "imefanywa wakati usio wa kawaida kwako",
+                French => "a lieu à une heure inhabituelle pour vous",
+                Spanish => "se realiza a una hora inusual para usted",
+                Portuguese => "é feita a uma hora invulgar para si",
+            },
+            FraudReason::UnfamiliarLocation => match language {
+                English => "comes from an unfamiliar location",
+                Swahili => "inatoka eneo lisilo la kawaida",
+                French => "provient d'un lieu inhabituel",
+                Spanish => "procede de una ubicación desconocida",
+                Portuguese => "provém de um local desconhecido",
+            },
+            FraudReason::UnfamiliarDevice => match language {
+                English => "comes from an unfamiliar device",
+                Swahili => "inatoka kwenye kifaa kisichojulikana",
+                French => "provient d'un appareil inconnu",
+                Spanish => "procede de un dispositivo desconocido",
+                Portuguese => "provém de um dispositivo desconhecido",
+            },
+            FraudReason::UnusualActivity => match language {
+                English => "follows an unusual burst of activity",
+                Swahili => "inafuata shughuli nyingi zisizo za kawaida",
+                French => "suit une activité inhabituellement intense",
+                Spanish => "sigue a una actividad inusualmente intensa",
+                Portuguese => "segue-se a uma atividade invulgarmente intensa",
+            },
+        }
+    }
+
+    /// "a, b and c" in the given language
+    fn join(reasons: &[FraudReason], language: Language) -> String {
+        let conjunction = match language {
+            Language::English => "and",
+            Language::Swahili => "na",
+            Language::French => "et",
+            Language::Spanish => "y",
+            Language::Portuguese => "e",
+        };
+        let phrases: Vec<&str> = reasons.iter().map(|reason| reason.phrase(language)).collect();
+        match phrases.split_last() {
+            Some((last, [])) => last.to_string(),
+            Some((last, rest)) => format!("{} {} {}", rest.join(", "), conjunction, last),
+            None => String::new(),
+        }
+    }
+}
+
+/// Why a PIN was refused, so the user is told which rule to satisfy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PinRejection {
+    /// Not 4-6 digits
+    Format,
+    /// Shorter than the 6 digits required when PIN complexity is on
+    TooShort,
+    /// Easy to guess: one repeated digit, a short pattern, a run, a year or a common choice
+    Weak,
+    /// The same as the PIN it replaces
+    Unchanged,
+    /// The same as the user's other PIN, regular or duress
+    MatchesOtherPin,
+    /// One of the user's recent PINs
+    RecentlyUsed,
+}
+
+impl PinRejection {
+    fn message(&self, language: Language) -> &'static str {
+        use Language::*;
+        match self {
+            PinRejection::Format => match language {
+                English => "PIN must be 4-6 digits. Please enter a valid PIN.",
+                Swahili => "PIN lazima iwe na tarakimu 4-6. Tafadhali weka PIN sahihi.",
+                French => "Le code PIN doit comporter 4 à 6 chiffres. Veuillez saisir un code PIN valide.",
+                Spanish => "El PIN debe tener de 4 a 6 dígitos. Introduzca un PIN válido.",
+                Portuguese => "O PIN deve ter de 4 a 6 dígitos. Introduza um PIN válido.",
+            },
+            PinRejection::TooShort => match language {
+                English => "PIN must be at least 6 digits. Please choose a longer PIN.",
+                Swahili => "PIN lazima iwe na angalau tarakimu 6. Tafadhali chagua PIN ndefu zaidi.",
+                French => "Le code PIN doit comporter au moins 6 chiffres. Veuillez en choisir un plus long.",
+                Spanish => "El PIN debe tener al menos 6 dígitos. Elija un PIN más largo.",
+                Portuguese => "O PIN deve ter pelo menos 6 dígitos. Escolha um PIN mais longo.",
+            },
+            PinRejection::Weak => match language {
+                English => "This PIN is too easy to guess. Please avoid repeated digits, sequences and years.",
+                Swahili => "PIN hii ni rahisi sana kukisia. Tafadhali epuka tarakimu zinazojirudia, mfuatano na miaka.",
+                French => "Ce code PIN est trop facile à deviner. Évitez les chiffres répétés, les suites et les années.",
+                Spanish => "Este PIN es demasiado fácil de adivinar. Evite dígitos repetidos, secuencias y años.",
+                Portuguese => "Este PIN é demasiado fácil de adivinhar. Evite dígitos repetidos, sequências e anos.",
+            },
+            PinRejection::Unchanged => match language {
+                English => "Your new PIN must be different from your current PIN.",
+                Swahili => "PIN yako mpya lazima iwe tofauti na PIN yako ya sasa.",
+                French => "Votre nouveau code PIN doit être différent de l'actuel.",
+                Spanish => "Su nuevo PIN debe ser distinto del actual.",
+                Portuguese => "O seu novo PIN deve ser diferente do atual.",
+            },
+            PinRejection::MatchesOtherPin => match language {
+                English => "Your PIN and your duress PIN must be different.",
+                Swahili => "PIN yako na PIN yako ya dharura lazima ziwe tofauti.",
+                French => "Votre code PIN et votre code PIN de contrainte doivent être différents.",
+                Spanish => "Su PIN y su PIN de coacción deben ser distintos.",
+                Portuguese => "O seu PIN e o seu PIN de coação devem ser diferentes.",
+            },
+            PinRejection::RecentlyUsed => match language {
+                English => "You have used this PIN recently. Please choose a new one.",
+                Swahili => "Umetumia PIN hii hivi karibuni. Tafadhali chagua nyingine.",
+                French => "Vous avez utilisé ce code PIN récemment. Veuillez en choisir un nouveau.",
+                Spanish => "Ha usado este PIN recientemente. Elija uno nuevo.",
+                Portuguese => "Usou este PIN recentemente. Escolha um novo.",
+            },
+        }
+    }
+}
+
+/// Languages with translated user messages
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    English,
+    Swahili,
+    French,
+    Spanish,
+    Portuguese,
+}
+
+impl Language {
+    fn from_code(lang: &str) -> Self {
+        match lang.to_lowercase().as_str() {
+            "swahili" | "sw" => Language::Swahili,
+            "french" | "fr" => Language::French,
+            "spanish" | "es" => Language::Spanish,
+            "portuguese" | "pt" => Language::Portuguese,
+            _ => Language::English,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ErrorSeverity {
     Low,
     Medium,
@@ -142,7 +546,7 @@ mod tests {
 
     #[test]
     fn test_error_user_messages() {
-        let error = SafeBankError::InvalidPin;
+        let error = SafeBankError::InvalidPin { reason: "PIN must be 4-6 digits".to_string(), rejection: PinRejection::Format };
         assert!(error.to_user_message().contains("PIN must be"));
         
         let error = SafeBankError::AccountLocked;
@@ -154,10 +558,73 @@ mod tests {
 
     #[test]
     fn test_error_severity() {
-        let fraud_error = SafeBankError::FraudDetected { fraud_score: 0.9 };
+        let fraud_error = SafeBankError::FraudDetected { fraud_score: 0.9, reasons: vec![], held: false };
         assert_eq!(fraud_error.severity(), ErrorSeverity::Critical);
         
         let auth_error = SafeBankError::AuthenticationFailed { message: "test".to_string() };
         assert_eq!(auth_error.severity(), ErrorSeverity::Medium);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_error_codes_are_unique() {
+        let message = || "test".to_string();
+        let errors = vec![
+            SafeBankError::AuthenticationFailed { message: message() },
+            SafeBankError::AccountLocked,
+            SafeBankError::AccountFrozen,
+            SafeBankError::UserNotFound { user_id: message() },
+            SafeBankError::InvalidPin { reason: "PIN must be 4-6 digits".to_string(), rejection: PinRejection::Format },
+            SafeBankError::OtpRequired,
+            SafeBankError::InvalidOtp,
+            SafeBankError::OtpExpired,
+            SafeBankError::UnrecognizedDevice { device_id: message() },
+            SafeBankError::RateLimited { retry_after_seconds: 60 },
+            SafeBankError::FraudDetected { fraud_score: 0.9, reasons: vec![], held: false },
+            SafeBankError::TransactionLimitExceeded { amount: 2.0, limit: 1.0 },
+            SafeBankError::TransactionCountLimitExceeded { count: 2, limit: 1 },
+            SafeBankError::RegionNotAllowed { location: message() },
+            SafeBankError::InsufficientFunds { balance: 1.0, required: 2.0 },
+            SafeBankError::NetworkError { message: message() },
+            SafeBankError::SerializationError { message: message() },
+            SafeBankError::ConfigError { message: message() },
+            SafeBankError::CryptographyError { message: message() },
+            SafeBankError::StorageError { message: message() },
+            SafeBankError::ResourceLimitExceeded { resource: message() },
+            SafeBankError::OfflineModeRestriction,
+            SafeBankError::TimeoutError { operation: message() },
+            SafeBankError::InvalidTransactionState { current_state: message() },
+        ];
+
+        let codes: std::collections::HashSet<_> = errors.iter().map(|e| e.code()).collect();
+        let numeric: std::collections::HashSet<_> = errors.iter().map(|e| e.numeric_code()).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert_eq!(numeric.len(), errors.len());
+        assert!(codes.iter().all(|code| !code.is_empty()));
+        assert_eq!(SafeBankError::AccountLocked.code(), "ACCOUNT_LOCKED");
+    }
+
+    #[test]
+    fn test_localized_user_messages() {
+        for error in [SafeBankError::InvalidPin { reason: "PIN must be 4-6 digits".to_string(), rejection: PinRejection::Format }, SafeBankError::AccountLocked] {
+            let english = error.to_user_message();
+            assert_eq!(error.to_user_message_localized("en"), english);
+            assert_ne!(error.to_user_message_localized("sw"), english);
+            assert_ne!(error.to_user_message_localized("French"), english);
+            // Unknown languages fall back to English
+            assert_eq!(error.to_user_message_localized("xx"), english);
+        }
+        assert!(SafeBankError::InvalidPin { reason: "PIN must be 4-6 digits".to_string(), rejection: PinRejection::Format }.to_user_message_localized("sw").contains("tarakimu 4-6"));
+    }
+
+    #[test]
+    fn test_pin_rejections_explain_their_rule() {
+        let message = |rejection| SafeBankError::InvalidPin { reason: "test".to_string(), rejection }.to_user_message();
+        assert!(message(PinRejection::Format).contains("4-6 digits"));
+        assert!(message(PinRejection::TooShort).contains("at least 6 digits"));
+        assert!(message(PinRejection::Weak).contains("too easy to guess"));
+        assert!(message(PinRejection::Unchanged).contains("different from your current PIN"));
+        assert!(message(PinRejection::MatchesOtherPin).contains("duress PIN"));
+        assert!(message(PinRejection::RecentlyUsed).contains("recently"));
+        assert!(!message(PinRejection::Weak).contains("4-6"));
+    }
+}
@@ -0,0 +1,42 @@
+//! Security escalations for SafeBank framework
+//! Records events that need attention from bank staff rather than the user
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::ErrorSeverity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EscalationKind {
+    /// User authenticated with their duress PIN
+    DuressAuthentication,
+    /// A transaction was silently held because the user is under duress
+    DuressTransactionHeld,
+    /// Combined daily volume across all users exceeded the configured ceiling
+    SystemVolumeCeiling,
+}
+
+/// A security event raised for operator review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Escalation {
+    pub escalation_id: Uuid,
+    pub kind: EscalationKind,
+    pub severity: ErrorSeverity,
+    pub user_id: Option<Uuid>,
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+}
+
+impl Escalation {
+    pub fn new(kind: EscalationKind, severity: ErrorSeverity, user_id: Option<Uuid>, message: String, raised_at: DateTime<Utc>) -> Self {
+        Self {
+            escalation_id: Uuid::new_v4(),
+            kind,
+            severity,
+            user_id,
+            message,
+            raised_at,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Transaction lifecycle events for SafeBank framework
+//! Lets integrators react to transactions (SMS, push notifications) without polling
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Transaction, TransactionStatus, logging, utils};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionEventKind {
+    /// The transaction was recorded, whatever its outcome
+    Created,
+    Approved,
+    /// Held for review (requires approval or flagged)
+    Flagged,
+    Rejected,
+    /// A reversal was recorded; the event carries the reversing transaction
+    Reversed,
+}
+
+/// A lifecycle event delivered to registered callbacks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionEvent {
+    pub kind: TransactionEventKind,
+    pub transaction: Transaction,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl TransactionEvent {
+    pub fn new(kind: TransactionEventKind, transaction: Transaction, occurred_at: DateTime<Utc>) -> Self {
+        Self { kind, transaction, occurred_at }
+    }
+
+    /// The outcome event for a committed transaction's status, if it has one
+    pub fn outcome_kind(status: &TransactionStatus) -> Option<TransactionEventKind> {
+        match status {
+            TransactionStatus::Approved => Some(TransactionEventKind::Approved),
+            TransactionStatus::Flagged | TransactionStatus::RequiresApproval => Some(TransactionEventKind::Flagged),
+            TransactionStatus::Rejected => Some(TransactionEventKind::Rejected),
+            TransactionStatus::Pending | TransactionStatus::RequiresStepUp => None,
+        }
+    }
+
+    /// Customer SMS for the event, using `utils::format_transaction_sms`; `Created` has none
+    pub fn to_sms(&self, currency: &str) -> Option<String> {
+        let status = match self.kind {
+            TransactionEventKind::Created => return None,
+            TransactionEventKind::Approved => "approved",
+            TransactionEventKind::Flagged => "pending",
+            TransactionEventKind::Rejected => "rejected",
+            TransactionEventKind::Reversed => "reversed",
+        };
+        let reference = self.transaction.transaction_id.simple().to_string()[..8].to_uppercase();
+        Some(utils::format_transaction_sms(self.transaction.amount, &self.transaction.recipient, status, &reference, currency, self.transaction.memo.as_deref()))
+    }
+}
+
+/// Callback invoked for every transaction event
+pub type TransactionEventHandler = Box<dyn Fn(&TransactionEvent) + Send + Sync>;
+
+/// Registered callbacks; a panicking callback is contained so it cannot break the core flow
+#[derive(Default)]
+pub struct EventHooks {
+    handlers: Vec<TransactionEventHandler>,
+}
+
+impl fmt::Debug for EventHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventHooks").field("handlers", &self.handlers.len()).finish()
+    }
+}
+
+impl EventHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, handler: TransactionEventHandler) {
+        self.handlers.push(handler);
+    }
+
+    /// Deliver an event to every callback in registration order
+    pub fn emit(&self, event: &TransactionEvent) {
+        for handler in &self.handlers {
+            if panic::catch_unwind(AssertUnwindSafe(|| handler(event))).is_err() {
+                logging::warn_event!(kind = ?event.kind, transaction_id = %event.transaction.transaction_id, "transaction event callback panicked");
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
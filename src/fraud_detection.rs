@@ -1,21 +1,170 @@
 //! Fraud detection module for SafeBank framework
 //! Implements behavioral pattern analysis and anomaly detection optimized for rural banking
 
-use std::collections::HashMap;
-use chrono::Timelike;
+use std::collections::{HashMap, HashSet, VecDeque};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    Transaction, UserProfile, BehavioralProfile,
-    config::SafeBankConfig, errors::Result
+    Transaction, TransactionType, UserProfile, BehavioralProfile, GeoLocation, logging,
+    clock::{self, SharedClock}, config::SafeBankConfig, errors::{FraudReason, Result, SafeBankError},
+    rules::{self, RuleAction, RuleContext}, storage::{self, SharedStorage}, transaction, utils
 };
 
+/// Below this many learned transactions the amount check falls back to deviation ratios
+const MIN_AMOUNT_SAMPLES: usize = 5;
+
+/// Number of most recent labels the true/false-positive rates are computed over
+const LABEL_WINDOW: usize = 500;
+
+/// Per-user cap on remembered transaction timestamps, bounding memory on low-end devices
+const MAX_RECENT_TIMESTAMPS: usize = 256;
+
+/// Observed-to-expected rate at which a burst starts to count as a frequency anomaly
+const FREQUENCY_SPIKE_RATIO: f64 = 3.0;
+
+/// Built-in coordinates (latitude, longitude) for common towns, keyed by lowercase name
+const KNOWN_TOWNS: &[(&str, f64, f64)] = &[
+    ("nairobi", -1.2921, 36.8219),
+    ("mombasa", -4.0435, 39.6682),
+    ("kisumu", -0.0917, 34.7680),
+    ("nakuru", -0.3031, 36.0800),
+    ("eldoret", 0.5143, 35.2698),
+    ("naivasha", -0.7167, 36.4333),
+    ("kampala", 0.3476, 32.5825),
+    ("dar es salaam", -6.7924, 39.2083),
+    ("lagos", 6.5244, 3.3792),
+    ("accra", 5.6037, -0.1870),
+];
+
+/// A dimension has shifted when its factor scores at least this
+const DRIFT_MIN_SCORE: f64 = 0.3;
+
+/// Times of a user's recent transactions and the dimensions each shifted, oldest first
+type DriftHistory = VecDeque<(DateTime<Utc>, Vec<RiskFactorType>)>;
+
+/// Places closer than this to somewhere the user usually transacts aren't anomalous (km)
+const NEARBY_KM: f64 = 25.0;
+
+/// Distance at which a location anomaly reaches its full score (km)
+const FAR_KM: f64 = 1000.0;
+
+/// Amounts within this fraction of a reviewer-approved one count as the same pattern
+const APPROVED_AMOUNT_TOLERANCE: f64 = 0.1;
+
+/// Recipients per user whose payments count towards promotion; beyond this the least recently paid drop out
+const MAX_COUNTED_RECIPIENTS: usize = 50;
+
+/// Completed payments to recipients not yet promoted, and when each was last paid, kept per user in storage
+type RecipientPayments = HashMap<String, (u32, DateTime<Utc>)>;
+
+fn recipient_payments_key(user_id: Uuid) -> String {
+    format!("recipient_payments/{}", user_id)
+}
+
+/// What confirming a transaction as fraud took from its recipient, so relabelling it restores that
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecipientDemotion {
+    user_id: Uuid,
+    recipient: String,
+    was_usual: bool,
+    was_allowed: bool,
+}
+
+fn recipient_demotion_key(transaction_id: Uuid) -> String {
+    format!("recipient_demotion/{}", transaction_id)
+}
+
+/// A transaction a reviewer approved, which damps the score of near-identical ones for a while
+#[derive(Debug, Clone)]
+struct ApprovedPattern {
+    recipient: String, // normalized like the recipient lists
+    amount: f64,
+    approved_at: DateTime<Utc>,
+}
+
 #[derive(Debug)]
 pub struct FraudDetector {
     config: SafeBankConfig,
-    user_profiles: HashMap<Uuid, BehavioralProfile>,
+    storage: SharedStorage,
+    profile_updated_at: HashMap<Uuid, DateTime<Utc>>, // user_id -> last profile refresh
+    last_located: HashMap<Uuid, (DateTime<Utc>, GeoLocation)>, // user_id -> (time, place) of last located transaction
+    recency_weights: HashMap<Uuid, RecencyWeights>, // user_id -> decayed frequencies for incremental learning
+    recent_activity: HashMap<Uuid, VecDeque<DateTime<Utc>>>, // user_id -> transaction times in the last day, oldest first
+    recent_drift: HashMap<Uuid, DriftHistory>, // user_id -> shifted dimensions of recent transactions
+    recipient_list: RecipientList,
+    approved_patterns: HashMap<Uuid, Vec<ApprovedPattern>>, // user_id -> reviewer approvals within the damping window
+    scores: HashMap<Uuid, f64>, // transaction_id -> score it was given, for judging labels
+    labels: Vec<FraudLabel>, // confirmed outcomes, oldest first, one per transaction
     fraud_statistics: FraudStatistics,
+    clock: SharedClock,
+}
+
+/// Recipients known to be fraudulent (for everyone) and recipients each user has vouched for.
+/// Names are matched case-insensitively, ignoring surrounding whitespace.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecipientList {
+    blocklist: HashSet<String>,
+    allowlists: HashMap<Uuid, HashSet<String>>, // user_id -> allowed recipients
+}
+
+impl RecipientList {
+    fn normalize(recipient: &str) -> String {
+        recipient.trim().to_lowercase()
+    }
+
+    pub fn is_blocked(&self, recipient: &str) -> bool {
+        self.blocklist.contains(&Self::normalize(recipient))
+    }
+
+    pub fn is_allowed(&self, user_id: Uuid, recipient: &str) -> bool {
+        self.allowlists
+            .get(&user_id)
+            .is_some_and(|allowed| allowed.contains(&Self::normalize(recipient)))
+    }
+}
+
+/// Exponentially decayed frequencies behind a profile's hour, recipient and location lists
+#[derive(Debug, Clone, Default)]
+struct RecencyWeights {
+    hours: HashMap<u8, f64>,
+    recipients: HashMap<String, f64>,
+    locations: HashMap<String, f64>,
+}
+
+impl RecencyWeights {
+    /// Weights below this are forgotten to keep the maps small
+    const MIN_WEIGHT: f64 = 0.01;
+
+    /// Start from a batch-learned profile, sharing weight equally within each list
+    fn seeded_from(profile: &BehavioralProfile) -> Self {
+        fn equal_shares<K: Clone + Eq + std::hash::Hash>(items: &[K]) -> HashMap<K, f64> {
+            let share = 1.0 / items.len().max(1) as f64;
+            items.iter().map(|item| (item.clone(), share)).collect()
+        }
+        Self {
+            hours: equal_shares(&profile.typical_transaction_times),
+            recipients: equal_shares(&profile.common_recipients),
+            locations: equal_shares(&profile.geographic_patterns),
+        }
+    }
+
+    /// Decay every weight by `1 - alpha` and credit `alpha` to the observed key
+    fn observe<K: Eq + std::hash::Hash>(weights: &mut HashMap<K, f64>, key: K, alpha: f64) {
+        for weight in weights.values_mut() {
+            *weight *= 1.0 - alpha;
+        }
+        *weights.entry(key).or_insert(0.0) += alpha;
+        weights.retain(|_, weight| *weight >= Self::MIN_WEIGHT);
+    }
+
+    /// The `n` most heavily weighted keys
+    fn top<K: Clone>(weights: &HashMap<K, f64>, n: usize) -> Vec<K> {
+        let mut entries: Vec<(&K, &f64)> = weights.iter().collect();
+        entries.sort_by(|a, b| b.1.total_cmp(a.1));
+        entries.into_iter().take(n).map(|(key, _)| key.clone()).collect()
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -23,10 +172,25 @@ pub struct FraudStatistics {
     pub total_transactions_analyzed: u64,
     pub transactions_flagged: u64,
     pub transactions_blocked: u64,
+    /// Share of confirmed-legitimate transactions that had been flagged, over recent labels
     pub false_positive_rate: f64,
+    /// Share of confirmed-fraud transactions that had been flagged, over recent labels
+    pub true_positive_rate: f64,
     pub fraud_detected: u64,
 }
 
+/// Operator-confirmed outcome for an analyzed transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FraudLabel {
+    pub transaction_id: Uuid,
+    pub is_fraud: bool,
+    /// Score the detector gave the transaction, if it analyzed it
+    pub fraud_score: Option<f64>,
+    /// Whether that score was above the flagging threshold
+    pub was_flagged: Option<bool>,
+    pub labeled_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FraudAnalysisResult {
     pub fraud_score: f64,
@@ -41,18 +205,72 @@ pub struct RiskFactor {
     pub description: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RiskFactorType {
     AmountAnomaly,
     TimeAnomaly,
     FrequencyAnomaly,
     RecipientAnomaly,
     LocationAnomaly,
+    /// The user could not have travelled here since their last located transaction
+    TravelAnomaly,
+    /// The amount is at or near the single transaction limit
+    LimitAnomaly,
     DeviceAnomaly,
     BehaviorPattern,
+    /// A market-tuned heuristic (late night, large round amount) matched
+    SuspiciousPattern,
+    /// An operator-defined fraud rule matched
+    CustomRule,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl RiskFactorType {
+    /// The reason to give the customer, if this kind of factor can be explained to them.
+    /// Operator rules and pattern heuristics stay undisclosed.
+    pub fn customer_reason(&self) -> Option<FraudReason> {
+        match self {
+            RiskFactorType::AmountAnomaly | RiskFactorType::LimitAnomaly => Some(FraudReason::UnusualAmount),
+            RiskFactorType::RecipientAnomaly => Some(FraudReason::NewRecipient),
+            RiskFactorType::TimeAnomaly => Some(FraudReason::UnusualTime),
+            RiskFactorType::LocationAnomaly | RiskFactorType::TravelAnomaly => Some(FraudReason::UnfamiliarLocation),
+            RiskFactorType::DeviceAnomaly => Some(FraudReason::UnfamiliarDevice),
+            RiskFactorType::FrequencyAnomaly => Some(FraudReason::UnusualActivity),
+            RiskFactorType::BehaviorPattern | RiskFactorType::SuspiciousPattern | RiskFactorType::CustomRule => None,
+        }
+    }
+
+    /// The behavioral dimension this factor is evidence of drift in, when looking for an account takeover
+    fn drift_dimension(&self) -> Option<RiskFactorType> {
+        match self {
+            RiskFactorType::AmountAnomaly | RiskFactorType::LimitAnomaly => Some(RiskFactorType::AmountAnomaly),
+            RiskFactorType::TimeAnomaly => Some(RiskFactorType::TimeAnomaly),
+            RiskFactorType::RecipientAnomaly => Some(RiskFactorType::RecipientAnomaly),
+            RiskFactorType::LocationAnomaly | RiskFactorType::TravelAnomaly => Some(RiskFactorType::LocationAnomaly),
+            RiskFactorType::DeviceAnomaly => Some(RiskFactorType::DeviceAnomaly),
+            RiskFactorType::FrequencyAnomaly
+            | RiskFactorType::BehaviorPattern
+            | RiskFactorType::SuspiciousPattern
+            | RiskFactorType::CustomRule => None,
+        }
+    }
+}
+
+/// A `FraudDetected` error explaining the strongest explainable risk factors, at most `max_reasons` of them.
+/// `held` says whether the transaction awaits review or was rejected outright, which decides the wording.
+pub fn fraud_error(fraud_score: f64, risk_factors: &[RiskFactor], max_reasons: usize, held: bool) -> SafeBankError {
+    let mut ranked: Vec<&RiskFactor> = risk_factors.iter().collect();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut reasons = Vec::new();
+    for reason in ranked.iter().filter_map(|factor| factor.factor_type.customer_reason()) {
+        if reasons.len() < max_reasons && !reasons.contains(&reason) {
+            reasons.push(reason);
+        }
+    }
+    SafeBankError::FraudDetected { fraud_score, reasons, held }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FraudRecommendation {
     Approve,
     Flag,
@@ -60,30 +278,174 @@ pub enum FraudRecommendation {
     RequireAdditionalAuth,
 }
 
+impl FraudRecommendation {
+    /// Ordering used when escalating: approve < step-up < flag < block
+    fn severity(&self) -> u8 {
+        match self {
+            FraudRecommendation::Approve => 0,
+            FraudRecommendation::RequireAdditionalAuth => 1,
+            FraudRecommendation::Flag => 2,
+            FraudRecommendation::Block => 3,
+        }
+    }
+}
+
 impl FraudDetector {
     pub fn new(config: &SafeBankConfig) -> Self {
+        Self::with_clock(config, clock::system_clock())
+    }
+
+    /// Create a fraud detector driven by the given clock
+    pub fn with_clock(config: &SafeBankConfig, clock: SharedClock) -> Self {
+        Self::with_storage(config, storage::in_memory_storage(), clock)
+    }
+
+    /// Create a fraud detector that persists learned profiles in the given storage backend
+    pub fn with_storage(config: &SafeBankConfig, storage: SharedStorage, clock: SharedClock) -> Self {
         Self {
             config: config.clone(),
-            user_profiles: HashMap::new(),
+            storage,
+            profile_updated_at: HashMap::new(),
+            last_located: HashMap::new(),
+            recency_weights: HashMap::new(),
+            recent_activity: HashMap::new(),
+            recent_drift: HashMap::new(),
+            recipient_list: RecipientList::default(),
+            approved_patterns: HashMap::new(),
+            scores: HashMap::new(),
+            labels: Vec::new(),
             fraud_statistics: FraudStatistics::default(),
+            clock,
         }
     }
 
     /// Analyze a transaction for fraud indicators
     pub fn analyze_transaction(&mut self, transaction: &Transaction, user: &UserProfile) -> Result<f64> {
-        if !self.config.enable_behavioral_analysis {
-            // Simple rule-based detection for minimal resource usage
-            return Ok(self.simple_fraud_detection(transaction));
+        Ok(self.analyze_transaction_detailed(transaction, user)?.fraud_score)
+    }
+
+    /// Analyze a transaction and explain the score with its contributing risk factors
+    pub fn analyze_transaction_detailed(&mut self, transaction: &Transaction, user: &UserProfile) -> Result<FraudAnalysisResult> {
+        let _span = logging::enter_span!("fraud_analysis", transaction_id = %transaction.transaction_id);
+
+        let result = self.score_transaction(transaction, user)?;
+        self.record_analysis(transaction, &result);
+        Ok(result)
+    }
+
+    /// Score a transaction without recording it: statistics, recent activity and
+    /// location history are left untouched, so the same call can be repeated freely
+    pub fn simulate_transaction(&self, transaction: &Transaction, user: &UserProfile) -> Result<FraudAnalysisResult> {
+        self.score_transaction(transaction, user)
+    }
+
+    fn score_transaction(&self, transaction: &Transaction, user: &UserProfile) -> Result<FraudAnalysisResult> {
+        // Known-fraudulent recipients are blocked outright, whatever the user's history
+        if self.recipient_list.is_blocked(&transaction.recipient) {
+            let factor = RiskFactor {
+                factor_type: RiskFactorType::RecipientAnomaly,
+                score: 1.0,
+                description: format!("Recipient {} is on the fraud blocklist", transaction.recipient),
+            };
+            return Ok(self.build_result(1.0, vec![factor]));
         }
 
-        self.fraud_statistics.total_transactions_analyzed += 1;
+        let learned_profile = if self.config.enable_behavioral_analysis {
+            self.storage.load_behavioral_profile(transaction.user_id)?
+        } else {
+            None
+        };
+        let behavioral_profile = learned_profile.as_ref().unwrap_or(&user.behavioral_profile);
+
+        let (score, mut risk_factors, terminal) = if self.config.enable_behavioral_analysis {
+            self.statistical_score(transaction, user, behavioral_profile)
+        } else {
+            // Simple rule-based detection for minimal resource usage. Terminal factors are policy
+            // rather than scoring, so they are still checked when the deployment names any.
+            let risk_factors = self.simple_fraud_detection(transaction);
+            let terminal = !self.config.terminal_risk_factors.is_empty()
+                && self.statistical_score(transaction, user, behavioral_profile).2;
+            (risk_factors.iter().map(|factor| factor.score).sum(), risk_factors, terminal)
+        };
+
+        let takeover = if self.config.enable_behavioral_analysis {
+            self.takeover_factor(transaction, &risk_factors)
+        } else {
+            None
+        };
+        let escalation = takeover.map(|(factor, escalation)| {
+            risk_factors.push(factor);
+            escalation
+        });
+
+        let mut result = self.apply_rules(transaction, user, behavioral_profile, score, risk_factors);
+        // Terminal factors block outright, overriding the score and any operator rule
+        if terminal {
+            result.recommendation = FraudRecommendation::Block;
+        }
+        // Several behaviors shifting at once escalate whatever the individual factors added up to
+        if let Some(escalation) = escalation.filter(|escalation| escalation.severity() > result.recommendation.severity()) {
+            result.recommendation = escalation;
+        }
+        Ok(result)
+    }
+
+    /// Account takeover signal: how many behavioral dimensions have shifted across this transaction and
+    /// the user's others within `takeover_window_minutes`, scored against `takeover_dimensions`.
+    /// Returns the factor and what it escalates to once at least two dimensions shift together.
+    fn takeover_factor(&self, transaction: &Transaction, risk_factors: &[RiskFactor]) -> Option<(RiskFactor, FraudRecommendation)> {
+        let window_start = transaction.timestamp - Duration::minutes(self.config.takeover_window_minutes as i64);
+        let mut shifted = Self::drifted_dimensions(risk_factors);
+        let recent = self.recent_drift.get(&transaction.user_id).into_iter().flatten()
+            .filter(|(time, _)| *time >= window_start)
+            .flat_map(|(_, dimensions)| dimensions);
+        for dimension in recent {
+            if !shifted.contains(dimension) {
+                shifted.push(dimension.clone());
+            }
+        }
+        if shifted.len() < 2 {
+            return None;
+        }
+
+        let needed = self.config.takeover_dimensions;
+        let escalation = if shifted.len() >= needed {
+            FraudRecommendation::Block
+        } else if shifted.len() + 1 == needed {
+            FraudRecommendation::RequireAdditionalAuth
+        } else {
+            FraudRecommendation::Approve
+        };
+        let factor = RiskFactor {
+            factor_type: RiskFactorType::BehaviorPattern,
+            score: (shifted.len() as f64 / needed as f64).min(1.0),
+            description: format!("Possible account takeover: {} behaviors changed at once", shifted.len()),
+        };
+        Some((factor, escalation))
+    }
 
-        let behavioral_profile = self.user_profiles
-            .get(&transaction.user_id)
-            .unwrap_or(&user.behavioral_profile);
+    /// Behavioral dimensions whose factors show a clear shift from the user's profile
+    fn drifted_dimensions(risk_factors: &[RiskFactor]) -> Vec<RiskFactorType> {
+        let mut dimensions = Vec::new();
+        for factor in risk_factors.iter().filter(|factor| factor.score >= DRIFT_MIN_SCORE) {
+            if let Some(dimension) = factor.factor_type.drift_dimension() {
+                if !dimensions.contains(&dimension) {
+                    dimensions.push(dimension);
+                }
+            }
+        }
+        dimensions
+    }
 
+    /// Weighted behavioral anomaly score, normalized to 0-1, and whether a terminal factor is present
+    fn statistical_score(&self, transaction: &Transaction, user: &UserProfile, behavioral_profile: &BehavioralProfile) -> (f64, Vec<RiskFactor>, bool) {
         let mut risk_factors = Vec::new();
-        let mut total_score = 0.0;
+        // Weighted per-factor scores, keyed by the names in `SafeBankConfig::risk_weight_map`
+        let mut factors = HashMap::new();
+        // Deviations from a long-unrefreshed or still-forming profile are weaker evidence
+        let confidence = self.profile_confidence(behavioral_profile) * self.learning_damping(user);
+        // A reviewer's approval vouches for the amount and recipient, not for the device or location
+        let approval = self.approval_damping(transaction);
 
         // Analyze amount anomaly
         let amount_score = self.analyze_amount_anomaly(transaction, behavioral_profile);
@@ -93,29 +455,30 @@ impl FraudDetector {
                 score: amount_score,
                 description: format!("Transaction amount ${:.2} deviates from typical pattern", transaction.amount),
             });
-            total_score += amount_score * 0.3; // Weight: 30%
+            factors.insert("amount".to_string(), amount_score * confidence * approval);
         }
 
         // Analyze time anomaly
-        let time_score = self.analyze_time_anomaly(transaction, behavioral_profile);
+        let time_score = self.analyze_time_anomaly(transaction, user, behavioral_profile);
         if time_score > 0.0 {
             risk_factors.push(RiskFactor {
                 factor_type: RiskFactorType::TimeAnomaly,
                 score: time_score,
                 description: "Transaction time unusual for user".to_string(),
             });
-            total_score += time_score * 0.2; // Weight: 20%
+            factors.insert("time".to_string(), time_score * confidence);
         }
 
-        // Analyze frequency anomaly
-        let frequency_score = self.analyze_frequency_anomaly(transaction, behavioral_profile);
-        if frequency_score > 0.0 {
-            risk_factors.push(RiskFactor {
-                factor_type: RiskFactorType::FrequencyAnomaly,
-                score: frequency_score,
-                description: "Unusual transaction frequency detected".to_string(),
-            });
-            total_score += frequency_score * 0.25; // Weight: 25%
+        // Analyze frequency anomaly against the user's recent activity
+        if let Some(frequency_factor) = self.analyze_frequency_anomaly(transaction, behavioral_profile) {
+            factors.insert("frequency".to_string(), frequency_factor.score);
+            risk_factors.push(frequency_factor);
+        }
+
+        // Analyze the device the transaction came from
+        if let Some(device_factor) = self.analyze_device_anomaly(transaction, user) {
+            factors.insert("device".to_string(), device_factor.score);
+            risk_factors.push(device_factor);
         }
 
         // Analyze recipient anomaly
@@ -126,27 +489,186 @@ impl FraudDetector {
                 score: recipient_score,
                 description: "Transaction to new or unusual recipient".to_string(),
             });
-            total_score += recipient_score * 0.15; // Weight: 15%
+            factors.insert("recipient".to_string(), recipient_score * confidence * approval);
+        }
+
+        // Analyze location anomaly
+        let location_score = self.analyze_location_anomaly(transaction, behavioral_profile);
+        if location_score > 0.0 {
+            risk_factors.push(RiskFactor {
+                factor_type: RiskFactorType::LocationAnomaly,
+                score: location_score,
+                description: format!(
+                    "Transaction from unfamiliar location {}",
+                    transaction.location.as_deref().unwrap_or_default()
+                ),
+            });
+            factors.insert("location".to_string(), location_score * confidence);
+        }
+
+        // Analyze travel velocity since the last located transaction
+        if let Some(travel_factor) = self.analyze_travel_velocity(transaction) {
+            factors.insert("travel".to_string(), travel_factor.score);
+            risk_factors.push(travel_factor);
         }
 
         // Check transaction limits
         let limit_score = self.check_transaction_limits(transaction);
         if limit_score > 0.0 {
-            total_score += limit_score * 0.1; // Weight: 10%
+            risk_factors.push(RiskFactor {
+                factor_type: RiskFactorType::LimitAnomaly,
+                score: limit_score,
+                description: "Transaction amount at or above the single transaction limit".to_string(),
+            });
+            factors.insert("limit".to_string(), limit_score);
+        }
+
+        // Late-night and round-number heuristics, only when the deployment weights them
+        let patterns = if self.config.risk_weights.pattern > 0.0 { self.pattern_factors(transaction) } else { Vec::new() };
+        if !patterns.is_empty() {
+            factors.insert("pattern".to_string(), patterns.iter().map(|factor| factor.score).sum());
+            risk_factors.extend(patterns);
+        }
+
+        let score = utils::calculate_bounded_risk_score(&factors, &self.config.risk_weight_map(), &self.config.risk_factor_bounds);
+        let terminal = self.config.terminal_risk_factors.iter()
+            .any(|factor| factors.get(factor).is_some_and(|score| *score > 0.0));
+        (score, risk_factors, terminal)
+    }
+
+    /// A user's own timezone offset, if they have one on record
+    fn user_timezone(&self, user_id: Uuid) -> Option<i32> {
+        self.storage.load_user_by_id(user_id).ok().flatten().and_then(|user| user.timezone_offset_minutes)
+    }
+
+    /// 1.0 for a recently learned profile, 0.5 once it is older than `profile_stale_after_days`
+    fn profile_confidence(&self, profile: &BehavioralProfile) -> f64 {
+        let stale = match (profile.last_updated, self.config.profile_stale_after_days) {
+            (Some(updated), Some(days)) => self.clock.now() - updated > Duration::days(days as i64),
+            _ => false,
+        };
+        if stale { 0.5 } else { 1.0 }
+    }
+
+    /// `learning_grace_damping` while a new user's habits are still being learned, 1.0 after.
+    /// Only behavioral anomalies are damped; limits, device and velocity checks apply in full.
+    /// History is the running clean-transaction count every commit path keeps in storage.
+    fn learning_damping(&self, user: &UserProfile) -> f64 {
+        let learning = self.clock.now() - user.created_at < Duration::days(self.config.learning_grace_days as i64)
+            && transaction::clean_transaction_count(self.storage.as_ref(), user) < self.config.learning_grace_transactions as usize;
+        if learning { self.config.learning_grace_damping } else { 1.0 }
+    }
+
+    /// `approval_damping` for a transaction matching one a reviewer approved within
+    /// `approval_damping_hours`, 1.0 otherwise. Blocklisted recipients are rejected
+    /// before scoring, so an approval never softens their block.
+    fn approval_damping(&self, transaction: &Transaction) -> f64 {
+        let Some(hours) = self.config.approval_damping_hours else {
+            return 1.0;
+        };
+        let cutoff = self.clock.now() - Duration::hours(hours as i64);
+        let recipient = RecipientList::normalize(&transaction.recipient);
+        let matches = self.approved_patterns.get(&transaction.user_id).is_some_and(|patterns| {
+            patterns.iter().any(|pattern| {
+                pattern.approved_at > cutoff
+                    && pattern.recipient == recipient
+                    && (transaction.amount - pattern.amount).abs() <= pattern.amount * APPROVED_AMOUNT_TOLERANCE
+            })
+        });
+        if matches { self.config.approval_damping } else { 1.0 }
+    }
+
+    /// Apply the operator's fraud rules on top of the statistical score
+    fn apply_rules(&self, transaction: &Transaction, user: &UserProfile, profile: &BehavioralProfile, score: f64, mut risk_factors: Vec<RiskFactor>) -> FraudAnalysisResult {
+        if self.config.fraud_rules.is_empty() {
+            return self.build_result(score, risk_factors);
+        }
+
+        let context = RuleContext {
+            amount: transaction.amount,
+            local_hour: self.config.user_local_hour(self.user_timezone(transaction.user_id), transaction.timestamp),
+            recipient_known: self.recipient_list.is_allowed(transaction.user_id, &transaction.recipient)
+                || profile.common_recipients.contains(&transaction.recipient),
+            device_trusted: user.device(&transaction.device_id).is_some_and(|device| device.is_trusted),
+            transaction_type: transaction.transaction_type.clone(),
+        };
+        let outcome = rules::evaluate(&self.config.fraud_rules, &context);
+        for rule in &outcome.fired {
+            let score = match rule.action {
+                RuleAction::AddScore(score) => score,
+                _ => 0.0,
+            };
+            risk_factors.push(RiskFactor {
+                factor_type: RiskFactorType::CustomRule,
+                score,
+                description: format!("Matched fraud rule '{}' ({:?})", rule.name, rule.action),
+            });
+        }
+
+        let mut result = self.build_result((score + outcome.score_adjustment).clamp(0.0, 1.0), risk_factors);
+        if let Some(recommendation) = outcome.decision {
+            result.recommendation = recommendation;
         }
+        result
+    }
 
-        // Normalize score to 0-1 range
-        let normalized_score = (total_score).min(1.0).max(0.0);
+    /// Record a scored transaction: statistics, the score given, and the activity
+    /// and location history later transactions are compared against
+    fn record_analysis(&mut self, transaction: &Transaction, result: &FraudAnalysisResult) {
+        self.scores.insert(transaction.transaction_id, result.fraud_score);
+
+        if self.recipient_list.is_blocked(&transaction.recipient) {
+            logging::warn_event!(user_id = %transaction.user_id, "transaction to blocklisted recipient blocked");
+            self.fraud_statistics.total_transactions_analyzed += 1;
+            self.fraud_statistics.transactions_flagged += 1;
+            self.fraud_statistics.transactions_blocked += 1;
+            return;
+        }
+        if !self.config.enable_behavioral_analysis {
+            return;
+        }
 
-        // Update statistics
-        if normalized_score > self.config.fraud_threshold_medium {
+        self.fraud_statistics.total_transactions_analyzed += 1;
+        if matches!(result.recommendation, FraudRecommendation::Flag | FraudRecommendation::Block) {
             self.fraud_statistics.transactions_flagged += 1;
         }
-        if normalized_score > self.config.fraud_threshold_high {
+        if result.recommendation == FraudRecommendation::Block {
             self.fraud_statistics.transactions_blocked += 1;
         }
 
-        Ok(normalized_score)
+        self.track_activity(transaction);
+        self.track_drift(transaction, &result.risk_factors);
+        if let Some(place) = self.locate(transaction) {
+            self.last_located.insert(transaction.user_id, (transaction.timestamp, place));
+        }
+
+        match result.recommendation {
+            FraudRecommendation::Block => logging::warn_event!(user_id = %transaction.user_id, score = result.fraud_score, factors = result.risk_factors.len(), "transaction blocked by fraud score"),
+            FraudRecommendation::Flag => logging::info_event!(user_id = %transaction.user_id, score = result.fraud_score, factors = result.risk_factors.len(), "transaction flagged for review"),
+            _ => logging::debug_event!(user_id = %transaction.user_id, score = result.fraud_score, "transaction scored"),
+        }
+    }
+
+    /// Attach the recommendation implied by the configured thresholds.
+    /// Moderate risk from an untrusted or unknown device asks the customer to step up rather than waiting on review.
+    fn build_result(&self, fraud_score: f64, risk_factors: Vec<RiskFactor>) -> FraudAnalysisResult {
+        let recommendation = if fraud_score > self.config.fraud_threshold_high {
+            FraudRecommendation::Block
+        } else if fraud_score > self.config.fraud_threshold_medium {
+            FraudRecommendation::Flag
+        } else if fraud_score > self.config.fraud_threshold_low
+            && risk_factors.iter().any(|factor| factor.factor_type == RiskFactorType::DeviceAnomaly)
+        {
+            FraudRecommendation::RequireAdditionalAuth
+        } else {
+            FraudRecommendation::Approve
+        };
+
+        FraudAnalysisResult {
+            fraud_score,
+            risk_factors,
+            recommendation,
+        }
     }
 
     /// Update user's behavioral profile based on transaction history
@@ -160,42 +682,58 @@ impl FraudDetector {
             typical_transaction_times: vec![],
             common_recipients: vec![],
             geographic_patterns: vec![],
+            usual_coordinates: vec![],
             usage_frequency: 0.0,
+            amount_std_dev: 0.0,
+            amount_sample_count: 0,
+            last_updated: None,
+        };
+
+        // Recent transactions weigh more when decay is configured
+        let now = self.clock.now();
+        let weight = |transaction: &Transaction| match self.config.profile_decay_half_life_days {
+            Some(half_life) => {
+                let age_days = (now - transaction.timestamp).num_seconds().max(0) as f64 / 86_400.0;
+                0.5f64.powf(age_days / half_life)
+            }
+            None => 1.0,
         };
+        let weights: Vec<f64> = transactions.iter().map(weight).collect();
+        let total_weight: f64 = weights.iter().sum();
 
         // Calculate typical transaction amount
-        let total_amount: f64 = transactions.iter().map(|t| t.amount).sum();
-        behavioral_profile.typical_transaction_amount = total_amount / transactions.len() as f64;
+        let mean = transactions.iter().zip(&weights).map(|(t, w)| t.amount * w).sum::<f64>() / total_weight;
+        behavioral_profile.typical_transaction_amount = mean;
 
-        // Analyze typical transaction times
-        let mut hour_counts = HashMap::new();
-        for transaction in transactions {
-            let hour = transaction.timestamp.hour() as u8;
-            *hour_counts.entry(hour).or_insert(0) += 1;
-        }
-        
-        // Get most common hours (top 3)
-        let mut hour_vec: Vec<(u8, i32)> = hour_counts.into_iter().collect();
-        hour_vec.sort_by(|a, b| b.1.cmp(&a.1));
-        behavioral_profile.typical_transaction_times = hour_vec
-            .into_iter()
-            .take(3)
-            .map(|(hour, _)| hour)
-            .collect();
+        // Spread of amounts, so naturally variable spenders aren't flagged for ordinary swings
+        let variance = transactions.iter().zip(&weights)
+            .map(|(t, w)| w * (t.amount - mean).powi(2))
+            .sum::<f64>() / total_weight;
+        behavioral_profile.amount_std_dev = variance.sqrt();
+        behavioral_profile.amount_sample_count = transactions.len();
 
-        // Analyze common recipients
-        let mut recipient_counts = HashMap::new();
-        for transaction in transactions {
-            *recipient_counts.entry(transaction.recipient.clone()).or_insert(0) += 1;
+        // Habits (hours, recipients, places) only count if seen within the pattern window
+        let window_start = self.config.profile_pattern_window_days.map(|days| now - Duration::days(days as i64));
+        let recent = || transactions.iter()
+            .zip(&weights)
+            .filter(|(t, _)| window_start.is_none_or(|start| t.timestamp >= start));
+
+        let mut hour_weights = HashMap::new();
+        let mut recipient_weights = HashMap::new();
+        let mut location_weights = HashMap::new();
+        let user_offset = self.user_timezone(user_id);
+        for (transaction, weight) in recent() {
+            *hour_weights.entry(self.config.user_local_hour(user_offset, transaction.timestamp)).or_insert(0.0) += weight;
+            *recipient_weights.entry(transaction.recipient.clone()).or_insert(0.0) += weight;
+            if let Some(location) = &transaction.location {
+                *location_weights.entry(location.clone()).or_insert(0.0) += weight;
+            }
         }
-        
-        let mut recipient_vec: Vec<(String, i32)> = recipient_counts.into_iter().collect();
-        recipient_vec.sort_by(|a, b| b.1.cmp(&a.1));
-        behavioral_profile.common_recipients = recipient_vec
-            .into_iter()
-            .take(5)
-            .map(|(recipient, _)| recipient)
-            .collect();
+        behavioral_profile.typical_transaction_times = RecencyWeights::top(&hour_weights, 3);
+        behavioral_profile.common_recipients = RecencyWeights::top(&recipient_weights, 5);
+        behavioral_profile.geographic_patterns = RecencyWeights::top(&location_weights, 5);
+        let reported: Vec<&GeoLocation> = transactions.iter().rev().filter_map(|t| t.geo_location.as_ref()).collect();
+        behavioral_profile.usual_coordinates = self.locate_places(&behavioral_profile.geographic_patterns, &reported);
 
         // Calculate usage frequency (transactions per day)
         if let (Some(first), Some(last)) = (transactions.first(), transactions.last()) {
@@ -203,31 +741,238 @@ impl FraudDetector {
             behavioral_profile.usage_frequency = transactions.len() as f64 / duration_days;
         }
 
-        self.user_profiles.insert(user_id, behavioral_profile);
+        behavioral_profile.last_updated = Some(now);
+        self.storage.save_behavioral_profile(user_id, &behavioral_profile)?;
+        self.profile_updated_at.insert(user_id, now);
+        // Incremental learning starts again from the rebuilt profile
+        self.recency_weights.remove(&user_id);
+        Ok(())
+    }
+
+    /// Fold a single new transaction into the user's learned profile without rescanning history.
+    /// The typical amount and its spread follow an exponential moving average and hour,
+    /// recipient and location frequencies decay, so recent habits outweigh old ones.
+    /// Use `update_behavioral_profile` for the initial backfill.
+    pub fn record_transaction(&mut self, user_id: Uuid, transaction: &Transaction) -> Result<()> {
+        let alpha = self.config.profile_ema_alpha;
+        let mut profile = match self.storage.load_behavioral_profile(user_id)? {
+            Some(profile) => profile,
+            None => BehavioralProfile {
+                typical_transaction_amount: 0.0,
+                typical_transaction_times: vec![],
+                common_recipients: vec![],
+                geographic_patterns: vec![],
+                usual_coordinates: vec![],
+                usage_frequency: 0.0,
+                amount_std_dev: 0.0,
+                amount_sample_count: 0,
+                last_updated: None,
+            },
+        };
+
+        if profile.amount_sample_count == 0 {
+            profile.typical_transaction_amount = transaction.amount;
+            profile.amount_std_dev = 0.0;
+        } else {
+            let deviation = transaction.amount - profile.typical_transaction_amount;
+            let variance = (1.0 - alpha) * (profile.amount_std_dev.powi(2) + alpha * deviation.powi(2));
+            profile.typical_transaction_amount += alpha * deviation;
+            profile.amount_std_dev = variance.sqrt();
+        }
+        profile.amount_sample_count += 1;
+
+        let local_hour = self.config.user_local_hour(self.user_timezone(user_id), transaction.timestamp);
+        let weights = self.recency_weights
+            .entry(user_id)
+            .or_insert_with(|| RecencyWeights::seeded_from(&profile));
+        RecencyWeights::observe(&mut weights.hours, local_hour, alpha);
+        RecencyWeights::observe(&mut weights.recipients, transaction.recipient.clone(), alpha);
+        if let Some(location) = &transaction.location {
+            RecencyWeights::observe(&mut weights.locations, location.clone(), alpha);
+        }
+        profile.typical_transaction_times = RecencyWeights::top(&weights.hours, 3);
+        profile.common_recipients = RecencyWeights::top(&weights.recipients, 5);
+        profile.geographic_patterns = RecencyWeights::top(&weights.locations, 5);
+        let reported: Vec<&GeoLocation> = transaction.geo_location.iter().chain(&profile.usual_coordinates).collect();
+        let usual_coordinates = self.locate_places(&profile.geographic_patterns, &reported);
+        profile.usual_coordinates = usual_coordinates;
+        profile.last_updated = Some(self.clock.now());
+
+        self.storage.save_behavioral_profile(user_id, &profile)?;
+        self.profile_updated_at.insert(user_id, self.clock.now());
+        Ok(())
+    }
+
+    /// Count a completed outgoing payment. The `trusted_recipient_after`th one to the same recipient
+    /// adds them to the user's common recipients, so later payments to them stop scoring as unusual.
+    /// Counts are kept in storage, so they survive a restart and are shared by instances over it.
+    /// Returns whether this payment promoted the recipient.
+    pub fn record_completed_payment(&mut self, user: &UserProfile, recipient: &str, transaction_type: &TransactionType) -> Result<bool> {
+        let Some(needed) = self.config.trusted_recipient_after else {
+            return Ok(false);
+        };
+        if !matches!(transaction_type, TransactionType::Transfer | TransactionType::Payment) {
+            return Ok(false);
+        }
+
+        let key = recipient_payments_key(user.user_id);
+        let mut payments: RecipientPayments = storage::load_json(self.storage.as_ref(), &key)?.unwrap_or_default();
+        let (count, last_paid) = payments.entry(recipient.to_string()).or_insert((0, self.clock.now()));
+        *count += 1;
+        *last_paid = self.clock.now();
+        let promoted = *count >= needed;
+        if promoted {
+            payments.remove(recipient);
+        }
+        while payments.len() > MAX_COUNTED_RECIPIENTS {
+            let Some(oldest) = payments.iter().min_by_key(|(_, (_, last_paid))| *last_paid).map(|(name, _)| name.clone()) else {
+                break;
+            };
+            payments.remove(&oldest);
+        }
+        storage::save_json(self.storage.as_ref(), &key, &payments)?;
+        if !promoted {
+            return Ok(false);
+        }
+
+        // Extend whichever profile scoring currently uses, so nothing else about it changes
+        let mut profile = self.storage.load_behavioral_profile(user.user_id)?
+            .unwrap_or_else(|| user.behavioral_profile.clone());
+        if profile.common_recipients.iter().any(|known| known == recipient) {
+            return Ok(false);
+        }
+        profile.common_recipients.push(recipient.to_string());
+        self.storage.save_behavioral_profile(user.user_id, &profile)?;
+        logging::debug_event!(user_id = %user.user_id, "recipient promoted to usual after repeated payments");
+        Ok(true)
+    }
+
+    /// Remember a transaction a reviewer approved, so near-identical ones from the same user
+    /// to the same recipient score lower for the next `approval_damping_hours`
+    pub fn record_approved_pattern(&mut self, user_id: Uuid, recipient: &str, amount: f64) {
+        let Some(hours) = self.config.approval_damping_hours else {
+            return;
+        };
+        let now = self.clock.now();
+        let cutoff = now - Duration::hours(hours as i64);
+        let patterns = self.approved_patterns.entry(user_id).or_default();
+        patterns.retain(|pattern| pattern.approved_at > cutoff);
+        patterns.push(ApprovedPattern { recipient: RecipientList::normalize(recipient), amount, approved_at: now });
+    }
+
+    /// Stop trusting the recipient of a transaction confirmed as fraud, for that user only: drop them
+    /// from the user's usual recipients and allowlist, and forget payments counted towards promoting
+    /// them. Blocking a recipient for everyone is left to an operator, through `add_to_blocklist`.
+    pub fn demote_recipient(&mut self, transaction_id: Uuid, user_id: Uuid, recipient: &str) -> Result<()> {
+        let payments_key = recipient_payments_key(user_id);
+        if let Some(mut payments) = storage::load_json::<RecipientPayments>(self.storage.as_ref(), &payments_key)? {
+            if payments.remove(recipient).is_some() {
+                storage::save_json(self.storage.as_ref(), &payments_key, &payments)?;
+            }
+        }
+        if let Some(patterns) = self.approved_patterns.get_mut(&user_id) {
+            let recipient = RecipientList::normalize(recipient);
+            patterns.retain(|pattern| pattern.recipient != recipient);
+        }
+        let was_allowed = self.remove_allowed_recipient(user_id, recipient);
+        if let Some(weights) = self.recency_weights.get_mut(&user_id) {
+            weights.recipients.remove(recipient);
+        }
+        let mut was_usual = false;
+        if let Some(mut profile) = self.storage.load_behavioral_profile(user_id)? {
+            was_usual = profile.common_recipients.iter().any(|known| known == recipient);
+            profile.common_recipients.retain(|known| known != recipient);
+            self.storage.save_behavioral_profile(user_id, &profile)?;
+        }
+        let demotion = RecipientDemotion { user_id, recipient: recipient.to_string(), was_usual, was_allowed };
+        storage::save_json(self.storage.as_ref(), &recipient_demotion_key(transaction_id), &demotion)
+    }
+
+    /// Undo `demote_recipient` for a transaction relabelled as legitimate
+    pub fn restore_recipient(&mut self, transaction_id: Uuid) -> Result<()> {
+        let key = recipient_demotion_key(transaction_id);
+        let Some(demotion) = storage::load_json::<RecipientDemotion>(self.storage.as_ref(), &key)? else {
+            return Ok(());
+        };
+        if demotion.was_allowed {
+            self.allow_recipient(demotion.user_id, &demotion.recipient);
+        }
+        if demotion.was_usual {
+            if let Some(mut profile) = self.storage.load_behavioral_profile(demotion.user_id)? {
+                if !profile.common_recipients.contains(&demotion.recipient) {
+                    profile.common_recipients.push(demotion.recipient.clone());
+                    self.storage.save_behavioral_profile(demotion.user_id, &profile)?;
+                }
+            }
+        }
+        self.storage.remove_record(&key)
+    }
+
+    /// All learned behavioral profiles, e.g. for writing to disk
+    pub fn export_profiles(&self) -> Result<HashMap<Uuid, BehavioralProfile>> {
+        self.storage.load_all_behavioral_profiles()
+    }
+
+    /// Load previously exported behavioral profiles, replacing any existing ones for the same users
+    pub fn import_profiles(&mut self, profiles: HashMap<Uuid, BehavioralProfile>) -> Result<()> {
+        let now = self.clock.now();
+        for (user_id, profile) in profiles {
+            self.storage.save_behavioral_profile(user_id, &profile)?;
+            self.profile_updated_at.entry(user_id).or_insert(now);
+        }
         Ok(())
     }
 
+    /// Users whose behavioral profile hasn't been refreshed within the given window
+    pub fn stale_profiles(&self, older_than: Duration) -> Vec<Uuid> {
+        let cutoff = self.clock.now() - older_than;
+        let mut stale: Vec<Uuid> = self.profile_updated_at
+            .iter()
+            .filter(|(_, updated_at)| **updated_at < cutoff)
+            .map(|(user_id, _)| *user_id)
+            .collect();
+        stale.sort();
+        stale
+    }
+
     /// Simple rule-based fraud detection for minimal resource usage
-    fn simple_fraud_detection(&self, transaction: &Transaction) -> f64 {
-        let mut score = 0.0;
+    fn simple_fraud_detection(&self, transaction: &Transaction) -> Vec<RiskFactor> {
+        let mut risk_factors = Vec::new();
 
         // Check for unusually large amounts
         if transaction.amount > self.config.single_transaction_limit * 0.8 {
-            score += 0.4;
+            risk_factors.push(RiskFactor {
+                factor_type: RiskFactorType::LimitAnomaly,
+                score: 0.4,
+                description: "Transaction amount close to the single transaction limit".to_string(),
+            });
         }
 
-        // Check for late night transactions (potential risk)
-        let hour = transaction.timestamp.hour();
-        if hour >= 23 || hour <= 5 {
-            score += 0.2;
+        risk_factors.extend(self.pattern_factors(transaction));
+        risk_factors
+    }
+
+    /// Market-tuned heuristics shared by the simple and behavioral paths: late-night hours and large round amounts
+    fn pattern_factors(&self, transaction: &Transaction) -> Vec<RiskFactor> {
+        let mut risk_factors = Vec::new();
+
+        if self.config.is_night_hour(self.config.user_local_hour(self.user_timezone(transaction.user_id), transaction.timestamp)) {
+            risk_factors.push(RiskFactor {
+                factor_type: RiskFactorType::SuspiciousPattern,
+                score: 0.2,
+                description: "Late night transaction".to_string(),
+            });
         }
 
-        // Check for round numbers (potentially suspicious)
-        if transaction.amount % 100.0 == 0.0 && transaction.amount >= 1000.0 {
-            score += 0.1;
+        if self.config.is_round_amount(transaction.amount) {
+            risk_factors.push(RiskFactor {
+                factor_type: RiskFactorType::SuspiciousPattern,
+                score: 0.1,
+                description: "Large round-number amount".to_string(),
+            });
         }
 
-        score
+        risk_factors
     }
 
     /// Analyze transaction amount compared to user's typical behavior
@@ -239,7 +984,24 @@ impl FraudDetector {
         let typical_amount = profile.typical_transaction_amount;
         let current_amount = transaction.amount;
 
-        // Calculate deviation ratio
+        // With enough history, score by how many standard deviations the amount is from the mean.
+        // The deviation is floored at 10% of the mean so very consistent spenders aren't
+        // flagged for small changes.
+        if profile.amount_sample_count >= MIN_AMOUNT_SAMPLES && profile.amount_std_dev > 0.0 {
+            let std_dev = profile.amount_std_dev.max(typical_amount * 0.1);
+            let z_score = (current_amount - typical_amount).abs() / std_dev;
+            return if z_score >= 3.0 {
+                0.8
+            } else if z_score >= 2.0 {
+                0.5
+            } else if z_score >= 1.0 {
+                0.2
+            } else {
+                0.0
+            };
+        }
+
+        // Cold start: calculate deviation ratio
         let deviation_ratio = if current_amount > typical_amount {
             current_amount / typical_amount
         } else {
@@ -258,13 +1020,13 @@ impl FraudDetector {
         }
     }
 
-    /// Analyze transaction time compared to user's typical behavior
-    fn analyze_time_anomaly(&self, transaction: &Transaction, profile: &BehavioralProfile) -> f64 {
+    /// Analyze transaction time compared to user's typical behavior, both in the user's local time
+    fn analyze_time_anomaly(&self, transaction: &Transaction, user: &UserProfile, profile: &BehavioralProfile) -> f64 {
         if profile.typical_transaction_times.is_empty() {
             return 0.0;
         }
 
-        let current_hour = transaction.timestamp.hour() as u8;
+        let current_hour = self.config.user_local_hour(user.timezone_offset_minutes, transaction.timestamp);
         
         // Check if current hour is in typical hours
         if profile.typical_transaction_times.contains(&current_hour) {
@@ -288,19 +1050,93 @@ impl FraudDetector {
         }
     }
 
-    /// Analyze transaction frequency anomalies
-    fn analyze_frequency_anomaly(&self, _transaction: &Transaction, profile: &BehavioralProfile) -> f64 {
-        // This would typically analyze recent transaction frequency vs typical
-        // For now, return a placeholder based on usage frequency
-        if profile.usage_frequency > 10.0 {
-            0.3 // High frequency users might be suspicious
-        } else {
-            0.0
+    /// Remember which dimensions shifted, dropping entries older than the takeover window
+    fn track_drift(&mut self, transaction: &Transaction, risk_factors: &[RiskFactor]) {
+        let window_start = transaction.timestamp - Duration::minutes(self.config.takeover_window_minutes as i64);
+        let recent = self.recent_drift.entry(transaction.user_id).or_default();
+        let shifted = Self::drifted_dimensions(risk_factors);
+        if !shifted.is_empty() {
+            recent.push_back((transaction.timestamp, shifted));
         }
+        while recent.front().is_some_and(|(time, _)| *time < window_start) || recent.len() > MAX_RECENT_TIMESTAMPS {
+            recent.pop_front();
+        }
+    }
+
+    /// Remember the transaction time, dropping entries older than a day and beyond the per-user cap
+    fn track_activity(&mut self, transaction: &Transaction) {
+        let window_start = transaction.timestamp - Duration::days(1);
+        let times = self.recent_activity.entry(transaction.user_id).or_default();
+        times.push_back(transaction.timestamp);
+        while times.front().is_some_and(|time| *time < window_start) || times.len() > MAX_RECENT_TIMESTAMPS {
+            times.pop_front();
+        }
+    }
+
+    /// Compare the user's transaction count over the last hour and day, including this one,
+    /// with their usual daily rate
+    fn analyze_frequency_anomaly(&self, transaction: &Transaction, profile: &BehavioralProfile) -> Option<RiskFactor> {
+        let times = self.recent_activity.get(&transaction.user_id);
+        let count_since = |window: Duration| {
+            let start = transaction.timestamp - window;
+            let earlier = times.map_or(0, |times| {
+                times.iter().filter(|time| **time > start && **time <= transaction.timestamp).count()
+            });
+            earlier + 1
+        };
+
+        // New users have no baseline yet; assume about one transaction a day
+        let expected_daily = profile.usage_frequency.max(1.0);
+        let expected_hourly = (expected_daily / 24.0).max(1.0);
+        let (window, observed, expected) = [
+            ("hour", count_since(Duration::hours(1)), expected_hourly),
+            ("day", count_since(Duration::days(1)), expected_daily),
+        ]
+        .into_iter()
+        .max_by(|a, b| (a.1 as f64 / a.2).total_cmp(&(b.1 as f64 / b.2)))?;
+
+        let ratio = observed as f64 / expected;
+        let score = if ratio >= FREQUENCY_SPIKE_RATIO * 3.0 {
+            0.9
+        } else if ratio >= FREQUENCY_SPIKE_RATIO * 2.0 {
+            0.6
+        } else if ratio >= FREQUENCY_SPIKE_RATIO {
+            0.3
+        } else {
+            return None;
+        };
+
+        Some(RiskFactor {
+            factor_type: RiskFactorType::FrequencyAnomaly,
+            score,
+            description: format!(
+                "{} transactions in the last {} against an expected {:.1}",
+                observed, window, expected
+            ),
+        })
+    }
+
+    /// Score a transaction from a device the user never registered, or registered but doesn't trust
+    fn analyze_device_anomaly(&self, transaction: &Transaction, user: &UserProfile) -> Option<RiskFactor> {
+        let (score, description) = match user.device(&transaction.device_id) {
+            Some(device) if device.is_trusted => return None,
+            Some(_) => (0.5, format!("Transaction from untrusted device {}", transaction.device_id)),
+            None => (1.0, format!("Transaction from unrecognized device {}", transaction.device_id)),
+        };
+
+        Some(RiskFactor {
+            factor_type: RiskFactorType::DeviceAnomaly,
+            score,
+            description,
+        })
     }
 
     /// Analyze recipient anomalies
     fn analyze_recipient_anomaly(&self, transaction: &Transaction, profile: &BehavioralProfile) -> f64 {
+        if self.recipient_list.is_allowed(transaction.user_id, &transaction.recipient) {
+            return 0.0; // Vouched for by the user
+        }
+
         if profile.common_recipients.is_empty() {
             return 0.1; // Slight risk for new users
         }
@@ -312,10 +1148,113 @@ impl FraudDetector {
         }
     }
 
-    /// Check transaction against configured limits
-    fn check_transaction_limits(&self, transaction: &Transaction) -> f64 {
-        if transaction.amount > self.config.single_transaction_limit {
-            1.0 // Exceeds limit
+    /// Analyze transaction location against the user's usual places.
+    /// When both can be placed on the map the score grows with distance from the nearest usual place.
+    /// Otherwise locations are compared as "Town, Region" labels, where a new town in a known region
+    /// is less suspicious than a new region.
+    fn analyze_location_anomaly(&self, transaction: &Transaction, profile: &BehavioralProfile) -> f64 {
+        if profile.geographic_patterns.is_empty() {
+            return 0.0; // No historical data
+        }
+
+        let usual: Vec<(f64, f64)> = profile.geographic_patterns.iter()
+            .filter_map(|label| profile.usual_coordinates.iter()
+                .find(|place| place.label == *label)
+                .map(GeoLocation::position)
+                .or_else(|| self.coordinates_for(label)))
+            .collect();
+        if let (Some(here), false) = (self.locate(transaction), usual.is_empty()) {
+            let nearest_km = usual.iter()
+                .map(|place| crate::utils::haversine_km(here.position(), *place))
+                .fold(f64::INFINITY, f64::min);
+            return if nearest_km < NEARBY_KM { 0.0 } else { (0.9 * nearest_km / FAR_KM).clamp(0.2, 0.9) };
+        }
+
+        let Some(location) = transaction.location.as_deref() else {
+            return 0.0; // No location signal
+        };
+
+        let normalize = |value: &str| value.trim().to_lowercase();
+        let region_of = |value: &str| normalize(value.rsplit(',').next().unwrap_or(value));
+
+        let location_key = normalize(location);
+        if profile.geographic_patterns.iter().any(|known| normalize(known) == location_key) {
+            return 0.0; // Known location
+        }
+
+        let region = region_of(location);
+        if profile.geographic_patterns.iter().any(|known| region_of(known) == region) {
+            0.3 // New place in a familiar region
+        } else {
+            0.7 // Brand-new region
+        }
+    }
+
+    /// Flag a transaction whose location couldn't plausibly be reached since the user's last located transaction
+    fn analyze_travel_velocity(&self, transaction: &Transaction) -> Option<RiskFactor> {
+        let here = self.locate(transaction)?;
+        let (last_time, last_place) = self.last_located.get(&transaction.user_id)?;
+
+        let distance_km = crate::utils::haversine_distance(last_place, &here);
+        // Ignore short hops where coordinate precision dominates
+        if distance_km < 50.0 {
+            return None;
+        }
+
+        let elapsed_hours = (transaction.timestamp - *last_time).num_seconds().max(1) as f64 / 3600.0;
+        let speed_kmh = distance_km / elapsed_hours;
+        if speed_kmh <= self.config.max_travel_speed_kmh {
+            return None;
+        }
+
+        Some(RiskFactor {
+            factor_type: RiskFactorType::TravelAnomaly,
+            score: 0.9,
+            description: format!(
+                "Impossible travel from {} to {} ({:.0} km in {:.1} h)",
+                last_place.label, here.label, distance_km, elapsed_hours
+            ),
+        })
+    }
+
+    /// Where a transaction happened: its reported coordinates, else its location name looked up in the town tables
+    fn locate(&self, transaction: &Transaction) -> Option<GeoLocation> {
+        if let Some(place) = &transaction.geo_location {
+            return Some(place.clone());
+        }
+        let label = transaction.location.as_deref()?;
+        let (lat, lon) = self.coordinates_for(label)?;
+        Some(GeoLocation::new(lat, lon, label))
+    }
+
+    /// Coordinates for each place label: the first matching reported position, else the town tables
+    fn locate_places(&self, labels: &[String], reported: &[&GeoLocation]) -> Vec<GeoLocation> {
+        labels.iter()
+            .filter_map(|label| reported.iter()
+                .find(|place| place.label == *label)
+                .map(|place| (*place).clone())
+                .or_else(|| self.coordinates_for(label).map(|(lat, lon)| GeoLocation::new(lat, lon, label.as_str()))))
+            .collect()
+    }
+
+    /// Coordinates for a location name: configured entries first, then the built-in town table
+    fn coordinates_for(&self, location: &str) -> Option<(f64, f64)> {
+        if let Some(coordinates) = self.config.configured_coordinates(location) {
+            return Some(coordinates);
+        }
+
+        let key = location.trim().to_lowercase();
+        let town = key.split(',').next().unwrap_or(&key).trim();
+        KNOWN_TOWNS
+            .iter()
+            .find(|(name, _, _)| *name == town)
+            .map(|(_, lat, lon)| (*lat, *lon))
+    }
+
+    /// Check transaction against configured limits
+    fn check_transaction_limits(&self, transaction: &Transaction) -> f64 {
+        if transaction.amount > self.config.single_transaction_limit {
+            1.0 // Exceeds limit
         } else if transaction.amount > self.config.single_transaction_limit * 0.8 {
             0.5 // Close to limit
         } else {
@@ -330,6 +1269,12 @@ impl FraudDetector {
         stats.insert("total_analyzed".to_string(), self.fraud_statistics.total_transactions_analyzed as f64);
         stats.insert("flagged".to_string(), self.fraud_statistics.transactions_flagged as f64);
         stats.insert("blocked".to_string(), self.fraud_statistics.transactions_blocked as f64);
+
+        if !self.labels.is_empty() {
+            stats.insert("confirmed_fraud".to_string(), self.fraud_statistics.fraud_detected as f64);
+            stats.insert("false_positive_rate_percent".to_string(), self.fraud_statistics.false_positive_rate * 100.0);
+            stats.insert("true_positive_rate_percent".to_string(), self.fraud_statistics.true_positive_rate * 100.0);
+        }
         
         if self.fraud_statistics.total_transactions_analyzed > 0 {
             let flag_rate = (self.fraud_statistics.transactions_flagged as f64) / 
@@ -344,11 +1289,75 @@ impl FraudDetector {
         stats
     }
 
-    /// Mark a transaction as confirmed fraud (for learning)
-    pub fn mark_as_fraud(&mut self, _transaction_id: Uuid, _is_fraud: bool) {
-        // This would be used to improve the fraud detection algorithm
-        // For now, just update statistics
-        self.fraud_statistics.fraud_detected += 1;
+    /// Block a recipient for all users; transactions to it are always recommended for blocking
+    pub fn add_to_blocklist(&mut self, recipient: &str) {
+        self.recipient_list.blocklist.insert(RecipientList::normalize(recipient));
+    }
+
+    /// Remove a recipient from the blocklist; returns whether it was listed
+    pub fn remove_from_blocklist(&mut self, recipient: &str) -> bool {
+        self.recipient_list.blocklist.remove(&RecipientList::normalize(recipient))
+    }
+
+    /// Trust a recipient for one user so payments to it never count as unusual
+    pub fn allow_recipient(&mut self, user_id: Uuid, recipient: &str) {
+        self.recipient_list.allowlists
+            .entry(user_id)
+            .or_default()
+            .insert(RecipientList::normalize(recipient));
+    }
+
+    /// Remove a recipient from a user's allowlist; returns whether it was listed
+    pub fn remove_allowed_recipient(&mut self, user_id: Uuid, recipient: &str) -> bool {
+        self.recipient_list.allowlists
+            .get_mut(&user_id)
+            .is_some_and(|allowed| allowed.remove(&RecipientList::normalize(recipient)))
+    }
+
+    /// Current blocklist and allowlists
+    pub fn recipient_list(&self) -> &RecipientList {
+        &self.recipient_list
+    }
+
+    /// Record an operator-confirmed outcome for a transaction. Labeling the same transaction
+    /// again replaces the earlier label. Detection rates are recomputed over recent labels.
+    pub fn mark_as_fraud(&mut self, transaction_id: Uuid, is_fraud: bool) {
+        let fraud_score = self.scores.get(&transaction_id).copied();
+        let label = FraudLabel {
+            transaction_id,
+            is_fraud,
+            fraud_score,
+            was_flagged: fraud_score.map(|score| score > self.config.fraud_threshold_medium),
+            labeled_at: self.clock.now(),
+        };
+
+        self.labels.retain(|existing| existing.transaction_id != transaction_id);
+        self.labels.push(label);
+        self.recompute_label_rates();
+    }
+
+    /// Confirmed outcomes recorded with `mark_as_fraud`, oldest first, e.g. for export
+    pub fn labeled_outcomes(&self) -> &[FraudLabel] {
+        &self.labels
+    }
+
+    fn recompute_label_rates(&mut self) {
+        let recent = &self.labels[self.labels.len().saturating_sub(LABEL_WINDOW)..];
+        let rate = |is_fraud: bool| {
+            let judged: Vec<bool> = recent.iter()
+                .filter(|label| label.is_fraud == is_fraud)
+                .filter_map(|label| label.was_flagged)
+                .collect();
+            if judged.is_empty() {
+                0.0
+            } else {
+                judged.iter().filter(|flagged| **flagged).count() as f64 / judged.len() as f64
+            }
+        };
+
+        self.fraud_statistics.false_positive_rate = rate(false);
+        self.fraud_statistics.true_positive_rate = rate(true);
+        self.fraud_statistics.fraud_detected = self.labels.iter().filter(|label| label.is_fraud).count() as u64;
     }
 
     /// Reset statistics (useful for testing)
@@ -360,33 +1369,38 @@ impl FraudDetector {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{UserProfile, DeviceInfo, config::SafeBankConfig, TransactionType};
-    use chrono::Utc;
+    use std::sync::Arc;
+    use crate::{UserProfile, DeviceInfo, clock::{Clock, MockClock}, config::{FactorBounds, RiskWeights, SafeBankConfig}, TransactionStatus, TransactionType};
 
     fn create_test_user() -> UserProfile {
         UserProfile {
             user_id: Uuid::new_v4(),
             phone_number: "+1234567890".to_string(),
             pin_hash: "dummy_hash".to_string(),
-            device_info: DeviceInfo {
-                device_id: "test-device".to_string(),
-                device_type: "smartphone".to_string(),
-                os_version: Some("Android 8.0".to_string()),
-                app_version: "1.0.0".to_string(),
-                is_trusted: true,
-                registered_at: Utc::now(),
-            },
+            primary_device_id: Some("test-device".to_string()),
+            devices: vec![DeviceInfo::test_device()],
             behavioral_profile: BehavioralProfile {
                 typical_transaction_amount: 100.0,
                 typical_transaction_times: vec![9, 12, 18], // 9 AM, 12 PM, 6 PM
                 common_recipients: vec!["John Doe".to_string()],
                 geographic_patterns: vec![],
+                usual_coordinates: vec![],
                 usage_frequency: 2.0,
+                amount_std_dev: 0.0,
+                amount_sample_count: 0,
+                last_updated: None,
             },
             created_at: Utc::now(),
             last_login: Some(Utc::now()),
+            last_login_device: None,
             failed_attempts: 0,
             is_locked: false,
+            frozen_reason: None,
+            last_confirmed_fraud: None,
+            timezone_offset_minutes: None,
+            duress_pin_hash: None,
+            pin_history: Vec::new(),
+            pin_entry_baseline: None,
         }
     }
 
@@ -399,9 +1413,25 @@ mod tests {
             transaction_type: TransactionType::Transfer,
             timestamp: Utc::now(),
             location: None,
+            geo_location: None,
             device_id: "test-device".to_string(),
             fraud_score: 0.0,
-            status: crate::TransactionStatus::Pending,
+            status: TransactionStatus::Pending,
+            fee: 0.0,
+            levy: 0.0,
+            fx_fee: 0.0,
+            group_id: None,
+            risk_factors: Vec::new(),
+            idempotency_key: None,
+            reverses: None,
+            reversed_by: None,
+            category: None,
+            prev_hash: None,
+            hash: None,
+            state_hash: None,
+            status_history: Vec::new(),
+            balance_after: None,
+            memo: None,
         }
     }
 
@@ -409,7 +1439,7 @@ mod tests {
     fn test_fraud_detector_initialization() {
         let config = SafeBankConfig::default();
         let detector = FraudDetector::new(&config);
-        assert!(detector.user_profiles.is_empty());
+        assert!(detector.storage.load_behavioral_profile(Uuid::new_v4()).unwrap().is_none());
     }
 
     #[test]
@@ -421,14 +1451,136 @@ mod tests {
         let transaction = create_test_transaction(100.0, user.user_id);
         
         let score = detector.analyze_transaction(&transaction, &user).unwrap();
-        assert!(score >= 0.0 && score <= 1.0);
+        assert!((0.0..=1.0).contains(&score));
+    }
+
+    #[test]
+    fn test_round_amount_modulus_is_configurable() {
+        let user = create_test_user();
+        let round_bump = |config: &SafeBankConfig, amount: f64| {
+            let mut transaction = create_test_transaction(amount, user.user_id);
+            transaction.timestamp = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 1, 12, 0, 0).unwrap();
+            FraudDetector::new(config).simple_fraud_detection(&transaction).iter()
+                .any(|factor| factor.factor_type == RiskFactorType::SuspiciousPattern)
+        };
+
+        let hundreds = SafeBankConfig::minimal();
+        assert!(round_bump(&hundreds, 1200.0));
+        assert!(!round_bump(&hundreds, 1250.0));
+        assert!(!round_bump(&hundreds, 900.0));
+
+        let fifties = SafeBankConfig { round_amount_modulus: 50.0, round_amount_minimum: 500.0, ..SafeBankConfig::minimal() };
+        fifties.validate().unwrap();
+        assert!(round_bump(&fifties, 1250.0));
+        assert!(round_bump(&fifties, 900.0));
+        assert!(!round_bump(&fifties, 1260.0));
+
+        let thousands = SafeBankConfig { round_amount_modulus: 1000.0, ..SafeBankConfig::minimal() };
+        assert!(!round_bump(&thousands, 1200.0));
+        assert!(round_bump(&thousands, 2000.0));
+
+        let empty_night = SafeBankConfig { night_start_hour: 5, night_end_hour: 5, ..SafeBankConfig::minimal() };
+        assert!(empty_night.validate().is_err());
+
+        // The behavioral path leaves the heuristics out until the deployment weights them
+        let behavioral_bump = |config: &SafeBankConfig| {
+            let transaction = create_test_transaction(2000.0, user.user_id);
+            FraudDetector::new(config).simulate_transaction(&transaction, &user).unwrap().risk_factors.iter()
+                .any(|factor| factor.factor_type == RiskFactorType::SuspiciousPattern)
+        };
+        let default = SafeBankConfig::default();
+        assert_eq!(default.risk_weights.pattern, 0.0);
+        assert!(!behavioral_bump(&default));
+        assert!(behavioral_bump(&SafeBankConfig { risk_weights: RiskWeights { pattern: 0.5, ..RiskWeights::default() }, ..default }));
+    }
+
+    #[test]
+    fn test_new_users_are_scored_gently_while_learning() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let mut strict = FraudDetector::new(&SafeBankConfig { learning_grace_transactions: 0, ..config.clone() });
+        let empty_profile = BehavioralProfile {
+            typical_transaction_amount: 0.0,
+            typical_transaction_times: vec![],
+            common_recipients: vec![],
+            geographic_patterns: vec![],
+            usual_coordinates: vec![],
+            usage_frequency: 0.0,
+            amount_std_dev: 0.0,
+            amount_sample_count: 0,
+            last_updated: None,
+        };
+        let user = UserProfile { behavioral_profile: empty_profile, ..create_test_user() };
+
+        for (amount, recipient) in [(250.0, "Village Shop"), (400.0, "Grain Co-op"), (150.0, "Water Vendor")] {
+            let transaction = Transaction { recipient: recipient.to_string(), ..create_test_transaction(amount, user.user_id) };
+            let lenient = detector.analyze_transaction_detailed(&transaction, &user).unwrap();
+            let full = strict.analyze_transaction_detailed(&transaction, &user).unwrap();
+            assert!(lenient.fraud_score < full.fraud_score, "{} vs {}", lenient.fraud_score, full.fraud_score);
+            assert!(lenient.fraud_score < config.fraud_threshold_low);
+            assert_eq!(lenient.recommendation, FraudRecommendation::Approve);
+            detector.record_transaction(user.user_id, &transaction).unwrap();
+            strict.record_transaction(user.user_id, &transaction).unwrap();
+        }
+
+        // The grace period ends once enough approved transactions are stored, however they were committed
+        let transaction = Transaction { recipient: "Stranger".to_string(), ..create_test_transaction(900.0, user.user_id) };
+        let fresh = FraudDetector::with_storage(&config, detector.storage.clone(), detector.clock.clone());
+        let fresh_strict = FraudDetector::with_storage(&strict.config, detector.storage.clone(), detector.clock.clone());
+        let score = |detector: &FraudDetector| detector.simulate_transaction(&transaction, &user).unwrap().fraud_score;
+        assert!(score(&fresh) < score(&fresh_strict));
+        detector.storage.save_user(&user).unwrap();
+        let mut manager = transaction::TransactionManager::with_storage(&config, detector.storage.clone(), detector.clock.clone());
+        manager.adjust_balance(user.user_id, 10_000.0).unwrap();
+        for _ in 0..config.learning_grace_transactions {
+            let history = Transaction { status: TransactionStatus::Approved, ..create_test_transaction(100.0, user.user_id) };
+            manager.process_transaction(history).unwrap();
+        }
+        assert_eq!(score(&fresh), score(&fresh_strict));
+
+        // Hard limits still apply in full
+        let over_limit = create_test_transaction(config.single_transaction_limit, user.user_id);
+        assert!(detector.simulate_transaction(&over_limit, &user).unwrap().risk_factors.iter()
+            .any(|factor| factor.description.contains("single transaction limit") && factor.score > 0.0));
+    }
+
+    #[test]
+    fn test_rejection_message_explains_main_factors() {
+        let config = SafeBankConfig::default();
+        let detector = FraudDetector::new(&config);
+        let user = create_test_user();
+        // Far above the usual 100 and not to the usual recipient, at a usual hour
+        let noon = Utc::now().date_naive().and_hms_opt(12, 0, 0).unwrap().and_utc();
+        let transaction = Transaction { timestamp: noon, ..create_test_transaction(4000.0, user.user_id) };
+
+        let result = detector.simulate_transaction(&transaction, &user).unwrap();
+        let error = fraud_error(result.fraud_score, &result.risk_factors, config.fraud_explanation_max_reasons, false);
+        let SafeBankError::FraudDetected { fraud_score, reasons, .. } = &error else { panic!("{:?}", error) };
+        assert_eq!(*fraud_score, result.fraud_score);
+        assert_eq!(reasons, &vec![FraudReason::UnusualAmount, FraudReason::NewRecipient]);
+
+        let message = error.to_user_message();
+        assert!(message.contains("larger than your usual amount and goes to a new recipient"), "{}", message);
+        assert!(message.contains("declined") && !message.contains("held"), "{}", message);
+        let held = fraud_error(result.fraud_score, &result.risk_factors, config.fraud_explanation_max_reasons, true);
+        assert!(held.to_user_message().contains("held for security review"));
+        assert!(!message.contains(&format!("{}", fraud_score)));
+        assert!(error.to_user_message_localized("sw").contains("mpokeaji mpya"));
+
+        // Operator rules are never disclosed, and explanations can be turned off
+        let rule = RiskFactor { factor_type: RiskFactorType::CustomRule, score: 1.0, description: "Matched fraud rule 'x'".to_string() };
+        assert!(matches!(fraud_error(0.9, &[rule], 2, false), SafeBankError::FraudDetected { reasons, .. } if reasons.is_empty()));
+        assert_eq!(fraud_error(0.9, &result.risk_factors, 0, true).to_user_message(), "Transaction flagged for security review. Please contact support.");
+        assert_eq!(fraud_error(0.9, &result.risk_factors, 0, false).to_user_message(), "Transaction declined for security reasons. Please contact support.");
     }
 
     #[test]
     fn test_large_amount_detection() {
-        let mut config = SafeBankConfig::default();
-        config.single_transaction_limit = 1000.0;
-        config.enable_behavioral_analysis = true; // Make sure behavioral analysis is enabled
+        let config = SafeBankConfig {
+            single_transaction_limit: 1000.0,
+            enable_behavioral_analysis: true, // Make sure behavioral analysis is enabled
+            ..SafeBankConfig::default()
+        };
         let mut detector = FraudDetector::new(&config);
         
         let user = create_test_user();
@@ -439,6 +1591,130 @@ mod tests {
         assert!(score >= 0.1); // Lower threshold since it's just above limit
     }
 
+    #[test]
+    fn test_composite_score_matches_weighted_factor_sum() {
+        let config = SafeBankConfig {
+            single_transaction_limit: 1000.0,
+            learning_grace_transactions: 0,
+            risk_weights: RiskWeights { amount: 0.1, time: 0.1, frequency: 0.1, recipient: 0.1, location: 0.1, travel: 0.2, limit: 0.05, pattern: 0.1 },
+            ..SafeBankConfig::default()
+        };
+        let mut detector = FraudDetector::new(&config);
+        let user = create_test_user();
+
+        // A located transaction first, so the next one carries a travel factor
+        let mut nairobi = create_test_transaction(100.0, user.user_id);
+        nairobi.location = Some("Nairobi".to_string());
+        detector.analyze_transaction_detailed(&nairobi, &user).unwrap();
+
+        let mut transaction = create_test_transaction(1500.0, user.user_id);
+        transaction.location = Some("Mombasa".to_string());
+        transaction.timestamp = nairobi.timestamp + Duration::minutes(20);
+        let result = detector.simulate_transaction(&transaction, &user).unwrap();
+        for expected_type in [RiskFactorType::LimitAnomaly, RiskFactorType::TravelAnomaly, RiskFactorType::SuspiciousPattern] {
+            assert!(result.risk_factors.iter().any(|factor| factor.factor_type == expected_type), "missing {:?}", expected_type);
+        }
+
+        // Every factor type the behavioral path emits, weighted as the configured map says
+        let weights = &config.risk_weights;
+        let expected: f64 = result.risk_factors.iter().map(|factor| {
+            let weight = match factor.factor_type {
+                RiskFactorType::AmountAnomaly => weights.amount,
+                RiskFactorType::TimeAnomaly => weights.time,
+                RiskFactorType::FrequencyAnomaly => weights.frequency,
+                RiskFactorType::RecipientAnomaly => weights.recipient,
+                RiskFactorType::LocationAnomaly => weights.location,
+                RiskFactorType::TravelAnomaly => weights.travel,
+                RiskFactorType::LimitAnomaly => weights.limit,
+                RiskFactorType::DeviceAnomaly => config.device_anomaly_weight,
+                RiskFactorType::SuspiciousPattern => weights.pattern,
+                // A takeover escalates the recommendation rather than adding to the score
+                RiskFactorType::BehaviorPattern => 0.0,
+                RiskFactorType::CustomRule => panic!("no rules are configured: {:?}", factor),
+            };
+            factor.score * weight
+        }).sum();
+        assert!(expected > 0.0 && expected < 1.0, "weighted sum {} should not need clamping", expected);
+        assert!((result.fraud_score - expected).abs() < 1e-9, "score {} != weighted sum {}", result.fraud_score, expected);
+    }
+
+    #[test]
+    fn test_terminal_factor_blocks_and_capped_factor_cannot_reject() {
+        // A terminal factor blocks on its own, even with no weight in the score
+        let config = SafeBankConfig {
+            device_anomaly_weight: 0.0,
+            terminal_risk_factors: vec!["device".to_string()],
+            ..SafeBankConfig::default()
+        };
+        let detector = FraudDetector::new(&config);
+        let user = create_test_user();
+        let mut transaction = create_test_transaction(100.0, user.user_id);
+        transaction.device_id = "stolen-phone".to_string();
+        let result = detector.simulate_transaction(&transaction, &user).unwrap();
+        assert!(result.fraud_score < config.fraud_threshold_low);
+        assert_eq!(result.recommendation, FraudRecommendation::Block);
+        // Also when behavioral scoring is turned off
+        let simple = FraudDetector::new(&SafeBankConfig { enable_behavioral_analysis: false, ..config.clone() });
+        assert_eq!(simple.simulate_transaction(&transaction, &user).unwrap().recommendation, FraudRecommendation::Block);
+        transaction.device_id = "test-device".to_string();
+        assert_ne!(simple.simulate_transaction(&transaction, &user).unwrap().recommendation, FraudRecommendation::Block);
+
+        // An unusual amount alone is enough to reject until the amount factor is capped
+        let uncapped = SafeBankConfig {
+            risk_weights: RiskWeights { amount: 1.0, ..RiskWeights::default() },
+            learning_grace_transactions: 0,
+            ..SafeBankConfig::default()
+        };
+        let noon = Utc::now().date_naive().and_hms_opt(12, 0, 0).unwrap().and_utc();
+        let transaction = Transaction { timestamp: noon, ..create_test_transaction(950.0, user.user_id) };
+        let result = FraudDetector::new(&uncapped).simulate_transaction(&transaction, &user).unwrap();
+        assert_eq!(result.recommendation, FraudRecommendation::Block);
+
+        let capped = SafeBankConfig {
+            risk_factor_bounds: HashMap::from([("amount".to_string(), FactorBounds { min: 0.0, max: 0.2 })]),
+            ..uncapped
+        };
+        let result = FraudDetector::new(&capped).simulate_transaction(&transaction, &user).unwrap();
+        assert!(result.fraud_score <= capped.fraud_threshold_high);
+        assert_ne!(result.recommendation, FraudRecommendation::Block);
+    }
+
+    #[test]
+    fn test_behaviors_shifting_together_signal_account_takeover() {
+        let config = SafeBankConfig::default();
+        let mut user = create_test_user();
+        user.devices.push(DeviceInfo { device_id: "old-tablet".to_string(), is_trusted: false, ..user.devices[0].clone() });
+        let at = |hour| chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 1, hour, 0, 0).unwrap();
+        let shifted = |hour, device: &str, recipient: &str| Transaction {
+            timestamp: at(hour),
+            device_id: device.to_string(),
+            recipient: recipient.to_string(),
+            ..create_test_transaction(100.0, user.user_id)
+        };
+
+        // Each shift on its own is moderate
+        let detector = FraudDetector::new(&config);
+        for transaction in [shifted(12, "old-tablet", "John Doe"), shifted(12, "test-device", "New Person"), shifted(3, "test-device", "John Doe")] {
+            let result = detector.simulate_transaction(&transaction, &user).unwrap();
+            assert!(!result.risk_factors.iter().any(|factor| factor.description.contains("takeover")));
+            assert_ne!(result.recommendation, FraudRecommendation::Block);
+        }
+
+        // Device, recipient and time shifting at once look like a takeover
+        let result = detector.simulate_transaction(&shifted(3, "old-tablet", "New Person"), &user).unwrap();
+        let takeover = result.risk_factors.iter().find(|factor| factor.description.contains("takeover")).unwrap();
+        assert_eq!(takeover.score, 1.0);
+        assert_eq!(result.recommendation, FraudRecommendation::Block);
+
+        // Shifts spread over transactions in the window count together: two step up, the third blocks
+        let mut detector = FraudDetector::new(&config);
+        let first = detector.analyze_transaction_detailed(&shifted(3, "test-device", "New Person"), &user).unwrap();
+        assert_eq!(first.recommendation, FraudRecommendation::RequireAdditionalAuth);
+        let mut second = shifted(3, "old-tablet", "John Doe");
+        second.timestamp += Duration::minutes(20);
+        assert_eq!(detector.analyze_transaction_detailed(&second, &user).unwrap().recommendation, FraudRecommendation::Block);
+    }
+
     #[test]
     fn test_behavioral_profile_update() {
         let config = SafeBankConfig::default();
@@ -454,10 +1730,195 @@ mod tests {
         let result = detector.update_behavioral_profile(user_id, &transactions);
         assert!(result.is_ok());
         
-        let profile = detector.user_profiles.get(&user_id).unwrap();
+        let profile = detector.storage.load_behavioral_profile(user_id).unwrap().unwrap();
         assert!((profile.typical_transaction_amount - 123.33).abs() < 0.1);
     }
 
+    #[test]
+    fn test_decay_window_drops_old_recipients() {
+        let config = SafeBankConfig {
+            profile_decay_half_life_days: Some(30.0),
+            profile_pattern_window_days: Some(60),
+            ..SafeBankConfig::default()
+        };
+        let now = Utc::now();
+        let mut detector = FraudDetector::with_clock(&config, Arc::new(MockClock::new(now)));
+        let user_id = Uuid::new_v4();
+
+        // A year of paying the old employer's canteen, then a month of the new one
+        let at = |days_ago: i64, amount: f64, recipient: &str| {
+            let mut transaction = create_test_transaction(amount, user_id);
+            transaction.timestamp = now - Duration::days(days_ago);
+            transaction.recipient = recipient.to_string();
+            transaction
+        };
+        let mut transactions: Vec<Transaction> = (90..365).step_by(5).map(|days| at(days, 1000.0, "Old Canteen")).collect();
+        transactions.extend((0..30).step_by(3).map(|days| at(days, 100.0, "New Canteen")));
+        transactions.sort_by_key(|t| t.timestamp);
+        detector.update_behavioral_profile(user_id, &transactions).unwrap();
+
+        let profile = detector.storage.load_behavioral_profile(user_id).unwrap().unwrap();
+        assert_eq!(profile.common_recipients, vec!["New Canteen".to_string()]);
+        assert_eq!(profile.last_updated, Some(now));
+        // The unweighted mean of 55 old and 10 recent amounts would be about 860
+        assert!(profile.typical_transaction_amount < 300.0);
+    }
+
+    #[test]
+    fn test_profiles_survive_export_and_import() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user = create_test_user();
+        let history: Vec<Transaction> = [100.0, 120.0, 80.0].iter()
+            .map(|amount| create_test_transaction(*amount, user.user_id))
+            .collect();
+        detector.update_behavioral_profile(user.user_id, &history).unwrap();
+
+        let serialized = serde_json::to_string(&detector.export_profiles().unwrap()).unwrap();
+
+        let mut restored = FraudDetector::new(&config);
+        restored.import_profiles(serde_json::from_str(&serialized).unwrap()).unwrap();
+
+        for amount in [100.0, 350.0, 1000.0] {
+            let transaction = create_test_transaction(amount, user.user_id);
+            let profile = detector.storage.load_behavioral_profile(user.user_id).unwrap().unwrap();
+            let restored_profile = restored.storage.load_behavioral_profile(user.user_id).unwrap().unwrap();
+            assert_eq!(
+                detector.analyze_amount_anomaly(&transaction, &profile),
+                restored.analyze_amount_anomaly(&transaction, &restored_profile)
+            );
+        }
+    }
+
+    #[test]
+    fn test_typical_times_follow_user_timezone() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user = UserProfile { timezone_offset_minutes: Some(180), ..create_test_user() };
+        detector.storage.save_user(&user).unwrap();
+
+        // Mornings around 9am in UTC+3 are 06:00 UTC
+        let morning = Utc::now().date_naive().and_hms_opt(6, 0, 0).unwrap().and_utc();
+        let history: Vec<Transaction> = (1..=5)
+            .map(|days| Transaction { timestamp: morning - Duration::days(days), ..create_test_transaction(100.0, user.user_id) })
+            .collect();
+        detector.update_behavioral_profile(user.user_id, &history).unwrap();
+        let profile = detector.storage.load_behavioral_profile(user.user_id).unwrap().unwrap();
+        assert_eq!(profile.typical_transaction_times, vec![9]);
+
+        let local_morning = Transaction { timestamp: morning + Duration::minutes(20), ..create_test_transaction(100.0, user.user_id) };
+        assert_eq!(detector.analyze_time_anomaly(&local_morning, &user, &profile), 0.0);
+        let local_evening = Transaction { timestamp: morning + Duration::hours(12), ..create_test_transaction(100.0, user.user_id) };
+        assert!(detector.analyze_time_anomaly(&local_evening, &user, &profile) > 0.0);
+    }
+
+    #[test]
+    fn test_location_anomaly() {
+        let config = SafeBankConfig::default();
+        let detector = FraudDetector::new(&config);
+        let mut user = create_test_user();
+        // Neither town has known coordinates, so labels are compared
+        user.behavioral_profile.geographic_patterns = vec!["Molo, Rift Valley".to_string()];
+
+        let at = |location: Option<&str>| {
+            let mut transaction = create_test_transaction(100.0, user.user_id);
+            transaction.location = location.map(str::to_string);
+            detector.analyze_location_anomaly(&transaction, &user.behavioral_profile)
+        };
+
+        assert_eq!(at(None), 0.0);
+        assert_eq!(at(Some("molo, rift valley")), 0.0);
+        assert_eq!(at(Some("Gilgil, Rift Valley")), 0.3);
+        assert_eq!(at(Some("Malindi, Coast")), 0.7);
+    }
+
+    #[test]
+    fn test_location_anomaly_grows_with_distance() {
+        let config = SafeBankConfig::default();
+        let detector = FraudDetector::new(&config);
+        let mut user = create_test_user();
+        user.behavioral_profile.geographic_patterns = vec!["Home".to_string()];
+        user.behavioral_profile.usual_coordinates = vec![GeoLocation::new(-1.2921, 36.8219, "Home")];
+
+        let at = |lat: f64, lon: f64| {
+            let mut transaction = create_test_transaction(100.0, user.user_id);
+            transaction.geo_location = Some(GeoLocation::new(lat, lon, "Somewhere"));
+            detector.analyze_location_anomaly(&transaction, &user.behavioral_profile)
+        };
+
+        let across_town = at(-1.2630, 36.8030);
+        let nakuru = at(-0.3031, 36.0800);
+        let mombasa = at(-4.0435, 39.6682);
+        let lagos = at(6.5244, 3.3792);
+        assert_eq!(across_town, 0.0);
+        assert!(0.0 < nakuru && nakuru < mombasa && mombasa < lagos);
+        assert_eq!(lagos, 0.9);
+    }
+
+    #[test]
+    fn test_impossible_travel_detection() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user = create_test_user();
+
+        let mut nairobi = create_test_transaction(100.0, user.user_id);
+        nairobi.location = Some("Nairobi".to_string());
+        detector.analyze_transaction_detailed(&nairobi, &user).unwrap();
+
+        // Mombasa is ~440 km away; 20 minutes later is not plausible
+        let mut mombasa = create_test_transaction(100.0, user.user_id);
+        mombasa.location = Some("Mombasa, Coast".to_string());
+        mombasa.timestamp = nairobi.timestamp + Duration::minutes(20);
+        let result = detector.analyze_transaction_detailed(&mombasa, &user).unwrap();
+        let travel = result.risk_factors.iter()
+            .find(|factor| factor.description.starts_with("Impossible travel"))
+            .expect("impossible travel flagged");
+        assert_eq!(travel.factor_type, RiskFactorType::TravelAnomaly);
+        assert!(travel.description.contains("Nairobi") && travel.description.contains("Mombasa"));
+
+        // A day later the same trip is fine
+        let mut back = create_test_transaction(100.0, user.user_id);
+        back.location = Some("Nairobi".to_string());
+        back.timestamp = mombasa.timestamp + Duration::days(1);
+        let result = detector.analyze_transaction_detailed(&back, &user).unwrap();
+        assert!(!result.risk_factors.iter().any(|factor| factor.description.starts_with("Impossible travel")));
+    }
+
+    #[test]
+    fn test_profile_learns_locations() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user_id = Uuid::new_v4();
+
+        let mut history = vec![
+            create_test_transaction(100.0, user_id),
+            create_test_transaction(100.0, user_id),
+            create_test_transaction(100.0, user_id),
+        ];
+        history[0].location = Some("Kisumu, Nyanza".to_string());
+        history[1].location = Some("Kisumu, Nyanza".to_string());
+        detector.update_behavioral_profile(user_id, &history).unwrap();
+
+        let profile = detector.storage.load_behavioral_profile(user_id).unwrap().unwrap();
+        assert_eq!(profile.geographic_patterns, vec!["Kisumu, Nyanza".to_string()]);
+    }
+
+    #[test]
+    fn test_stale_profiles() {
+        let config = SafeBankConfig::default();
+        let clock = MockClock::new(Utc::now());
+        let mut detector = FraudDetector::with_clock(&config, Arc::new(clock.clone()));
+
+        let old_user = Uuid::new_v4();
+        let fresh_user = Uuid::new_v4();
+        detector.update_behavioral_profile(old_user, &[create_test_transaction(100.0, old_user)]).unwrap();
+
+        clock.advance(Duration::days(40));
+        detector.update_behavioral_profile(fresh_user, &[create_test_transaction(100.0, fresh_user)]).unwrap();
+
+        assert_eq!(detector.stale_profiles(Duration::days(30)), vec![old_user]);
+    }
+
     #[test]
     fn test_statistics_tracking() {
         let config = SafeBankConfig::default();
@@ -471,4 +1932,240 @@ mod tests {
         let stats = detector.get_statistics();
         assert_eq!(stats["total_analyzed"], 1.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_detailed_analysis_explains_score() {
+        let config = SafeBankConfig::minimal();
+        let mut detector = FraudDetector::new(&config);
+
+        let user = create_test_user();
+        let transaction = create_test_transaction(config.single_transaction_limit, user.user_id);
+
+        let result = detector.analyze_transaction_detailed(&transaction, &user).unwrap();
+        assert!(result.fraud_score > 0.0);
+        assert!(result.risk_factors.iter().any(|factor| factor.factor_type == RiskFactorType::LimitAnomaly));
+        assert_eq!(detector.analyze_transaction(&transaction, &user).unwrap(), result.fraud_score);
+    }
+
+    #[test]
+    fn test_amount_anomaly_uses_spread() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user_id = Uuid::new_v4();
+
+        // A user whose amounts naturally swing between small and large payments
+        let history: Vec<Transaction> = [20.0, 30.0, 2500.0, 40.0, 25.0, 2400.0, 60.0, 1800.0]
+            .into_iter()
+            .map(|amount| create_test_transaction(amount, user_id))
+            .collect();
+        detector.update_behavioral_profile(user_id, &history).unwrap();
+        let profile = detector.storage.load_behavioral_profile(user_id).unwrap().unwrap();
+        assert_eq!(profile.amount_sample_count, 8);
+        assert!(profile.amount_std_dev > 1000.0);
+
+        // More than twice the mean, but within one standard deviation
+        let moderate = create_test_transaction(1900.0, user_id);
+        assert_eq!(detector.analyze_amount_anomaly(&moderate, &profile), 0.0);
+        let extreme = create_test_transaction(5000.0, user_id);
+        assert_eq!(detector.analyze_amount_anomaly(&extreme, &profile), 0.8);
+
+        // Without enough history the ratio buckets still apply
+        let cold = BehavioralProfile { amount_sample_count: 2, ..profile };
+        assert_eq!(detector.analyze_amount_anomaly(&moderate, &cold), 0.4);
+    }
+
+    #[test]
+    fn test_incremental_profile_tracks_recent_spending() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user_id = Uuid::new_v4();
+
+        let history: Vec<Transaction> = (0..10).map(|_| create_test_transaction(100.0, user_id)).collect();
+        detector.update_behavioral_profile(user_id, &history).unwrap();
+
+        // Spending shifts upward, to a new shop
+        let mut recent = Vec::new();
+        for _ in 0..5 {
+            let mut transaction = create_test_transaction(500.0, user_id);
+            transaction.recipient = "Agro Dealer".to_string();
+            detector.record_transaction(user_id, &transaction).unwrap();
+            recent.push(transaction);
+        }
+
+        let profile = detector.storage.load_behavioral_profile(user_id).unwrap().unwrap();
+        let plain_mean = history.iter().chain(&recent).map(|t| t.amount).sum::<f64>() / 15.0;
+        assert!(profile.typical_transaction_amount > plain_mean);
+        assert!(profile.typical_transaction_amount > 300.0);
+        assert_eq!(profile.amount_sample_count, 15);
+        assert_eq!(profile.common_recipients[0], "Agro Dealer");
+    }
+
+    #[test]
+    fn test_burst_of_transactions_raises_frequency_score() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user = create_test_user(); // Usually two transactions a day
+        let mut now = Utc::now();
+
+        let frequency_score = |detector: &mut FraudDetector, now: DateTime<Utc>| {
+            let mut transaction = create_test_transaction(100.0, user.user_id);
+            transaction.timestamp = now;
+            detector.analyze_transaction_detailed(&transaction, &user).unwrap()
+                .risk_factors
+                .into_iter()
+                .find(|factor| factor.factor_type == RiskFactorType::FrequencyAnomaly)
+                .map_or(0.0, |factor| factor.score)
+        };
+
+        assert_eq!(frequency_score(&mut detector, now), 0.0);
+        now += Duration::minutes(1);
+        assert_eq!(frequency_score(&mut detector, now), 0.0);
+
+        let mut scores = Vec::new();
+        for _ in 0..10 {
+            now += Duration::minutes(1);
+            scores.push(frequency_score(&mut detector, now));
+        }
+        assert!(scores.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert_eq!(scores.last().copied(), Some(0.9));
+
+        // A day later the burst has aged out of the window
+        now += Duration::days(2);
+        assert_eq!(frequency_score(&mut detector, now), 0.0);
+        assert_eq!(detector.recent_activity[&user.user_id].len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_device_scores_higher() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user = create_test_user();
+
+        let trusted = create_test_transaction(100.0, user.user_id);
+        let mut unknown = trusted.clone();
+        unknown.transaction_id = Uuid::new_v4();
+        unknown.device_id = "stolen-phone".to_string();
+
+        let trusted_result = detector.analyze_transaction_detailed(&trusted, &user).unwrap();
+        let unknown_result = FraudDetector::new(&config).analyze_transaction_detailed(&unknown, &user).unwrap();
+
+        assert!(!trusted_result.risk_factors.iter().any(|factor| factor.factor_type == RiskFactorType::DeviceAnomaly));
+        let device_factor = unknown_result.risk_factors.iter()
+            .find(|factor| factor.factor_type == RiskFactorType::DeviceAnomaly)
+            .unwrap();
+        assert!(device_factor.description.contains("stolen-phone"));
+        assert!((unknown_result.fraud_score - trusted_result.fraud_score - config.device_anomaly_weight).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_recipient_blocklist_and_allowlist() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user = create_test_user();
+
+        // A blocklisted recipient is blocked even with a small, ordinary amount
+        detector.add_to_blocklist("Scam Agent");
+        let mut transaction = create_test_transaction(100.0, user.user_id);
+        transaction.recipient = " scam agent ".to_string();
+        let result = detector.analyze_transaction_detailed(&transaction, &user).unwrap();
+        assert_eq!(result.fraud_score, 1.0);
+        assert_eq!(result.recommendation, FraudRecommendation::Block);
+        assert!(result.risk_factors[0].description.contains("blocklist"));
+
+        assert!(detector.remove_from_blocklist("Scam Agent"));
+        let result = detector.analyze_transaction_detailed(&transaction, &user).unwrap();
+        assert_ne!(result.recommendation, FraudRecommendation::Block);
+
+        // An allowlisted recipient no longer counts as unusual for that user
+        let mut transaction = create_test_transaction(100.0, user.user_id);
+        transaction.recipient = "New Shop".to_string();
+        let has_recipient_factor = |detector: &mut FraudDetector| {
+            detector.analyze_transaction_detailed(&transaction, &user).unwrap()
+                .risk_factors.iter()
+                .any(|factor| factor.factor_type == RiskFactorType::RecipientAnomaly)
+        };
+        assert!(has_recipient_factor(&mut detector));
+        detector.allow_recipient(user.user_id, "New Shop");
+        assert!(!has_recipient_factor(&mut detector));
+        assert!(detector.remove_allowed_recipient(user.user_id, "new shop"));
+        assert!(has_recipient_factor(&mut detector));
+    }
+
+    #[test]
+    fn test_fraud_labels_drive_detection_rates() {
+        let config = SafeBankConfig::default();
+        let mut detector = FraudDetector::new(&config);
+        let user = create_test_user();
+
+        let flagged = create_test_transaction(100.0, user.user_id);
+        let normal_a = create_test_transaction(100.0, user.user_id);
+        let normal_b = create_test_transaction(100.0, user.user_id);
+        detector.analyze_transaction(&normal_a, &user).unwrap();
+        assert!(detector.scores.contains_key(&normal_a.transaction_id));
+
+        // Pin the scores so one transaction sits above the flagging threshold
+        detector.scores.insert(flagged.transaction_id, 0.9);
+        detector.scores.insert(normal_a.transaction_id, 0.1);
+        detector.scores.insert(normal_b.transaction_id, 0.1);
+
+        // The flagged one turns out legitimate, and an unflagged one was fraud
+        detector.mark_as_fraud(flagged.transaction_id, false);
+        detector.mark_as_fraud(normal_a.transaction_id, true);
+        detector.mark_as_fraud(normal_b.transaction_id, false);
+
+        let labels = detector.labeled_outcomes();
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels[0].was_flagged, Some(true));
+        assert_eq!(detector.fraud_statistics.fraud_detected, 1);
+        assert_eq!(detector.fraud_statistics.false_positive_rate, 0.5);
+        assert_eq!(detector.fraud_statistics.true_positive_rate, 0.0);
+
+        // Relabeling replaces the earlier label
+        detector.mark_as_fraud(normal_a.transaction_id, false);
+        assert_eq!(detector.labeled_outcomes().len(), 3);
+        assert_eq!(detector.fraud_statistics.fraud_detected, 0);
+        assert!((detector.fraud_statistics.false_positive_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert!(detector.get_statistics().contains_key("false_positive_rate_percent"));
+    }
+
+    #[test]
+    fn test_reviewer_approval_damps_matching_transactions_until_window_ends() {
+        let config = SafeBankConfig::default();
+        let clock = MockClock::new(Utc::now());
+        let mut detector = FraudDetector::with_clock(&config, Arc::new(clock.clone()));
+        let user = create_test_user();
+
+        let mut transaction = create_test_transaction(900.0, user.user_id);
+        transaction.timestamp = clock.now();
+        let baseline = detector.simulate_transaction(&transaction, &user).unwrap().fraud_score;
+        assert!(baseline > 0.0);
+
+        // Approving one transaction lowers the score of the next near-identical one
+        detector.record_approved_pattern(user.user_id, "test recipient ", 880.0);
+        let damped = detector.simulate_transaction(&transaction, &user).unwrap().fraud_score;
+        assert!(damped < baseline);
+
+        // Only the amount and recipient are vouched for: an unrecognized device still scores in full
+        let mut unrecognized = transaction.clone();
+        unrecognized.device_id = "unknown-device".to_string();
+        let device_score = detector.simulate_transaction(&unrecognized, &user).unwrap().fraud_score;
+        let weights = config.risk_weight_map();
+        assert!((device_score - damped - weights["device"]).abs() < 1e-9);
+
+        // A different amount or recipient isn't the same pattern
+        let mut larger = transaction.clone();
+        larger.amount = 1500.0;
+        assert!(detector.simulate_transaction(&larger, &user).unwrap().fraud_score > damped);
+
+        // A blocklisted recipient stays blocked whatever was approved
+        detector.add_to_blocklist("Test Recipient");
+        assert_eq!(detector.simulate_transaction(&transaction, &user).unwrap().recommendation, FraudRecommendation::Block);
+        detector.remove_from_blocklist("Test Recipient");
+
+        // The damping wears off once the window has passed
+        clock.advance(Duration::hours(config.approval_damping_hours.unwrap() as i64 + 1));
+        transaction.timestamp = clock.now();
+        let later = detector.simulate_transaction(&transaction, &user).unwrap().fraud_score;
+        assert!(later > damped);
+    }
+}
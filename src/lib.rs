@@ -6,14 +6,34 @@
 //! - Transaction security with lightweight encryption
 //! - Offline capability and data synchronization
 
+pub mod anonymization;
+pub mod async_framework;
+pub mod audit;
 pub mod auth;
+pub mod clock;
 pub mod fraud_detection;
+mod logging;
+pub mod money;
 pub mod transaction;
 pub mod config;
+pub mod connectivity;
 pub mod errors;
+pub mod escalation;
+pub mod events;
+pub mod metrics;
+pub mod notification;
+pub mod otp;
+pub mod rate_limit;
+pub mod report;
+pub mod rules;
+pub mod shared;
+pub mod sealed;
+pub mod storage;
+pub mod system;
 pub mod utils;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -25,6 +45,15 @@ pub struct SafeBankFramework {
     auth_manager: auth::AuthManager,
     fraud_detector: fraud_detection::FraudDetector,
     transaction_manager: transaction::TransactionManager,
+    clock: clock::SharedClock,
+    notifier: notification::SharedNotifier,
+    connectivity: connectivity::SharedConnectivityProbe,
+    pseudonymizer: anonymization::Pseudonymizer,
+    escalations: Vec<escalation::Escalation>,
+    event_hooks: events::EventHooks,
+    storage: storage::SharedStorage,
+    /// Codes for transfers awaiting confirmation, keyed by transaction id
+    transfer_codes: otp::OtpManager,
 }
 
 /// User profile for rural banking context
@@ -33,12 +62,80 @@ pub struct UserProfile {
     pub user_id: Uuid,
     pub phone_number: String,
     pub pin_hash: String,
-    pub device_info: DeviceInfo,
+    /// Devices registered to the account, starting with the one used at sign-up.
+    /// Records from before multi-device support carry a single `device_info` object instead.
+    #[serde(alias = "device_info", deserialize_with = "deserialize_devices")]
+    pub devices: Vec<DeviceInfo>,
+    /// The sign-up device, accepted at login without being trusted first.
+    /// Records from before it was stored treat the first listed device as primary.
+    #[serde(default)]
+    pub primary_device_id: Option<String>,
     pub behavioral_profile: BehavioralProfile,
     pub created_at: DateTime<Utc>,
     pub last_login: Option<DateTime<Utc>>,
+    /// The registered device the user last logged in from
+    #[serde(default)]
+    pub last_login_device: Option<String>,
     pub failed_attempts: u32,
+    /// Set while an operator has frozen the account; unlike a lockout it never expires by itself
     pub is_locked: bool,
+    /// Why the account was frozen
+    #[serde(default)]
+    pub frozen_reason: Option<String>,
+    /// When fraud on the account was last confirmed; limit growth restarts from here
+    #[serde(default)]
+    pub last_confirmed_fraud: Option<DateTime<Utc>>,
+    /// The user's own timezone in minutes east of UTC; `None` uses the deployment's offset
+    #[serde(default)]
+    pub timezone_offset_minutes: Option<i32>,
+    #[serde(default)]
+    pub duress_pin_hash: Option<String>,
+    /// Hashes of the user's previous PINs, most recent first, so old PINs can't be reused
+    #[serde(default)]
+    pub pin_history: Vec<String>,
+    /// How long the user usually takes to enter their PIN
+    #[serde(default)]
+    pub pin_entry_baseline: Option<auth::EntryBaseline>,
+}
+
+impl UserProfile {
+    /// The registered device with the given id, if any
+    pub fn device(&self, device_id: &str) -> Option<&DeviceInfo> {
+        self.devices.iter().find(|device| device.device_id == device_id)
+    }
+
+    /// The device the account was registered with; `None` once it has been revoked
+    pub fn primary_device(&self) -> Option<&DeviceInfo> {
+        match &self.primary_device_id {
+            Some(device_id) => self.device(device_id),
+            None => self.devices.first(),
+        }
+    }
+
+    /// Record the primary device explicitly, so removing devices can't promote another one
+    pub(crate) fn pin_primary_device(&mut self) {
+        if self.primary_device_id.is_none() {
+            self.primary_device_id = self.devices.first().map(|device| device.device_id.clone());
+        }
+    }
+}
+
+/// Accept either a device list or the single device object stored by older versions
+fn deserialize_devices<'de, D>(deserializer: D) -> Result<Vec<DeviceInfo>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(DeviceInfo),
+        Many(Vec<DeviceInfo>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(device) => vec![device],
+        OneOrMany::Many(devices) => devices,
+    })
 }
 
 /// Device information for security tracking
@@ -50,6 +147,63 @@ pub struct DeviceInfo {
     pub app_version: String,
     pub is_trusted: bool,
     pub registered_at: DateTime<Utc>,
+    /// What the device reported it can do; `None` for clients that don't say
+    #[serde(default)]
+    pub capabilities: Option<utils::DeviceCapabilities>,
+    /// Second factor to ask this device for at login and step-up, from `auth::MfaMethod::for_device`
+    #[serde(default)]
+    pub mfa_method: auth::MfaMethod,
+    /// Key the bank issued to the device's secure hardware through `enroll_attestation_key`.
+    /// Anything the client sends here at registration is discarded.
+    #[serde(default)]
+    pub attestation_key: Option<String>,
+    /// When the bank trusted the device through `trust_device`. Unlike `is_trusted`, which the
+    /// client supplies at sign-up, only this counts towards a raised daily limit.
+    #[serde(default)]
+    pub trusted_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+impl DeviceInfo {
+    /// Trusted smartphone registered as "test-device", shared by the unit tests
+    pub(crate) fn test_device() -> Self {
+        DeviceInfo {
+            device_id: "test-device".to_string(),
+            device_type: "smartphone".to_string(),
+            os_version: Some("Android 8.0".to_string()),
+            app_version: "1.0.0".to_string(),
+            is_trusted: true,
+            registered_at: Utc::now(),
+            capabilities: None,
+            mfa_method: auth::MfaMethod::Otp,
+            attestation_key: None,
+            trusted_at: None,
+        }
+    }
+}
+
+/// A point on the map, with a label for display
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoLocation {
+    pub lat: f64,
+    pub lon: f64,
+    pub label: String,
+}
+
+impl GeoLocation {
+    pub fn new(lat: f64, lon: f64, label: impl Into<String>) -> Self {
+        Self { lat, lon, label: label.into() }
+    }
+
+    /// (latitude, longitude)
+    pub fn position(&self) -> (f64, f64) {
+        (self.lat, self.lon)
+    }
+
+    /// Whether the coordinates are finite and within latitude and longitude range
+    pub fn is_valid(&self) -> bool {
+        self.lat.is_finite() && self.lon.is_finite() && self.lat.abs() <= 90.0 && self.lon.abs() <= 180.0
+    }
 }
 
 /// Behavioral pattern for fraud detection
@@ -59,7 +213,32 @@ pub struct BehavioralProfile {
     pub typical_transaction_times: Vec<u8>, // Hour of day (0-23)
     pub common_recipients: Vec<String>,
     pub geographic_patterns: Vec<String>,
+    /// Coordinates of the places in `geographic_patterns` that could be located
+    #[serde(default)]
+    pub usual_coordinates: Vec<GeoLocation>,
     pub usage_frequency: f64, // transactions per day
+    /// Population standard deviation of transaction amounts
+    #[serde(default)]
+    pub amount_std_dev: f64,
+    /// Number of transactions the amount statistics were learned from
+    #[serde(default)]
+    pub amount_sample_count: usize,
+    /// When the profile was last learned; `None` for a profile that has never been learned
+    #[serde(default)]
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+/// Serializable copy of the framework's persistent state, for backup or moving between devices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameworkSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub users: Vec<UserProfile>,
+    pub transactions: Vec<Transaction>,
+    pub behavioral_profiles: HashMap<Uuid, BehavioralProfile>,
+    pub escalations: Vec<escalation::Escalation>,
+    /// Named manager state such as the recipient directory, keyed as in storage
+    #[serde(default)]
+    pub records: HashMap<String, String>,
 }
 
 /// Transaction record
@@ -72,9 +251,183 @@ pub struct Transaction {
     pub transaction_type: TransactionType,
     pub timestamp: DateTime<Utc>,
     pub location: Option<String>,
+    /// Where the transaction was initiated, when the device reported coordinates
+    #[serde(default)]
+    pub geo_location: Option<GeoLocation>,
     pub device_id: String,
     pub fraud_score: f64,
     pub status: TransactionStatus,
+    #[serde(default)]
+    pub fee: f64,
+    #[serde(default)]
+    pub levy: f64,
+    #[serde(default)]
+    pub fx_fee: f64,
+    /// Shared by the legs of a split transfer
+    #[serde(default)]
+    pub group_id: Option<Uuid>,
+    /// Why the fraud detector scored this transaction the way it did
+    #[serde(default)]
+    pub risk_factors: Vec<fraud_detection::RiskFactor>,
+    /// Client-generated key that makes retried submissions return the original transaction
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    /// The original transaction this one compensates
+    #[serde(default)]
+    pub reverses: Option<Uuid>,
+    /// The compensating transaction that reversed this one
+    #[serde(default)]
+    pub reversed_by: Option<Uuid>,
+    /// What the money was for, when the client said
+    #[serde(default)]
+    pub category: Option<TransactionCategory>,
+    /// Hash of the user's previous transaction in the tamper-evidence chain (`None` for the first)
+    #[serde(default)]
+    pub prev_hash: Option<String>,
+    /// Hash over this transaction's contents and `prev_hash`; absent on records from before the chain
+    #[serde(default)]
+    pub hash: Option<String>,
+    /// Hash over `hash` and the fields that legitimately change after commit (status, its history,
+    /// the balance stamp and reversal link), renewed each time the transaction is stored
+    #[serde(default)]
+    pub state_hash: Option<String>,
+    /// Every status change, oldest first, for dispute resolution
+    #[serde(default)]
+    pub status_history: Vec<StatusChange>,
+    /// The sender's balance once this transaction moved money; `None` while it hasn't
+    #[serde(default)]
+    pub balance_after: Option<f64>,
+    /// Reference the user attached, e.g. "June rent" or an invoice number
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+/// One entry in a transaction's status timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub from: TransactionStatus,
+    pub to: TransactionStatus,
+    pub at: DateTime<Utc>,
+    /// Who made the change: a reviewer's name, "customer", or `transaction::SYSTEM_ACTOR`
+    pub actor: String,
+    pub reason: Option<String>,
+}
+
+impl Transaction {
+    /// Move to a new status, appending the change to `status_history`
+    pub fn change_status(&mut self, to: TransactionStatus, at: DateTime<Utc>, actor: &str, reason: Option<String>) {
+        self.status_history.push(StatusChange {
+            from: self.status.clone(),
+            to: to.clone(),
+            at,
+            actor: actor.to_string(),
+            reason,
+        });
+        self.status = to;
+    }
+}
+
+/// A transaction as submitted by a client, before fraud scoring and processing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionRequest {
+    pub user_id: Uuid,
+    pub amount: f64,
+    pub recipient: String,
+    pub transaction_type: TransactionType,
+    pub idempotency_key: Option<String>,
+    /// Where the transaction was initiated, as "Town, Region"
+    pub location: Option<String>,
+    #[serde(default)]
+    pub geo_location: Option<GeoLocation>,
+    #[serde(default)]
+    pub category: Option<TransactionCategory>,
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+impl TransactionRequest {
+    pub fn new(user_id: Uuid, amount: f64, recipient: impl Into<String>, transaction_type: TransactionType) -> Self {
+        Self {
+            user_id,
+            amount,
+            recipient: recipient.into(),
+            transaction_type,
+            idempotency_key: None,
+            location: None,
+            geo_location: None,
+            category: None,
+            memo: None,
+        }
+    }
+
+    /// Deduplicate retries: a repeated key for the same user returns the first transaction
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Record where the transaction was initiated, for location anomaly checks
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Record the device's coordinates; the label doubles as the location for geofencing and display
+    pub fn with_geo_location(mut self, geo_location: GeoLocation) -> Self {
+        self.location = Some(geo_location.label.clone());
+        self.geo_location = Some(geo_location);
+        self
+    }
+
+    /// Tag what the money is for, for budgeting and per-category statistics
+    pub fn with_category(mut self, category: TransactionCategory) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    /// Attach a reference for the user's and recipient's records, checked against `max_memo_length`
+    pub fn with_memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+}
+
+/// A transaction held for the user's second factor
+#[derive(Debug, Clone)]
+pub struct TransferChallenge {
+    /// Transaction to confirm, or for a split the group id the device signs
+    pub transaction_id: Uuid,
+    /// A texted code, or the device's `auth::sign_attestation` over `transaction_id`
+    pub method: auth::MfaMethod,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A transfer, or the legs of a split transfer, waiting on the user's second factor.
+/// Stored under the transaction id, or the group id for a split.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PendingConfirmation {
+    /// Also held by fraud checks, so still awaiting review once confirmed
+    held_for_review: bool,
+    /// The transactions the confirmation releases
+    transaction_ids: Vec<Uuid>,
+    #[serde(default)]
+    method: auth::MfaMethod,
+    /// When a biometric confirmation lapses; a texted code carries its own expiry
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of `process_batch`: one result per request, in submission order, plus tallies
+#[derive(Debug, Default)]
+pub struct BatchResult {
+    pub results: Vec<Result<Transaction, errors::SafeBankError>>,
+    pub approved: usize,
+    /// Recorded but held for review (flagged or awaiting approval)
+    pub held: usize,
+    /// Recorded as rejected by fraud checks
+    pub rejected: usize,
+    /// Not recorded at all, e.g. over a limit or for an unknown user
+    pub failed: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -85,6 +438,49 @@ pub enum TransactionType {
     Deposit,
 }
 
+impl TransactionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::Transfer => "transfer",
+            TransactionType::Payment => "payment",
+            TransactionType::Withdrawal => "withdrawal",
+            TransactionType::Deposit => "deposit",
+        }
+    }
+}
+
+/// What a transaction was for
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TransactionCategory {
+    SchoolFees,
+    Agriculture,
+    Airtime,
+    Utilities,
+    Groceries,
+    Healthcare,
+    Transport,
+    Savings,
+    /// Anything not covered above, described by the client
+    Other(String),
+}
+
+impl TransactionCategory {
+    /// Stable snake_case name used in statistics keys; `Other` uses its normalized description
+    pub fn key(&self) -> String {
+        match self {
+            TransactionCategory::SchoolFees => "school_fees".to_string(),
+            TransactionCategory::Agriculture => "agriculture".to_string(),
+            TransactionCategory::Airtime => "airtime".to_string(),
+            TransactionCategory::Utilities => "utilities".to_string(),
+            TransactionCategory::Groceries => "groceries".to_string(),
+            TransactionCategory::Healthcare => "healthcare".to_string(),
+            TransactionCategory::Transport => "transport".to_string(),
+            TransactionCategory::Savings => "savings".to_string(),
+            TransactionCategory::Other(description) => description.trim().to_lowercase().replace(char::is_whitespace, "_"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum TransactionStatus {
     Pending,
@@ -92,85 +488,2251 @@ pub enum TransactionStatus {
     Rejected,
     Flagged,
     RequiresApproval,
+    /// Waiting for the customer to confirm with the code texted to them before it commits
+    RequiresStepUp,
 }
 
 impl SafeBankFramework {
     pub fn new(config: config::SafeBankConfig) -> Self {
+        Self::with_clock(config, clock::system_clock())
+    }
+
+    /// Create a framework whose managers share the given clock
+    pub fn with_clock(config: config::SafeBankConfig, clock: clock::SharedClock) -> Self {
+        Self::with_storage(config, storage::in_memory_storage(), clock)
+    }
+
+    /// Create a framework backed by the given storage, e.g. one that survives restarts
+    /// or is shared with other framework instances
+    pub fn with_storage(config: config::SafeBankConfig, storage: storage::SharedStorage, clock: clock::SharedClock) -> Self {
+        let system = Arc::new(system::SystemState::new(&config));
+        Self::with_system_state(config, storage, clock, system)
+    }
+
+    /// Create a framework that shares system-wide volume and login throttling with the other
+    /// instances over the same storage
+    pub fn with_system_state(config: config::SafeBankConfig, storage: storage::SharedStorage, clock: clock::SharedClock, system: system::SharedSystemState) -> Self {
         Self {
-            auth_manager: auth::AuthManager::new(&config),
-            fraud_detector: fraud_detection::FraudDetector::new(&config),
-            transaction_manager: transaction::TransactionManager::new(&config),
+            auth_manager: auth::AuthManager::with_system_state(&config, storage.clone(), clock.clone(), system.clone()),
+            fraud_detector: fraud_detection::FraudDetector::with_storage(&config, storage.clone(), clock.clone()),
+            transaction_manager: transaction::TransactionManager::with_system_state(&config, storage.clone(), clock.clone(), system),
+            connectivity: connectivity::probe_from_config(&config),
+            notifier: notification::notifier_from_config(&config),
+            transfer_codes: otp::OtpManager::new(config.otp_length, config.otp_ttl_seconds, config.otp_max_attempts, storage.clone(), "transfer_code/", clock.clone()),
             config,
+            clock,
+            pseudonymizer: anonymization::Pseudonymizer::new(utils::random_hex(16)),
+            escalations: Vec::new(),
+            event_hooks: events::EventHooks::new(),
+            storage,
         }
     }
 
+    /// Set the channel used to deliver user notifications
+    pub fn set_notifier(&mut self, notifier: notification::SharedNotifier) {
+        self.notifier = notifier;
+    }
+
+    /// Replace the reachability probe, e.g. with `connectivity::FixedProbe` in tests
+    pub fn set_connectivity_probe(&mut self, probe: connectivity::SharedConnectivityProbe) {
+        self.connectivity = probe;
+    }
+
+    /// Probe the network now
+    pub fn connectivity_status(&self) -> utils::ConnectivityStatus {
+        self.connectivity.check()
+    }
+
+    /// Whether a sync should run now, given when the last one finished and the current connectivity
+    pub fn is_sync_due(&self, last_sync: DateTime<Utc>) -> bool {
+        self.config.sync_interval(&self.connectivity_status())
+            .is_some_and(|interval| self.clock.now() - last_sync >= interval)
+    }
+
+    /// Register a callback fired when transactions are created, approved, flagged, rejected or reversed.
+    /// Callbacks run synchronously after the transaction is committed; a panicking callback is logged and skipped.
+    pub fn on_transaction_event(&mut self, handler: events::TransactionEventHandler) {
+        self.event_hooks.register(handler);
+    }
+
     /// Initialize a new user profile
     pub fn register_user(&mut self, phone_number: String, pin: String, device_info: DeviceInfo) -> Result<UserProfile, errors::SafeBankError> {
         self.auth_manager.register_user(phone_number, pin, device_info)
     }
 
+    /// Cache a user's credential on the device for offline login
+    pub fn cache_credentials(&mut self, user_id: Uuid) -> Result<auth::CachedCredential, errors::SafeBankError> {
+        self.auth_manager.cache_credentials(user_id)
+    }
+
     /// Authenticate user with PIN and device verification
     pub fn authenticate_user(&mut self, phone_number: &str, pin: &str, device_id: &str) -> Result<UserProfile, errors::SafeBankError> {
-        self.auth_manager.authenticate(phone_number, pin, device_id)
+        self.authenticate_with_factors(phone_number, pin, device_id, None, None)
+    }
+
+    /// Authenticate a request arriving from a known network address; failures are throttled per address as well as per device
+    pub fn authenticate_user_from(&mut self, phone_number: &str, pin: &str, device_id: &str, client_address: &str) -> Result<UserProfile, errors::SafeBankError> {
+        self.authenticate_with_factors(phone_number, pin, device_id, Some(client_address), None)
+    }
+
+    /// Issue a one-time code and deliver it to the user's phone through the notifier
+    pub fn send_otp(&mut self, phone_number: &str) -> Result<(), errors::SafeBankError> {
+        let code = self.auth_manager.issue_otp(phone_number)?;
+        self.notifier.send(phone_number, &format!("SafeBank: Your verification code is {}. Do not share it.", code))
+    }
+
+    /// Authenticate user with PIN, device verification and a one-time code from `send_otp`
+    pub fn authenticate_user_with_otp(&mut self, phone_number: &str, pin: &str, device_id: &str, otp: &str) -> Result<UserProfile, errors::SafeBankError> {
+        self.authenticate_with_factors(phone_number, pin, device_id, None, Some(otp))
+    }
+
+    /// Authenticate with PIN and report what else is needed to finish the login.
+    /// On `MfaRequired` with `MfaMethod::Otp` a one-time code is sent to the user's phone for
+    /// `complete_mfa`; with `MfaMethod::Biometric` the device signs the challenge itself.
+    pub fn authenticate_ex(&mut self, phone_number: &str, pin: &str, device_id: &str, entry_metrics: Option<auth::EntryMetrics>) -> Result<auth::AuthOutcome, errors::SafeBankError> {
+        let (outcome, used_duress_pin) = self.auth_manager.authenticate_ex_checked(phone_number, pin, device_id, entry_metrics.as_ref())?;
+
+        match &outcome {
+            auth::AuthOutcome::Authenticated(user) => {
+                let user_id = user.user_id;
+                self.escalate_duress_login(user_id, used_duress_pin);
+            }
+            auth::AuthOutcome::MfaRequired { method: auth::MfaMethod::Otp, .. } => self.send_otp(phone_number)?,
+            auth::AuthOutcome::MfaRequired { method: auth::MfaMethod::Biometric, .. } => {}
+            auth::AuthOutcome::DeviceVerificationRequired { .. } => {}
+        }
+
+        Ok(outcome)
+    }
+
+    /// Finish an `MfaRequired` login with the code sent to the user's phone, or for a
+    /// `Biometric` challenge the device's `auth::sign_attestation` over the challenge id
+    pub fn complete_mfa(&mut self, challenge_id: Uuid, code: &str) -> Result<UserProfile, errors::SafeBankError> {
+        let (user, used_duress_pin) = self.auth_manager.complete_mfa_checked(challenge_id, code)?;
+        self.escalate_duress_login(user.user_id, used_duress_pin);
+        Ok(user)
+    }
+
+    fn authenticate_with_factors(&mut self, phone_number: &str, pin: &str, device_id: &str, client_address: Option<&str>, otp: Option<&str>) -> Result<UserProfile, errors::SafeBankError> {
+        let (user, used_duress_pin) = self.auth_manager.authenticate_checked(phone_number, pin, device_id, client_address, otp)?;
+        self.escalate_duress_login(user.user_id, used_duress_pin);
+        Ok(user)
+    }
+
+    fn escalate_duress_login(&mut self, user_id: Uuid, used_duress_pin: bool) {
+        if used_duress_pin {
+            self.raise_escalation(
+                escalation::EscalationKind::DuressAuthentication,
+                errors::ErrorSeverity::Critical,
+                Some(user_id),
+                "User authenticated with duress PIN".to_string(),
+            );
+        }
+    }
+
+    /// Authenticate against a locally cached credential while offline
+    pub fn authenticate_offline(&mut self, phone_number: &str, pin: &str, device_id: &str) -> Result<auth::OfflineSession, errors::SafeBankError> {
+        let (session, used_duress_pin) = self.auth_manager.authenticate_offline_checked(phone_number, pin, device_id)?;
+        self.escalate_duress_login(session.user_id, used_duress_pin);
+        Ok(session)
+    }
+
+    /// Sync offline logins back into the user store once connectivity returns
+    pub fn sync_offline_logins(&mut self) -> Result<usize, errors::SafeBankError> {
+        self.auth_manager.sync_offline_logins()
+    }
+
+    /// Create and queue an offline transaction for a user authenticated offline.
+    /// The session must be one `authenticate_offline` issued and still valid, and
+    /// amounts are restricted to the configured offline transaction limit.
+    pub fn create_offline_transaction(&mut self, session: &auth::OfflineSession, amount: f64, recipient: String, transaction_type: TransactionType, keyring: &transaction::SigningKeyring) -> Result<transaction::OfflineTransaction, errors::SafeBankError> {
+        let session = self.auth_manager.issued_offline_session(session)?;
+        // A freeze placed before the device went offline still applies to its cached session
+        self.auth_manager.active_user(session.user_id)?;
+        let limit = self.config.offline_transaction_limit;
+        if amount > limit {
+            return Err(errors::SafeBankError::TransactionLimitExceeded { amount, limit });
+        }
+
+        let transaction = self.new_transaction(session.user_id, session.device_id.clone(), amount, &recipient, transaction_type)?;
+        self.transaction_manager.queue_offline_transaction(&transaction, keyring)
+    }
+
+    /// Apply offline transactions collected while disconnected; see `TransactionManager::sync_offline_batch`
+    /// Each transaction is scored like an online one before it is applied. The customer isn't there to
+    /// enter a code, so a step-up recommendation holds the transaction for review instead.
+    pub fn sync_offline_batch(&mut self, batch: Vec<transaction::OfflineTransaction>, keyring: &transaction::SigningKeyring) -> transaction::SyncReport {
+        let auth_manager = &self.auth_manager;
+        let fraud_detector = &mut self.fraud_detector;
+        let duress_cap = self.config.duress_transaction_cap;
+        let mut duress_holds = Vec::new();
+        let report = self.transaction_manager.sync_offline_batch_with(batch, keyring, |transaction| {
+            let user = auth_manager.active_user(transaction.user_id)?;
+            let analysis = fraud_detector.analyze_transaction_detailed(transaction, &user)?;
+            transaction.fraud_score = analysis.fraud_score;
+            transaction.risk_factors = analysis.risk_factors;
+            transaction.status = match Self::recommended_status(&analysis.recommendation) {
+                TransactionStatus::RequiresStepUp => TransactionStatus::RequiresApproval,
+                status => status,
+            };
+            if auth_manager.is_under_duress(user.user_id) && transaction.amount > duress_cap {
+                transaction.status = TransactionStatus::RequiresApproval;
+                duress_holds.push((transaction.user_id, transaction.transaction_id, transaction.amount));
+            }
+            Ok(())
+        });
+        for (user_id, transaction_id, amount) in duress_holds {
+            self.escalate_duress_hold(user_id, transaction_id, amount);
+        }
+        report
+    }
+
+    /// Set a duress PIN for a user
+    pub fn set_duress_pin(&mut self, user_id: Uuid, duress_pin: &str) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.set_duress_pin(user_id, duress_pin)
+    }
+
+    /// Add a device to a user's account; it must be trusted before it can log in
+    pub fn register_device(&mut self, user_id: Uuid, device_info: DeviceInfo) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.register_device(user_id, device_info)
+    }
+
+    /// Trust a registered device
+    pub fn trust_device(&mut self, user_id: Uuid, device_id: String) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.trust_device(user_id, device_id)
+    }
+
+    /// Record what the device the user logs in with can do; the primary device's capabilities
+    /// set the hashing cost of PINs changed later
+    pub fn update_device_capabilities(&mut self, phone_number: &str, pin: &str, device_id: &str, capabilities: utils::DeviceCapabilities) -> Result<(), errors::SafeBankError> {
+        let user = self.authenticate_user(phone_number, pin, device_id)?;
+        self.auth_manager.set_device_capabilities(user.user_id, device_id, capabilities)
+    }
+
+    /// Issue an attestation key to the secure hardware of the device the user logs in with.
+    /// Once the device also reports a fingerprint sensor or camera, it confirms logins and
+    /// transfers with a biometric match rather than a texted code.
+    pub fn enroll_device_attestation(&mut self, phone_number: &str, pin: &str, device_id: &str) -> Result<String, errors::SafeBankError> {
+        let user = self.authenticate_user(phone_number, pin, device_id)?;
+        self.auth_manager.enroll_attestation_key(user.user_id, device_id)
+    }
+
+    /// Remove a device from a user's account
+    pub fn revoke_device(&mut self, user_id: Uuid, device_id: &str) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.revoke_device(user_id, device_id)
+    }
+
+    /// Devices registered to a user
+    pub fn list_devices(&self, user_id: Uuid) -> Result<Vec<DeviceInfo>, errors::SafeBankError> {
+        self.auth_manager.list_devices(user_id)
+    }
+
+    /// Every user profile, for backup or migration
+    pub fn export_users(&self) -> Result<Vec<UserProfile>, errors::SafeBankError> {
+        self.auth_manager.export_users()
+    }
+
+    /// Bulk-load exported user profiles, skipping users already registered
+    pub fn import_users(&mut self, users: Vec<UserProfile>) -> Result<auth::ImportReport, errors::SafeBankError> {
+        self.auth_manager.import_users(users)
+    }
+
+    /// Security audit events matching the filter
+    pub fn query_audit(&self, filter: &audit::AuditFilter) -> Vec<audit::AuditEvent> {
+        self.auth_manager.query_audit(filter)
+    }
+
+    /// Change a user's PIN after verifying the current one
+    pub fn change_pin(&mut self, user_id: Uuid, old_pin: &str, new_pin: &str) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.change_pin(user_id, old_pin, new_pin)
+    }
+
+    /// Reset a user's PIN without the current one (staff-assisted recovery)
+    pub fn reset_pin(&mut self, user_id: Uuid, new_pin: &str) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.reset_pin(user_id, new_pin)
+    }
+
+    /// Set the timezone a user's typical transaction times are learned in, in minutes east of UTC.
+    /// Typical hours already learned are re-learned from the user's history in the new timezone.
+    pub fn set_timezone(&mut self, user_id: Uuid, offset_minutes: i32) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.set_timezone(user_id, offset_minutes)?;
+        self.update_behavioral_profile(user_id)
+    }
+
+    /// Freeze an account so it can neither log in nor transact until unfrozen
+    pub fn freeze_account(&mut self, user_id: Uuid, reason: &str) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.freeze_account(user_id, reason)
+    }
+
+    /// Lift an administrative freeze
+    pub fn unfreeze_account(&mut self, user_id: Uuid) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.unfreeze_account(user_id)
+    }
+
+    /// Release a user's duress hold once staff have confirmed they are safe
+    pub fn clear_duress(&mut self, user_id: Uuid) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.clear_duress(user_id)
     }
 
     /// Process a transaction with fraud detection
     pub fn process_transaction(&mut self, user_id: Uuid, amount: f64, recipient: String, transaction_type: TransactionType) -> Result<Transaction, errors::SafeBankError> {
-        // Get user profile for fraud analysis
+        self.process_request(TransactionRequest::new(user_id, amount, recipient, transaction_type))
+    }
+
+    /// Process a transaction request.
+    /// If the request carries an idempotency key the user has already used, the stored
+    /// transaction is returned without being processed (or charged) again.
+    pub fn process_request(&mut self, request: TransactionRequest) -> Result<Transaction, errors::SafeBankError> {
+        // Get user profile for fraud analysis; frozen accounts can't transact
+        let user = self.auth_manager.active_user(request.user_id)?;
+
+        if let Some(key) = &request.idempotency_key {
+            if let Some(existing) = self.transaction_manager.find_by_idempotency_key(request.user_id, key)? {
+                return Ok(existing);
+            }
+        }
+
+        let mut transaction = self.new_transaction(request.user_id, Self::transacting_device(&user)?, request.amount, &request.recipient, request.transaction_type)?;
+        transaction.idempotency_key = request.idempotency_key;
+        transaction.location = request.location;
+        transaction.geo_location = request.geo_location;
+        transaction.category = request.category;
+        transaction.memo = request.memo;
+        self.submit_transaction(&user, transaction)
+    }
+
+    /// The customer-facing error for a transaction fraud checks rejected, explaining its main risk factors
+    pub fn fraud_rejection(&self, transaction: &Transaction) -> Option<errors::SafeBankError> {
+        (transaction.status == TransactionStatus::Rejected && transaction.fraud_score > 0.0).then(|| {
+            fraud_detection::fraud_error(transaction.fraud_score, &transaction.risk_factors, self.config.fraud_explanation_max_reasons, false)
+        })
+    }
+
+    /// Preview the fraud analysis a transaction would receive, without processing it.
+    /// Nothing is stored: balances, daily limits, statistics and the fraud history are unchanged.
+    pub fn simulate_transaction(&self, user_id: Uuid, amount: f64, recipient: &str, transaction_type: TransactionType) -> Result<fraud_detection::FraudAnalysisResult, errors::SafeBankError> {
         let user = self.auth_manager.get_user_by_id(user_id)?;
-        
-        // Create transaction
-        let mut transaction = Transaction {
+        let recipient = self.transaction_manager.protected_recipient(recipient)?;
+        let transaction = self.draft_transaction(user_id, Self::transacting_device(&user)?, amount, recipient, transaction_type);
+        self.fraud_detector.simulate_transaction(&transaction, &user)
+    }
+
+    /// Process queued requests one after another. Each item succeeds or fails on its own,
+    /// and daily limits apply cumulatively in order, so item N sees the effect of items 1..N-1.
+    pub fn process_batch(&mut self, requests: Vec<TransactionRequest>) -> BatchResult {
+        let mut batch = BatchResult::default();
+        for request in requests {
+            let result = self.process_request(request);
+            match &result {
+                Ok(transaction) => match transaction.status {
+                    TransactionStatus::Approved => batch.approved += 1,
+                    TransactionStatus::Rejected => batch.rejected += 1,
+                    _ => batch.held += 1,
+                },
+                Err(_) => batch.failed += 1,
+            }
+            batch.results.push(result);
+        }
+        batch
+    }
+
+    /// Process a transfer denominated in a foreign currency.
+    /// The amount is converted to the local currency at the configured mid-market rate less the FX margin,
+    /// and the margin is recorded as the transaction's `fx_fee`.
+    pub fn process_cross_currency_transaction(&mut self, user_id: Uuid, amount: f64, currency: &str, recipient: String, transaction_type: TransactionType) -> Result<Transaction, errors::SafeBankError> {
+        let user = self.auth_manager.active_user(user_id)?;
+        let (converted_amount, fx_fee) = self.transaction_manager.convert_to_local(amount, currency)?;
+
+        let mut transaction = self.new_transaction(user_id, Self::transacting_device(&user)?, converted_amount, &recipient, transaction_type)?;
+        transaction.fx_fee = fx_fee;
+        self.submit_transaction(&user, transaction)
+    }
+
+    /// The device a user's online transaction is stamped with: the one they last logged in from,
+    /// or the primary device if that one is gone
+    fn transacting_device(user: &UserProfile) -> Result<String, errors::SafeBankError> {
+        user.last_login_device.as_deref()
+            .and_then(|device_id| user.device(device_id))
+            .or_else(|| user.primary_device())
+            .map(|device| device.device_id.clone())
+            .ok_or_else(|| errors::SafeBankError::UnrecognizedDevice { device_id: String::new() })
+    }
+
+    /// Build a pending transaction; fraud matching operates on the stored (possibly hashed) recipient
+    fn new_transaction(&mut self, user_id: Uuid, device_id: String, amount: f64, recipient: &str, transaction_type: TransactionType) -> Result<Transaction, errors::SafeBankError> {
+        let recipient = self.transaction_manager.protect_recipient(recipient)?;
+        Ok(self.draft_transaction(user_id, device_id, amount, recipient, transaction_type))
+    }
+
+    /// An unsaved pending transaction; the recipient must already be in its stored form
+    fn draft_transaction(&self, user_id: Uuid, device_id: String, amount: f64, recipient: String, transaction_type: TransactionType) -> Transaction {
+        Transaction {
             transaction_id: Uuid::new_v4(),
             user_id,
             amount,
-            recipient: recipient.clone(),
+            recipient,
             transaction_type,
-            timestamp: Utc::now(),
+            timestamp: self.clock.now(),
             location: None,
-            device_id: user.device_info.device_id.clone(),
+            geo_location: None,
+            device_id,
             fraud_score: 0.0,
             status: TransactionStatus::Pending,
-        };
+            fee: 0.0,
+            levy: 0.0,
+            fx_fee: 0.0,
+            group_id: None,
+            risk_factors: Vec::new(),
+            idempotency_key: None,
+            reverses: None,
+            reversed_by: None,
+            category: None,
+            prev_hash: None,
+            hash: None,
+            state_hash: None,
+            status_history: Vec::new(),
+            balance_after: None,
+            memo: None,
+        }
+    }
+
+    /// Run fraud detection on a new transaction, decide its status, and hand it to the transaction manager
+    fn submit_transaction(&mut self, user: &UserProfile, mut transaction: Transaction) -> Result<Transaction, errors::SafeBankError> {
+        // Don't spend fraud analysis on a transaction that can't succeed
+        self.transaction_manager.precheck(&transaction)?;
 
         // Run fraud detection
-        transaction.fraud_score = self.fraud_detector.analyze_transaction(&transaction, &user)?;
-        
-        // Determine transaction status based on fraud score
-        transaction.status = if transaction.fraud_score > self.config.fraud_threshold_high {
-            TransactionStatus::Rejected
-        } else if transaction.fraud_score > self.config.fraud_threshold_medium {
-            TransactionStatus::RequiresApproval
-        } else {
-            TransactionStatus::Approved
-        };
+        let analysis = self.fraud_detector.analyze_transaction_detailed(&transaction, user)?;
+        transaction.fraud_score = analysis.fraud_score;
+        transaction.risk_factors = analysis.risk_factors;
+        transaction.status = self.decide_status(user.user_id, &transaction, &analysis.recommendation);
+        self.submit_decided(user, transaction, false).map(|(transaction, _)| transaction)
+    }
+
+    /// Commit a scored transaction, holding it for a confirmation code when fraud checks asked for step-up
+    /// authentication, or when it's a transfer over the threshold (or `force_confirmation` is set) and
+    /// wasn't rejected outright
+    fn submit_decided(&mut self, user: &UserProfile, mut transaction: Transaction, force_confirmation: bool) -> Result<(Transaction, Option<TransferChallenge>), errors::SafeBankError> {
+        let (needs_confirmation, held_for_review) = self.hold_for_confirmation(&mut transaction, force_confirmation);
 
         // Process transaction
-        self.transaction_manager.process_transaction(transaction)
+        let usage_before = self.transaction_manager.daily_usage(user.user_id);
+        let transaction = self.transaction_manager.process_transaction(transaction)?;
+        let challenge = needs_confirmation.then(|| {
+            let pending = PendingConfirmation { held_for_review, transaction_ids: vec![transaction.transaction_id], ..Default::default() };
+            self.open_transfer_challenge(user, &transaction, pending)
+        }).transpose()?;
+        self.learn_recipient(user, &transaction);
+        self.notify_outcome(&user.phone_number, &transaction);
+        self.after_commit(user, usage_before);
+        self.emit_committed(&transaction);
+
+        Ok((transaction, challenge))
     }
 
-    /// Update user behavioral profile based on transaction history
-    pub fn update_behavioral_profile(&mut self, user_id: Uuid) -> Result<(), errors::SafeBankError> {
-        let transactions = self.transaction_manager.get_user_transactions(user_id)?;
-        self.fraud_detector.update_behavioral_profile(user_id, &transactions)?;
-        Ok(())
+    /// Count an approved payment towards making its recipient one of the user's usual ones.
+    /// Learning is best effort; a storage failure here doesn't undo the committed payment.
+    fn learn_recipient(&mut self, user: &UserProfile, transaction: &Transaction) {
+        if transaction.status != TransactionStatus::Approved {
+            return;
+        }
+        let recipient = self.transaction_manager.reveal_recipient(&transaction.recipient)
+            .unwrap_or_else(|| transaction.recipient.clone());
+        let _ = self.fraud_detector.record_completed_payment(user, &recipient, &transaction.transaction_type);
     }
 
-    /// Get fraud statistics for monitoring
-    pub fn get_fraud_statistics(&self) -> HashMap<String, f64> {
-        self.fraud_detector.get_statistics()
+    fn requires_confirmation(&self, transaction: &Transaction) -> bool {
+        transaction.transaction_type == TransactionType::Transfer
+            && self.config.transfer_confirmation_threshold.is_some_and(|threshold| transaction.amount > threshold)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Whether a scored transaction must wait for a confirmation code, and whether fraud checks also
+    /// hold it for review. One needing a code that isn't held for review is moved to `RequiresStepUp`.
+    fn hold_for_confirmation(&self, transaction: &mut Transaction, force_confirmation: bool) -> (bool, bool) {
+        let needs_confirmation = transaction.status == TransactionStatus::RequiresStepUp
+            || (transaction.status != TransactionStatus::Rejected
+                && (force_confirmation || self.requires_confirmation(transaction)));
+        let held_for_review = transaction.status == TransactionStatus::RequiresApproval;
+        if needs_confirmation && !held_for_review {
+            transaction.status = TransactionStatus::RequiresStepUp;
+        }
+        (needs_confirmation, held_for_review)
+    }
 
-    #[test]
-    fn test_framework_initialization() {
-        let config = config::SafeBankConfig::default();
-        let framework = SafeBankFramework::new(config);
-        // Basic initialization test
-        assert!(framework.config.max_failed_attempts > 0);
+    /// Text the user a code for a held transfer, or for a split's combined transfer carrying its group id.
+    /// The pending confirmation is stored, so it survives a restart and can be completed on any
+    /// instance sharing the backend.
+    fn open_transfer_challenge(&mut self, user: &UserProfile, transaction: &Transaction, mut pending: PendingConfirmation) -> Result<TransferChallenge, errors::SafeBankError> {
+        // Ask for the second factor stored for the device the transfer came from
+        let method = auth::AuthManager::mfa_method(user, &transaction.device_id);
+        let expires_at = self.clock.now() + chrono::Duration::seconds(self.config.otp_ttl_seconds as i64);
+        pending.method = method;
+        match method {
+            auth::MfaMethod::Otp => {
+                let code = self.transfer_codes.issue_otp(&transaction.transaction_id.to_string())?;
+                storage::save_json(self.storage.as_ref(), &Self::confirmation_key(transaction.transaction_id), &pending)?;
+
+                let recipient = self.transaction_manager.reveal_recipient(&transaction.recipient)
+                    .unwrap_or_else(|| transaction.recipient.clone());
+                let message = notification::format_transfer_confirmation(transaction.amount, &recipient, &code, &self.config.local_currency);
+                // The user can ask for the transfer to be re-sent if the text doesn't arrive
+                let _ = self.notifier.send(&user.phone_number, &message);
+            }
+            auth::MfaMethod::Biometric => {
+                // The app prompts for the match and signs the transaction id; nothing is texted
+                pending.expires_at = Some(expires_at);
+                storage::save_json(self.storage.as_ref(), &Self::confirmation_key(transaction.transaction_id), &pending)?;
+            }
+        }
+
+        Ok(TransferChallenge {
+            transaction_id: transaction.transaction_id,
+            method,
+            expires_at,
+        })
+    }
+
+    fn confirmation_key(transaction_id: Uuid) -> String {
+        format!("transfer_confirmation/{}", transaction_id)
+    }
+
+    /// The confirmation a transaction is waiting on, kept under its split's group id if it has one
+    fn pending_confirmation(&self, transaction: &Transaction) -> Result<Option<PendingConfirmation>, errors::SafeBankError> {
+        let key = transaction.group_id.unwrap_or(transaction.transaction_id);
+        storage::load_json(self.storage.as_ref(), &Self::confirmation_key(key))
+    }
+
+    /// Forget a transfer's pending confirmation and any code still outstanding for it
+    fn clear_confirmation(&mut self, transaction_id: Uuid) -> Result<(), errors::SafeBankError> {
+        self.storage.remove_record(&Self::confirmation_key(transaction_id))?;
+        self.transfer_codes.revoke_otp(&transaction_id.to_string())
+    }
+
+    /// Start a transfer that only commits once the user confirms it with the code texted to them.
+    /// The transaction is recorded as `RequiresStepUp`; no challenge is issued if fraud checks reject it.
+    pub fn initiate_transfer(&mut self, user_id: Uuid, amount: f64, recipient: String) -> Result<(Transaction, Option<TransferChallenge>), errors::SafeBankError> {
+        let user = self.auth_manager.active_user(user_id)?;
+        let mut transaction = self.new_transaction(user_id, Self::transacting_device(&user)?, amount, &recipient, TransactionType::Transfer)?;
+        self.transaction_manager.precheck(&transaction)?;
+
+        let analysis = self.fraud_detector.analyze_transaction_detailed(&transaction, &user)?;
+        transaction.fraud_score = analysis.fraud_score;
+        transaction.risk_factors = analysis.risk_factors;
+        transaction.status = self.decide_status(user_id, &transaction, &analysis.recommendation);
+        self.submit_decided(&user, transaction, true)
+    }
+
+    /// Finish a transaction held in `RequiresStepUp` with the code texted to the user, or for a
+    /// `Biometric` challenge the device's `auth::sign_attestation` over the challenge's transaction id.
+    /// Confirming any leg of a split transfer releases every leg, as they share one confirmation.
+    /// A wrong code leaves the transfer pending until `otp_max_attempts` wrong codes discard it, and each
+    /// one, like a bad signature, counts towards the account lockout. A transfer also held by fraud
+    /// checks still awaits review.
+    pub fn confirm_transfer(&mut self, transaction_id: Uuid, code: &str) -> Result<Transaction, errors::SafeBankError> {
+        let transaction = self.transaction_manager.get_transaction(transaction_id)?;
+        let Some(pending) = self.pending_confirmation(&transaction)? else {
+            return Err(errors::SafeBankError::InvalidTransactionState {
+                current_state: "Transaction is not awaiting confirmation".to_string(),
+            });
+        };
+        // An account frozen while the code was outstanding can't confirm it
+        let user = self.auth_manager.active_user(transaction.user_id)?;
+        if self.auth_manager.is_account_locked(&user.phone_number) {
+            return Err(errors::SafeBankError::AccountLocked);
+        }
+        let confirmation_id = transaction.group_id.unwrap_or(transaction_id);
+        match pending.method {
+            auth::MfaMethod::Otp => {
+                let key = confirmation_id.to_string();
+                if let Err(e) = self.transfer_codes.verify_otp(&key, code) {
+                    if matches!(e, errors::SafeBankError::InvalidOtp) {
+                        self.auth_manager.record_failed_attempt(&user.phone_number);
+                    }
+                    if !self.transfer_codes.is_outstanding(&key) {
+                        self.clear_confirmation(confirmation_id)?;
+                    }
+                    return Err(e);
+                }
+            }
+            auth::MfaMethod::Biometric => {
+                if pending.expires_at.is_none_or(|expires_at| self.clock.now() > expires_at) {
+                    self.clear_confirmation(confirmation_id)?;
+                    return Err(errors::SafeBankError::OtpExpired);
+                }
+                self.auth_manager.check_attestation(&user, &transaction.device_id, confirmation_id, code)?;
+            }
+        }
+        self.clear_confirmation(confirmation_id)?;
+
+        if pending.held_for_review {
+            return Ok(transaction);
+        }
+        let confirmed = self.transaction_manager.confirm_step_up_group(&pending.transaction_ids)?;
+        for leg in &confirmed {
+            self.learn_recipient(&user, leg);
+            self.notify_outcome(&user.phone_number, leg);
+            if let Some(kind) = events::TransactionEvent::outcome_kind(&leg.status) {
+                self.emit_event(kind, leg);
+            }
+        }
+        self.transaction_manager.get_transaction(transaction_id)
+    }
+
+    /// Approve a transaction held for review. The approval also damps the fraud score of
+    /// near-identical transactions for `approval_damping_hours`, so a reviewer isn't asked again,
+    /// unless the user had yet to confirm the transfer with their code.
+    pub fn approve_transaction(&mut self, transaction_id: Uuid, reviewer: &str) -> Result<Transaction, errors::SafeBankError> {
+        let unconfirmed = self.pending_confirmation(&self.transaction_manager.get_transaction(transaction_id)?)?.is_some();
+        let transaction = self.transaction_manager.approve_transaction(transaction_id, reviewer)?;
+        if transaction.status != TransactionStatus::Approved {
+            // Now waiting on the shared account's members
+            return Ok(transaction);
+        }
+        let user = self.auth_manager.get_user_by_id(transaction.user_id)?;
+        let recipient = self.transaction_manager.reveal_recipient(&transaction.recipient)
+            .unwrap_or_else(|| transaction.recipient.clone());
+        if !unconfirmed {
+            self.fraud_detector.record_approved_pattern(transaction.user_id, &recipient, transaction.amount);
+        }
+        self.learn_recipient(&user, &transaction);
+        self.notify_outcome(&user.phone_number, &transaction);
+        if let Some(kind) = events::TransactionEvent::outcome_kind(&transaction.status) {
+            self.emit_event(kind, &transaction);
+        }
+        Ok(transaction)
+    }
+
+    /// Register an account operated by a group, such as a co-op; every member must be a registered user
+    pub fn register_shared_account(&mut self, account: transaction::SharedAccount) -> Result<(), errors::SafeBankError> {
+        for member in &account.members {
+            self.auth_manager.get_user_by_id(*member)?;
+        }
+        self.transaction_manager.register_shared_account(account)
+    }
+
+    /// Approve a held shared-account payment as one of the account's members, who logs in with
+    /// their own PIN and device. The payment commits once enough members have approved it.
+    pub fn co_approve_transaction(&mut self, transaction_id: Uuid, phone_number: &str, pin: &str, device_id: &str) -> Result<Transaction, errors::SafeBankError> {
+        let member = self.authenticate_user(phone_number, pin, device_id)?;
+        let transaction = self.transaction_manager.co_approve_transaction(transaction_id, member.user_id)?;
+        if transaction.status != TransactionStatus::Approved {
+            return Ok(transaction);
+        }
+        let user = self.auth_manager.get_user_by_id(transaction.user_id)?;
+        self.learn_recipient(&user, &transaction);
+        self.notify_outcome(&user.phone_number, &transaction);
+        self.emit_event(events::TransactionEventKind::Approved, &transaction);
+        Ok(transaction)
     }
-}
\ No newline at end of file
+
+    /// Reject transactions left waiting for review or confirmation past `pending_expiry_hours`,
+    /// discarding any confirmation codes still outstanding for them and telling their users
+    pub fn expire_stale_transactions(&mut self) -> Result<Vec<Transaction>, errors::SafeBankError> {
+        let expired = self.transaction_manager.expire_stale_transactions(self.clock.now())?;
+        for transaction in &expired {
+            self.clear_confirmation(transaction.group_id.unwrap_or(transaction.transaction_id))?;
+            if let Ok(user) = self.auth_manager.get_user_by_id(transaction.user_id) {
+                self.notify_outcome(&user.phone_number, transaction);
+            }
+            self.emit_event(events::TransactionEventKind::Rejected, transaction);
+        }
+        Ok(expired)
+    }
+
+    /// Pay several recipients as one logical transfer.
+    /// Each leg is checked against the single transaction limit and the combined amount against the
+    /// daily limit; the combined amount is fraud-scored once, and either every leg is recorded
+    /// (sharing a group id) or none is. A split over `transfer_confirmation_threshold` holds every leg
+    /// in `RequiresStepUp` under one texted code, released for all of them by `confirm_transfer`.
+    pub fn process_split_transfer(&mut self, user_id: Uuid, legs: Vec<(String, f64)>) -> Result<Vec<Transaction>, errors::SafeBankError> {
+        if legs.is_empty() {
+            return Err(errors::SafeBankError::ConfigError {
+                message: "Split transfer requires at least one recipient".to_string(),
+            });
+        }
+
+        let user = self.auth_manager.active_user(user_id)?;
+        let group_id = Uuid::new_v4();
+        let mut children: Vec<Transaction> = legs
+            .iter()
+            .map(|(recipient, amount)| {
+                let mut child = self.new_transaction(user_id, Self::transacting_device(&user)?, *amount, recipient, TransactionType::Transfer)?;
+                child.group_id = Some(group_id);
+                Ok(child)
+            })
+            .collect::<Result<_, errors::SafeBankError>>()?;
+        self.transaction_manager.precheck_group(&children)?;
+
+        // Score the aggregate so splitting a payment can't dodge amount-based checks
+        let mut aggregate = children[0].clone();
+        aggregate.amount = children.iter().map(|child| child.amount).sum();
+        let analysis = self.fraud_detector.analyze_transaction_detailed(&aggregate, &user)?;
+        aggregate.fraud_score = analysis.fraud_score;
+        aggregate.status = self.decide_status(user_id, &aggregate, &analysis.recommendation);
+        // Held for one code over the combined amount, so splitting can't dodge the confirmation threshold
+        let (needs_confirmation, held_for_review) = self.hold_for_confirmation(&mut aggregate, false);
+
+        for child in children.iter_mut() {
+            child.fraud_score = analysis.fraud_score;
+            child.risk_factors = analysis.risk_factors.clone();
+            child.status = aggregate.status.clone();
+        }
+
+        let usage_before = self.transaction_manager.daily_usage(user_id);
+        let committed = self.transaction_manager.process_transaction_group(children)?;
+        if needs_confirmation {
+            let pending = PendingConfirmation {
+                held_for_review,
+                transaction_ids: committed.iter().map(|leg| leg.transaction_id).collect(),
+                ..Default::default()
+            };
+            aggregate.transaction_id = group_id;
+            aggregate.recipient = legs.iter().map(|(recipient, _)| recipient.as_str()).collect::<Vec<_>>().join(", ");
+            self.open_transfer_challenge(&user, &aggregate, pending)?;
+        }
+        for transaction in &committed {
+            self.learn_recipient(&user, transaction);
+            self.notify_outcome(&user.phone_number, transaction);
+        }
+        self.after_commit(&user, usage_before);
+        for transaction in &committed {
+            self.emit_committed(transaction);
+        }
+
+        Ok(committed)
+    }
+
+    /// Map a fraud recommendation to a status, silently holding large transfers made under duress
+    fn decide_status(&mut self, user_id: Uuid, transaction: &Transaction, recommendation: &fraud_detection::FraudRecommendation) -> TransactionStatus {
+        // Under duress, silently hold anything above the cap for staff review
+        if self.auth_manager.is_under_duress(user_id) && transaction.amount > self.config.duress_transaction_cap {
+            self.escalate_duress_hold(user_id, transaction.transaction_id, transaction.amount);
+            return TransactionStatus::RequiresApproval;
+        }
+
+        Self::recommended_status(recommendation)
+    }
+
+    /// The status a fraud recommendation asks for, reflecting both the score thresholds and any operator fraud rules
+    fn recommended_status(recommendation: &fraud_detection::FraudRecommendation) -> TransactionStatus {
+        match recommendation {
+            fraud_detection::FraudRecommendation::Block => TransactionStatus::Rejected,
+            fraud_detection::FraudRecommendation::Flag => TransactionStatus::RequiresApproval,
+            fraud_detection::FraudRecommendation::RequireAdditionalAuth => TransactionStatus::RequiresStepUp,
+            fraud_detection::FraudRecommendation::Approve => TransactionStatus::Approved,
+        }
+    }
+
+    fn escalate_duress_hold(&mut self, user_id: Uuid, transaction_id: Uuid, amount: f64) {
+        self.raise_escalation(
+            escalation::EscalationKind::DuressTransactionHeld,
+            errors::ErrorSeverity::Critical,
+            Some(user_id),
+            format!("Held transaction {} of {:.2} made under duress", transaction_id, amount),
+        );
+    }
+
+    /// Post-commit monitoring: daily-limit warnings and system volume escalation
+    fn after_commit(&mut self, user: &UserProfile, usage_before: f64) {
+        let usage_after = self.transaction_manager.daily_usage(user.user_id);
+        self.notify_if_approaching_limit(user, usage_before, usage_after);
+        self.escalate_if_system_volume_exceeded();
+    }
+
+    /// Text the user whether a transaction was approved or rejected; held transactions stay silent
+    fn notify_outcome(&self, phone_number: &str, transaction: &Transaction) {
+        if !self.config.transaction_notifications {
+            return;
+        }
+        let status = match transaction.status {
+            TransactionStatus::Approved => "approved",
+            TransactionStatus::Rejected => "rejected",
+            _ => return,
+        };
+
+        let recipient = self.transaction_manager.reveal_recipient(&transaction.recipient)
+            .unwrap_or_else(|| transaction.recipient.clone());
+        let reference = self.transaction_manager.create_receipt(transaction).confirmation_code;
+        let message = utils::format_transaction_sms(transaction.amount, &recipient, status, &reference, &self.config.local_currency, transaction.memo.as_deref());
+        // Notifications are best-effort and must not fail the transaction
+        let _ = self.notifier.send(phone_number, &message);
+    }
+
+    /// Warn the user once when daily usage crosses the configured percentage of the limit
+    fn notify_if_approaching_limit(&self, user: &UserProfile, usage_before: f64, usage_after: f64) {
+        let Some(percent) = self.config.daily_limit_warning_percent else {
+            return;
+        };
+
+        let limit = self.transaction_manager.effective_daily_limit(user.user_id);
+        let threshold = limit * percent / 100.0;
+        if usage_before < threshold && usage_after >= threshold {
+            let message = notification::format_limit_warning(usage_after, limit, &self.config.local_currency);
+            // Notifications are best-effort and must not fail the transaction
+            let _ = self.notifier.send(&user.phone_number, &message);
+        }
+    }
+
+    /// Escalate once per day, on whichever instance first sees combined system volume over the configured ceiling
+    fn escalate_if_system_volume_exceeded(&mut self) {
+        let Some(ceiling) = self.config.system_daily_volume_ceiling else {
+            return;
+        };
+
+        if self.transaction_manager.claim_volume_escalation(ceiling) {
+            self.raise_escalation(
+                escalation::EscalationKind::SystemVolumeCeiling,
+                errors::ErrorSeverity::High,
+                None,
+                format!("System daily volume {:.2} exceeded ceiling {:.2}", self.transaction_manager.system_daily_volume(), ceiling),
+            );
+        }
+    }
+
+    /// Fire `Created` followed by the event matching the transaction's status
+    fn emit_committed(&self, transaction: &Transaction) {
+        self.emit_event(events::TransactionEventKind::Created, transaction);
+        if let Some(kind) = events::TransactionEvent::outcome_kind(&transaction.status) {
+            self.emit_event(kind, transaction);
+        }
+    }
+
+    fn emit_event(&self, kind: events::TransactionEventKind, transaction: &Transaction) {
+        if self.event_hooks.is_empty() {
+            return;
+        }
+        self.event_hooks.emit(&events::TransactionEvent::new(kind, transaction.clone(), self.clock.now()));
+    }
+
+    /// Security escalations raised for operator review
+    pub fn escalations(&self) -> &[escalation::Escalation] {
+        &self.escalations
+    }
+
+    fn raise_escalation(&mut self, kind: escalation::EscalationKind, severity: errors::ErrorSeverity, user_id: Option<Uuid>, message: String) {
+        let raised_at = self.clock.now();
+        self.escalations.push(escalation::Escalation::new(kind, severity, user_id, message, raised_at));
+    }
+
+    /// Update user behavioral profile based on transaction history
+    pub fn update_behavioral_profile(&mut self, user_id: Uuid) -> Result<(), errors::SafeBankError> {
+        let transactions = self.transaction_manager.get_user_transactions(user_id)?;
+        self.fraud_detector.update_behavioral_profile(user_id, &transactions)?;
+        Ok(())
+    }
+
+    /// Export users and transactions for analytics with PII replaced by stable pseudonyms
+    pub fn export_anonymized(&self) -> Result<anonymization::AnonymizedDataset, errors::SafeBankError> {
+        let users = self.auth_manager.list_users()?;
+        let transactions = self.transaction_manager.all_transactions()?;
+        Ok(self.pseudonymizer.anonymize(&users, &transactions, self.clock.now()))
+    }
+
+    /// Reverse an approved transaction, refunding its amount to the sender
+    pub fn reverse_transaction(&mut self, transaction_id: Uuid, reason: String) -> Result<Transaction, errors::SafeBankError> {
+        let reversal = self.transaction_manager.reverse_transaction(transaction_id, reason)?;
+        self.emit_event(events::TransactionEventKind::Reversed, &reversal);
+        Ok(reversal)
+    }
+
+    /// Filtered, paginated view of stored transactions
+    pub fn query_transactions(&self, filter: transaction::TransactionFilter, page: usize, page_size: usize) -> Result<transaction::TransactionPage, errors::SafeBankError> {
+        self.transaction_manager.query_transactions(filter, page, page_size)
+    }
+
+    /// A user's transactions as a CSV or JSON statement, oldest first
+    pub fn export_transactions(&self, user_id: Uuid, format: transaction::ExportFormat) -> Result<String, errors::SafeBankError> {
+        self.transaction_manager.export_transactions(user_id, format)
+    }
+
+    /// Check a user's transaction hash chain for altered, removed or reordered entries
+    pub fn verify_chain(&self, user_id: Uuid) -> Result<(), errors::SafeBankError> {
+        self.transaction_manager.verify_chain(user_id)
+    }
+
+    /// Current account balance for a registered user
+    pub fn get_balance(&self, user_id: Uuid) -> Result<f64, errors::SafeBankError> {
+        self.auth_manager.get_user_by_id(user_id)?;
+        self.transaction_manager.get_balance(user_id)
+    }
+
+    /// The user's transactions in the order they were posted, with the running balance after each
+    pub fn get_statement(&self, user_id: Uuid) -> Result<Vec<transaction::StatementLine>, errors::SafeBankError> {
+        self.auth_manager.get_user_by_id(user_id)?;
+        self.transaction_manager.get_statement(user_id)
+    }
+
+    /// Capture users, transactions, learned profiles and escalations so a later instance can resume
+    pub fn snapshot(&self) -> Result<FrameworkSnapshot, errors::SafeBankError> {
+        Ok(FrameworkSnapshot {
+            taken_at: self.clock.now(),
+            users: self.auth_manager.list_users()?,
+            transactions: self.transaction_manager.all_transactions()?,
+            behavioral_profiles: self.fraud_detector.export_profiles()?,
+            escalations: self.escalations.clone(),
+            records: self.storage.load_all_records()?,
+        })
+    }
+
+    /// Load a snapshot taken by `snapshot`, merging it into the current state
+    pub fn restore(&mut self, snapshot: FrameworkSnapshot) -> Result<(), errors::SafeBankError> {
+        self.auth_manager.restore_users(&snapshot.users)?;
+        self.transaction_manager.restore_transactions(&snapshot.transactions)?;
+        self.fraud_detector.import_profiles(snapshot.behavioral_profiles)?;
+        self.escalations.extend(snapshot.escalations);
+        for (key, value) in &snapshot.records {
+            self.storage.save_record(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Write a snapshot to disk encrypted under a passphrase, so PIN hashes, transactions
+    /// and behavioral profiles never sit in plaintext on the device
+    pub fn save_encrypted(&self, path: impl AsRef<std::path::Path>, passphrase: &str) -> Result<(), errors::SafeBankError> {
+        let path = path.as_ref();
+        let serialized = serde_json::to_vec(&self.snapshot()?)
+            .map_err(|e| errors::SafeBankError::SerializationError { message: e.to_string() })?;
+        let sealed = sealed::seal(&serialized, passphrase)?;
+
+        // Write then rename so an interrupted save never leaves a truncated file
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, sealed)
+            .and_then(|_| std::fs::rename(&temp_path, path))
+            .map_err(|e| errors::SafeBankError::StorageError { message: e.to_string() })
+    }
+
+    /// Restore a snapshot written by `save_encrypted`; a wrong passphrase fails with `CryptographyError`
+    pub fn load_encrypted(&mut self, path: impl AsRef<std::path::Path>, passphrase: &str) -> Result<(), errors::SafeBankError> {
+        let sealed = std::fs::read(path)
+            .map_err(|e| errors::SafeBankError::StorageError { message: e.to_string() })?;
+        let serialized = sealed::open(&sealed, passphrase)?;
+        let snapshot = serde_json::from_slice(&serialized)
+            .map_err(|e| errors::SafeBankError::SerializationError { message: e.to_string() })?;
+        self.restore(snapshot)
+    }
+
+    /// Users whose behavioral profile is older than the given window, for a maintenance refresh
+    pub fn stale_profiles(&self, older_than: chrono::Duration) -> Vec<Uuid> {
+        self.fraud_detector.stale_profiles(older_than)
+    }
+
+    /// Get fraud statistics for monitoring
+    pub fn get_fraud_statistics(&self) -> HashMap<String, f64> {
+        self.fraud_detector.get_statistics()
+    }
+
+    /// Current time on the framework's clock, for callers that need to agree with its managers
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Start a read-only monitoring report; safe to build from a thread holding a shared reference
+    pub fn report(&self) -> report::ReportBuilder<'_> {
+        report::ReportBuilder::new(self)
+    }
+
+    /// Fraud, transaction and authentication counters in Prometheus text exposition format
+    pub fn metrics_text(&self) -> String {
+        use metrics::MetricKind::{Counter, Gauge};
+
+        let mut metrics = metrics::MetricsWriter::new();
+        let transaction_stats = self.transaction_manager.get_transaction_statistics();
+        metrics.metric("safebank_storage_up", Gauge, "Whether transaction statistics could be read from storage", if transaction_stats.is_ok() { 1.0 } else { 0.0 });
+
+        let transaction_stats = transaction_stats.unwrap_or_default();
+        let stat = |key: &str| transaction_stats.get(key).copied().unwrap_or(0.0);
+        let settled = stat("approved_count") + stat("rejected_count") + stat("flagged_count");
+        metrics.labeled("safebank_transactions_total", Counter, "Transactions recorded, by status", "status", &[
+            ("approved".to_string(), stat("approved_count")),
+            ("rejected".to_string(), stat("rejected_count")),
+            ("flagged".to_string(), stat("flagged_count")),
+            ("pending".to_string(), stat("total_transactions") - settled),
+        ]);
+        metrics.metric("safebank_transaction_volume_total", Counter, "Sum of all recorded transaction amounts", stat("total_volume"));
+
+        let mut categories: Vec<(String, f64)> = transaction_stats.iter()
+            .filter_map(|(key, value)| key.strip_prefix("category_count.").map(|category| (category.to_string(), *value)))
+            .collect();
+        categories.sort_by(|a, b| a.0.cmp(&b.0));
+        metrics.labeled("safebank_transactions_by_category_total", Counter, "Transactions recorded, by category", "category", &categories);
+
+        let mut fraud_stats: Vec<(String, f64)> = self.fraud_detector.get_statistics().into_iter().collect();
+        fraud_stats.sort_by(|a, b| a.0.cmp(&b.0));
+        for (key, value) in fraud_stats {
+            if key.ends_with("_percent") {
+                metrics.metric(&format!("safebank_fraud_{}", key), Gauge, "Fraud detection rate", value);
+            } else {
+                metrics.metric(&format!("safebank_fraud_{}_total", key), Counter, "Fraud detection outcomes", value);
+            }
+        }
+
+        let auth_stats = self.auth_manager.get_statistics();
+        let auth_stat = |key: &str| auth_stats.get(key).copied().unwrap_or(0.0);
+        metrics.metric("safebank_auth_active_lockouts", Gauge, "Accounts currently locked out after failed logins", auth_stat("active_lockouts"));
+        metrics.metric("safebank_auth_failed_attempts", Gauge, "Failed login attempts not yet cleared by a successful login", auth_stat("failed_attempts"));
+        metrics.metric("safebank_auth_duress_sessions", Gauge, "Users held under duress until staff clear the flag", auth_stat("duress_sessions"));
+        metrics.metric("safebank_auth_pending_mfa_challenges", Gauge, "Logins waiting for their second factor", auth_stat("pending_mfa_challenges"));
+
+        metrics.finish()
+    }
+
+    /// Record an operator-confirmed outcome for an analyzed transaction
+    pub fn mark_as_fraud(&mut self, transaction_id: Uuid, is_fraud: bool) {
+        self.fraud_detector.mark_as_fraud(transaction_id, is_fraud);
+        if is_fraud {
+            // Confirmed fraud resets the account's daily limit growth and stops the user trusting
+            // the recipient; labeling still stands if either fails
+            if let Ok(transaction) = self.transaction_manager.get_transaction(transaction_id) {
+                let _ = self.auth_manager.record_confirmed_fraud(transaction.user_id);
+                let recipient = self.transaction_manager.reveal_recipient(&transaction.recipient)
+                    .unwrap_or_else(|| transaction.recipient.clone());
+                let _ = self.fraud_detector.demote_recipient(transaction_id, transaction.user_id, &recipient);
+            }
+        } else {
+            // A transaction relabelled as legitimate gives its recipient back
+            let _ = self.fraud_detector.restore_recipient(transaction_id);
+        }
+    }
+
+    /// A user's current daily limit, including any growth earned by a seasoned, trusted account
+    pub fn effective_daily_limit(&self, user_id: Uuid) -> f64 {
+        self.transaction_manager.effective_daily_limit(user_id)
+    }
+
+    /// Confirmed fraud/legitimate labels, e.g. for export
+    pub fn labeled_outcomes(&self) -> &[fraud_detection::FraudLabel] {
+        self.fraud_detector.labeled_outcomes()
+    }
+
+    /// Block a recipient for all users
+    pub fn add_to_blocklist(&mut self, recipient: &str) {
+        self.fraud_detector.add_to_blocklist(recipient)
+    }
+
+    /// Remove a recipient from the blocklist; returns whether it was listed
+    pub fn remove_from_blocklist(&mut self, recipient: &str) -> bool {
+        self.fraud_detector.remove_from_blocklist(recipient)
+    }
+
+    /// Trust a recipient for one user
+    pub fn allow_recipient(&mut self, user_id: Uuid, recipient: &str) {
+        self.fraud_detector.allow_recipient(user_id, recipient)
+    }
+
+    /// Remove a recipient from a user's allowlist; returns whether it was listed
+    pub fn remove_allowed_recipient(&mut self, user_id: Uuid, recipient: &str) -> bool {
+        self.fraud_detector.remove_allowed_recipient(user_id, recipient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct RecordingNotifier {
+        messages: Mutex<Vec<(String, String)>>,
+    }
+
+    impl notification::Notifier for RecordingNotifier {
+        fn send(&self, to: &str, message: &str) -> Result<(), errors::SafeBankError> {
+            self.messages.lock().unwrap().push((to.to_string(), message.to_string()));
+            Ok(())
+        }
+    }
+
+    /// Give a test account enough money that balance checks don't get in the way
+    fn fund(framework: &mut SafeBankFramework, user_id: Uuid) {
+        framework.transaction_manager.adjust_balance(user_id, 100_000.0).unwrap();
+    }
+
+    #[test]
+    fn test_framework_initialization() {
+        let config = config::SafeBankConfig::default();
+        let framework = SafeBankFramework::new(config);
+        // Basic initialization test
+        assert!(framework.config.max_failed_attempts > 0);
+    }
+
+    #[test]
+    fn test_approaching_daily_limit_notification() {
+        let config = config::SafeBankConfig {
+            daily_transaction_limit: 1000.0,
+            single_transaction_limit: 1000.0,
+            daily_limit_warning_percent: Some(80.0),
+            transaction_notifications: false,
+            ..config::SafeBankConfig::default()
+        };
+        let mut framework = SafeBankFramework::new(config);
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+
+        fund(&mut framework, user.user_id);
+
+        // Staying below the threshold triggers nothing
+        framework.process_transaction(user.user_id, 500.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        assert!(notifier.messages.lock().unwrap().is_empty());
+
+        // Crossing 80% triggers exactly one warning, further usage does not repeat it
+        framework.process_transaction(user.user_id, 350.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        framework.process_transaction(user.user_id, 50.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+
+        let messages = notifier.messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].0, "+254712345678");
+        assert!(messages[0].1.contains("daily limit"));
+    }
+
+    #[test]
+    fn test_export_anonymized_strips_pii() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let mary = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, mary.user_id);
+        let john = framework.register_user("+254787654321".to_string(), "7392".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, john.user_id);
+
+        framework.process_transaction(mary.user_id, 120.0, "Grace Wanjiku".to_string(), TransactionType::Transfer).unwrap();
+        framework.process_transaction(mary.user_id, 45.5, "Grace Wanjiku".to_string(), TransactionType::Transfer).unwrap();
+        framework.process_transaction(john.user_id, 300.0, "Agro Supplies Ltd".to_string(), TransactionType::Payment).unwrap();
+
+        let dataset = framework.export_anonymized().unwrap();
+        let serialized = serde_json::to_string(&dataset).unwrap();
+
+        for pii in ["+254712345678", "+254787654321", "Grace Wanjiku", "Agro Supplies Ltd", "test-device"] {
+            assert!(!serialized.contains(pii), "export leaked {}", pii);
+        }
+
+        assert_eq!(dataset.users.len(), 2);
+        assert_eq!(dataset.transactions.len(), 3);
+        let mut amounts: Vec<f64> = dataset.transactions.iter().map(|t| t.amount).collect();
+        amounts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(amounts, vec![45.5, 120.0, 300.0]);
+
+        // The same recipient maps to the same pseudonym
+        let grace: Vec<&String> = dataset.transactions.iter()
+            .filter(|t| t.amount < 200.0)
+            .map(|t| &t.recipient_pseudonym)
+            .collect();
+        assert_eq!(grace[0], grace[1]);
+    }
+
+    #[test]
+    fn test_shared_storage_survives_framework_restart() {
+        let backend = Arc::new(storage::TestStorage::default());
+
+        let mut first = SafeBankFramework::with_storage(config::SafeBankConfig::default(), backend.clone(), clock::system_clock());
+        let user = first.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut first, user.user_id);
+        first.process_transaction(user.user_id, 150.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        drop(first);
+
+        // A fresh instance over the same backend sees the user and their history
+        let mut second = SafeBankFramework::with_storage(config::SafeBankConfig::default(), backend.clone(), clock::system_clock());
+        let restored = second.authenticate_user("+254712345678", "4821", "test-device").unwrap();
+        assert_eq!(restored.user_id, user.user_id);
+        assert_eq!(second.transaction_manager.get_user_transactions(user.user_id).unwrap().len(), 1);
+        assert!(backend.user_saves.load(std::sync::atomic::Ordering::SeqCst) >= 2);
+    }
+
+    #[test]
+    fn test_otp_step_when_required() {
+        let config = config::SafeBankConfig {
+            require_otp: true,
+            ..config::SafeBankConfig::default()
+        };
+        let mut framework = SafeBankFramework::new(config);
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+
+        assert!(matches!(
+            framework.authenticate_user("+254712345678", "4821", "test-device"),
+            Err(errors::SafeBankError::OtpRequired)
+        ));
+
+        framework.send_otp("+254712345678").unwrap();
+        let message = notifier.messages.lock().unwrap()[0].1.clone();
+        let code: String = message.chars().filter(|c| c.is_ascii_digit()).collect();
+
+        assert!(matches!(
+            framework.authenticate_user_with_otp("+254712345678", "4821", "test-device", "000000x"),
+            Err(errors::SafeBankError::InvalidOtp)
+        ));
+        assert!(framework.authenticate_user_with_otp("+254712345678", "4821", "test-device", &code).is_ok());
+    }
+
+    #[test]
+    fn test_legacy_single_device_profile_deserializes() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+
+        let mut json = serde_json::to_value(&user).unwrap();
+        let devices = json.as_object_mut().unwrap().remove("devices").unwrap();
+        json["device_info"] = devices[0].clone();
+
+        let legacy: UserProfile = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy.devices.len(), 1);
+        assert_eq!(legacy.primary_device().unwrap().device_id, "test-device");
+    }
+
+    #[test]
+    fn test_transactions_carry_the_login_device() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.register_device(user.user_id, DeviceInfo { device_id: "second-phone".to_string(), ..DeviceInfo::test_device() }).unwrap();
+        framework.trust_device(user.user_id, "second-phone".to_string()).unwrap();
+
+        framework.authenticate_user("+254712345678", "4821", "second-phone").unwrap();
+        let transaction = framework.process_transaction(user.user_id, 50.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        assert_eq!(transaction.device_id, "second-phone");
+
+        // Once that device is revoked, transactions fall back to the primary device
+        framework.revoke_device(user.user_id, "second-phone").unwrap();
+        let transaction = framework.process_transaction(user.user_id, 50.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        assert_eq!(transaction.device_id, "test-device");
+    }
+
+    #[test]
+    fn test_fingerprint_device_confirms_transfers_once_enrolled_by_the_user() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        let capabilities = utils::DeviceCapabilities {
+            has_camera: true,
+            has_fingerprint: true,
+            has_sms: true,
+            has_internet: true,
+            ram_mb: Some(4096),
+            storage_mb: Some(65536),
+            screen_size: utils::DeviceScreenSize::Large,
+        };
+
+        assert!(framework.update_device_capabilities("+254712345678", "9999", "test-device", capabilities.clone()).is_err());
+        assert!(framework.list_devices(user.user_id).unwrap()[0].capabilities.is_none());
+
+        framework.update_device_capabilities("+254712345678", "4821", "test-device", capabilities).unwrap();
+        let device = &framework.list_devices(user.user_id).unwrap()[0];
+        assert!(device.capabilities.as_ref().is_some_and(|capabilities| capabilities.has_fingerprint));
+        // A fingerprint sensor alone isn't enough without a key to prove the match
+        assert_eq!(device.mfa_method, auth::MfaMethod::Otp);
+
+        assert!(framework.enroll_device_attestation("+254712345678", "9999", "test-device").is_err());
+        let key = framework.enroll_device_attestation("+254712345678", "4821", "test-device").unwrap();
+        assert_eq!(framework.list_devices(user.user_id).unwrap()[0].mfa_method, auth::MfaMethod::Biometric);
+
+        // The transfer is confirmed on the device, so no code is texted
+        let (pending, challenge) = framework.initiate_transfer(user.user_id, 500.0, "Grain Co-op".to_string()).unwrap();
+        let challenge = challenge.unwrap();
+        assert_eq!(challenge.method, auth::MfaMethod::Biometric);
+        assert!(!notifier.messages.lock().unwrap().iter().any(|(_, text)| text.contains("confirm with code")));
+
+        let forged = auth::sign_attestation("not-the-enrolled-key", challenge.transaction_id);
+        assert!(framework.confirm_transfer(pending.transaction_id, &forged).is_err());
+        assert_eq!(framework.transaction_manager.get_transaction(pending.transaction_id).unwrap().status, TransactionStatus::RequiresStepUp);
+
+        let signature = auth::sign_attestation(&key, challenge.transaction_id);
+        let confirmed = framework.confirm_transfer(pending.transaction_id, &signature).unwrap();
+        assert_eq!(confirmed.status, TransactionStatus::Approved);
+        assert!(framework.confirm_transfer(pending.transaction_id, &signature).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let config = config::SafeBankConfig::default();
+        let mut original = SafeBankFramework::new(config.clone());
+        let user = original.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut original, user.user_id);
+        original.process_transaction(user.user_id, 120.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        original.process_transaction(user.user_id, 80.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        original.update_behavioral_profile(user.user_id).unwrap();
+
+        let serialized = serde_json::to_string(&original.snapshot().unwrap()).unwrap();
+
+        let mut restored = SafeBankFramework::new(config);
+        restored.restore(serde_json::from_str(&serialized).unwrap()).unwrap();
+
+        assert_eq!(restored.authenticate_user("+254712345678", "4821", "test-device").unwrap().user_id, user.user_id);
+        assert_eq!(restored.transaction_manager.daily_usage(user.user_id), 200.0);
+        assert_eq!(
+            restored.fraud_detector.export_profiles().unwrap()[&user.user_id].typical_transaction_amount,
+            100.0
+        );
+    }
+
+    #[test]
+    fn test_encrypted_snapshot_round_trip() {
+        let config = config::SafeBankConfig::default();
+        let mut original = SafeBankFramework::new(config.clone());
+        let user = original.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut original, user.user_id);
+        original.process_transaction(user.user_id, 120.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+
+        let path = std::env::temp_dir().join(format!("safebank-{}.sealed", Uuid::new_v4()));
+        original.save_encrypted(&path, "correct horse battery staple").unwrap();
+        let on_disk = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&on_disk).contains("+254712345678"));
+
+        let mut restored = SafeBankFramework::new(config);
+        restored.load_encrypted(&path, "correct horse battery staple").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.authenticate_user("+254712345678", "4821", "test-device").unwrap().user_id, user.user_id);
+        assert_eq!(restored.transaction_manager.daily_usage(user.user_id), 120.0);
+    }
+
+    #[test]
+    fn test_encrypted_snapshot_rejects_wrong_passphrase() {
+        let mut original = SafeBankFramework::new(config::SafeBankConfig::default());
+        original.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        let path = std::env::temp_dir().join(format!("safebank-{}.sealed", Uuid::new_v4()));
+        original.save_encrypted(&path, "correct horse battery staple").unwrap();
+
+        let mut restored = SafeBankFramework::new(config::SafeBankConfig::default());
+        let result = restored.load_encrypted(&path, "wrong passphrase");
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(errors::SafeBankError::CryptographyError { .. })));
+        assert!(restored.auth_manager.list_users().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_idempotency_key_prevents_double_spend() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        let starting_balance = framework.get_balance(user.user_id).unwrap();
+
+        let request = TransactionRequest::new(user.user_id, 250.0, "Grace Wanjiku", TransactionType::Transfer)
+            .with_idempotency_key("client-retry-1");
+        let first = framework.process_request(request.clone()).unwrap();
+        let retried = framework.process_request(request).unwrap();
+
+        assert_eq!(first.transaction_id, retried.transaction_id);
+        assert_eq!(framework.transaction_manager.get_user_transactions(user.user_id).unwrap().len(), 1);
+        assert_eq!(framework.get_balance(user.user_id).unwrap(), starting_balance - first.amount - first.fee);
+
+        // Keys are scoped per user
+        let other = framework.register_user("+254787654321".to_string(), "7392".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, other.user_id);
+        let theirs = framework.process_request(
+            TransactionRequest::new(other.user_id, 250.0, "Grace Wanjiku", TransactionType::Transfer)
+                .with_idempotency_key("client-retry-1"),
+        ).unwrap();
+        assert_ne!(theirs.transaction_id, first.transaction_id);
+    }
+
+    #[test]
+    fn test_processed_transaction_carries_risk_factors() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+
+        let transaction = framework.process_transaction(user.user_id, 100.0, "Stranger".to_string(), TransactionType::Transfer).unwrap();
+
+        assert!(transaction.fraud_score > 0.0);
+        assert!(transaction.risk_factors.iter().any(|factor| factor.factor_type == fraud_detection::RiskFactorType::RecipientAnomaly));
+    }
+
+    #[test]
+    fn test_duress_pin_holds_large_transfers() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.set_duress_pin(user.user_id, "9731").unwrap();
+
+        // Normal PIN behaves normally
+        framework.authenticate_user("+254712345678", "4821", "test-device").unwrap();
+        let normal = framework.process_transaction(user.user_id, 400.0, "Local Shop".to_string(), TransactionType::Transfer).unwrap();
+        assert_eq!(normal.status, TransactionStatus::Approved);
+        assert!(framework.escalations().is_empty());
+
+        // Duress PIN appears to succeed
+        assert!(framework.authenticate_user("+254712345678", "9731", "test-device").is_ok());
+
+        let small = framework.process_transaction(user.user_id, 20.0, "Local Shop".to_string(), TransactionType::Transfer).unwrap();
+        assert_eq!(small.status, TransactionStatus::Approved);
+
+        let large = framework.process_transaction(user.user_id, 400.0, "Stranger".to_string(), TransactionType::Transfer).unwrap();
+        assert_ne!(large.status, TransactionStatus::Approved);
+
+        let escalations = framework.escalations();
+        assert!(escalations.iter().any(|e| e.kind == escalation::EscalationKind::DuressAuthentication));
+        assert!(escalations.iter().any(|e| e.kind == escalation::EscalationKind::DuressTransactionHeld
+            && e.severity == errors::ErrorSeverity::Critical));
+    }
+
+    #[test]
+    fn test_duress_hold_survives_restart_until_staff_clear_it() {
+        let backend = storage::in_memory_storage();
+        let mut first = SafeBankFramework::with_storage(config::SafeBankConfig::default(), backend.clone(), clock::system_clock());
+        let user = first.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut first, user.user_id);
+        first.set_duress_pin(user.user_id, "9731").unwrap();
+        first.authenticate_user("+254712345678", "9731", "test-device").unwrap();
+        drop(first);
+
+        // A rebuilt framework still holds large transfers, even after a normal-PIN login
+        let mut second = SafeBankFramework::with_storage(config::SafeBankConfig::default(), backend.clone(), clock::system_clock());
+        second.authenticate_user("+254712345678", "4821", "test-device").unwrap();
+        let held = second.process_transaction(user.user_id, 400.0, "Stranger".to_string(), TransactionType::Transfer).unwrap();
+        assert_ne!(held.status, TransactionStatus::Approved);
+        assert!(second.escalations().iter().any(|e| e.kind == escalation::EscalationKind::DuressTransactionHeld));
+
+        second.clear_duress(user.user_id).unwrap();
+        let cleared = second.query_audit(&audit::AuditFilter {
+            user_id: Some(user.user_id),
+            event_type: Some(audit::AuditEventType::DuressCleared),
+            ..Default::default()
+        });
+        assert_eq!(cleared.len(), 1);
+        let released = second.process_transaction(user.user_id, 400.0, "Local Shop".to_string(), TransactionType::Transfer).unwrap();
+        assert_eq!(released.status, TransactionStatus::Approved);
+    }
+
+    #[test]
+    fn test_duress_pin_raises_the_alarm_offline() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        framework.cache_credentials(user.user_id).unwrap();
+        framework.set_duress_pin(user.user_id, "9731").unwrap();
+
+        let session = framework.authenticate_offline("+254712345678", "9731", "test-device").unwrap();
+        assert_eq!(session.user_id, user.user_id);
+        assert!(framework.auth_manager.is_under_duress(user.user_id));
+        assert!(framework.escalations().iter().any(|e| e.kind == escalation::EscalationKind::DuressAuthentication));
+    }
+
+    #[test]
+    fn test_offline_login_restricts_to_offline_limit() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.cache_credentials(user.user_id).unwrap();
+
+        let session = framework.authenticate_offline("+254712345678", "4821", "test-device").unwrap();
+
+        let limit = framework.config.offline_transaction_limit;
+        let keyring = transaction::SigningKeyring::new(1, "device-key");
+        let within = framework.create_offline_transaction(&session, limit / 2.0, "Local Shop".to_string(), TransactionType::Payment, &keyring);
+        assert!(within.is_ok());
+
+        let above = framework.create_offline_transaction(&session, limit + 1.0, "Local Shop".to_string(), TransactionType::Payment, &keyring);
+        assert!(matches!(above, Err(errors::SafeBankError::TransactionLimitExceeded { .. })));
+
+        // A raised limit or a session the manager never issued is refused
+        let raised = auth::OfflineSession { transaction_limit: limit * 10.0, ..session.clone() };
+        let above = framework.create_offline_transaction(&raised, limit + 1.0, "Local Shop".to_string(), TransactionType::Payment, &keyring);
+        assert!(matches!(above, Err(errors::SafeBankError::TransactionLimitExceeded { .. })));
+        let forged = auth::OfflineSession { session_id: Uuid::new_v4(), ..session.clone() };
+        let result = framework.create_offline_transaction(&forged, 10.0, "Local Shop".to_string(), TransactionType::Payment, &keyring);
+        assert!(matches!(result, Err(errors::SafeBankError::AuthenticationFailed { .. })));
+        let impersonated = auth::OfflineSession { user_id: Uuid::new_v4(), ..session };
+        let result = framework.create_offline_transaction(&impersonated, 10.0, "Local Shop".to_string(), TransactionType::Payment, &keyring);
+        assert!(matches!(result, Err(errors::SafeBankError::AuthenticationFailed { .. })));
+    }
+
+    #[test]
+    fn test_offline_sync_scores_transactions() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.cache_credentials(user.user_id).unwrap();
+        framework.add_to_blocklist("Known Scammer");
+
+        let session = framework.authenticate_offline("+254712345678", "4821", "test-device").unwrap();
+        let keyring = transaction::SigningKeyring::new(1, "device-key");
+        let shop = framework.create_offline_transaction(&session, 50.0, "Local Shop".to_string(), TransactionType::Payment, &keyring).unwrap();
+        let scam = framework.create_offline_transaction(&session, 50.0, "Known Scammer".to_string(), TransactionType::Payment, &keyring).unwrap();
+
+        let report = framework.sync_offline_batch(vec![shop.clone(), scam.clone()], &keyring);
+        assert_eq!(report.applied, 2);
+        let stored = |id: Uuid| framework.transaction_manager.get_transaction(id).unwrap();
+        assert_eq!(stored(shop.transaction.transaction_id).status, TransactionStatus::Approved);
+        let blocked = stored(scam.transaction.transaction_id);
+        assert_eq!(blocked.status, TransactionStatus::Rejected);
+        assert!(blocked.fraud_score > 0.0);
+        // Only the approved payment (and its fee) left the account
+        let balance = framework.get_balance(user.user_id).unwrap();
+        assert!(balance <= 100_000.0 - 50.0 && balance > 100_000.0 - 100.0);
+    }
+
+    #[test]
+    fn test_frozen_account_cannot_transact_offline_or_confirm() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.cache_credentials(user.user_id).unwrap();
+
+        let session = framework.authenticate_offline("+254712345678", "4821", "test-device").unwrap();
+        let keyring = transaction::SigningKeyring::new(1, "device-key");
+        let queued = framework.create_offline_transaction(&session, 50.0, "Local Shop".to_string(), TransactionType::Payment, &keyring).unwrap();
+        let (pending, _) = framework.initiate_transfer(user.user_id, 500.0, "Grain Co-op".to_string()).unwrap();
+        let code = {
+            let messages = notifier.messages.lock().unwrap();
+            let (_, text) = messages.iter().rev().find(|(_, text)| text.contains("confirm with code")).unwrap();
+            text.split("code ").nth(1).unwrap().trim_end_matches(". Do not share this code.").to_string()
+        };
+
+        framework.freeze_account(user.user_id, "Reported stolen phone").unwrap();
+        assert!(matches!(
+            framework.create_offline_transaction(&session, 50.0, "Local Shop".to_string(), TransactionType::Payment, &keyring),
+            Err(errors::SafeBankError::AccountFrozen)
+        ));
+        let report = framework.sync_offline_batch(vec![queued], &keyring);
+        assert_eq!((report.applied, report.rejected), (0, 1));
+        assert!(matches!(framework.confirm_transfer(pending.transaction_id, &code), Err(errors::SafeBankError::AccountFrozen)));
+        assert_eq!(framework.get_balance(user.user_id).unwrap(), 100_000.0);
+    }
+
+    #[test]
+    fn test_system_volume_ceiling_escalates_once() {
+        let config = config::SafeBankConfig {
+            system_daily_volume_ceiling: Some(1000.0),
+            ..config::SafeBankConfig::default()
+        };
+        let mut framework = SafeBankFramework::new(config);
+        let mary = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, mary.user_id);
+        let john = framework.register_user("+254787654321".to_string(), "7392".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, john.user_id);
+
+        framework.process_transaction(mary.user_id, 600.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        framework.process_transaction(john.user_id, 300.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        assert!(framework.escalations().is_empty());
+
+        // Each user is well within their own limit, but together they cross the system ceiling
+        framework.process_transaction(john.user_id, 200.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+        framework.process_transaction(mary.user_id, 100.0, "Local Shop".to_string(), TransactionType::Payment).unwrap();
+
+        let volume_escalations = framework.escalations().iter()
+            .filter(|e| e.kind == escalation::EscalationKind::SystemVolumeCeiling)
+            .count();
+        assert_eq!(volume_escalations, 1);
+    }
+
+    #[test]
+    fn test_cross_currency_transfer_applies_fx_margin() {
+        let mut config = config::SafeBankConfig {
+            local_currency: "KES".to_string(),
+            fx_margin_percent: 2.0,
+            ..config::SafeBankConfig::default()
+        };
+        config.exchange_rates.insert("USD".to_string(), 130.0);
+        let mut framework = SafeBankFramework::new(config);
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+
+        let transaction = framework.process_cross_currency_transaction(
+            user.user_id, 10.0, "USD", "Relative Abroad".to_string(), TransactionType::Transfer,
+        ).unwrap();
+
+        // Mid-market value is 1300.00; the 2% margin is taken as the FX fee
+        assert_eq!(transaction.fx_fee, 26.0);
+        assert_eq!(transaction.amount, 1274.0);
+        assert!(transaction.amount < 1300.0);
+
+        let unknown = framework.process_cross_currency_transaction(
+            user.user_id, 10.0, "XYZ", "Relative Abroad".to_string(), TransactionType::Transfer,
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_split_transfer_is_atomic() {
+        let config = config::SafeBankConfig {
+            daily_transaction_limit: 8000.0,
+            single_transaction_limit: 2000.0,
+            ..config::SafeBankConfig::default()
+        };
+        let backend = Arc::new(storage::TestStorage::default());
+        let mut framework = SafeBankFramework::with_storage(config, backend.clone(), clock::system_clock());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        let laborers = |count: usize, amount: f64| (0..count).map(|i| (format!("Day Laborer {}", i), amount)).collect::<Vec<_>>();
+
+        // A leg over the single limit, or a combined amount over the daily limit: nothing is committed
+        for legs in [laborers(1, 2500.0), laborers(6, 1500.0)] {
+            let over_limit = framework.process_split_transfer(user.user_id, legs);
+            assert!(matches!(over_limit, Err(errors::SafeBankError::TransactionLimitExceeded { .. })));
+        }
+        assert!(framework.transaction_manager.get_user_transactions(user.user_id).unwrap().is_empty());
+
+        // A leg that fails to store undoes the legs already committed
+        let balance_before = framework.get_balance(user.user_id).unwrap();
+        backend.fail_transaction_save(2);
+        let failed = framework.process_split_transfer(user.user_id, laborers(3, 500.0));
+        assert!(matches!(failed, Err(errors::SafeBankError::StorageError { .. })));
+        assert_eq!(framework.get_balance(user.user_id).unwrap(), balance_before);
+        assert_eq!(framework.transaction_manager.daily_usage(user.user_id), 0.0);
+        let stored = framework.transaction_manager.get_user_transactions(user.user_id).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored.iter().all(|leg| leg.status == TransactionStatus::Rejected));
+
+        // Legs that each fit the single limit can add up to more than it
+        let children = framework.process_split_transfer(user.user_id, laborers(5, 1500.0)).unwrap();
+        assert_eq!(children.len(), 5);
+        let group_id = children[0].group_id.expect("split legs share a group id");
+        assert!(children.iter().all(|child| child.group_id == Some(group_id) && child.status == TransactionStatus::Approved));
+        framework.verify_chain(user.user_id).unwrap();
+    }
+
+    #[test]
+    fn test_transaction_event_callbacks() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+
+        let created = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = created.clone();
+        framework.on_transaction_event(Box::new(move |event| {
+            if event.kind == events::TransactionEventKind::Created {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }));
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let sink = messages.clone();
+        framework.on_transaction_event(Box::new(move |event| {
+            if let Some(sms) = event.to_sms("KES") {
+                sink.lock().unwrap().push(sms);
+            }
+        }));
+        // A misbehaving callback must not break processing or later callbacks
+        framework.on_transaction_event(Box::new(|_| panic!("integrator bug")));
+
+        let first = framework.process_transaction(user.user_id, 120.0, "Grace Wanjiku".to_string(), TransactionType::Transfer).unwrap();
+        framework.process_transaction(user.user_id, 45.0, "Grace Wanjiku".to_string(), TransactionType::Transfer).unwrap();
+        assert_eq!(created.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        framework.reverse_transaction(first.transaction_id, "Sent in error".to_string()).unwrap();
+        assert_eq!(created.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let messages = messages.lock().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].contains("APPROVED"));
+        assert!(messages[2].contains("reversed"));
+    }
+
+    #[test]
+    fn test_sync_schedule_follows_connectivity() {
+        let last_sync = Utc::now();
+        let clock = clock::MockClock::new(last_sync);
+        let mut framework = SafeBankFramework::with_clock(config::SafeBankConfig::default(), Arc::new(clock.clone()));
+        clock.advance(chrono::Duration::minutes(45));
+
+        framework.set_connectivity_probe(Arc::new(connectivity::FixedProbe(utils::ConnectivityStatus::Online)));
+        assert!(framework.is_sync_due(last_sync));
+
+        // Limited links sync at half the frequency in low-connectivity mode
+        framework.set_connectivity_probe(Arc::new(connectivity::FixedProbe(utils::ConnectivityStatus::Limited)));
+        assert!(!framework.is_sync_due(last_sync));
+        clock.advance(chrono::Duration::minutes(20));
+        assert!(framework.is_sync_due(last_sync));
+
+        framework.set_connectivity_probe(Arc::new(connectivity::FixedProbe(utils::ConnectivityStatus::Offline)));
+        assert!(!framework.is_sync_due(last_sync));
+    }
+
+    #[test]
+    fn test_metrics_text_is_valid_prometheus() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.process_request(
+            TransactionRequest::new(user.user_id, 120.0, "Grace Wanjiku".to_string(), TransactionType::Transfer)
+                .with_category(TransactionCategory::SchoolFees),
+        ).unwrap();
+        for _ in 0..3 {
+            let _ = framework.authenticate_user("+254712345678", "0000", "test-device");
+        }
+
+        let text = framework.metrics_text();
+        let comment = regex::Regex::new(r"^# (HELP|TYPE) [a-zA-Z_:][a-zA-Z0-9_:]* .+$").unwrap();
+        let sample = regex::Regex::new(
+            r#"^[a-zA-Z_:][a-zA-Z0-9_:]*(\{[a-zA-Z_][a-zA-Z0-9_]*="([^"\\]|\\.)*"(,[a-zA-Z_][a-zA-Z0-9_]*="([^"\\]|\\.)*")*\})? (-?[0-9.]+(e[+-]?[0-9]+)?|NaN|[+-]Inf)$"#,
+        ).unwrap();
+        for line in text.lines() {
+            assert!(comment.is_match(line) || sample.is_match(line), "invalid line: {}", line);
+        }
+
+        assert!(text.contains("# TYPE safebank_transactions_total counter"));
+        assert!(text.contains("safebank_transactions_total{status=\"approved\"} 1"));
+        assert!(text.contains("safebank_transactions_by_category_total{category=\"school_fees\"} 1"));
+        assert!(text.contains("safebank_fraud_total_analyzed_total"));
+        assert!(text.contains("# TYPE safebank_auth_active_lockouts gauge"));
+        assert!(text.contains("safebank_auth_active_lockouts 1"));
+    }
+
+    #[test]
+    fn test_batch_enforces_daily_limit_in_order() {
+        let config = config::SafeBankConfig { daily_transaction_limit: 1000.0, ..config::SafeBankConfig::default() };
+        let mut framework = SafeBankFramework::new(config);
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.add_to_blocklist("Scam Line");
+
+        let request = |amount: f64, recipient: &str| TransactionRequest::new(user.user_id, amount, recipient, TransactionType::Payment);
+        let batch = framework.process_batch(vec![
+            request(300.0, "Village Shop"),
+            request(300.0, "Village Shop"),
+            request(300.0, "Village Shop"),
+            // 1200 would cross the 1000 daily limit, but the smaller item after it still fits
+            request(300.0, "Village Shop"),
+            request(50.0, "Village Shop"),
+            request(20.0, "Scam Line"),
+            request(10.0, "Village Shop"),
+        ]);
+
+        assert_eq!(batch.results.len(), 7);
+        assert!(batch.results[..3].iter().all(|result| result.is_ok()));
+        assert!(matches!(batch.results[3], Err(errors::SafeBankError::TransactionLimitExceeded { .. })));
+        assert!(batch.results[4].is_ok());
+        assert_eq!(batch.results[5].as_ref().unwrap().status, TransactionStatus::Rejected);
+        assert!(batch.results[6].is_ok());
+        assert_eq!((batch.approved + batch.held, batch.rejected, batch.failed), (5, 1, 1));
+        // The rejected item still counts toward the day's usage, as it does outside a batch
+        assert_eq!(framework.transaction_manager.daily_usage(user.user_id), 980.0);
+    }
+
+    #[test]
+    fn test_simulation_leaves_state_unchanged() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.process_transaction(user.user_id, 200.0, "Village Shop".to_string(), TransactionType::Payment).unwrap();
+        framework.add_to_blocklist("Scam Line");
+
+        let transaction_stats = framework.transaction_manager.get_transaction_statistics().unwrap();
+        let fraud_stats = framework.get_fraud_statistics();
+        let usage = framework.transaction_manager.daily_usage(user.user_id);
+        let balance = framework.get_balance(user.user_id).unwrap();
+
+        let preview = framework.simulate_transaction(user.user_id, 300.0, "Village Shop", TransactionType::Payment).unwrap();
+        assert_eq!(preview.recommendation, fraud_detection::FraudRecommendation::Approve);
+        let blocked = framework.simulate_transaction(user.user_id, 20.0, "Scam Line", TransactionType::Payment).unwrap();
+        assert_eq!(blocked.recommendation, fraud_detection::FraudRecommendation::Block);
+
+        assert_eq!(framework.transaction_manager.get_transaction_statistics().unwrap(), transaction_stats);
+        assert_eq!(framework.get_fraud_statistics(), fraud_stats);
+        assert_eq!(framework.transaction_manager.daily_usage(user.user_id), usage);
+        assert_eq!(framework.get_balance(user.user_id).unwrap(), balance);
+    }
+
+    #[test]
+    fn test_configured_fraud_rule_blocks_night_transfers() {
+        let mut config: config::SafeBankConfig = toml::from_str(r#"
+            timezone_offset_minutes = 180
+
+            [[fraud_rules]]
+            name = "night transfer to new recipient"
+            min_amount = 3000.0
+            hours = [23, 5]
+            recipient_known = false
+            transaction_types = ["Transfer"]
+            action = "Block"
+        "#).unwrap();
+        config.validate().unwrap();
+        // Large daytime transfers would otherwise wait for a confirmation code
+        config.transfer_confirmation_threshold = None;
+
+        // 23:30 UTC is 02:30 local time
+        let night = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 1, 23, 30, 0).unwrap();
+        let clock = clock::MockClock::new(night);
+        let mut framework = SafeBankFramework::with_clock(config, Arc::new(clock.clone()));
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+
+        let transfer = |framework: &mut SafeBankFramework, amount: f64, recipient: &str| {
+            framework.process_transaction(user.user_id, amount, recipient.to_string(), TransactionType::Transfer).unwrap()
+        };
+
+        let blocked = transfer(&mut framework, 3500.0, "New Contact");
+        assert_eq!(blocked.status, TransactionStatus::Rejected);
+        assert!(blocked.risk_factors.iter().any(|factor| factor.factor_type == fraud_detection::RiskFactorType::CustomRule));
+
+        // Below the amount, or in the daytime, the rule doesn't fire
+        assert_eq!(transfer(&mut framework, 1000.0, "New Contact").status, TransactionStatus::Approved);
+        clock.advance(chrono::Duration::hours(10));
+        assert_eq!(transfer(&mut framework, 3500.0, "Another Contact").status, TransactionStatus::Approved);
+    }
+
+    #[test]
+    fn test_shared_account_members_co_approve_with_their_own_login() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let coop = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        let alice = framework.register_user("+254712345679".to_string(), "5930".to_string(), DeviceInfo::test_device()).unwrap();
+        let bob = framework.register_user("+254712345670".to_string(), "6142".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, coop.user_id);
+        let account = |members: &[Uuid]| transaction::SharedAccount {
+            account_id: coop.user_id,
+            members: members.iter().copied().collect(),
+            quorum: 2,
+            approval_threshold: 200.0,
+        };
+        assert!(matches!(
+            framework.register_shared_account(account(&[alice.user_id, Uuid::new_v4()])),
+            Err(errors::SafeBankError::UserNotFound { .. })
+        ));
+        framework.register_shared_account(account(&[alice.user_id, bob.user_id])).unwrap();
+
+        let held = framework.process_transaction(coop.user_id, 300.0, "Grain Co-op".to_string(), TransactionType::Transfer).unwrap();
+        assert_eq!(held.status, TransactionStatus::RequiresApproval);
+        assert!(framework.approve_transaction(held.transaction_id, "reviewer-7").is_err());
+        assert!(matches!(
+            framework.co_approve_transaction(held.transaction_id, "+254712345679", "0000", "test-device"),
+            Err(errors::SafeBankError::AuthenticationFailed { .. })
+        ));
+        let pending = framework.co_approve_transaction(held.transaction_id, "+254712345679", "5930", "test-device").unwrap();
+        assert_eq!(pending.status, TransactionStatus::RequiresApproval);
+        assert_eq!(framework.get_balance(coop.user_id).unwrap(), 100_000.0);
+
+        let approved = framework.co_approve_transaction(held.transaction_id, "+254712345670", "6142", "test-device").unwrap();
+        assert_eq!(approved.status, TransactionStatus::Approved);
+        assert_eq!(framework.get_balance(coop.user_id).unwrap(), 100_000.0 - (300.0 + approved.fee + approved.levy));
+    }
+
+    #[test]
+    fn test_transaction_outcome_is_texted_to_user() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        framework.add_to_blocklist("Scam Line");
+
+        let approved = framework.process_transaction(user.user_id, 120.0, "Grace Wanjiku".to_string(), TransactionType::Transfer).unwrap();
+        framework.process_transaction(user.user_id, 20.0, "Scam Line".to_string(), TransactionType::Transfer).unwrap();
+
+        let messages = notifier.messages.lock().unwrap();
+        assert_eq!(messages.len(), 2);
+        let reference = framework.transaction_manager.create_receipt(&approved).confirmation_code;
+        assert_eq!(messages[0], (
+            "+254712345678".to_string(),
+            utils::format_transaction_sms(120.0, "Grace Wanjiku", "approved", &reference, "USD", None),
+        ));
+        assert!(messages[1].1.contains("REJECTED"));
+    }
+
+    #[test]
+    fn test_frozen_account_cannot_log_in_or_transact() {
+        let clock = clock::MockClock::new(Utc::now());
+        let mut framework = SafeBankFramework::with_clock(config::SafeBankConfig::default(), Arc::new(clock.clone()));
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+
+        framework.freeze_account(user.user_id, "SIM swap reported").unwrap();
+        // Unlike a lockout, a freeze doesn't wear off
+        clock.advance(chrono::Duration::days(30));
+        assert!(matches!(
+            framework.authenticate_user("+254712345678", "4821", "test-device"),
+            Err(errors::SafeBankError::AccountFrozen)
+        ));
+        assert!(matches!(
+            framework.process_transaction(user.user_id, 100.0, "Village Shop".to_string(), TransactionType::Payment),
+            Err(errors::SafeBankError::AccountFrozen)
+        ));
+        let frozen = framework.auth_manager.get_user_by_id(user.user_id).unwrap();
+        assert_eq!(frozen.frozen_reason.as_deref(), Some("SIM swap reported"));
+
+        framework.unfreeze_account(user.user_id).unwrap();
+        assert!(framework.authenticate_user("+254712345678", "4821", "test-device").is_ok());
+        assert!(framework.process_transaction(user.user_id, 100.0, "Village Shop".to_string(), TransactionType::Payment).is_ok());
+    }
+
+    #[test]
+    fn test_seasoned_trusted_account_earns_higher_daily_limit() {
+        let clock = clock::MockClock::new(Utc::now());
+        let config = config::SafeBankConfig { trusted_daily_limit_ceiling: Some(15000.0), ..config::SafeBankConfig::default() };
+        let mut framework = SafeBankFramework::with_clock(config, Arc::new(clock.clone()));
+        let seasoned = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        framework.trust_device(seasoned.user_id, "test-device".to_string()).unwrap();
+        // Claims to be trusted, but the bank never trusted the device
+        let untrusted = framework.register_user("+254712345679".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, seasoned.user_id);
+        assert_eq!(framework.effective_daily_limit(seasoned.user_id), 10000.0);
+
+        // Every 25 clean transactions add 10%
+        for _ in 0..25 {
+            framework.process_transaction(seasoned.user_id, 10.0, "Village Shop".to_string(), TransactionType::Payment).unwrap();
+        }
+        assert!((framework.effective_daily_limit(seasoned.user_id) - 11000.0).abs() < 1e-6);
+
+        // Three months of age add another 30%; a year would pass the ceiling
+        clock.advance(chrono::Duration::days(95));
+        assert!((framework.effective_daily_limit(seasoned.user_id) - 14000.0).abs() < 1e-6);
+        assert_eq!(framework.effective_daily_limit(untrusted.user_id), 10000.0);
+        clock.advance(chrono::Duration::days(270));
+        assert_eq!(framework.effective_daily_limit(seasoned.user_id), 15000.0);
+
+        let transaction = framework.process_transaction(seasoned.user_id, 100.0, "Village Shop".to_string(), TransactionType::Payment).unwrap();
+        framework.mark_as_fraud(transaction.transaction_id, true);
+        assert_eq!(framework.effective_daily_limit(seasoned.user_id), 10000.0);
+    }
+
+    #[test]
+    fn test_unaffordable_transaction_fails_before_fraud_analysis() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        framework.transaction_manager.adjust_balance(user.user_id, 100.0).unwrap();
+
+        let result = framework.process_transaction(user.user_id, 500.0, "Village Shop".to_string(), TransactionType::Payment);
+        assert!(matches!(result, Err(errors::SafeBankError::InsufficientFunds { .. })));
+        let over_limit = framework.process_transaction(user.user_id, framework.config.single_transaction_limit + 1.0, "Village Shop".to_string(), TransactionType::Deposit);
+        assert!(matches!(over_limit, Err(errors::SafeBankError::TransactionLimitExceeded { .. })));
+        // Split legs that each fit the balance but together don't, or one over the limit, are refused the same way
+        let unaffordable = framework.process_split_transfer(user.user_id, vec![("Laborer A".to_string(), 60.0), ("Laborer B".to_string(), 60.0)]);
+        assert!(matches!(unaffordable, Err(errors::SafeBankError::InsufficientFunds { .. })));
+        let over_limit = framework.process_split_transfer(user.user_id, vec![("Laborer A".to_string(), framework.config.single_transaction_limit + 1.0)]);
+        assert!(matches!(over_limit, Err(errors::SafeBankError::TransactionLimitExceeded { .. })));
+        assert_eq!(framework.get_fraud_statistics()["total_analyzed"], 0.0);
+
+        framework.process_transaction(user.user_id, 50.0, "Village Shop".to_string(), TransactionType::Payment).unwrap();
+        assert_eq!(framework.get_fraud_statistics()["total_analyzed"], 1.0);
+    }
+
+    #[test]
+    fn test_medium_risk_from_untrusted_device_requires_step_up() {
+        let config = config::SafeBankConfig { fraud_threshold_low: 0.1, ..config::SafeBankConfig::default() };
+        // 09:00 UTC is midday local time, clear of the night hours
+        let midday = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 1, 9, 0, 0).unwrap();
+        let mut framework = SafeBankFramework::with_clock(config, Arc::new(clock::MockClock::new(midday)));
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo { is_trusted: false, ..DeviceInfo::test_device() }).unwrap();
+        fund(&mut framework, user.user_id);
+
+        let balance_before = framework.transaction_manager.get_balance(user.user_id).unwrap();
+
+        let held = framework.process_transaction(user.user_id, 2000.0, "Stranger".to_string(), TransactionType::Payment).unwrap();
+        assert_eq!(held.status, TransactionStatus::RequiresStepUp);
+        assert!(held.fraud_score > framework.config.fraud_threshold_low && held.fraud_score <= framework.config.fraud_threshold_medium);
+        assert_eq!(framework.transaction_manager.get_balance(user.user_id).unwrap(), balance_before);
+
+        let code = {
+            let messages = notifier.messages.lock().unwrap();
+            let (_, text) = messages.iter().rev().find(|(_, text)| text.contains("confirm with code")).unwrap();
+            text.split("code ").nth(1).unwrap().trim_end_matches(". Do not share this code.").to_string()
+        };
+        let confirmed = framework.confirm_transfer(held.transaction_id, &code).unwrap();
+        assert_eq!(confirmed.status, TransactionStatus::Approved);
+        assert!(framework.transaction_manager.get_balance(user.user_id).unwrap() < balance_before - 2000.0 + 1e-9);
+
+        // The same payment from a trusted device goes straight through
+        framework.trust_device(user.user_id, "test-device".to_string()).unwrap();
+        let approved = framework.process_transaction(user.user_id, 2000.0, "Stranger".to_string(), TransactionType::Payment).unwrap();
+        assert_eq!(approved.status, TransactionStatus::Approved);
+    }
+
+    #[test]
+    fn test_user_timezone_drives_night_hours_and_relearns_typical_hours() {
+        let config = config::SafeBankConfig {
+            risk_weights: config::RiskWeights { pattern: 0.5, ..config::RiskWeights::default() },
+            ..config::SafeBankConfig::default()
+        };
+        // 09:00 UTC: morning for the deployment, 23:00 for a user at UTC+14
+        let morning = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 1, 9, 0, 0).unwrap();
+        let mut framework = SafeBankFramework::with_clock(config, Arc::new(clock::MockClock::new(morning)));
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        for _ in 0..3 {
+            framework.process_transaction(user.user_id, 50.0, "Village Shop".to_string(), TransactionType::Payment).unwrap();
+        }
+        framework.update_behavioral_profile(user.user_id).unwrap();
+        let typical_hours = |framework: &SafeBankFramework| framework.storage.load_behavioral_profile(user.user_id).unwrap().unwrap().typical_transaction_times;
+        assert_eq!(typical_hours(&framework), vec![9]);
+
+        let late_night = |framework: &SafeBankFramework| framework.simulate_transaction(user.user_id, 50.0, "Village Shop", TransactionType::Payment).unwrap()
+            .risk_factors.iter()
+            .any(|factor| factor.factor_type == fraud_detection::RiskFactorType::SuspiciousPattern);
+        assert!(!late_night(&framework));
+
+        framework.set_timezone(user.user_id, 14 * 60).unwrap();
+        assert_eq!(typical_hours(&framework), vec![23]);
+        assert!(late_night(&framework));
+    }
+
+    #[test]
+    fn test_large_transfer_commits_only_with_confirmation_code() {
+        let config = config::SafeBankConfig { transfer_confirmation_threshold: Some(3000.0), ..config::SafeBankConfig::default() };
+        let mut framework = SafeBankFramework::new(config);
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+
+        // Over the threshold, even a plain transfer is held until confirmed
+        let held = framework.process_transaction(user.user_id, 3500.0, "Village Shop".to_string(), TransactionType::Transfer).unwrap();
+        assert_eq!(held.status, TransactionStatus::RequiresStepUp);
+        let balance_before = framework.transaction_manager.get_balance(user.user_id).unwrap();
+
+        let (pending, challenge) = framework.initiate_transfer(user.user_id, 500.0, "Grain Co-op".to_string()).unwrap();
+        assert_eq!(pending.status, TransactionStatus::RequiresStepUp);
+        assert_eq!(challenge.unwrap().transaction_id, pending.transaction_id);
+        let code = {
+            let messages = notifier.messages.lock().unwrap();
+            let (_, text) = messages.iter().rev().find(|(_, text)| text.contains("confirm with code")).unwrap();
+            text.split("code ").nth(1).unwrap().trim_end_matches(". Do not share this code.").to_string()
+        };
+
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+        assert!(matches!(framework.confirm_transfer(pending.transaction_id, wrong), Err(errors::SafeBankError::InvalidOtp)));
+        assert_eq!(framework.transaction_manager.get_transaction(pending.transaction_id).unwrap().status, TransactionStatus::RequiresStepUp);
+        assert_eq!(framework.transaction_manager.get_balance(user.user_id).unwrap(), balance_before);
+
+        let confirmed = framework.confirm_transfer(pending.transaction_id, &code).unwrap();
+        assert_eq!(confirmed.status, TransactionStatus::Approved);
+        assert!(framework.transaction_manager.get_balance(user.user_id).unwrap() < balance_before - 500.0 + 1e-9);
+        // Codes are single use
+        assert!(framework.confirm_transfer(pending.transaction_id, &code).is_err());
+    }
+
+    #[test]
+    fn test_split_transfer_over_threshold_commits_only_with_confirmation_code() {
+        let config = config::SafeBankConfig { transfer_confirmation_threshold: Some(3000.0), ..config::SafeBankConfig::default() };
+        let mut framework = SafeBankFramework::new(config);
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        let balance_before = framework.get_balance(user.user_id).unwrap();
+
+        // Each leg is under the threshold but together they are over it, so every leg waits on one code
+        let legs = framework.process_split_transfer(user.user_id, vec![
+            ("Day Laborer A".to_string(), 1500.0),
+            ("Day Laborer B".to_string(), 1500.0),
+            ("Day Laborer C".to_string(), 500.0),
+        ]).unwrap();
+        assert!(legs.iter().all(|leg| leg.status == TransactionStatus::RequiresStepUp));
+        assert_eq!(framework.get_balance(user.user_id).unwrap(), balance_before);
+        let code = {
+            let messages = notifier.messages.lock().unwrap();
+            let (_, text) = messages.iter().rev().find(|(_, text)| text.contains("confirm with code")).unwrap();
+            assert!(text.contains("Day Laborer A, Day Laborer B, Day Laborer C"));
+            text.split("code ").nth(1).unwrap().trim_end_matches(". Do not share this code.").to_string()
+        };
+
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+        assert!(matches!(framework.confirm_transfer(legs[1].transaction_id, wrong), Err(errors::SafeBankError::InvalidOtp)));
+        assert_eq!(framework.get_balance(user.user_id).unwrap(), balance_before);
+
+        // Confirming any leg releases the whole group
+        assert_eq!(framework.confirm_transfer(legs[1].transaction_id, &code).unwrap().status, TransactionStatus::Approved);
+        for leg in &legs {
+            assert_eq!(framework.transaction_manager.get_transaction(leg.transaction_id).unwrap().status, TransactionStatus::Approved);
+        }
+        assert!(framework.get_balance(user.user_id).unwrap() <= balance_before - 3500.0);
+        assert!(framework.confirm_transfer(legs[0].transaction_id, &code).is_err());
+    }
+
+    #[test]
+    fn test_approval_before_confirmation_code_does_not_damp_later_transfers() {
+        let config = config::SafeBankConfig { trusted_recipient_after: None, ..config::SafeBankConfig::default() };
+        let mut framework = SafeBankFramework::new(config);
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        let hold_for_review = |framework: &mut SafeBankFramework| {
+            let mut transaction = framework.new_transaction(user.user_id, "test-device".to_string(), 900.0, "Grain Co-op", TransactionType::Transfer).unwrap();
+            transaction.status = TransactionStatus::RequiresApproval;
+            framework.submit_decided(&user, transaction, true).unwrap()
+        };
+        let score = |framework: &SafeBankFramework| framework.simulate_transaction(user.user_id, 900.0, "Grain Co-op", TransactionType::Transfer).unwrap().fraud_score;
+        let baseline = score(&framework);
+
+        // Approving before the user has confirmed with their code doesn't vouch for the pattern
+        let (held, challenge) = hold_for_review(&mut framework);
+        assert!(challenge.is_some());
+        assert_eq!(framework.approve_transaction(held.transaction_id, "reviewer").unwrap().status, TransactionStatus::Approved);
+        assert!((score(&framework) - baseline).abs() < 1e-9);
+
+        // Once confirmed, the reviewer's approval does
+        let (held, challenge) = hold_for_review(&mut framework);
+        assert!(challenge.is_some());
+        let code = {
+            let messages = notifier.messages.lock().unwrap();
+            let (_, text) = messages.last().unwrap();
+            text.split("code ").nth(1).unwrap().trim_end_matches(". Do not share this code.").to_string()
+        };
+        assert_eq!(framework.confirm_transfer(held.transaction_id, &code).unwrap().status, TransactionStatus::RequiresApproval);
+        framework.approve_transaction(held.transaction_id, "reviewer").unwrap();
+        assert!(score(&framework) < baseline);
+    }
+
+    #[test]
+    fn test_unconfirmed_transfer_expires_with_its_code() {
+        let clock = Arc::new(clock::MockClock::new(Utc::now()));
+        let config = config::SafeBankConfig { pending_expiry_hours: Some(24), ..config::SafeBankConfig::default() };
+        let mut framework = SafeBankFramework::with_clock(config, clock.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+
+        let (pending, challenge) = framework.initiate_transfer(user.user_id, 500.0, "Grain Co-op".to_string()).unwrap();
+        assert!(challenge.is_some());
+        clock.advance(chrono::Duration::hours(25));
+        let expired = framework.expire_stale_transactions().unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].status, TransactionStatus::Rejected);
+
+        let key = SafeBankFramework::confirmation_key(pending.transaction_id);
+        assert!(framework.storage.load_record(&key).unwrap().is_none());
+        assert!(matches!(
+            framework.confirm_transfer(pending.transaction_id, "000000"),
+            Err(errors::SafeBankError::InvalidTransactionState { .. })
+        ));
+    }
+
+    #[test]
+    fn test_transfer_codes_resist_guessing_and_survive_restart() {
+        let config = config::SafeBankConfig::default();
+        let shared = storage::in_memory_storage();
+        let mut framework = SafeBankFramework::with_storage(config.clone(), shared.clone(), clock::system_clock());
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        let last_code = || {
+            let messages = notifier.messages.lock().unwrap();
+            let (_, text) = messages.iter().rev().find(|(_, text)| text.contains("confirm with code")).unwrap();
+            text.split("code ").nth(1).unwrap().trim_end_matches(". Do not share this code.").to_string()
+        };
+
+        // A pending confirmation is stored, so a restarted instance can complete it
+        let (pending, _) = framework.initiate_transfer(user.user_id, 500.0, "Grain Co-op".to_string()).unwrap();
+        let code = last_code();
+        let mut restarted = SafeBankFramework::with_storage(config.clone(), shared, clock::system_clock());
+        assert_eq!(restarted.confirm_transfer(pending.transaction_id, &code).unwrap().status, TransactionStatus::Approved);
+
+        // Wrong codes use up the code's attempts and count towards the account lockout
+        let (pending, _) = framework.initiate_transfer(user.user_id, 500.0, "Grain Co-op".to_string()).unwrap();
+        let code = last_code();
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+        for _ in 0..config.otp_max_attempts {
+            assert!(matches!(framework.confirm_transfer(pending.transaction_id, wrong), Err(errors::SafeBankError::InvalidOtp)));
+        }
+        assert!(matches!(
+            framework.confirm_transfer(pending.transaction_id, &code),
+            Err(errors::SafeBankError::InvalidTransactionState { .. })
+        ));
+        assert!(matches!(
+            framework.authenticate_user("+254712345678", "4821", "test-device"),
+            Err(errors::SafeBankError::AccountLocked)
+        ));
+    }
+
+    #[test]
+    fn test_repeated_recipient_becomes_trusted() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        let pay = |framework: &mut SafeBankFramework| {
+            framework.process_transaction(user.user_id, 200.0, "Mama Mboga".to_string(), TransactionType::Transfer).unwrap()
+        };
+        let recipient_flagged = |transaction: &Transaction| {
+            transaction.risk_factors.iter().any(|factor| factor.factor_type == fraud_detection::RiskFactorType::RecipientAnomaly)
+        };
+
+        assert!(recipient_flagged(&pay(&mut framework)));
+        assert!(recipient_flagged(&pay(&mut framework)));
+        let third = pay(&mut framework);
+        assert_eq!(third.status, TransactionStatus::Approved);
+        assert!(!recipient_flagged(&third));
+
+        // Confirmed fraud demotes the recipient for this user only, without blocking them for everyone
+        framework.mark_as_fraud(third.transaction_id, true);
+        assert!(recipient_flagged(&pay(&mut framework)));
+        let neighbour = framework.register_user("+254712345679".to_string(), "5930".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, neighbour.user_id);
+        let neighbour_payment = framework.process_transaction(neighbour.user_id, 200.0, "Mama Mboga".to_string(), TransactionType::Transfer).unwrap();
+        assert_ne!(neighbour_payment.status, TransactionStatus::Rejected);
+        // Relabelling the transaction as legitimate gives the recipient back
+        framework.mark_as_fraud(third.transaction_id, false);
+        assert!(!recipient_flagged(&pay(&mut framework)));
+
+        // Recipients paid in split transfers are learned the same way
+        let split = |framework: &mut SafeBankFramework| {
+            let legs = vec![("Day Laborer A".to_string(), 150.0), ("Day Laborer B".to_string(), 150.0)];
+            framework.process_split_transfer(user.user_id, legs).unwrap()
+        };
+        assert!(recipient_flagged(&split(&mut framework)[0]));
+        assert!(recipient_flagged(&split(&mut framework)[0]));
+        assert!(!recipient_flagged(&split(&mut framework)[0]));
+    }
+
+    #[test]
+    fn test_recipient_payment_counts_survive_restart() {
+        let config = config::SafeBankConfig::default();
+        let storage: storage::SharedStorage = Arc::new(storage::InMemoryStorage::default());
+        let mut framework = SafeBankFramework::with_storage(config.clone(), storage.clone(), clock::system_clock());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+        let needed = config.trusted_recipient_after.unwrap();
+        for _ in 1..needed {
+            framework.process_transaction(user.user_id, 200.0, "Mama Mboga".to_string(), TransactionType::Transfer).unwrap();
+        }
+
+        let mut restarted = SafeBankFramework::with_storage(config, storage, clock::system_clock());
+        restarted.process_transaction(user.user_id, 200.0, "Mama Mboga".to_string(), TransactionType::Transfer).unwrap();
+        let next = restarted.process_transaction(user.user_id, 200.0, "Mama Mboga".to_string(), TransactionType::Transfer).unwrap();
+        assert!(next.risk_factors.iter().all(|factor| factor.factor_type != fraud_detection::RiskFactorType::RecipientAnomaly));
+    }
+
+    #[test]
+    fn test_framework_lockout_wears_off_on_mock_clock() {
+        let clock = clock::MockClock::new(Utc::now());
+        let config = config::SafeBankConfig::default();
+        let lockout = chrono::Duration::minutes(config.lockout_duration_minutes as i64);
+        let mut framework = SafeBankFramework::with_clock(config.clone(), Arc::new(clock.clone()));
+        framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+
+        for _ in 0..config.max_failed_attempts {
+            let _ = framework.authenticate_user("+254712345678", "0000", "test-device");
+        }
+        assert!(matches!(
+            framework.authenticate_user("+254712345678", "4821", "test-device"),
+            Err(errors::SafeBankError::AccountLocked)
+        ));
+
+        clock.advance(lockout - chrono::Duration::minutes(1));
+        assert!(framework.authenticate_user("+254712345678", "4821", "test-device").is_err());
+        clock.advance(chrono::Duration::minutes(2));
+        assert_eq!(framework.now(), clock::Clock::now(&clock));
+        assert!(framework.authenticate_user("+254712345678", "4821", "test-device").is_ok());
+    }
+
+    #[test]
+    fn test_transaction_memo_round_trips_and_bad_memos_are_rejected() {
+        let mut framework = SafeBankFramework::new(config::SafeBankConfig::default());
+        let notifier = Arc::new(RecordingNotifier::default());
+        framework.set_notifier(notifier.clone());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        fund(&mut framework, user.user_id);
+
+        let request = TransactionRequest::new(user.user_id, 120.0, "Grace Wanjiku", TransactionType::Transfer).with_memo("  June rent ");
+        let transaction = framework.process_request(request).unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Approved);
+        let stored = framework.transaction_manager.get_transaction(transaction.transaction_id).unwrap();
+        assert_eq!(stored.memo.as_deref(), Some("June rent"));
+        assert_eq!(framework.transaction_manager.create_receipt(&stored).memo.as_deref(), Some("June rent"));
+        assert!(notifier.messages.lock().unwrap().last().unwrap().1.ends_with("Memo: \"June rent\""));
+        framework.verify_chain(user.user_id).unwrap();
+
+        // Over-long memos and ones carrying control, bidi or other invisible characters or formulas are
+        // refused, and nothing is recorded
+        let count = framework.transaction_manager.get_user_transactions(user.user_id).unwrap().len();
+        let hidden = ["rent \u{202E}tnuocca", "June\u{200B}rent", "rent\u{2066}", "rent\u{E0041}", "\u{FEFF}rent"];
+        for memo in ["x".repeat(81), "rent\nSafeBank: PIN reset".to_string(), "=HYPERLINK(\"x\")".to_string()]
+            .into_iter()
+            .chain(hidden.map(str::to_string))
+        {
+            let request = TransactionRequest::new(user.user_id, 50.0, "Grace Wanjiku", TransactionType::Transfer).with_memo(memo);
+            assert!(matches!(framework.process_request(request), Err(errors::SafeBankError::ConfigError { .. })));
+        }
+        assert_eq!(framework.transaction_manager.get_user_transactions(user.user_id).unwrap().len(), count);
+    }
+}
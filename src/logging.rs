@@ -0,0 +1,47 @@
+//! Structured diagnostics for SafeBank framework
+//! Events go through the `tracing` facade when the `tracing` feature is enabled (the default)
+//! and compile to nothing otherwise. Phone numbers and free text pass through
+//! `utils::sanitize_for_logging` before they are recorded.
+
+#[cfg(feature = "tracing")]
+macro_rules! warn_event {
+    ($($arg:tt)*) => { tracing::warn!($($arg)*) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! warn_event {
+    ($($arg:tt)*) => { () };
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! info_event {
+    ($($arg:tt)*) => { tracing::info!($($arg)*) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! info_event {
+    ($($arg:tt)*) => { () };
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! debug_event {
+    ($($arg:tt)*) => { tracing::debug!($($arg)*) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug_event {
+    ($($arg:tt)*) => { () };
+}
+
+/// Enter an info-level span for the rest of the enclosing scope: `let _span = enter_span!("name", field = value);`
+#[cfg(feature = "tracing")]
+macro_rules! enter_span {
+    ($($arg:tt)*) => { tracing::info_span!($($arg)*).entered() };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! enter_span {
+    ($($arg:tt)*) => { () };
+}
+
+pub(crate) use {debug_event, enter_span, info_event, warn_event};
@@ -3,16 +3,37 @@
 //! A lightweight, secure banking application optimized for rural environments
 //! with low-end devices and limited connectivity.
 
+use std::path::{Path, PathBuf};
 use clap::{Arg, Command};
-use std::io::Write;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 
 use safebank::{
-    SafeBankFramework, UserProfile, DeviceInfo, TransactionType, 
-    config::SafeBankConfig, errors::SafeBankError, utils
+    FrameworkSnapshot, SafeBankFramework, UserProfile, DeviceInfo, TransactionType,
+    config::SafeBankConfig, errors::SafeBankError, sealed, transaction::TransactionFilter, utils
 };
 
+/// How long a `login` lets later commands act for the user
+const CLI_SESSION_MINUTES: i64 = 15;
+
+/// Number of transactions `history` prints
+const HISTORY_LENGTH: usize = 10;
+
+/// Framework state and login session carried between CLI invocations
+#[derive(Debug, Serialize, Deserialize)]
+struct CliState {
+    snapshot: FrameworkSnapshot,
+    session: Option<CliSession>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CliSession {
+    user_id: Uuid,
+    phone_number: String,
+    expires_at: DateTime<Utc>,
+}
+
 fn main() {
     let matches = Command::new("SafeBank")
         .version("1.0.0")
@@ -36,12 +57,14 @@ fn main() {
                 .about("Register a new user")
                 .arg(Arg::new("phone").required(true).help("Phone number"))
                 .arg(Arg::new("pin").required(true).help("4-6 digit PIN"))
+                .arg(Arg::new("device").long("device").default_value("cli").help("Device ID"))
         )
         .subcommand(
             Command::new("login")
                 .about("Authenticate user")
                 .arg(Arg::new("phone").required(true).help("Phone number"))
                 .arg(Arg::new("pin").required(true).help("PIN"))
+                .arg(Arg::new("device").long("device").default_value("cli").help("Device ID"))
         )
         .subcommand(
             Command::new("transfer")
@@ -51,12 +74,19 @@ fn main() {
         )
         .subcommand(
             Command::new("balance")
-                .about("Check account balance (simulated)")
+                .about("Check account balance")
+                .arg(Arg::new("phone").help("Phone number (defaults to the logged-in user)"))
+                .arg(Arg::new("pin").help("PIN"))
+                .arg(Arg::new("device").long("device").default_value("cli").help("Device ID"))
         )
         .subcommand(
             Command::new("history")
                 .about("View transaction history")
         )
+        .subcommand(
+            Command::new("logout")
+                .about("End the current session")
+        )
         .subcommand(
             Command::new("demo")
                 .about("Run a complete demo showcasing fraud detection")
@@ -68,7 +98,15 @@ fn main() {
         .get_matches();
 
     // Initialize configuration
-    let config = if matches.get_flag("minimal") {
+    let config = if let Some(path) = matches.get_one::<String>("config") {
+        match SafeBankConfig::from_file(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+    } else if matches.get_flag("minimal") {
         SafeBankConfig::minimal()
     } else {
         SafeBankConfig::default()
@@ -79,23 +117,111 @@ fn main() {
         return;
     }
 
-    // Initialize SafeBank framework
+    // Initialize SafeBank framework, picking up state saved by earlier invocations
     let mut framework = SafeBankFramework::new(config.clone());
+    let state_path = state_file();
+    let mut session = match load_state(&mut framework, &state_path) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("❌ Could not load saved state from {}: {}", state_path.display(), e);
+            return;
+        }
+    };
 
     match matches.subcommand() {
         Some(("register", sub_matches)) => {
             let phone = sub_matches.get_one::<String>("phone").unwrap();
             let pin = sub_matches.get_one::<String>("pin").unwrap();
+            let device = sub_matches.get_one::<String>("device").unwrap();
             
-            match register_user(&mut framework, phone, pin) {
+            match register_user(&mut framework, phone, pin, device) {
                 Ok(user) => {
                     println!("✅ User registered successfully!");
                     println!("User ID: {}", user.user_id);
                     println!("Phone: {}", user.phone_number);
+                    save_or_report(&framework, session.as_ref(), &state_path);
                 }
                 Err(e) => eprintln!("❌ Registration failed: {}", e.to_user_message()),
             }
         }
+        Some(("login", sub_matches)) => {
+            let phone = sub_matches.get_one::<String>("phone").unwrap();
+            let pin = sub_matches.get_one::<String>("pin").unwrap();
+            let device = sub_matches.get_one::<String>("device").unwrap();
+
+            match framework.authenticate_user(phone, pin, device) {
+                Ok(user) => {
+                    let expires_at = framework.now() + Duration::minutes(CLI_SESSION_MINUTES);
+                    println!("✅ Logged in as {} (session valid for {} minutes)", user.phone_number, CLI_SESSION_MINUTES);
+                    session = Some(CliSession { user_id: user.user_id, phone_number: user.phone_number, expires_at });
+                    save_or_report(&framework, session.as_ref(), &state_path);
+                }
+                Err(e) => {
+                    eprintln!("❌ Login failed: {}", e.to_user_message());
+                    // Keep the failure count so lockouts hold across invocations
+                    save_or_report(&framework, session.as_ref(), &state_path);
+                }
+            }
+        }
+        Some(("logout", _)) => {
+            session = None;
+            save_or_report(&framework, session.as_ref(), &state_path);
+            println!("👋 Logged out");
+        }
+        Some(("transfer", sub_matches)) => {
+            let Some(user_id) = session_user(session.as_ref()) else { return };
+            let Some(amount) = parse_amount(sub_matches.get_one::<String>("amount").unwrap()) else { return };
+            let recipient = sub_matches.get_one::<String>("recipient").unwrap();
+
+            match framework.process_transaction(user_id, amount, recipient.clone(), TransactionType::Transfer) {
+                Ok(tx) => {
+                    print_transaction_result(&tx, &config.local_currency);
+                    save_or_report(&framework, session.as_ref(), &state_path);
+                }
+                Err(e) => eprintln!("❌ Transfer failed: {}", e.to_user_message()),
+            }
+        }
+        Some(("balance", sub_matches)) => {
+            let result = match (sub_matches.get_one::<String>("phone"), sub_matches.get_one::<String>("pin")) {
+                (Some(phone), Some(pin)) => {
+                    let device = sub_matches.get_one::<String>("device").unwrap();
+                    check_balance(&mut framework, phone, pin, device)
+                }
+                (Some(_), None) => {
+                    eprintln!("❌ A PIN is required with a phone number");
+                    return;
+                }
+                _ => {
+                    let Some(user_id) = session_user(session.as_ref()) else { return };
+                    framework.get_balance(user_id)
+                }
+            };
+
+            match result {
+                Ok(balance) => println!("💰 Balance: {}", utils::format_currency(balance, &config.local_currency)),
+                Err(e) => eprintln!("❌ Balance check failed: {}", e.to_user_message()),
+            }
+        }
+        Some(("history", _)) => {
+            let Some(user_id) = session_user(session.as_ref()) else { return };
+            let filter = TransactionFilter { user_id: Some(user_id), ..TransactionFilter::default() };
+
+            match framework.query_transactions(filter, 0, HISTORY_LENGTH) {
+                Ok(page) if page.transactions.is_empty() => println!("📭 No transactions yet"),
+                Ok(page) => {
+                    println!("📜 Last {} of {} transactions:", page.transactions.len(), page.total_count);
+                    for tx in &page.transactions {
+                        println!("   {}  {:<10} {:>14}  {:<20} {:?}",
+                            tx.timestamp.format("%Y-%m-%d %H:%M"),
+                            tx.transaction_type.as_str(),
+                            utils::format_currency(tx.amount, &config.local_currency),
+                            tx.recipient,
+                            tx.status);
+                    }
+                }
+                Err(e) => eprintln!("❌ Could not load history: {}", e.to_user_message()),
+            }
+        }
         Some(("demo", _)) => {
             println!("🏦 SafeBank Demo - Rural Digital Banking Security");
             println!("{}", "=".repeat(50));
@@ -115,19 +241,135 @@ fn main() {
     }
 }
 
-fn register_user(framework: &mut SafeBankFramework, phone: &str, pin: &str) -> Result<UserProfile, SafeBankError> {
+/// Where CLI state lives: `$SAFEBANK_STATE`, else `safebank/state.sealed` in the user's config directory
+fn state_file() -> PathBuf {
+    if let Some(path) = std::env::var_os("SAFEBANK_STATE") {
+        return PathBuf::from(path);
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    config_dir.join("safebank").join("state.sealed")
+}
+
+/// Passphrase the state file is sealed under, from `$SAFEBANK_PASSPHRASE`
+fn state_passphrase() -> Result<String, SafeBankError> {
+    std::env::var("SAFEBANK_PASSPHRASE")
+        .ok()
+        .filter(|passphrase| !passphrase.is_empty())
+        .ok_or_else(|| SafeBankError::ConfigError {
+            message: "Set SAFEBANK_PASSPHRASE to the passphrase protecting saved state".to_string(),
+        })
+}
+
+/// Restore saved state into the framework and return the session if it hasn't expired
+fn load_state(framework: &mut SafeBankFramework, path: &Path) -> Result<Option<CliSession>, SafeBankError> {
+    let sealed = match std::fs::read(path) {
+        Ok(sealed) => sealed,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(SafeBankError::StorageError { message: e.to_string() }),
+    };
+    let contents = sealed::open(&sealed, &state_passphrase()?)?;
+    let state: CliState = serde_json::from_slice(&contents)
+        .map_err(|e| SafeBankError::SerializationError { message: e.to_string() })?;
+
+    framework.restore(state.snapshot)?;
+    Ok(state.session.filter(|session| session.expires_at > framework.now()))
+}
+
+fn save_state(framework: &SafeBankFramework, session: Option<&CliSession>, path: &Path) -> Result<(), SafeBankError> {
+    let state = CliState { snapshot: framework.snapshot()?, session: session.cloned() };
+    let contents = serde_json::to_vec(&state)
+        .map_err(|e| SafeBankError::SerializationError { message: e.to_string() })?;
+    // PIN hashes, transactions and the session never reach the disk in plaintext
+    let sealed = sealed::seal(&contents, &state_passphrase()?)?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| SafeBankError::StorageError { message: e.to_string() })?;
+    }
+    // Write then rename so an interrupted save never leaves a truncated state file
+    let temp_path = path.with_extension("sealed.tmp");
+    write_private(&temp_path, &sealed)
+        .and_then(|_| std::fs::rename(&temp_path, path))
+        .map_err(|e| SafeBankError::StorageError { message: e.to_string() })
+}
+
+/// Write a file only its owner can read
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // A leftover file keeps its old mode through `open`, so tighten it explicitly
+        if let Ok(metadata) = std::fs::metadata(path) {
+            let mut permissions = metadata.permissions();
+            permissions.set_mode(0o600);
+            std::fs::set_permissions(path, permissions)?;
+        }
+    }
+    options.open(path)?.write_all(contents)
+}
+
+fn save_or_report(framework: &SafeBankFramework, session: Option<&CliSession>, path: &Path) {
+    if let Err(e) = save_state(framework, session, path) {
+        eprintln!("⚠️  Could not save state to {}: {}", path.display(), e);
+    }
+}
+
+fn session_user(session: Option<&CliSession>) -> Option<Uuid> {
+    match session {
+        Some(session) => Some(session.user_id),
+        None => {
+            eprintln!("🔒 Not logged in (or the session expired). Run `safebank login <phone> <pin>` first.");
+            None
+        }
+    }
+}
+
+fn parse_amount(raw: &str) -> Option<f64> {
+    match raw.parse::<f64>() {
+        Ok(amount) if amount.is_finite() && amount > 0.0 => Some(amount),
+        _ => {
+            eprintln!("❌ Invalid amount: {}", raw);
+            None
+        }
+    }
+}
+
+fn print_transaction_result(tx: &safebank::Transaction, currency: &str) {
+    println!("✅ {} {} - Status: {:?}", tx.transaction_type.as_str(), utils::format_currency(tx.amount, currency), tx.status);
+    println!("   Fee: {}  Levy: {}  Fraud score: {:.2}",
+        utils::format_currency(tx.fee, currency), utils::format_currency(tx.levy, currency), tx.fraud_score);
+}
+
+fn register_user(framework: &mut SafeBankFramework, phone: &str, pin: &str, device_id: &str) -> Result<UserProfile, SafeBankError> {
     let device_info = DeviceInfo {
-        device_id: format!("device_{}", Uuid::new_v4().to_string()[..8].to_uppercase()),
+        device_id: device_id.to_string(),
         device_type: "smartphone".to_string(),
         os_version: Some("Android 8.0".to_string()),
         app_version: "1.0.0".to_string(),
         is_trusted: false,
-        registered_at: Utc::now(),
+        registered_at: framework.now(),
+        capabilities: None,
+        mfa_method: safebank::auth::MfaMethod::Otp,
+        attestation_key: None,
+        trusted_at: None,
     };
 
     framework.register_user(phone.to_string(), pin.to_string(), device_info)
 }
 
+fn check_balance(framework: &mut SafeBankFramework, phone: &str, pin: &str, device: &str) -> Result<f64, SafeBankError> {
+    let user = framework.authenticate_user(phone, pin, device)?;
+    framework.get_balance(user.user_id)
+}
+
 fn run_demo(framework: &mut SafeBankFramework) {
     println!("Initializing rural banking security demonstration...");
     
@@ -157,15 +399,16 @@ fn run_demo(framework: &mut SafeBankFramework) {
 
 fn create_demo_users(framework: &mut SafeBankFramework) -> Vec<UserProfile> {
     let demo_users = vec![
-        ("+254712345678", "1234", "Mary - Maize Farmer"),
-        ("+254787654321", "5678", "John - Shop Owner"),
-        ("+254756789012", "9876", "Grace - Teacher"),
+        ("+254712345678", "4821", "Mary - Maize Farmer"),
+        ("+254787654321", "7392", "John - Shop Owner"),
+        ("+254756789012", "6150", "Grace - Teacher"),
     ];
 
     let mut users = Vec::new();
     
     for (phone, pin, description) in demo_users {
-        match register_user(framework, phone, pin) {
+        let device_id = format!("device_{}", Uuid::new_v4().to_string()[..8].to_uppercase());
+        match register_user(framework, phone, pin, &device_id) {
             Ok(user) => {
                 println!("   ✅ Registered {}: {}", description, phone);
                 users.push(user);
@@ -186,7 +429,7 @@ fn demonstrate_authentication(framework: &mut SafeBankFramework, users: &[UserPr
     
     // Successful authentication
     println!("   Testing valid authentication...");
-    match framework.authenticate_user(&user.phone_number, "1234", &user.device_info.device_id) {
+    match framework.authenticate_user(&user.phone_number, "4821", &user.devices[0].device_id) {
         Ok(_) => println!("   ✅ Authentication successful"),
         Err(e) => println!("   ❌ Authentication failed: {}", e.to_user_message()),
     }
@@ -194,7 +437,7 @@ fn demonstrate_authentication(framework: &mut SafeBankFramework, users: &[UserPr
     // Failed authentication attempts
     println!("   Testing invalid PIN protection...");
     for i in 1..=3 {
-        match framework.authenticate_user(&user.phone_number, "0000", &user.device_info.device_id) {
+        match framework.authenticate_user(&user.phone_number, "0000", &user.devices[0].device_id) {
             Ok(_) => println!("   ❌ Unexpected success"),
             Err(e) => println!("   ✅ Failed attempt {}: {}", i, e.to_user_message()),
         }
@@ -202,7 +445,7 @@ fn demonstrate_authentication(framework: &mut SafeBankFramework, users: &[UserPr
     
     // Device verification
     println!("   Testing device verification...");
-    match framework.authenticate_user(&user.phone_number, "1234", "unknown-device") {
+    match framework.authenticate_user(&user.phone_number, "4821", "unknown-device") {
         Ok(_) => println!("   ❌ Unexpected success with unknown device"),
         Err(e) => println!("   ✅ Unknown device rejected: {}", e.to_user_message()),
     }
@@ -215,6 +458,12 @@ fn demonstrate_fraud_detection(framework: &mut SafeBankFramework, users: &[UserP
 
     let user = &users[0];
     
+    println!("   Depositing funds at a cash agent...");
+    match framework.process_transaction(user.user_id, 3000.0, "Cash Agent".to_string(), TransactionType::Deposit) {
+        Ok(_) => println!("   ✅ Deposit received"),
+        Err(e) => println!("   ❌ Deposit failed: {}", e.to_user_message()),
+    }
+
     println!("   Processing normal transactions...");
     
     // Normal transactions
@@ -262,6 +511,10 @@ fn demonstrate_fraud_detection(framework: &mut SafeBankFramework, users: &[UserP
             Err(e) => println!("   🚫 Blocked transaction: {}", e.to_user_message()),
         }
     }
+
+    if let Ok(balance) = framework.get_balance(user.user_id) {
+        println!("   💰 Remaining balance: {}", utils::format_currency(balance, "KES"));
+    }
 }
 
 fn show_statistics(framework: &SafeBankFramework) {
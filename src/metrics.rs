@@ -0,0 +1,68 @@
+//! Prometheus metrics export for SafeBank framework
+//! Renders monitoring counters in the text exposition format so a scraper can collect them
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+impl MetricKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+/// Builds a Prometheus text exposition document, one metric family at a time
+#[derive(Debug, Default)]
+pub struct MetricsWriter {
+    output: String,
+}
+
+impl MetricsWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write a metric family with a single unlabeled sample
+    pub fn metric(&mut self, name: &str, kind: MetricKind, help: &str, value: f64) {
+        self.header(name, kind, help);
+        let _ = writeln!(self.output, "{} {}", name, format_value(value));
+    }
+
+    /// Write a metric family with one sample per label value
+    pub fn labeled(&mut self, name: &str, kind: MetricKind, help: &str, label: &str, samples: &[(String, f64)]) {
+        self.header(name, kind, help);
+        for (label_value, value) in samples {
+            let _ = writeln!(self.output, "{}{{{}=\"{}\"}} {}", name, label, escape_label_value(label_value), format_value(*value));
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    fn header(&mut self, name: &str, kind: MetricKind, help: &str) {
+        let _ = writeln!(self.output, "# HELP {} {}", name, help.replace('\\', "\\\\").replace('\n', "\\n"));
+        let _ = writeln!(self.output, "# TYPE {} {}", name, kind.as_str());
+    }
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,141 @@
+//! Fixed-point money for SafeBank framework
+//! Ledger arithmetic (balances, daily limits, fees) runs on integer minor units so
+//! thousands of small amounts add up exactly; `f64` is only used at the API boundary.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::RoundingMode, utils};
+
+/// An amount in integer minor units of the local currency (cents for two-decimal currencies)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_minor_units(minor_units: i64) -> Self {
+        Money(minor_units)
+    }
+
+    pub const fn minor_units(self) -> i64 {
+        self.0
+    }
+
+    /// Convert a decimal amount at the boundary, rounding to the minor unit with `mode`
+    pub fn from_decimal(amount: f64, decimal_places: u32, mode: RoundingMode) -> Self {
+        let rounded = utils::round_money(amount, decimal_places, mode);
+        Money((rounded * Self::scale(decimal_places) as f64).round() as i64)
+    }
+
+    /// The amount as a decimal number, for callers that still work in `f64`
+    pub fn to_decimal(self, decimal_places: u32) -> f64 {
+        self.0 as f64 / Self::scale(decimal_places) as f64
+    }
+
+    /// Exact decimal text without currency symbol, e.g. "-12.05"
+    pub fn to_decimal_string(self, decimal_places: u32) -> String {
+        let scale = Self::scale(decimal_places) as u64;
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        if decimal_places == 0 {
+            format!("{}{}", sign, abs)
+        } else {
+            format!("{}{}.{:0width$}", sign, abs / scale, abs % scale, width = decimal_places as usize)
+        }
+    }
+
+    /// `self * numerator / denominator`, rounded half away from zero in integer arithmetic
+    pub fn mul_ratio(self, numerator: i64, denominator: i64) -> Self {
+        let product = self.0 as i128 * numerator as i128;
+        let denominator = denominator as i128;
+        let magnitude = (product.abs() + denominator.abs() / 2) / denominator.abs();
+        let negative = (product < 0) != (denominator < 0);
+        Money((if negative { -magnitude } else { magnitude }) as i64)
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn abs(self) -> Self {
+        Money(self.0.abs())
+    }
+
+    fn scale(decimal_places: u32) -> i64 {
+        10_i64.pow(decimal_places)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+/// Displays minor units as a two-decimal amount; use `to_decimal_string` for other currencies
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_decimal_string(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_many_small_amounts_sum_exactly() {
+        let cent = Money::from_decimal(0.01, 2, RoundingMode::HalfUp);
+        let total: Money = std::iter::repeat_n(cent, 10_000).sum();
+        assert_eq!(total, Money::from_minor_units(10_000));
+        assert_eq!(total.to_decimal(2), 100.0);
+
+        // The same sum in floating point drifts
+        let float_total: f64 = std::iter::repeat_n(0.01, 10_000).sum();
+        assert_ne!(float_total, 100.0);
+
+        assert_eq!(Money::from_minor_units(-1205).to_decimal_string(2), "-12.05");
+        assert_eq!(Money::from_minor_units(1000).mul_ratio(5, 1000), Money::from_minor_units(5));
+        assert_eq!(Money::from_minor_units(-15).mul_ratio(1, 10), Money::from_minor_units(-2));
+    }
+}
@@ -0,0 +1,69 @@
+//! Notification delivery for SafeBank framework
+//! Provides the seam through which user-facing messages (SMS, USSD, push) are sent
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::{config::SafeBankConfig, errors::Result, logging};
+
+/// Delivery channel for user notifications
+pub trait Notifier: Debug + Send + Sync {
+    /// Send a message to a recipient (usually a phone number)
+    fn send(&self, to: &str, message: &str) -> Result<()>;
+}
+
+/// Notifier that discards every message
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopNotifier;
+
+impl Notifier for NoopNotifier {
+    fn send(&self, _to: &str, _message: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Notifier that only logs that a message went out, for running without an SMS gateway.
+/// The recipient is masked and the body left out so one-time codes never reach the logs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn send(&self, to: &str, message: &str) -> Result<()> {
+        logging::info_event!(to = %crate::utils::sanitize_for_logging(to), length = message.chars().count(), "notification sent");
+        Ok(())
+    }
+}
+
+/// Shared notifier handle used by the framework
+pub type SharedNotifier = Arc<dyn Notifier>;
+
+/// Default notifier: logging when transaction notifications are on, silent in minimal mode
+pub fn notifier_from_config(config: &SafeBankConfig) -> SharedNotifier {
+    if config.transaction_notifications {
+        Arc::new(LogNotifier)
+    } else {
+        Arc::new(NoopNotifier)
+    }
+}
+
+/// Format the text carrying the code that confirms a held transfer
+pub fn format_transfer_confirmation(amount: f64, recipient: &str, code: &str, currency: &str) -> String {
+    format!(
+        "SafeBank: To send {} to {}, confirm with code {}. Do not share this code.",
+        crate::utils::format_currency(amount, currency),
+        recipient,
+        code
+    )
+}
+
+/// Format the warning sent when a user nears their daily limit
+pub fn format_limit_warning(used: f64, limit: f64, currency: &str) -> String {
+    let percent = if limit > 0.0 { used / limit * 100.0 } else { 100.0 };
+    format!(
+        "SafeBank: You have used {:.0}% of your daily limit of {}. Remaining today: {}.",
+        percent,
+        crate::utils::format_currency(limit, currency),
+        crate::utils::format_currency((limit - used).max(0.0), currency)
+    )
+}
@@ -0,0 +1,156 @@
+//! One-time password handling for SafeBank framework
+//! Issues random numeric codes per key (a phone number or transaction id) and verifies them once before expiry.
+//! Outstanding codes are kept hashed in storage, so they survive a restart and are shared by
+//! instances on the same backend.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    clock::SharedClock,
+    errors::{Result, SafeBankError},
+    storage::{self, SharedStorage},
+    utils
+};
+
+/// An issued code awaiting verification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IssuedOtp {
+    code_hash: String,
+    expires_at: DateTime<Utc>,
+    /// Wrong codes entered against this one so far
+    #[serde(default)]
+    failures: u32,
+}
+
+#[derive(Debug)]
+pub struct OtpManager {
+    code_length: usize,
+    ttl: Duration,
+    max_attempts: u32,
+    storage: SharedStorage,
+    record_prefix: &'static str, // storage key prefix, so separate managers don't share codes
+    clock: SharedClock,
+}
+
+impl OtpManager {
+    pub fn new(code_length: usize, ttl_seconds: u64, max_attempts: u32, storage: SharedStorage, record_prefix: &'static str, clock: SharedClock) -> Self {
+        Self {
+            code_length,
+            ttl: Duration::seconds(ttl_seconds as i64),
+            max_attempts,
+            storage,
+            record_prefix,
+            clock,
+        }
+    }
+
+    /// Issue a fresh code for a key, replacing any outstanding one
+    pub fn issue_otp(&mut self, key: &str) -> Result<String> {
+        let code = utils::generate_simple_otp(self.code_length);
+        storage::save_json(self.storage.as_ref(), &self.record_key(key), &IssuedOtp {
+            code_hash: Self::hash_code(key, &code),
+            expires_at: self.clock.now() + self.ttl,
+            failures: 0,
+        })?;
+        Ok(code)
+    }
+
+    /// Verify and consume the outstanding code for a key.
+    /// Expired codes are discarded. A wrong code leaves the outstanding one in place until
+    /// `max_attempts` wrong codes have been entered, after which it is discarded too.
+    pub fn verify_otp(&mut self, key: &str, code: &str) -> Result<()> {
+        let record_key = self.record_key(key);
+        let mut issued: IssuedOtp = storage::load_json(self.storage.as_ref(), &record_key)?
+            .ok_or(SafeBankError::InvalidOtp)?;
+
+        if self.clock.now() > issued.expires_at {
+            self.storage.remove_record(&record_key)?;
+            return Err(SafeBankError::OtpExpired);
+        }
+
+        if !utils::constant_time_eq(issued.code_hash.as_bytes(), Self::hash_code(key, code).as_bytes()) {
+            issued.failures += 1;
+            if issued.failures >= self.max_attempts {
+                self.storage.remove_record(&record_key)?;
+            } else {
+                storage::save_json(self.storage.as_ref(), &record_key, &issued)?;
+            }
+            return Err(SafeBankError::InvalidOtp);
+        }
+
+        self.storage.remove_record(&record_key)?;
+        Ok(())
+    }
+
+    /// Whether a key still has a code that can be entered
+    pub fn is_outstanding(&self, key: &str) -> bool {
+        storage::load_json::<IssuedOtp>(self.storage.as_ref(), &self.record_key(key))
+            .ok()
+            .flatten()
+            .is_some_and(|issued| self.clock.now() <= issued.expires_at)
+    }
+
+    /// Discard the outstanding code for a key, if any
+    pub fn revoke_otp(&mut self, key: &str) -> Result<()> {
+        self.storage.remove_record(&self.record_key(key))
+    }
+
+    fn record_key(&self, key: &str) -> String {
+        format!("{}{}", self.record_prefix, key)
+    }
+
+    /// Codes are short, so the key is mixed in to keep equal codes for different keys apart
+    fn hash_code(key: &str, code: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update((key.len() as u64).to_le_bytes());
+        hasher.update(key.as_bytes());
+        hasher.update(code.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_otp_single_use_and_expiry() {
+        let clock = MockClock::new(Utc::now());
+        let mut otp = OtpManager::new(6, 120, 3, storage::in_memory_storage(), "otp/", Arc::new(clock.clone()));
+
+        let code = otp.issue_otp("+254712345678").unwrap();
+        assert_eq!(code.len(), 6);
+        assert!(matches!(otp.verify_otp("+254712345678", "not-it"), Err(SafeBankError::InvalidOtp)));
+        assert!(otp.verify_otp("+254712345678", &code).is_ok());
+        assert!(matches!(otp.verify_otp("+254712345678", &code), Err(SafeBankError::InvalidOtp)));
+
+        let code = otp.issue_otp("+254712345678").unwrap();
+        clock.advance(Duration::seconds(121));
+        assert!(matches!(otp.verify_otp("+254712345678", &code), Err(SafeBankError::OtpExpired)));
+    }
+
+    #[test]
+    fn test_otp_discarded_after_max_attempts() {
+        let shared = storage::in_memory_storage();
+        let mut otp = OtpManager::new(4, 600, 3, shared.clone(), "otp/", crate::clock::system_clock());
+
+        let code = otp.issue_otp("+254712345678").unwrap();
+        let wrong = if code == "0000" { "1111" } else { "0000" };
+        for _ in 0..3 {
+            assert!(matches!(otp.verify_otp("+254712345678", wrong), Err(SafeBankError::InvalidOtp)));
+        }
+        // The right code no longer works once the attempts are used up
+        assert!(!otp.is_outstanding("+254712345678"));
+        assert!(matches!(otp.verify_otp("+254712345678", &code), Err(SafeBankError::InvalidOtp)));
+
+        // Codes live in storage, hashed, so another instance on the same backend can verify them
+        let code = otp.issue_otp("+254712345678").unwrap();
+        assert!(!shared.load_all_records().unwrap().values().any(|record| record.contains(&format!("\"{}\"", code))));
+        let mut restarted = OtpManager::new(4, 600, 3, shared, "otp/", crate::clock::system_clock());
+        assert!(restarted.verify_otp("+254712345678", &code).is_ok());
+    }
+}
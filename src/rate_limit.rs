@@ -0,0 +1,77 @@
+//! Sliding-window rate limiting for SafeBank framework
+//! Counts events per source (a device or a client address) so that one source
+//! spraying many accounts is throttled even though no single account locks out
+
+use std::collections::{HashMap, VecDeque};
+use chrono::{DateTime, Duration, Utc};
+
+/// Sources tracked before idle ones are swept, bounding memory on low-end devices
+const SWEEP_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct SlidingWindowLimiter {
+    max_events: u32,
+    window: Duration,
+    events: HashMap<String, VecDeque<DateTime<Utc>>>, // source key -> event times in the window, oldest first
+}
+
+impl SlidingWindowLimiter {
+    pub fn new(max_events: u32, window: Duration) -> Self {
+        Self { max_events, window, events: HashMap::new() }
+    }
+
+    /// How long the source must wait, if it already has `max_events` events in the window
+    pub fn retry_after(&mut self, key: &str, now: DateTime<Utc>) -> Option<Duration> {
+        let times = self.events.get_mut(key)?;
+        Self::prune(times, now - self.window);
+        if times.is_empty() {
+            self.events.remove(key);
+            return None;
+        }
+        if times.len() < self.max_events as usize {
+            return None;
+        }
+        // The window frees a slot once the oldest counted event ages out
+        Some(times[times.len() - self.max_events as usize] + self.window - now)
+    }
+
+    pub fn record(&mut self, key: &str, now: DateTime<Utc>) {
+        if self.events.len() >= SWEEP_THRESHOLD {
+            let window_start = now - self.window;
+            self.events.retain(|_, times| {
+                Self::prune(times, window_start);
+                !times.is_empty()
+            });
+        }
+
+        let times = self.events.entry(key.to_string()).or_default();
+        times.push_back(now);
+        Self::prune(times, now - self.window);
+    }
+
+    fn prune(times: &mut VecDeque<DateTime<Utc>>, window_start: DateTime<Utc>) {
+        while times.front().is_some_and(|time| *time <= window_start) {
+            times.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sliding_window_frees_slots_as_events_age() {
+        let start = Utc::now();
+        let mut limiter = SlidingWindowLimiter::new(3, Duration::minutes(10));
+        for minute in 0..3 {
+            assert_eq!(limiter.retry_after("device:a", start + Duration::minutes(minute)), None);
+            limiter.record("device:a", start + Duration::minutes(minute));
+        }
+
+        let now = start + Duration::minutes(4);
+        assert_eq!(limiter.retry_after("device:a", now), Some(Duration::minutes(6)));
+        assert_eq!(limiter.retry_after("device:b", now), None);
+        assert_eq!(limiter.retry_after("device:a", start + Duration::minutes(10)), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! Read-only monitoring reports for SafeBank framework
+//! A report is assembled entirely through `&self`, so a monitoring thread holding a shared
+//! reference (or a read lock) can build one without touching fraud, auth or ledger state.
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{SafeBankFramework, TransactionStatus, errors::Result};
+
+/// Recipients listed in `Report::top_flagged_recipients` unless the builder says otherwise
+const DEFAULT_TOP_RECIPIENTS: usize = 5;
+
+/// Snapshot of fraud, transaction and authentication monitoring data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub generated_at: DateTime<Utc>,
+    /// Range the per-transaction sections cover; `None` leaves that end open
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Lifetime fraud detector counters, as from `get_fraud_statistics`
+    pub fraud_statistics: HashMap<String, f64>,
+    /// Lifetime transaction counters, as from `TransactionManager::get_transaction_statistics`
+    pub transaction_statistics: HashMap<String, f64>,
+    pub active_lockouts: usize,
+    /// Recipients of the most held or rejected transactions in range, most first
+    pub top_flagged_recipients: Vec<(String, usize)>,
+    /// Transaction counts in range, keyed by status, e.g. "requires_approval"
+    pub count_by_status: HashMap<String, usize>,
+    /// Sum of transaction amounts in range, keyed like `count_by_status`
+    pub volume_by_status: HashMap<String, f64>,
+}
+
+/// Builds a `Report` from a shared reference to the framework
+#[derive(Debug)]
+pub struct ReportBuilder<'a> {
+    framework: &'a SafeBankFramework,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    top_recipients: usize,
+}
+
+impl<'a> ReportBuilder<'a> {
+    pub fn new(framework: &'a SafeBankFramework) -> Self {
+        Self { framework, from: None, to: None, top_recipients: DEFAULT_TOP_RECIPIENTS }
+    }
+
+    /// Only count transactions at or after this time
+    pub fn from(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Only count transactions before this time
+    pub fn to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    pub fn top_recipients(mut self, count: usize) -> Self {
+        self.top_recipients = count;
+        self
+    }
+
+    pub fn build(&self) -> Result<Report> {
+        let framework = self.framework;
+        let transactions = framework.transaction_manager.all_transactions()?;
+        let in_range = transactions.iter().filter(|transaction| {
+            self.from.is_none_or(|from| transaction.timestamp >= from)
+                && self.to.is_none_or(|to| transaction.timestamp < to)
+        });
+
+        let mut count_by_status = HashMap::new();
+        let mut volume_by_status = HashMap::new();
+        let mut flagged_recipients: HashMap<String, usize> = HashMap::new();
+        for transaction in in_range {
+            let status = status_key(&transaction.status).to_string();
+            *count_by_status.entry(status.clone()).or_insert(0) += 1;
+            *volume_by_status.entry(status).or_insert(0.0) += transaction.amount;
+
+            if matches!(transaction.status, TransactionStatus::Flagged | TransactionStatus::RequiresApproval | TransactionStatus::Rejected) {
+                let recipient = framework.transaction_manager.reveal_recipient(&transaction.recipient)
+                    .unwrap_or_else(|| transaction.recipient.clone());
+                *flagged_recipients.entry(recipient).or_insert(0) += 1;
+            }
+        }
+
+        let mut top_flagged_recipients: Vec<(String, usize)> = flagged_recipients.into_iter().collect();
+        top_flagged_recipients.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_flagged_recipients.truncate(self.top_recipients);
+
+        let auth_statistics = framework.auth_manager.get_statistics();
+        Ok(Report {
+            generated_at: framework.clock.now(),
+            from: self.from,
+            to: self.to,
+            fraud_statistics: framework.fraud_detector.get_statistics(),
+            transaction_statistics: framework.transaction_manager.get_transaction_statistics()?,
+            active_lockouts: auth_statistics.get("active_lockouts").copied().unwrap_or(0.0) as usize,
+            top_flagged_recipients,
+            count_by_status,
+            volume_by_status,
+        })
+    }
+}
+
+/// Stable snake_case name for a status in report keys
+fn status_key(status: &TransactionStatus) -> &'static str {
+    match status {
+        TransactionStatus::Pending => "pending",
+        TransactionStatus::Approved => "approved",
+        TransactionStatus::Rejected => "rejected",
+        TransactionStatus::Flagged => "flagged",
+        TransactionStatus::RequiresApproval => "requires_approval",
+        TransactionStatus::RequiresStepUp => "requires_step_up",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::{DeviceInfo, TransactionType, config::SafeBankConfig};
+
+    #[test]
+    fn test_report_matches_individual_statistics() {
+        let mut framework = SafeBankFramework::new(SafeBankConfig::default());
+        let user = framework.register_user("+254712345678".to_string(), "4821".to_string(), DeviceInfo::test_device()).unwrap();
+        framework.process_transaction(user.user_id, 2000.0, "Cash agent".to_string(), TransactionType::Deposit).unwrap();
+        framework.process_transaction(user.user_id, 150.0, "Village Shop".to_string(), TransactionType::Payment).unwrap();
+        framework.add_to_blocklist("Scam Agent");
+        framework.process_transaction(user.user_id, 80.0, "Scam Agent".to_string(), TransactionType::Transfer).unwrap();
+        framework.process_transaction(user.user_id, 90.0, "Scam Agent".to_string(), TransactionType::Transfer).unwrap();
+        for _ in 0..3 {
+            let _ = framework.authenticate_user("+254712345678", "0000", "test-device");
+        }
+
+        let framework = &framework;
+        let report = ReportBuilder::new(framework).build().unwrap();
+        assert_eq!(report.fraud_statistics, framework.get_fraud_statistics());
+        assert_eq!(report.transaction_statistics, framework.transaction_manager.get_transaction_statistics().unwrap());
+        assert_eq!(report.active_lockouts, 1);
+        assert_eq!(report.top_flagged_recipients, vec![("Scam Agent".to_string(), 2)]);
+
+        let counted: usize = report.count_by_status.values().sum();
+        assert_eq!(counted as f64, report.transaction_statistics["total_transactions"]);
+        assert_eq!(report.count_by_status["rejected"] as f64, report.transaction_statistics["rejected_count"]);
+        assert_eq!(report.volume_by_status["rejected"], 170.0);
+        let volume: f64 = report.volume_by_status.values().sum();
+        assert!((volume - report.transaction_statistics["total_volume"]).abs() < 1e-9);
+        assert!(serde_json::to_string(&report).is_ok());
+
+        // A range that ends before any transaction leaves the per-transaction sections empty
+        let earlier = ReportBuilder::new(framework).to(Utc::now() - Duration::days(1)).build().unwrap();
+        assert!(earlier.count_by_status.is_empty() && earlier.top_flagged_recipients.is_empty());
+        assert_eq!(earlier.fraud_statistics, report.fraud_statistics);
+    }
+}
@@ -0,0 +1,198 @@
+//! Operator-defined fraud rules, evaluated after the statistical fraud score
+//!
+//! A rule fires when every condition it sets holds; unset conditions match anything.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{TransactionType, fraud_detection::FraudRecommendation};
+
+/// What a matching rule does to the analysis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RuleAction {
+    Approve,
+    Flag,
+    Block,
+    /// Add to the fraud score (negative values lower it); the result stays within 0-1
+    AddScore(f64),
+}
+
+impl RuleAction {
+    /// The recommendation a decisive action imposes; `None` for score adjustments
+    pub fn recommendation(&self) -> Option<FraudRecommendation> {
+        match self {
+            RuleAction::Approve => Some(FraudRecommendation::Approve),
+            RuleAction::Flag => Some(FraudRecommendation::Flag),
+            RuleAction::Block => Some(FraudRecommendation::Block),
+            RuleAction::AddScore(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FraudRule {
+    pub name: String,
+    /// Amount range, inclusive at both ends
+    #[serde(default)]
+    pub min_amount: Option<f64>,
+    #[serde(default)]
+    pub max_amount: Option<f64>,
+    /// Local hours `[start, end)`; wraps past midnight when start > end, so (23, 5) is 11pm-5am
+    #[serde(default)]
+    pub hours: Option<(u8, u8)>,
+    #[serde(default)]
+    pub recipient_known: Option<bool>,
+    #[serde(default)]
+    pub device_trusted: Option<bool>,
+    /// Transaction types the rule applies to; empty means all
+    #[serde(default)]
+    pub transaction_types: Vec<TransactionType>,
+    pub action: RuleAction,
+}
+
+/// The facts about a transaction that rules are matched against
+#[derive(Debug, Clone)]
+pub struct RuleContext {
+    pub amount: f64,
+    pub local_hour: u8,
+    pub recipient_known: bool,
+    pub device_trusted: bool,
+    pub transaction_type: TransactionType,
+}
+
+impl FraudRule {
+    pub fn matches(&self, context: &RuleContext) -> bool {
+        let in_hours = |(start, end): (u8, u8)| {
+            if start <= end {
+                (start..end).contains(&context.local_hour)
+            } else {
+                context.local_hour >= start || context.local_hour < end
+            }
+        };
+
+        self.min_amount.is_none_or(|min| context.amount >= min)
+            && self.max_amount.is_none_or(|max| context.amount <= max)
+            && self.hours.is_none_or(in_hours)
+            && self.recipient_known.is_none_or(|known| known == context.recipient_known)
+            && self.device_trusted.is_none_or(|trusted| trusted == context.device_trusted)
+            && (self.transaction_types.is_empty() || self.transaction_types.contains(&context.transaction_type))
+    }
+
+    /// Why the rule can never be applied as written, if it can't
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Fraud rules must be named".to_string());
+        }
+        if let (Some(min), Some(max)) = (self.min_amount, self.max_amount) {
+            if min > max {
+                return Err(format!("Fraud rule '{}' has min_amount above max_amount", self.name));
+            }
+        }
+        if self.hours.is_some_and(|(start, end)| start > 23 || end > 24) {
+            return Err(format!("Fraud rule '{}' has hours outside 0-24", self.name));
+        }
+        if let RuleAction::AddScore(score) = self.action {
+            if !score.is_finite() {
+                return Err(format!("Fraud rule '{}' adds a non-finite score", self.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rules that fired, in order: every score adjustment, and the first decisive action if any
+#[derive(Debug, Clone, Default)]
+pub struct RuleOutcome<'a> {
+    pub fired: Vec<&'a FraudRule>,
+    pub score_adjustment: f64,
+    pub decision: Option<FraudRecommendation>,
+}
+
+/// Evaluate rules in order. All matching `AddScore` rules apply; of the decisive rules
+/// (`Approve`, `Flag`, `Block`) only the first match counts, as in a firewall rule list.
+pub fn evaluate<'a>(rules: &'a [FraudRule], context: &RuleContext) -> RuleOutcome<'a> {
+    let mut outcome = RuleOutcome::default();
+    for rule in rules.iter().filter(|rule| rule.matches(context)) {
+        match (&rule.action, rule.action.recommendation()) {
+            (RuleAction::AddScore(score), _) => outcome.score_adjustment += score,
+            (_, decision) if outcome.decision.is_none() => outcome.decision = decision,
+            _ => continue,
+        }
+        outcome.fired.push(rule);
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn night_transfer_to_new_recipient() -> FraudRule {
+        FraudRule {
+            name: "night transfer to new recipient".to_string(),
+            min_amount: Some(3000.0),
+            max_amount: None,
+            hours: Some((23, 5)),
+            recipient_known: Some(false),
+            device_trusted: None,
+            transaction_types: vec![TransactionType::Transfer],
+            action: RuleAction::Block,
+        }
+    }
+
+    fn context(amount: f64, local_hour: u8, recipient_known: bool) -> RuleContext {
+        RuleContext {
+            amount,
+            local_hour,
+            recipient_known,
+            device_trusted: true,
+            transaction_type: TransactionType::Transfer,
+        }
+    }
+
+    #[test]
+    fn test_composite_rule_needs_every_condition() {
+        let rule = night_transfer_to_new_recipient();
+
+        assert!(rule.matches(&context(3500.0, 2, false)));
+        assert!(rule.matches(&context(3000.0, 23, false)));
+        assert!(!rule.matches(&context(2999.0, 2, false)));
+        assert!(!rule.matches(&context(3500.0, 5, false)));
+        assert!(!rule.matches(&context(3500.0, 2, true)));
+        assert!(!rule.matches(&RuleContext { transaction_type: TransactionType::Payment, ..context(3500.0, 2, false) }));
+    }
+
+    #[test]
+    fn test_first_decisive_rule_wins_and_scores_accumulate() {
+        let untrusted_device = FraudRule {
+            name: "untrusted device".to_string(),
+            min_amount: None,
+            max_amount: None,
+            hours: None,
+            recipient_known: None,
+            device_trusted: Some(false),
+            transaction_types: Vec::new(),
+            action: RuleAction::AddScore(0.2),
+        };
+        let small_known = FraudRule {
+            name: "small payment to known recipient".to_string(),
+            max_amount: Some(100.0),
+            min_amount: None,
+            hours: None,
+            recipient_known: Some(true),
+            device_trusted: None,
+            transaction_types: Vec::new(),
+            action: RuleAction::Approve,
+        };
+        let anything_known = FraudRule { name: "flag known".to_string(), max_amount: None, action: RuleAction::Flag, ..small_known.clone() };
+        let rules = vec![untrusted_device.clone(), small_known, anything_known, untrusted_device];
+
+        let outcome = evaluate(&rules, &RuleContext { device_trusted: false, ..context(50.0, 12, true) });
+        assert_eq!(outcome.decision, Some(FraudRecommendation::Approve));
+        assert!((outcome.score_adjustment - 0.4).abs() < 1e-9);
+        assert_eq!(outcome.fired.len(), 3);
+
+        let outcome = evaluate(&rules, &context(500.0, 12, false));
+        assert!(outcome.fired.is_empty());
+        assert_eq!(outcome.decision, None);
+    }
+}
@@ -0,0 +1,61 @@
+//! Passphrase encryption for SafeBank state written to disk
+//! Keys are derived with Argon2id under a random salt and data is sealed with AES-256-GCM
+
+use aes_gcm::{Aes256Gcm, Key, Nonce, aead::{Aead, AeadCore, KeyInit, OsRng}};
+use argon2::Argon2;
+use rand_core::RngCore;
+
+use crate::errors::{Result, SafeBankError};
+
+/// Marks a sealed file and its format version
+const MAGIC: &[u8] = b"SBSEAL1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Encrypt data under a passphrase: magic, salt, nonce, then ciphertext with its authentication tag
+pub fn seal(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt)?);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher.encrypt(&nonce, plaintext)
+        .map_err(|e| SafeBankError::CryptographyError {
+            message: format!("Failed to encrypt data: {}", e),
+        })?;
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypt data sealed by `seal`. A wrong passphrase or any tampering fails authentication.
+pub fn open(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let body = sealed.strip_prefix(MAGIC)
+        .filter(|body| body.len() >= SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| SafeBankError::CryptographyError {
+            message: "Not a sealed SafeBank file".to_string(),
+        })?;
+    let (salt, rest) = body.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, salt)?);
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| SafeBankError::CryptographyError {
+            message: "Wrong passphrase or corrupted data".to_string(),
+        })
+}
+
+/// Derive a 256-bit key from the passphrase with Argon2id
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| SafeBankError::CryptographyError {
+            message: format!("Failed to derive key: {}", e),
+        })?;
+    Ok(key)
+}
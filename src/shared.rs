@@ -0,0 +1,243 @@
+//! Thread-safe SafeBank handle for multi-threaded servers
+//! Users are sharded by phone number across framework instances that share one storage backend,
+//! so different users' operations run in parallel while each user's stay serialized.
+//!
+//! Per-user state (balances, daily limits, behavioral history) always lives on the user's shard;
+//! lockouts, codes and pending confirmations live in storage. The system daily volume, the login
+//! throttle and the transaction statistics are shared by every shard through one `SystemState`.
+//! Escalations and fraud statistics are kept per shard; use `with_shards` to aggregate them.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+use crate::{
+    DeviceInfo, SafeBankFramework, Transaction, TransactionRequest, TransactionType, TransferChallenge, UserProfile,
+    auth::{AuthOutcome, EntryMetrics},
+    clock::{self, SharedClock},
+    config::SafeBankConfig,
+    errors::{Result, SafeBankError},
+    storage::{self, SharedStorage},
+    system::SystemState,
+    utils,
+};
+
+/// Shards used by `SharedSafeBank::new`
+const DEFAULT_SHARDS: usize = 16;
+
+/// Cloneable, `Sync` handle to a sharded framework; every method takes `&self`
+#[derive(Debug, Clone)]
+pub struct SharedSafeBank {
+    shards: Arc<Vec<Mutex<SafeBankFramework>>>,
+    storage: SharedStorage,
+    default_phone_region: String,
+}
+
+impl SharedSafeBank {
+    pub fn new(config: SafeBankConfig) -> Self {
+        Self::with_storage(config, storage::in_memory_storage(), clock::system_clock(), DEFAULT_SHARDS)
+    }
+
+    /// Create a handle with `shard_count` framework instances over the given storage
+    pub fn with_storage(config: SafeBankConfig, storage: SharedStorage, clock: SharedClock, shard_count: usize) -> Self {
+        let system = Arc::new(SystemState::new(&config));
+        let shards = (0..shard_count.max(1))
+            .map(|_| Mutex::new(SafeBankFramework::with_system_state(config.clone(), storage.clone(), clock.clone(), system.clone())))
+            .collect();
+        Self {
+            shards: Arc::new(shards),
+            storage,
+            default_phone_region: config.default_phone_region,
+        }
+    }
+
+    pub fn register_user(&self, phone_number: String, pin: String, device_info: DeviceInfo) -> Result<UserProfile> {
+        self.phone_shard(&phone_number)?.register_user(phone_number, pin, device_info)
+    }
+
+    pub fn authenticate_user(&self, phone_number: &str, pin: &str, device_id: &str) -> Result<UserProfile> {
+        self.phone_shard(phone_number)?.authenticate_user(phone_number, pin, device_id)
+    }
+
+    pub fn authenticate_ex(&self, phone_number: &str, pin: &str, device_id: &str, entry_metrics: Option<EntryMetrics>) -> Result<AuthOutcome> {
+        self.phone_shard(phone_number)?.authenticate_ex(phone_number, pin, device_id, entry_metrics)
+    }
+
+    pub fn complete_mfa(&self, challenge_id: Uuid, code: &str) -> Result<UserProfile> {
+        self.challenge_shard(challenge_id)?.complete_mfa(challenge_id, code)
+    }
+
+    pub fn process_transaction(&self, user_id: Uuid, amount: f64, recipient: String, transaction_type: TransactionType) -> Result<Transaction> {
+        self.user_shard(user_id)?.process_transaction(user_id, amount, recipient, transaction_type)
+    }
+
+    pub fn process_request(&self, request: TransactionRequest) -> Result<Transaction> {
+        self.user_shard(request.user_id)?.process_request(request)
+    }
+
+    pub fn initiate_transfer(&self, user_id: Uuid, amount: f64, recipient: String) -> Result<(Transaction, Option<TransferChallenge>)> {
+        self.user_shard(user_id)?.initiate_transfer(user_id, amount, recipient)
+    }
+
+    pub fn confirm_transfer(&self, transaction_id: Uuid, code: &str) -> Result<Transaction> {
+        self.transaction_shard(transaction_id)?.confirm_transfer(transaction_id, code)
+    }
+
+    pub fn approve_transaction(&self, transaction_id: Uuid, reviewer: &str) -> Result<Transaction> {
+        self.transaction_shard(transaction_id)?.approve_transaction(transaction_id, reviewer)
+    }
+
+    pub fn co_approve_transaction(&self, transaction_id: Uuid, phone_number: &str, pin: &str, device_id: &str) -> Result<Transaction> {
+        self.transaction_shard(transaction_id)?.co_approve_transaction(transaction_id, phone_number, pin, device_id)
+    }
+
+    pub fn reverse_transaction(&self, transaction_id: Uuid, reason: String) -> Result<Transaction> {
+        self.transaction_shard(transaction_id)?.reverse_transaction(transaction_id, reason)
+    }
+
+    pub fn get_balance(&self, user_id: Uuid) -> Result<f64> {
+        self.user_shard(user_id)?.get_balance(user_id)
+    }
+
+    /// Block a recipient on every shard
+    pub fn add_to_blocklist(&self, recipient: &str) -> Result<()> {
+        self.with_shards(|framework| framework.add_to_blocklist(recipient))
+    }
+
+    /// Run a closure on the shard that owns a user, for operations without a wrapper
+    pub fn with_user<T>(&self, user_id: Uuid, f: impl FnOnce(&mut SafeBankFramework) -> Result<T>) -> Result<T> {
+        f(&mut *self.user_shard(user_id)?)
+    }
+
+    /// Run a closure on each shard in turn, e.g. to aggregate monitoring data
+    pub fn with_shards(&self, mut f: impl FnMut(&mut SafeBankFramework)) -> Result<()> {
+        for index in 0..self.shards.len() {
+            f(&mut *self.lock(index)?);
+        }
+        Ok(())
+    }
+
+    /// The shard for a phone number, which owns that user for good
+    fn phone_shard(&self, phone_number: &str) -> Result<MutexGuard<'_, SafeBankFramework>> {
+        let canonical = utils::normalize_phone_number(phone_number, &self.default_phone_region)
+            .unwrap_or_else(|_| phone_number.to_string());
+        let mut hasher = DefaultHasher::new();
+        canonical.hash(&mut hasher);
+        self.lock((hasher.finish() % self.shards.len() as u64) as usize)
+    }
+
+    fn user_shard(&self, user_id: Uuid) -> Result<MutexGuard<'_, SafeBankFramework>> {
+        let user = self.storage.load_user_by_id(user_id)?
+            .ok_or_else(|| SafeBankError::UserNotFound { user_id: user_id.to_string() })?;
+        self.phone_shard(&user.phone_number)
+    }
+
+    /// The shard owning the user a stored transaction belongs to
+    fn transaction_shard(&self, transaction_id: Uuid) -> Result<MutexGuard<'_, SafeBankFramework>> {
+        let transaction = self.storage.load_transaction(transaction_id)?
+            .ok_or_else(|| SafeBankError::InvalidTransactionState {
+                current_state: "Transaction not found".to_string(),
+            })?;
+        self.user_shard(transaction.user_id)
+    }
+
+    /// The shard that opened a login challenge; challenges are held in memory where they were opened
+    fn challenge_shard(&self, challenge_id: Uuid) -> Result<MutexGuard<'_, SafeBankFramework>> {
+        for index in 0..self.shards.len() {
+            let shard = self.lock(index)?;
+            if shard.auth_manager.has_mfa_challenge(challenge_id) {
+                return Ok(shard);
+            }
+        }
+        Err(SafeBankError::AuthenticationFailed {
+            message: "Unknown login challenge".to_string(),
+        })
+    }
+
+    fn lock(&self, index: usize) -> Result<MutexGuard<'_, SafeBankFramework>> {
+        self.shards[index].lock().map_err(|_| SafeBankError::StorageError {
+            message: "A framework shard was poisoned by a panic".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_users_keep_every_update() {
+        let bank = SharedSafeBank::with_storage(SafeBankConfig::default(), storage::in_memory_storage(), clock::system_clock(), 4);
+        let users: Vec<UserProfile> = (0..8)
+            .map(|i| bank.register_user(format!("+2547123456{:02}", i), "4821".to_string(), DeviceInfo::test_device()).unwrap())
+            .collect();
+
+        let handles: Vec<_> = users.iter().map(|user| {
+            let bank = bank.clone();
+            let user_id = user.user_id;
+            thread::spawn(move || {
+                bank.process_transaction(user_id, 5000.0, "Cash agent".to_string(), TransactionType::Deposit).unwrap();
+                for _ in 0..10 {
+                    bank.process_transaction(user_id, 20.0, "Village Shop".to_string(), TransactionType::Payment).unwrap();
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for user in &users {
+            let transactions = bank.with_user(user.user_id, |framework| framework.get_statement(user.user_id)).unwrap();
+            assert_eq!(transactions.len(), 11);
+            let expected: f64 = transactions.iter().map(|line| line.balance_change).sum();
+            assert!((bank.get_balance(user.user_id).unwrap() - expected).abs() < 1e-9);
+            let usage = bank.with_user(user.user_id, |framework| Ok(framework.transaction_manager.daily_usage(user.user_id))).unwrap();
+            assert_eq!(usage, 5000.0 + 10.0 * 20.0);
+        }
+    }
+
+    #[test]
+    fn test_system_volume_and_login_throttle_span_shards() {
+        let config = SafeBankConfig {
+            system_daily_volume_ceiling: Some(1000.0),
+            auth_rate_limit_attempts: 3,
+            require_otp: true,
+            ..SafeBankConfig::default()
+        };
+        let bank = SharedSafeBank::with_storage(config, storage::in_memory_storage(), clock::system_clock(), 4);
+        let users: Vec<UserProfile> = (0..8)
+            .map(|i| bank.register_user(format!("+2547123456{:02}", i), "4821".to_string(), DeviceInfo::test_device()).unwrap())
+            .collect();
+
+        // No shard sees more than a few deposits, but together they pass the ceiling once
+        for user in &users {
+            bank.process_transaction(user.user_id, 200.0, "Cash agent".to_string(), TransactionType::Deposit).unwrap();
+        }
+        let mut volume_escalations = 0;
+        bank.with_shards(|framework| {
+            volume_escalations += framework.escalations().iter()
+                .filter(|e| e.kind == crate::escalation::EscalationKind::SystemVolumeCeiling)
+                .count();
+        }).unwrap();
+        assert_eq!(volume_escalations, 1);
+
+        // A login challenge is finished on the shard that opened it
+        let challenge_id = match bank.authenticate_ex(&users[0].phone_number, "4821", "test-device", None).unwrap() {
+            AuthOutcome::MfaRequired { challenge_id, .. } => challenge_id,
+            other => panic!("expected a login challenge, got {:?}", other),
+        };
+        assert!(matches!(bank.complete_mfa(challenge_id, "000000"), Err(SafeBankError::InvalidOtp)));
+        assert!(matches!(bank.complete_mfa(Uuid::new_v4(), "000000"), Err(SafeBankError::AuthenticationFailed { .. })));
+
+        // Failures from one device are throttled across every account, whichever shard owns it
+        for user in &users[1..4] {
+            assert!(bank.authenticate_user(&user.phone_number, "0000", "attacker-phone").is_err());
+        }
+        assert!(matches!(
+            bank.authenticate_user(&users[5].phone_number, "4821", "attacker-phone"),
+            Err(SafeBankError::RateLimited { .. })
+        ));
+    }
+}
@@ -0,0 +1,285 @@
+//! Storage backends for SafeBank framework
+//! Persists users, transactions and behavioral profiles behind a pluggable trait
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use serde::{Serialize, de::DeserializeOwned};
+use uuid::Uuid;
+
+use crate::{
+    BehavioralProfile, Transaction, UserProfile,
+    errors::{Result, SafeBankError}
+};
+
+/// Persistence backend shared by the managers.
+/// Methods take `&self` so one backend can be shared across managers and framework instances;
+/// implementations handle their own synchronization.
+pub trait Storage: Debug + Send + Sync {
+    /// Insert or replace a user profile
+    fn save_user(&self, user: &UserProfile) -> Result<()>;
+    fn load_user_by_phone(&self, phone_number: &str) -> Result<Option<UserProfile>>;
+    fn load_user_by_id(&self, user_id: Uuid) -> Result<Option<UserProfile>>;
+    fn load_all_users(&self) -> Result<Vec<UserProfile>>;
+
+    /// Insert or replace a transaction
+    fn save_transaction(&self, transaction: &Transaction) -> Result<()>;
+    fn load_transaction(&self, transaction_id: Uuid) -> Result<Option<Transaction>>;
+    fn load_user_transactions(&self, user_id: Uuid) -> Result<Vec<Transaction>>;
+    fn load_all_transactions(&self) -> Result<Vec<Transaction>>;
+
+    /// Insert or replace the learned behavioral profile for a user
+    fn save_behavioral_profile(&self, user_id: Uuid, profile: &BehavioralProfile) -> Result<()>;
+    fn load_behavioral_profile(&self, user_id: Uuid) -> Result<Option<BehavioralProfile>>;
+    fn load_all_behavioral_profiles(&self) -> Result<HashMap<Uuid, BehavioralProfile>>;
+
+    /// Insert or replace a user's account balance
+    fn save_balance(&self, user_id: Uuid, balance: f64) -> Result<()>;
+    fn load_balance(&self, user_id: Uuid) -> Result<Option<f64>>;
+
+    /// Insert or replace a named record of manager state, serialized as JSON
+    fn save_record(&self, key: &str, value: &str) -> Result<()>;
+    fn load_record(&self, key: &str) -> Result<Option<String>>;
+    fn remove_record(&self, key: &str) -> Result<()>;
+    fn load_all_records(&self) -> Result<HashMap<String, String>>;
+}
+
+/// Shared storage handle used by the managers
+pub type SharedStorage = Arc<dyn Storage>;
+
+/// Default backend that keeps everything in process memory
+#[derive(Debug, Default)]
+pub struct InMemoryStorage {
+    inner: RwLock<InMemoryData>,
+}
+
+#[derive(Debug, Default)]
+struct InMemoryData {
+    users: HashMap<Uuid, UserProfile>,
+    phone_index: HashMap<String, Uuid>, // phone_number -> user_id
+    transactions: HashMap<Uuid, Transaction>,
+    user_transactions: HashMap<Uuid, Vec<Uuid>>, // user_id -> transaction_ids
+    behavioral_profiles: HashMap<Uuid, BehavioralProfile>,
+    balances: HashMap<Uuid, f64>,
+    records: HashMap<String, String>,
+}
+
+impl InMemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> Result<std::sync::RwLockReadGuard<'_, InMemoryData>> {
+        self.inner.read().map_err(|_| SafeBankError::StorageError {
+            message: "In-memory storage lock poisoned".to_string(),
+        })
+    }
+
+    fn write(&self) -> Result<std::sync::RwLockWriteGuard<'_, InMemoryData>> {
+        self.inner.write().map_err(|_| SafeBankError::StorageError {
+            message: "In-memory storage lock poisoned".to_string(),
+        })
+    }
+}
+
+impl Storage for InMemoryStorage {
+    fn save_user(&self, user: &UserProfile) -> Result<()> {
+        let mut data = self.write()?;
+        if let Some(previous) = data.users.get(&user.user_id) {
+            if previous.phone_number != user.phone_number {
+                let old_phone = previous.phone_number.clone();
+                data.phone_index.remove(&old_phone);
+            }
+        }
+        data.phone_index.insert(user.phone_number.clone(), user.user_id);
+        data.users.insert(user.user_id, user.clone());
+        Ok(())
+    }
+
+    fn load_user_by_phone(&self, phone_number: &str) -> Result<Option<UserProfile>> {
+        let data = self.read()?;
+        Ok(data.phone_index
+            .get(phone_number)
+            .and_then(|user_id| data.users.get(user_id))
+            .cloned())
+    }
+
+    fn load_user_by_id(&self, user_id: Uuid) -> Result<Option<UserProfile>> {
+        Ok(self.read()?.users.get(&user_id).cloned())
+    }
+
+    fn load_all_users(&self) -> Result<Vec<UserProfile>> {
+        Ok(self.read()?.users.values().cloned().collect())
+    }
+
+    fn save_transaction(&self, transaction: &Transaction) -> Result<()> {
+        let mut data = self.write()?;
+        let is_new = data.transactions
+            .insert(transaction.transaction_id, transaction.clone())
+            .is_none();
+        if is_new {
+            data.user_transactions
+                .entry(transaction.user_id)
+                .or_default()
+                .push(transaction.transaction_id);
+        }
+        Ok(())
+    }
+
+    fn load_transaction(&self, transaction_id: Uuid) -> Result<Option<Transaction>> {
+        Ok(self.read()?.transactions.get(&transaction_id).cloned())
+    }
+
+    fn load_user_transactions(&self, user_id: Uuid) -> Result<Vec<Transaction>> {
+        let data = self.read()?;
+        Ok(data.user_transactions
+            .get(&user_id)
+            .map(|ids| ids.iter().filter_map(|id| data.transactions.get(id)).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn load_all_transactions(&self) -> Result<Vec<Transaction>> {
+        Ok(self.read()?.transactions.values().cloned().collect())
+    }
+
+    fn save_behavioral_profile(&self, user_id: Uuid, profile: &BehavioralProfile) -> Result<()> {
+        self.write()?.behavioral_profiles.insert(user_id, profile.clone());
+        Ok(())
+    }
+
+    fn load_behavioral_profile(&self, user_id: Uuid) -> Result<Option<BehavioralProfile>> {
+        Ok(self.read()?.behavioral_profiles.get(&user_id).cloned())
+    }
+
+    fn load_all_behavioral_profiles(&self) -> Result<HashMap<Uuid, BehavioralProfile>> {
+        Ok(self.read()?.behavioral_profiles.clone())
+    }
+
+    fn save_balance(&self, user_id: Uuid, balance: f64) -> Result<()> {
+        self.write()?.balances.insert(user_id, balance);
+        Ok(())
+    }
+
+    fn load_balance(&self, user_id: Uuid) -> Result<Option<f64>> {
+        Ok(self.read()?.balances.get(&user_id).copied())
+    }
+
+    fn save_record(&self, key: &str, value: &str) -> Result<()> {
+        self.write()?.records.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn load_record(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.read()?.records.get(key).cloned())
+    }
+
+    fn remove_record(&self, key: &str) -> Result<()> {
+        self.write()?.records.remove(key);
+        Ok(())
+    }
+
+    fn load_all_records(&self) -> Result<HashMap<String, String>> {
+        Ok(self.read()?.records.clone())
+    }
+}
+
+/// Serialize a value into the named record
+pub fn save_json<T: Serialize>(storage: &dyn Storage, key: &str, value: &T) -> Result<()> {
+    let serialized = serde_json::to_string(value)
+        .map_err(|e| SafeBankError::SerializationError { message: e.to_string() })?;
+    storage.save_record(key, &serialized)
+}
+
+/// Deserialize the named record, if it exists
+pub fn load_json<T: DeserializeOwned>(storage: &dyn Storage, key: &str) -> Result<Option<T>> {
+    storage.load_record(key)?
+        .map(|serialized| serde_json::from_str(&serialized)
+            .map_err(|e| SafeBankError::SerializationError { message: e.to_string() }))
+        .transpose()
+}
+
+/// Default storage for a standalone framework instance
+pub fn in_memory_storage() -> SharedStorage {
+    Arc::new(InMemoryStorage::new())
+}
+
+/// Test backend that delegates to the in-memory store, counting saves and optionally failing one
+/// transaction save to exercise rollback paths
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct TestStorage {
+    inner: InMemoryStorage,
+    pub(crate) user_saves: std::sync::atomic::AtomicUsize,
+    transaction_saves: std::sync::atomic::AtomicUsize,
+    failing_transaction_save: std::sync::Mutex<Option<usize>>,
+}
+
+#[cfg(test)]
+impl TestStorage {
+    /// Fail the transaction save `later` saves from now (0 fails the next one)
+    pub(crate) fn fail_transaction_save(&self, later: usize) {
+        let next = self.transaction_saves.load(std::sync::atomic::Ordering::SeqCst);
+        *self.failing_transaction_save.lock().unwrap() = Some(next + later);
+    }
+}
+
+#[cfg(test)]
+impl Storage for TestStorage {
+    fn save_user(&self, user: &UserProfile) -> Result<()> {
+        self.user_saves.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.inner.save_user(user)
+    }
+    fn load_user_by_phone(&self, phone_number: &str) -> Result<Option<UserProfile>> {
+        self.inner.load_user_by_phone(phone_number)
+    }
+    fn load_user_by_id(&self, user_id: Uuid) -> Result<Option<UserProfile>> {
+        self.inner.load_user_by_id(user_id)
+    }
+    fn load_all_users(&self) -> Result<Vec<UserProfile>> {
+        self.inner.load_all_users()
+    }
+    fn save_transaction(&self, transaction: &Transaction) -> Result<()> {
+        let index = self.transaction_saves.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if *self.failing_transaction_save.lock().unwrap() == Some(index) {
+            return Err(SafeBankError::StorageError { message: "disk full".to_string() });
+        }
+        self.inner.save_transaction(transaction)
+    }
+    fn load_transaction(&self, transaction_id: Uuid) -> Result<Option<Transaction>> {
+        self.inner.load_transaction(transaction_id)
+    }
+    fn load_user_transactions(&self, user_id: Uuid) -> Result<Vec<Transaction>> {
+        self.inner.load_user_transactions(user_id)
+    }
+    fn load_all_transactions(&self) -> Result<Vec<Transaction>> {
+        self.inner.load_all_transactions()
+    }
+    fn save_behavioral_profile(&self, user_id: Uuid, profile: &BehavioralProfile) -> Result<()> {
+        self.inner.save_behavioral_profile(user_id, profile)
+    }
+    fn load_behavioral_profile(&self, user_id: Uuid) -> Result<Option<BehavioralProfile>> {
+        self.inner.load_behavioral_profile(user_id)
+    }
+    fn load_all_behavioral_profiles(&self) -> Result<HashMap<Uuid, BehavioralProfile>> {
+        self.inner.load_all_behavioral_profiles()
+    }
+    fn save_balance(&self, user_id: Uuid, balance: f64) -> Result<()> {
+        self.inner.save_balance(user_id, balance)
+    }
+    fn load_balance(&self, user_id: Uuid) -> Result<Option<f64>> {
+        self.inner.load_balance(user_id)
+    }
+
+    fn save_record(&self, key: &str, value: &str) -> Result<()> {
+        self.inner.save_record(key, value)
+    }
+    fn load_record(&self, key: &str) -> Result<Option<String>> {
+        self.inner.load_record(key)
+    }
+    fn remove_record(&self, key: &str) -> Result<()> {
+        self.inner.remove_record(key)
+    }
+    fn load_all_records(&self) -> Result<HashMap<String, String>> {
+        self.inner.load_all_records()
+    }
+}
@@ -0,0 +1,118 @@
+//! System-wide state for SafeBank framework
+//! Counters and throttles that describe the whole deployment rather than one user. Framework
+//! instances over one storage backend, such as the shards of a `SharedSafeBank`, share a single
+//! `SystemState` so the volume ceiling, the login throttle and the transaction statistics see
+//! every instance's traffic.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{config::SafeBankConfig, money::Money, rate_limit::SlidingWindowLimiter, transaction::TransactionStats};
+
+#[derive(Debug)]
+pub struct SystemState {
+    daily_volume: Mutex<DailyVolume>,
+    login_throttle: Mutex<SlidingWindowLimiter>, // failed logins per device and per client address
+    transaction_stats: Mutex<Option<TransactionStats>>, // counted from storage by the first instance, then kept running
+}
+
+/// Handle shared by every framework instance in a deployment
+pub type SharedSystemState = Arc<SystemState>;
+
+/// Combined volume for one banking day, and whether the ceiling escalation has fired for it
+#[derive(Debug, Default)]
+struct DailyVolume {
+    day: Option<NaiveDate>,
+    total: Money,
+    ceiling_escalated: bool,
+}
+
+impl SystemState {
+    pub fn new(config: &SafeBankConfig) -> Self {
+        Self {
+            daily_volume: Mutex::new(DailyVolume::default()),
+            login_throttle: Mutex::new(SlidingWindowLimiter::new(
+                config.auth_rate_limit_attempts,
+                Duration::seconds(config.auth_rate_limit_window_seconds as i64),
+            )),
+            transaction_stats: Mutex::new(None),
+        }
+    }
+
+    /// Combined volume recorded for a banking day
+    pub fn daily_volume(&self, day: NaiveDate) -> Money {
+        let volume = lock(&self.daily_volume);
+        if volume.day == Some(day) { volume.total } else { Money::ZERO }
+    }
+
+    /// Add to the volume for a banking day, starting afresh when the day turns over.
+    /// A negative amount takes back volume whose commit was rolled back; days already past are ignored.
+    pub fn add_daily_volume(&self, day: NaiveDate, amount: Money) {
+        let mut volume = lock(&self.daily_volume);
+        match volume.day {
+            Some(current) if current > day => return,
+            Some(current) if current == day => {}
+            _ => *volume = DailyVolume { day: Some(day), ..DailyVolume::default() },
+        }
+        volume.total += amount;
+    }
+
+    /// True the first time the day's volume is seen over `ceiling`, so only one instance escalates it
+    pub fn claim_ceiling_escalation(&self, day: NaiveDate, ceiling: Money) -> bool {
+        let mut volume = lock(&self.daily_volume);
+        if volume.day != Some(day) || volume.total <= ceiling || volume.ceiling_escalated {
+            return false;
+        }
+        volume.ceiling_escalated = true;
+        true
+    }
+
+    /// How long a login source must wait, if it has too many recent failures
+    pub fn login_retry_after(&self, source: &str, now: DateTime<Utc>) -> Option<Duration> {
+        lock(&self.login_throttle).retry_after(source, now)
+    }
+
+    pub fn record_login_failure(&self, source: &str, now: DateTime<Utc>) {
+        lock(&self.login_throttle).record(source, now);
+    }
+
+    /// Run `f` on the running transaction statistics, counting them with `count` if no instance has yet
+    pub(crate) fn with_transaction_stats<T>(&self, count: impl FnOnce() -> TransactionStats, f: impl FnOnce(&mut TransactionStats) -> T) -> T {
+        f(lock(&self.transaction_stats).get_or_insert_with(count))
+    }
+}
+
+/// The state is plain counters that stay consistent even if a holder panicked, so a poisoned lock is still used
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_volume_turns_over_and_escalates_once() {
+        let state = SystemState::new(&SafeBankConfig::default());
+        let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+        let ceiling = Money::from_minor_units(1000);
+
+        state.add_daily_volume(monday, Money::from_minor_units(800));
+        assert!(!state.claim_ceiling_escalation(monday, ceiling));
+        state.add_daily_volume(monday, Money::from_minor_units(300));
+        assert!(state.claim_ceiling_escalation(monday, ceiling));
+        assert!(!state.claim_ceiling_escalation(monday, ceiling));
+
+        // A rolled-back commit takes its volume back
+        state.add_daily_volume(monday, -Money::from_minor_units(300));
+        assert_eq!(state.daily_volume(monday), Money::from_minor_units(800));
+
+        state.add_daily_volume(tuesday, Money::from_minor_units(1100));
+        assert_eq!(state.daily_volume(monday), Money::ZERO);
+        assert!(state.claim_ceiling_escalation(tuesday, ceiling));
+        // Late additions for a past day don't reset today's count
+        state.add_daily_volume(monday, Money::from_minor_units(50));
+        assert_eq!(state.daily_volume(tuesday), Money::from_minor_units(1100));
+    }
+}
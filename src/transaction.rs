@@ -1,32 +1,62 @@
 //! Transaction management module for SafeBank framework
 //! Handles secure transaction processing with encryption and validation
 
-use std::collections::HashMap;
-use chrono::{DateTime, Utc, Duration};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use chrono::{DateTime, Utc, Duration, NaiveDate};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
+use hmac::{Hmac, Mac};
+use hkdf::Hkdf;
+use aes_gcm::{Aes256Gcm, Key, Nonce, aead::{Aead, AeadCore, KeyInit, OsRng}};
 use hex;
 
 use crate::{
-    Transaction, TransactionStatus,
-    config::SafeBankConfig, errors::{SafeBankError, Result}
+    StatusChange, Transaction, TransactionCategory, TransactionStatus, TransactionType, logging,
+    clock::{self, SharedClock}, config::SafeBankConfig, errors::{SafeBankError, Result},
+    money::Money, storage::{self, SharedStorage}, system::{SharedSystemState, SystemState}
 };
 
 #[derive(Debug)]
 pub struct TransactionManager {
     config: SafeBankConfig,
-    transactions: HashMap<Uuid, Transaction>,
-    user_transactions: HashMap<Uuid, Vec<Uuid>>, // user_id -> transaction_ids
+    storage: SharedStorage,
     daily_limits: HashMap<Uuid, DailyLimit>,
+    recipient_salt: Option<String>,
+    idempotency_keys: HashMap<(Uuid, String), Uuid>, // (user_id, idempotency key) -> transaction_id
+    pending_offline: HashMap<Uuid, OfflineTransaction>, // transaction_id -> queued offline transaction
+    system: SharedSystemState, // combined daily volume across all users, shared with other instances
+    clock: SharedClock,
 }
 
 #[derive(Debug, Clone)]
 pub struct DailyLimit {
     pub user_id: Uuid,
     pub date: DateTime<Utc>,
-    pub total_amount: f64,
+    pub total_amount: Money,
     pub transaction_count: u32,
+    /// Amount sent to each recipient (as stored) during the day
+    pub recipient_totals: HashMap<String, Money>,
+}
+
+impl DailyLimit {
+    fn empty(user_id: Uuid, date: DateTime<Utc>) -> Self {
+        Self {
+            user_id,
+            date,
+            total_amount: Money::ZERO,
+            transaction_count: 0,
+            recipient_totals: HashMap::new(),
+        }
+    }
+
+    /// `counted` is what the transaction adds to the daily total; per-recipient totals track the amount sent
+    fn record(&mut self, recipient: &str, amount: Money, counted: Money) {
+        self.total_amount += counted;
+        self.transaction_count += 1;
+        *self.recipient_totals.entry(recipient.to_string()).or_insert(Money::ZERO) += amount;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,28 +68,520 @@ pub struct TransactionReceipt {
     pub status: TransactionStatus,
     pub confirmation_code: String,
     pub fraud_score: f64,
+    pub fee: f64,
+    pub levy: f64,
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+/// Output format for `export_transactions`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// A statement row as written to an export: what the account holder sees on a receipt,
+/// without device, location or fraud-scoring internals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedTransaction {
+    pub transaction_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub transaction_type: TransactionType,
+    /// The plaintext recipient; blank when a hashed recipient can't be resolved
+    pub recipient: String,
+    pub category: Option<TransactionCategory>,
+    pub amount: f64,
+    pub fee: f64,
+    pub levy: f64,
+    pub fx_fee: f64,
+    pub status: TransactionStatus,
+    pub memo: Option<String>,
+    pub confirmation_code: String,
+}
+
+/// Criteria for `query_transactions`; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionFilter {
+    pub user_id: Option<Uuid>,
+    pub status: Option<TransactionStatus>,
+    pub transaction_type: Option<TransactionType>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub recipient: Option<String>,
+}
+
+/// One page of query results, most recent first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    pub page: usize,
+    pub page_size: usize,
+    pub total_count: usize,
+    pub has_more: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OfflineTransaction {
     pub transaction: Transaction,
+    /// Hex-encoded nonce followed by the AES-256-GCM ciphertext
     pub encrypted_data: String,
+    /// Hex-encoded 96-bit nonce used for this encryption
+    #[serde(default)]
+    pub nonce: String,
     pub signature: String,
     pub expires_at: DateTime<Utc>,
+    /// Version of the keyring key this was encrypted and signed with
+    #[serde(default)]
+    pub key_version: u32,
+}
+
+/// Offline signing keys by version. New offline transactions use the current key; older keys
+/// still open transactions signed before a rotation until they are retired.
+#[derive(Clone)]
+pub struct SigningKeyring {
+    keys: HashMap<u32, String>,
+    current: u32,
+}
+
+impl SigningKeyring {
+    pub fn new(version: u32, secret_key: impl Into<String>) -> Self {
+        Self {
+            keys: HashMap::from([(version, secret_key.into())]),
+            current: version,
+        }
+    }
+
+    /// Add a key and sign with it from now on; the previous keys remain valid for verification
+    pub fn rotate(&mut self, version: u32, secret_key: impl Into<String>) {
+        self.keys.insert(version, secret_key.into());
+        self.current = version;
+    }
+
+    /// Stop accepting a key; the current key can't be retired
+    pub fn retire(&mut self, version: u32) -> Result<()> {
+        if version == self.current {
+            return Err(SafeBankError::CryptographyError {
+                message: "Cannot retire the current signing key".to_string(),
+            });
+        }
+        self.keys.remove(&version);
+        Ok(())
+    }
+
+    pub fn current_version(&self) -> u32 {
+        self.current
+    }
+
+    fn current_key(&self) -> &str {
+        &self.keys[&self.current]
+    }
+
+    fn key(&self, version: u32) -> Result<&str> {
+        self.keys.get(&version)
+            .map(String::as_str)
+            .ok_or_else(|| SafeBankError::CryptographyError {
+                message: format!("Unknown or retired signing key v{}", version),
+            })
+    }
+}
+
+impl std::fmt::Debug for SigningKeyring {
+    // Never print the secrets themselves
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut versions: Vec<_> = self.keys.keys().collect();
+        versions.sort();
+        f.debug_struct("SigningKeyring")
+            .field("versions", &versions)
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+/// Account operated by a group, such as a co-op, whose larger payments need members to co-approve them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedAccount {
+    pub account_id: Uuid,
+    /// Users allowed to approve payments from the account
+    pub members: HashSet<Uuid>,
+    /// Distinct member approvals a held payment needs
+    pub quorum: usize,
+    /// Outgoing payments of at least this amount are held for approval
+    pub approval_threshold: f64,
+}
+
+/// Running totals behind `get_transaction_statistics`, kept current as transactions are stored.
+/// They live in the `SystemState` so every instance writing to the same storage keeps one count.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TransactionStats {
+    total: i64,
+    approved: i64,
+    rejected: i64,
+    flagged: i64,
+    volume: Money,
+    categories: HashMap<String, (i64, Money)>, // category key -> (count, volume)
+}
+
+impl TransactionStats {
+    /// Add (`sign` 1) or remove (`sign` -1) one stored transaction's contribution
+    fn apply(&mut self, transaction: &Transaction, amount: Money, sign: i64) {
+        self.total += sign;
+        match transaction.status {
+            TransactionStatus::Approved => self.approved += sign,
+            TransactionStatus::Rejected => self.rejected += sign,
+            TransactionStatus::Flagged | TransactionStatus::RequiresApproval => self.flagged += sign,
+            _ => {}
+        }
+        let amount = Money::from_minor_units(amount.minor_units() * sign);
+        self.volume += amount;
+
+        let category = transaction.category.as_ref().map_or_else(|| "uncategorized".to_string(), |category| category.key());
+        let (count, volume) = self.categories.entry(category).or_insert((0, Money::ZERO));
+        *count += sign;
+        *volume += amount;
+    }
+}
+
+/// One row of an account statement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatementLine {
+    pub transaction_id: Uuid,
+    /// When the transaction moved money, or when it was submitted if it never did
+    pub posted_at: DateTime<Utc>,
+    pub transaction_type: TransactionType,
+    pub status: TransactionStatus,
+    pub amount: f64,
+    /// Fee plus levy
+    pub charges: f64,
+    /// Signed change to the balance; zero for held and rejected transactions
+    pub balance_change: f64,
+    pub balance_after: f64,
+}
+
+/// What happened to one offline transaction during a batch sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncStatus {
+    Applied,
+    Rejected,
+    Expired,
+    Duplicate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncOutcome {
+    pub transaction_id: Uuid,
+    pub status: SyncStatus,
+    /// Why the transaction was not applied
+    pub reason: Option<String>,
+}
+
+/// Result of `sync_offline_batch`, with outcomes in the order transactions were evaluated
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub applied: usize,
+    pub rejected: usize,
+    pub expired: usize,
+    pub duplicate: usize,
+    pub outcomes: Vec<SyncOutcome>,
+}
+
+impl SyncReport {
+    fn record(&mut self, transaction_id: Uuid, status: SyncStatus, reason: Option<String>) {
+        match status {
+            SyncStatus::Applied => self.applied += 1,
+            SyncStatus::Rejected => self.rejected += 1,
+            SyncStatus::Expired => self.expired += 1,
+            SyncStatus::Duplicate => self.duplicate += 1,
+        }
+        self.outcomes.push(SyncOutcome { transaction_id, status, reason });
+    }
+}
+
+/// Actor recorded for status changes the system makes on its own, such as fraud screening at commit
+pub const SYSTEM_ACTOR: &str = "system";
+
+/// Length of an AES-GCM nonce in bytes
+const NONCE_LEN: usize = 12;
+
+/// Domain separation for keys derived from offline secrets
+const OFFLINE_KEY_INFO: &[u8] = b"safebank-offline-transaction-v1";
+
+/// How far reported coordinates may be from the position configured for their label
+const GEO_LABEL_TOLERANCE_KM: f64 = 50.0;
+
+/// Fraction of the daily limit added per step of account age or clean history
+const LIMIT_STEP: f64 = 0.1;
+
+/// Monthly age steps stop counting after a year
+const MAX_AGE_STEPS: i64 = 12;
+
+/// Approved transactions per history step, and the most history steps that count
+const CLEAN_TRANSACTIONS_PER_STEP: usize = 25;
+const MAX_HISTORY_STEPS: usize = 10;
+
+/// A user's approved, unreversed transactions since their clean history began, kept as a running
+/// count in storage so limit checks don't rescan the history
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CleanHistory {
+    since: Option<DateTime<Utc>>,
+    count: usize,
+}
+
+/// Approved and not reversed: counts towards a raised daily limit
+fn is_clean(transaction: &Transaction) -> bool {
+    transaction.status == TransactionStatus::Approved && transaction.reversed_by.is_none()
+}
+
+/// Clean history restarts at the last confirmed fraud
+fn clean_history_since(user: &crate::UserProfile) -> DateTime<Utc> {
+    user.last_confirmed_fraud.map_or(user.created_at, |fraud| fraud.max(user.created_at))
+}
+
+fn clean_history_key(user_id: Uuid) -> String {
+    format!("clean_history/{}", user_id)
+}
+
+/// A user's running count of clean transactions since their clean history began.
+/// Unreadable storage counts as no history.
+pub(crate) fn clean_transaction_count(storage: &dyn storage::Storage, user: &crate::UserProfile) -> usize {
+    let since = clean_history_since(user);
+    storage::load_json::<CleanHistory>(storage, &clean_history_key(user.user_id))
+        .ok()
+        .flatten()
+        .filter(|history| history.since == Some(since))
+        .map_or(0, |history| history.count)
+}
+
+/// Member approvals of a held shared-account payment, kept in storage so a restart or another
+/// instance sees the same count
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CoApproval {
+    approvals: HashSet<Uuid>,
+    /// Any review or step-up hold on the payment has been released, leaving only the members
+    released: bool,
+}
+
+fn shared_account_key(account_id: Uuid) -> String {
+    format!("shared_account/{}", account_id)
+}
+
+fn co_approval_key(transaction_id: Uuid) -> String {
+    format!("co_approval/{}", transaction_id)
+}
+
+/// Feed one length-prefixed field to a chain hash
+fn hash_field(hasher: &mut Sha256, field: &[u8]) {
+    hasher.update((field.len() as u64).to_le_bytes());
+    hasher.update(field);
+}
+
+/// Feed an optional field, keeping an absent value distinct from an empty one
+fn hash_optional(hasher: &mut Sha256, field: Option<&str>) {
+    match field {
+        Some(value) => {
+            hasher.update([1u8]);
+            hash_field(hasher, value.as_bytes());
+        }
+        None => hasher.update([0u8]),
+    }
 }
 
 impl TransactionManager {
     pub fn new(config: &SafeBankConfig) -> Self {
+        Self::with_clock(config, clock::system_clock())
+    }
+
+    /// Create a transaction manager driven by the given clock
+    pub fn with_clock(config: &SafeBankConfig, clock: SharedClock) -> Self {
+        Self::with_storage(config, storage::in_memory_storage(), clock)
+    }
+
+    /// Create a transaction manager that persists transactions in the given storage backend
+    pub fn with_storage(config: &SafeBankConfig, storage: SharedStorage, clock: SharedClock) -> Self {
+        Self::with_system_state(config, storage, clock, Arc::new(SystemState::new(config)))
+    }
+
+    /// Create a transaction manager that counts system volume in state shared with other instances
+    pub fn with_system_state(config: &SafeBankConfig, storage: SharedStorage, clock: SharedClock, system: SharedSystemState) -> Self {
+        // One pass over existing history by the first instance; from then on statistics are
+        // kept incrementally. Storage that can't be read yet starts the counters from zero.
+        system.with_transaction_stats(|| {
+            let mut stats = TransactionStats::default();
+            for transaction in storage.load_all_transactions().unwrap_or_default() {
+                stats.apply(&transaction, config.to_money(transaction.amount), 1);
+            }
+            stats
+        }, |_| ());
+
         Self {
             config: config.clone(),
-            transactions: HashMap::new(),
-            user_transactions: HashMap::new(),
+            storage,
             daily_limits: HashMap::new(),
+            recipient_salt: config.recipient_hash_salt.clone().filter(|salt| !salt.is_empty()),
+            idempotency_keys: HashMap::new(),
+            pending_offline: HashMap::new(),
+            system,
+            clock,
         }
     }
 
     /// Process a transaction with validation and security checks
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))] // errors are only inspected for logging
     pub fn process_transaction(&mut self, mut transaction: Transaction) -> Result<Transaction> {
+        // A retried submission returns the original instead of processing again
+        if let Some(key) = &transaction.idempotency_key {
+            if let Some(existing) = self.find_by_idempotency_key(transaction.user_id, key)? {
+                return Ok(existing);
+            }
+        }
+
+        let _span = logging::enter_span!("process_transaction", transaction_id = %transaction.transaction_id);
+        let user_id = transaction.user_id;
+        self.prepare_transaction(&mut transaction)
+            .inspect_err(|e| logging::warn_event!(%user_id, error_code = e.code(), "transaction rejected before commit"))?;
+
+        // Check daily limits
+        self.check_daily_limit(&self.daily_limit_state(user_id), &[&transaction])
+            .inspect_err(|e| logging::warn_event!(%user_id, error_code = e.code(), "daily limit breached"))?;
+
+        // Only approved transactions move money, so only they need covering funds
+        if transaction.status == TransactionStatus::Approved {
+            self.check_funds(user_id, self.balance_delta(&transaction))
+                .inspect_err(|e| logging::info_event!(%user_id, error_code = e.code(), "insufficient funds"))?;
+        }
+
+        let payment_total = transaction.amount;
+        self.commit_transaction(transaction, payment_total)
+    }
+
+    /// Process linked transactions atomically: either every leg commits or none does.
+    /// Each leg is checked against the single transaction limit and the combined amount against
+    /// the daily limit, and a single fee for the combined amount is charged on the first leg. If a leg fails to commit, the legs
+    /// already stored are recorded as rejected and the balance and usage they moved are restored.
+    pub fn process_transaction_group(&mut self, mut legs: Vec<Transaction>) -> Result<Vec<Transaction>> {
+        let Some(first) = legs.first() else {
+            return Ok(Vec::new());
+        };
+        let user_id = first.user_id;
+        if legs.len() > self.config.max_split_recipients {
+            return Err(SafeBankError::ConfigError {
+                message: format!("Split transfer supports at most {} recipients", self.config.max_split_recipients),
+            });
+        }
+        if legs.iter().any(|leg| leg.user_id != user_id) {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: "Grouped transactions must belong to one user".to_string(),
+            });
+        }
+
+        for leg in legs.iter_mut() {
+            self.prepare_transaction(leg)?;
+        }
+
+        let total = self.charge_group_fee(&mut legs);
+        self.check_daily_limit(&self.daily_limit_state(user_id), &legs.iter().collect::<Vec<_>>())?;
+
+        let total_delta: Money = legs.iter()
+            .filter(|leg| leg.status == TransactionStatus::Approved)
+            .map(|leg| self.balance_delta(leg))
+            .sum();
+        self.check_funds(user_id, total_delta)?;
+
+        let balance_before = self.balance_money(user_id)?;
+        let daily_limit_before = self.daily_limits.get(&user_id).cloned();
+        let mut committed = Vec::with_capacity(legs.len());
+        for leg in legs {
+            match self.commit_transaction(leg, total) {
+                Ok(leg) => committed.push(leg),
+                Err(e) => {
+                    logging::warn_event!(%user_id, error_code = e.code(), legs = committed.len(), "split transfer failed part way, rolling back");
+                    self.roll_back_group(user_id, committed, balance_before, daily_limit_before);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(committed)
+    }
+
+    /// Charge one fee for the group's combined amount on its first leg; returns the combined amount
+    fn charge_group_fee(&self, legs: &mut [Transaction]) -> f64 {
+        let total = self.config.from_money(legs.iter().map(|leg| self.config.to_money(leg.amount)).sum());
+        let mut combined = legs[0].clone();
+        combined.amount = total;
+        let group_fee = self.calculate_fee(&combined);
+        for (index, leg) in legs.iter_mut().enumerate() {
+            leg.fee = if index == 0 { group_fee } else { 0.0 };
+        }
+        total
+    }
+
+    /// Undo the legs of a group committed before a later leg failed. Stored legs stay on record
+    /// as rejected; rollback is best effort so the original failure is what the caller sees.
+    fn roll_back_group(&mut self, user_id: Uuid, committed: Vec<Transaction>, balance_before: Money, daily_limit_before: Option<DailyLimit>) {
+        let now = self.clock.now();
+        for mut leg in committed {
+            // Take back the leg's share of today's system volume
+            if self.config.banking_day(leg.timestamp) >= self.config.banking_day(now) {
+                self.record_system_volume(-leg.amount);
+            }
+            leg.change_status(TransactionStatus::Rejected, now, SYSTEM_ACTOR, Some("Split transfer rolled back".to_string()));
+            let _ = self.store_transaction(&mut leg);
+        }
+        let _ = self.storage.save_balance(user_id, self.config.from_money(balance_before));
+        match daily_limit_before {
+            Some(daily_limit) => self.daily_limits.insert(user_id, daily_limit),
+            None => self.daily_limits.remove(&user_id),
+        };
+    }
+
+    /// Cheap checks that would certainly fail a transaction, run before fraud analysis:
+    /// the same validation as commit, plus whether the balance covers the amount and charges
+    pub fn precheck(&self, transaction: &Transaction) -> Result<()> {
+        let mut transaction = transaction.clone();
+        self.validate_transaction(&mut transaction)?;
+        self.check_funds(transaction.user_id, self.balance_delta(&transaction))
+    }
+
+    /// `precheck` for the legs of a group: each leg is validated, and the balance must cover
+    /// the combined amount and the single group fee
+    pub fn precheck_group(&self, legs: &[Transaction]) -> Result<()> {
+        let Some(first) = legs.first() else {
+            return Ok(());
+        };
+        let mut legs = legs.to_vec();
+        for leg in legs.iter_mut() {
+            self.validate_transaction(leg)?;
+        }
+        self.charge_group_fee(&mut legs);
+        self.check_funds(first.user_id, legs.iter().map(|leg| self.balance_delta(leg)).sum())
+    }
+
+    /// Normalize and validate a transaction ahead of commit
+    fn prepare_transaction(&mut self, transaction: &mut Transaction) -> Result<()> {
+        self.validate_transaction(transaction)?;
+
+        // Minimize PII at rest when recipient hashing is enabled
+        transaction.recipient = self.protect_recipient(&transaction.recipient)?;
+
+        // Validate transaction status progression
+        self.validate_transaction_status(transaction)
+    }
+
+    /// Round the amount, itemize charges, clean the memo and apply the policy checks shared by precheck and commit
+    fn validate_transaction(&self, transaction: &mut Transaction) -> Result<()> {
+        // Reduce the amount to the currency's minor unit before any limit arithmetic
+        transaction.amount = self.config.round_money(transaction.amount);
+
+        // Charges are itemized separately: the bank fee and any government levy
+        transaction.fee = self.calculate_fee(transaction);
+        transaction.levy = self.calculate_levy(transaction);
+
+        if let Some(memo) = transaction.memo.take() {
+            transaction.memo = crate::utils::clean_memo(&memo, self.config.max_memo_length)?;
+        }
+
         // Validate transaction amount
         if transaction.amount <= 0.0 {
             return Err(SafeBankError::ConfigError {
@@ -67,6 +589,24 @@ impl TransactionManager {
             });
         }
 
+        // Reported coordinates must be a real point, near any position configured for their label
+        if let Some(place) = &transaction.geo_location {
+            let disagrees = self.config.configured_coordinates(&place.label)
+                .is_some_and(|configured| crate::utils::haversine_km(configured, place.position()) > GEO_LABEL_TOLERANCE_KM);
+            if !place.is_valid() || disagrees {
+                return Err(SafeBankError::ConfigError {
+                    message: format!("Transaction coordinates for '{}' are not valid", place.label),
+                });
+            }
+        }
+
+        // Geofence is a hard policy gate, separate from location anomaly scoring
+        if !self.config.is_location_allowed(transaction.location.as_deref()) {
+            return Err(SafeBankError::RegionNotAllowed {
+                location: transaction.location.clone().unwrap_or_else(|| "an unknown location".to_string()),
+            });
+        }
+
         // Check single transaction limit
         if transaction.amount > self.config.single_transaction_limit {
             return Err(SafeBankError::TransactionLimitExceeded {
@@ -75,66 +615,356 @@ impl TransactionManager {
             });
         }
 
-        // Check daily limits
-        self.check_daily_limit(&transaction)?;
+        Ok(())
+    }
 
-        // Validate transaction status progression
-        self.validate_transaction_status(&transaction)?;
+    /// Store a validated transaction and update history and limits. Every path commits through here,
+    /// so this is where larger shared-account payments are held for their members; `payment_total`
+    /// is the whole payment, which for a split transfer spans its legs.
+    fn commit_transaction(&mut self, mut transaction: Transaction, payment_total: f64) -> Result<Transaction> {
+        let co_approval = self.hold_for_co_approval(&mut transaction, payment_total)?;
 
-        // Generate transaction hash for integrity
-        let _transaction_hash = self.generate_transaction_hash(&transaction);
-        
-        // Store transaction
-        self.transactions.insert(transaction.transaction_id, transaction.clone());
+        // Transactions arrive already screened; record the move out of Pending
+        if transaction.status != TransactionStatus::Pending && transaction.status_history.is_empty() {
+            let status = std::mem::replace(&mut transaction.status, TransactionStatus::Pending);
+            transaction.change_status(status, self.clock.now(), SYSTEM_ACTOR, None);
+        }
+
+        // Link into the user's hash chain for tamper evidence
+        self.append_to_chain(&mut transaction)?;
         
-        // Update user transaction history
-        self.user_transactions
-            .entry(transaction.user_id)
-            .or_insert_with(Vec::new)
-            .push(transaction.transaction_id);
+        if transaction.status == TransactionStatus::Approved {
+            self.stamp_balance(&mut transaction)?;
+        }
 
-        // Update daily limits
-        self.update_daily_limit(&transaction)?;
+        // Store transaction (the backend also maintains the user's history and chain head)
+        self.store_transaction(&mut transaction)?;
+        self.remember_idempotency_key(&transaction);
+        if let Some(co_approval) = &co_approval {
+            storage::save_json(self.storage.as_ref(), &co_approval_key(transaction.transaction_id), co_approval)?;
+        }
 
-        // Set final status based on fraud score and other factors
         if transaction.status == TransactionStatus::Approved {
-            transaction.status = TransactionStatus::Approved;
+            self.apply_balance(&transaction)?;
         }
 
-        // Update stored transaction
-        self.transactions.insert(transaction.transaction_id, transaction.clone());
+        // Update daily limits; back-dated offline transactions count towards their own day
+        if self.config.banking_day(transaction.timestamp) >= self.config.banking_day(self.clock.now()) {
+            self.update_daily_limit(&transaction);
+            self.record_system_volume(transaction.amount);
+        }
 
+        logging::debug_event!(
+            user_id = %transaction.user_id, status = ?transaction.status, amount = transaction.amount,
+            memo = ?transaction.memo.as_deref().map(crate::utils::sanitize_for_logging), "transaction committed"
+        );
         Ok(transaction)
     }
 
+    /// Seal a transaction's current state into its chain entry and save it
+    fn store_transaction(&mut self, transaction: &mut Transaction) -> Result<()> {
+        if transaction.hash.is_some() {
+            transaction.state_hash = Some(Self::generate_state_hash(transaction));
+        }
+        self.save_counted(transaction)
+    }
+
+    /// Save a transaction as is, moving its contribution in the running statistics from its previous state
+    fn save_counted(&mut self, transaction: &Transaction) -> Result<()> {
+        let previous = self.storage.load_transaction(transaction.transaction_id)?;
+        self.storage.save_transaction(transaction)?;
+        self.system.with_transaction_stats(TransactionStats::default, |stats| {
+            if let Some(previous) = &previous {
+                stats.apply(previous, self.config.to_money(previous.amount), -1);
+            }
+            stats.apply(transaction, self.config.to_money(transaction.amount), 1);
+        });
+
+        let was_clean = previous.as_ref().is_some_and(is_clean);
+        if was_clean != is_clean(transaction) {
+            self.count_clean_transaction(transaction, if was_clean { -1 } else { 1 })?;
+        }
+        Ok(())
+    }
+
+    /// Move a user's running clean-history count, restarting it if fraud was confirmed since it was last kept
+    fn count_clean_transaction(&self, transaction: &Transaction, delta: isize) -> Result<()> {
+        let Some(user) = self.storage.load_user_by_id(transaction.user_id)? else {
+            return Ok(());
+        };
+        let since = clean_history_since(&user);
+        if transaction.timestamp < since {
+            return Ok(());
+        }
+
+        let key = clean_history_key(user.user_id);
+        let mut history: CleanHistory = storage::load_json(self.storage.as_ref(), &key)?
+            .filter(|history: &CleanHistory| history.since == Some(since))
+            .unwrap_or(CleanHistory { since: Some(since), count: 0 });
+        history.count = history.count.saturating_add_signed(delta);
+        storage::save_json(self.storage.as_ref(), &key, &history)
+    }
+
     /// Get transactions for a specific user
     pub fn get_user_transactions(&self, user_id: Uuid) -> Result<Vec<Transaction>> {
-        let empty_vec = Vec::new();
-        let transaction_ids = self.user_transactions.get(&user_id)
-            .unwrap_or(&empty_vec);
-        
-        let mut transactions = Vec::new();
-        for &transaction_id in transaction_ids {
-            if let Some(transaction) = self.transactions.get(&transaction_id) {
-                transactions.push(transaction.clone());
-            }
+        let filter = TransactionFilter {
+            user_id: Some(user_id),
+            ..TransactionFilter::default()
+        };
+        Ok(self.query_transactions(filter, 0, usize::MAX)?.transactions)
+    }
+
+    /// Filter transactions and return one page of matches, most recent first. Pages start at 0.
+    pub fn query_transactions(&self, filter: TransactionFilter, page: usize, page_size: usize) -> Result<TransactionPage> {
+        if page_size == 0 {
+            return Err(SafeBankError::ConfigError {
+                message: "Page size must be greater than 0".to_string(),
+            });
         }
 
+        let candidates = match filter.user_id {
+            Some(user_id) => self.storage.load_user_transactions(user_id)?,
+            None => self.storage.load_all_transactions()?,
+        };
+
+        // Recipients may be stored hashed, so compare in the stored form
+        let recipient = filter.recipient.as_deref()
+            .map(|recipient| self.protected_recipient(recipient))
+            .transpose()?;
+
+        let mut matches: Vec<Transaction> = candidates
+            .into_iter()
+            .filter(|t| filter.status.as_ref().is_none_or(|status| &t.status == status))
+            .filter(|t| filter.transaction_type.as_ref().is_none_or(|kind| &t.transaction_type == kind))
+            .filter(|t| filter.min_amount.is_none_or(|min| t.amount >= min))
+            .filter(|t| filter.max_amount.is_none_or(|max| t.amount <= max))
+            .filter(|t| filter.from.is_none_or(|from| t.timestamp >= from))
+            .filter(|t| filter.to.is_none_or(|to| t.timestamp <= to))
+            .filter(|t| recipient.as_ref().is_none_or(|recipient| &t.recipient == recipient))
+            .collect();
+
         // Sort by timestamp (most recent first)
-        transactions.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        
+        matches.sort_by_key(|t| std::cmp::Reverse(t.timestamp));
+
+        let total_count = matches.len();
+        let start = page.saturating_mul(page_size).min(total_count);
+        let end = start.saturating_add(page_size).min(total_count);
+
+        Ok(TransactionPage {
+            transactions: matches.drain(start..end).collect(),
+            page,
+            page_size,
+            total_count,
+            has_more: end < total_count,
+        })
+    }
+
+    /// Reload previously committed transactions, rebuilding today's limit usage from them
+    pub fn restore_transactions(&mut self, transactions: &[Transaction]) -> Result<()> {
+        let today = self.config.banking_day(self.clock.now());
+        for transaction in transactions {
+            self.save_counted(transaction)?;
+            self.remember_idempotency_key(transaction);
+            if self.config.banking_day(transaction.timestamp) == today && transaction.reverses.is_none() {
+                self.update_daily_limit(transaction);
+                self.record_system_volume(transaction.amount);
+            }
+            if transaction.status == TransactionStatus::Approved {
+                self.apply_balance(transaction)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Transaction previously submitted by this user under the given idempotency key.
+    /// Falls back to the user's stored history so keys survive restarts and shared storage.
+    pub fn find_by_idempotency_key(&self, user_id: Uuid, key: &str) -> Result<Option<Transaction>> {
+        if let Some(transaction_id) = self.idempotency_keys.get(&(user_id, key.to_string())) {
+            return self.storage.load_transaction(*transaction_id);
+        }
+
+        Ok(self.storage.load_user_transactions(user_id)?
+            .into_iter()
+            .find(|transaction| transaction.idempotency_key.as_deref() == Some(key)))
+    }
+
+    /// Current account balance; users who have never transacted have a zero balance
+    pub fn get_balance(&self, user_id: Uuid) -> Result<f64> {
+        Ok(self.config.from_money(self.balance_money(user_id)?))
+    }
+
+    /// Current account balance in minor units
+    pub fn balance_money(&self, user_id: Uuid) -> Result<Money> {
+        Ok(self.config.to_money(self.storage.load_balance(user_id)?.unwrap_or(0.0)))
+    }
+
+    /// The user's transactions in posting order with a running balance. Approved transactions post
+    /// when they were approved and carry the balance they left; held and rejected ones move nothing.
+    pub fn get_statement(&self, user_id: Uuid) -> Result<Vec<StatementLine>> {
+        let mut transactions = self.storage.load_user_transactions(user_id)?;
+        let posted_at = |transaction: &Transaction| transaction.status_history.iter()
+            .find(|change| change.to == TransactionStatus::Approved)
+            .map_or(transaction.timestamp, |change| change.at);
+        transactions.sort_by_key(|transaction| (posted_at(transaction), transaction.timestamp));
+
+        // Balances set outside the transaction flow (e.g. opening balances) come before the first posting
+        let moved = |transaction: &Transaction| transaction.status == TransactionStatus::Approved;
+        let mut running = transactions.iter()
+            .find_map(|transaction| transaction.balance_after
+                .filter(|_| moved(transaction))
+                .map(|after| self.config.to_money(after) - self.balance_delta(transaction)))
+            .unwrap_or(Money::ZERO);
+
+        Ok(transactions.iter().map(|transaction| {
+            let change = if moved(transaction) { self.balance_delta(transaction) } else { Money::ZERO };
+            running = match (moved(transaction), transaction.balance_after) {
+                (true, Some(balance_after)) => self.config.to_money(balance_after),
+                _ => running + change,
+            };
+            StatementLine {
+                transaction_id: transaction.transaction_id,
+                posted_at: posted_at(transaction),
+                transaction_type: transaction.transaction_type.clone(),
+                status: transaction.status.clone(),
+                amount: transaction.amount,
+                charges: transaction.fee + transaction.levy,
+                balance_change: self.config.from_money(change),
+                balance_after: self.config.from_money(running),
+            }
+        }).collect())
+    }
+
+    /// Manually credit (positive) or debit (negative) an account outside the transaction flow,
+    /// e.g. to carry over the opening balance of a migrated account. Returns the new balance.
+    pub fn adjust_balance(&mut self, user_id: Uuid, delta: f64) -> Result<f64> {
+        let delta = self.config.to_money(delta);
+        self.check_funds(user_id, delta)?;
+        let balance = self.config.from_money(self.balance_money(user_id)? + delta);
+        self.storage.save_balance(user_id, balance)?;
+        Ok(balance)
+    }
+
+    /// Amount the user has already transacted today
+    pub fn daily_usage(&self, user_id: Uuid) -> f64 {
+        self.config.from_money(self.daily_usage_money(user_id))
+    }
+
+    /// Amount the user has already transacted today, in minor units
+    pub fn daily_usage_money(&self, user_id: Uuid) -> Money {
+        self.daily_limit_state(user_id).total_amount
+    }
+
+    /// Number of transactions the user has made today
+    pub fn daily_transaction_count(&self, user_id: Uuid) -> u32 {
+        self.daily_limit_state(user_id).transaction_count
+    }
+
+    /// Today's usage for a user; empty if they have not transacted today
+    fn daily_limit_state(&self, user_id: Uuid) -> DailyLimit {
+        let now = self.clock.now();
+        let today = self.config.banking_day(now);
+        self.daily_limits
+            .get(&user_id)
+            .filter(|daily_limit| self.config.banking_day(daily_limit.date) == today)
+            .cloned()
+            .unwrap_or_else(|| DailyLimit::empty(user_id, now))
+    }
+
+    /// The user's usage on a given day, rebuilt from stored history
+    fn usage_on(&self, user_id: Uuid, day: NaiveDate, date: DateTime<Utc>) -> Result<DailyLimit> {
+        let mut usage = DailyLimit::empty(user_id, date);
+        for transaction in self.storage.load_user_transactions(user_id)?
+            .iter()
+            .filter(|t| self.config.banking_day(t.timestamp) == day && t.reverses.is_none())
+        {
+            usage.record(&transaction.recipient, self.config.to_money(transaction.amount), self.limit_amount(transaction));
+        }
+        Ok(usage)
+    }
+
+    /// All stored transactions across users
+    pub fn all_transactions(&self) -> Result<Vec<Transaction>> {
+        let mut transactions = self.storage.load_all_transactions()?;
+        transactions.sort_by_key(|t| t.timestamp);
         Ok(transactions)
     }
 
+    /// Combined volume processed across all users today
+    pub fn system_daily_volume(&self) -> f64 {
+        self.config.from_money(self.system.daily_volume(self.config.banking_day(self.clock.now())))
+    }
+
+    /// Whether today's system volume has passed `ceiling` and no instance has escalated it yet
+    pub fn claim_volume_escalation(&self, ceiling: f64) -> bool {
+        self.system.claim_ceiling_escalation(self.config.banking_day(self.clock.now()), self.config.to_money(ceiling))
+    }
+
     /// Get transaction by ID
     pub fn get_transaction(&self, transaction_id: Uuid) -> Result<Transaction> {
-        self.transactions.get(&transaction_id)
-            .cloned()
+        self.storage.load_transaction(transaction_id)?
             .ok_or_else(|| SafeBankError::StorageError {
                 message: format!("Transaction not found: {}", transaction_id),
             })
     }
 
+    /// Bank fee for a transaction, rounded to the minor unit
+    pub fn calculate_fee(&self, transaction: &Transaction) -> f64 {
+        let fee = crate::utils::calculate_scheduled_fee(transaction.amount, transaction.transaction_type.as_str(), true, &self.config.fee_schedule);
+        self.config.round_money(fee)
+    }
+
+    /// Government levy for a transaction under the configured schedule, rounded to the minor unit
+    pub fn calculate_levy(&self, transaction: &Transaction) -> f64 {
+        self.config.levy_schedule
+            .as_ref()
+            .map(|schedule| self.config.round_money(schedule.levy_for(transaction.amount, &transaction.transaction_type)))
+            .unwrap_or(0.0)
+    }
+
+    /// Convert a foreign-currency amount into the local currency.
+    /// Returns the converted amount after the FX margin and the margin itself as the FX fee.
+    pub fn convert_to_local(&self, amount: f64, currency: &str) -> Result<(f64, f64)> {
+        let currency = currency.to_uppercase();
+        if currency == self.config.local_currency.to_uppercase() {
+            return Ok((self.config.round_money(amount), 0.0));
+        }
+
+        let rate = self.config.exchange_rates.get(&currency)
+            .ok_or_else(|| SafeBankError::ConfigError {
+                message: format!("No exchange rate configured for {}", currency),
+            })?;
+
+        let (converted, fx_fee) = crate::utils::convert_with_margin(amount, *rate, self.config.fx_margin_percent);
+        Ok((self.config.round_money(converted), self.config.round_money(fx_fee)))
+    }
+
+    /// Map a recipient to the identifier stored at rest, recording hashes in the persisted directory.
+    /// Returns a salted hash when `hash_recipients` is enabled, otherwise the recipient unchanged.
+    pub fn protect_recipient(&mut self, recipient: &str) -> Result<String> {
+        let protected = self.protected_recipient(recipient)?;
+        if protected != recipient {
+            self.storage.save_record(&Self::directory_key(&protected), recipient)?;
+        }
+        Ok(protected)
+    }
+
+    /// The form `protect_recipient` would store, without recording it in the directory
+    pub fn protected_recipient(&self, recipient: &str) -> Result<String> {
+        if !self.config.hash_recipients || self.storage.load_record(&Self::directory_key(recipient))?.is_some() {
+            return Ok(recipient.to_string());
+        }
+        self.hash_recipient(recipient)
+    }
+
+    /// The plaintext recipient behind a stored hash, for display by the framework to the account holder or operators
+    pub(crate) fn reveal_recipient(&self, stored_recipient: &str) -> Option<String> {
+        self.storage.load_record(&Self::directory_key(stored_recipient)).ok().flatten()
+    }
+
+    fn directory_key(stored_recipient: &str) -> String {
+        format!("recipient_directory/{}", stored_recipient)
+    }
+
     /// Create a transaction receipt
     pub fn create_receipt(&self, transaction: &Transaction) -> TransactionReceipt {
         let confirmation_code = self.generate_confirmation_code(transaction);
@@ -147,28 +977,297 @@ impl TransactionManager {
             status: transaction.status.clone(),
             confirmation_code,
             fraud_score: transaction.fraud_score,
+            fee: transaction.fee,
+            levy: transaction.levy,
+            memo: transaction.memo.clone(),
         }
     }
 
-    /// Approve a flagged transaction (manual review)
-    pub fn approve_transaction(&mut self, transaction_id: Uuid) -> Result<Transaction> {
-        let mut transaction = self.get_transaction(transaction_id)?;
+    /// Export a user's transactions, oldest first, as a statement for the user or an auditor.
+    /// A user without transactions gets a header-only CSV or an empty JSON array.
+    pub fn export_transactions(&self, user_id: Uuid, format: ExportFormat) -> Result<String> {
+        let mut transactions = self.get_user_transactions(user_id)?;
+        transactions.reverse();
+        let rows: Vec<ExportedTransaction> = transactions.iter()
+            .map(|transaction| self.exported_transaction(transaction))
+            .collect();
+
+        match format {
+            ExportFormat::Csv => {
+                let mut csv = String::from(
+                    "transaction_id,timestamp,type,recipient,category,amount,fee,levy,status,confirmation_code\n",
+                );
+                let currency = &self.config.local_currency;
+                for row in &rows {
+                    let fields = [
+                        row.transaction_id.to_string(),
+                        row.timestamp.to_rfc3339(),
+                        format!("{:?}", row.transaction_type),
+                        row.recipient.clone(),
+                        row.category.as_ref().map(|category| category.key()).unwrap_or_default(),
+                        crate::utils::format_currency(row.amount, currency),
+                        crate::utils::format_currency(row.fee, currency),
+                        crate::utils::format_currency(row.levy, currency),
+                        format!("{:?}", row.status),
+                        row.confirmation_code.clone(),
+                    ];
+                    let cells: Vec<String> = fields.iter().map(|field| crate::utils::escape_csv_field(field)).collect();
+                    csv.push_str(&cells.join(","));
+                    csv.push('\n');
+                }
+                Ok(csv)
+            }
+            ExportFormat::Json => {
+                serde_json::to_string_pretty(&rows).map_err(|e| SafeBankError::SerializationError {
+                    message: format!("Failed to export transactions: {}", e),
+                })
+            }
+        }
+    }
+
+    /// The statement row for a stored transaction, with a hashed recipient resolved through the directory
+    fn exported_transaction(&self, transaction: &Transaction) -> ExportedTransaction {
+        let recipient = self.reveal_recipient(&transaction.recipient).unwrap_or_else(|| {
+            if self.config.hash_recipients { String::new() } else { transaction.recipient.clone() }
+        });
+
+        ExportedTransaction {
+            transaction_id: transaction.transaction_id,
+            timestamp: transaction.timestamp,
+            transaction_type: transaction.transaction_type.clone(),
+            recipient,
+            category: transaction.category.clone(),
+            amount: transaction.amount,
+            fee: transaction.fee,
+            levy: transaction.levy,
+            fx_fee: transaction.fx_fee,
+            status: transaction.status.clone(),
+            memo: transaction.memo.clone(),
+            confirmation_code: self.generate_confirmation_code(transaction),
+        }
+    }
+
+    /// Approve a transaction held for manual review, recording who approved it. Step-up holds
+    /// can only be released by the customer's code, through `confirm_step_up`, and a shared-account
+    /// payment waiting only on its members by their approvals, through `co_approve_transaction`.
+    pub fn approve_transaction(&mut self, transaction_id: Uuid, actor: &str) -> Result<Transaction> {
+        let transaction = self.get_transaction(transaction_id)?;
         
-        if transaction.status != TransactionStatus::RequiresApproval 
-            && transaction.status != TransactionStatus::Flagged {
+        if !matches!(transaction.status, TransactionStatus::RequiresApproval | TransactionStatus::Flagged) {
             return Err(SafeBankError::InvalidTransactionState {
                 current_state: format!("{:?}", transaction.status),
             });
         }
+        if self.co_approval(transaction_id)?.is_some_and(|co_approval| co_approval.released) {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: "Awaiting approval by the account's members".to_string(),
+            });
+        }
+
+        self.commit_approval(transaction, actor)
+    }
+
+    /// Commit a `RequiresStepUp` transaction once the customer has confirmed it with their code
+    pub fn confirm_step_up(&mut self, transaction_id: Uuid) -> Result<Transaction> {
+        let transaction = self.get_transaction(transaction_id)?;
+        if transaction.status != TransactionStatus::RequiresStepUp {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: format!("{:?}", transaction.status),
+            });
+        }
+        self.commit_approval(transaction, "customer")
+    }
+
+    /// `confirm_step_up` for every leg of a split transfer confirmed with one code. The balance
+    /// must cover every leg before any is committed.
+    pub fn confirm_step_up_group(&mut self, transaction_ids: &[Uuid]) -> Result<Vec<Transaction>> {
+        let legs = transaction_ids.iter()
+            .map(|transaction_id| self.get_transaction(*transaction_id))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(leg) = legs.iter().find(|leg| leg.status != TransactionStatus::RequiresStepUp) {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: format!("{:?}", leg.status),
+            });
+        }
+        if let Some(first) = legs.first() {
+            self.check_funds(first.user_id, legs.iter().map(|leg| self.balance_delta(leg)).sum())?;
+        }
+        legs.into_iter().map(|leg| self.commit_approval(leg, "customer")).collect()
+    }
+
+    /// Count an authenticated member's approval of a held shared-account payment. The payment
+    /// commits once the account's quorum of distinct members has approved it and any review or
+    /// step-up hold on it has been released.
+    pub fn co_approve_transaction(&mut self, transaction_id: Uuid, member: Uuid) -> Result<Transaction> {
+        let transaction = self.get_transaction(transaction_id)?;
+        let not_awaiting = || SafeBankError::InvalidTransactionState {
+            current_state: "Transaction is not awaiting co-approval".to_string(),
+        };
+        if !matches!(transaction.status, TransactionStatus::RequiresApproval | TransactionStatus::Flagged | TransactionStatus::RequiresStepUp) {
+            return Err(not_awaiting());
+        }
+        let mut co_approval = self.co_approval(transaction_id)?.ok_or_else(not_awaiting)?;
+        let account = self.shared_account(transaction.user_id)?.ok_or_else(not_awaiting)?;
+        if !account.members.contains(&member) {
+            return Err(SafeBankError::AuthenticationFailed {
+                message: "Only members of the shared account can approve its payments".to_string(),
+            });
+        }
+        if !co_approval.approvals.insert(member) {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: "Already approved by this member".to_string(),
+            });
+        }
+        logging::info_event!(%transaction_id, approvals = co_approval.approvals.len(), quorum = account.quorum, "shared account approval recorded");
 
-        transaction.status = TransactionStatus::Approved;
-        self.transactions.insert(transaction_id, transaction.clone());
+        if co_approval.approvals.len() < account.quorum || !co_approval.released {
+            storage::save_json(self.storage.as_ref(), &co_approval_key(transaction_id), &co_approval)?;
+            return Ok(transaction);
+        }
+        self.storage.remove_record(&co_approval_key(transaction_id))?;
+        self.commit_approval(transaction, &member.to_string())
+    }
+
+    /// Move a held transaction to `Approved` and apply it to the balance. A shared-account payment
+    /// still short of its members' quorum moves to `RequiresApproval` to wait for them instead.
+    fn commit_approval(&mut self, mut transaction: Transaction, actor: &str) -> Result<Transaction> {
+        let key = co_approval_key(transaction.transaction_id);
+        if let Some(mut co_approval) = storage::load_json::<CoApproval>(self.storage.as_ref(), &key)? {
+            let quorum = self.shared_account(transaction.user_id)?.map_or(0, |account| account.quorum);
+            if co_approval.approvals.len() < quorum {
+                co_approval.released = true;
+                storage::save_json(self.storage.as_ref(), &key, &co_approval)?;
+                if transaction.status != TransactionStatus::RequiresApproval {
+                    let reason = Some("Awaiting approval by the account's members".to_string());
+                    transaction.change_status(TransactionStatus::RequiresApproval, self.clock.now(), actor, reason);
+                    self.store_transaction(&mut transaction)?;
+                }
+                return Ok(transaction);
+            }
+            self.storage.remove_record(&key)?;
+        }
+
+        self.check_funds(transaction.user_id, self.balance_delta(&transaction))?;
+        transaction.change_status(TransactionStatus::Approved, self.clock.now(), actor, None);
+        self.stamp_balance(&mut transaction)?;
+        self.store_transaction(&mut transaction)?;
+        self.apply_balance(&transaction)?;
         
         Ok(transaction)
     }
 
-    /// Reject a transaction
-    pub fn reject_transaction(&mut self, transaction_id: Uuid, _reason: String) -> Result<Transaction> {
+    /// Register a shared account; its own balance is kept under `account_id` like any user's
+    pub fn register_shared_account(&mut self, account: SharedAccount) -> Result<()> {
+        if account.quorum == 0 || account.quorum > account.members.len() {
+            return Err(SafeBankError::ConfigError {
+                message: "Shared account quorum must be between 1 and the number of members".to_string(),
+            });
+        }
+        storage::save_json(self.storage.as_ref(), &shared_account_key(account.account_id), &account)
+    }
+
+    pub fn shared_account(&self, account_id: Uuid) -> Result<Option<SharedAccount>> {
+        storage::load_json(self.storage.as_ref(), &shared_account_key(account_id))
+    }
+
+    fn co_approval(&self, transaction_id: Uuid) -> Result<Option<CoApproval>> {
+        storage::load_json(self.storage.as_ref(), &co_approval_key(transaction_id))
+    }
+
+    /// Hold a larger outgoing payment from a shared account until its members co-approve it,
+    /// whatever other hold it is under. Returns the approvals record to store with the payment.
+    fn hold_for_co_approval(&self, transaction: &mut Transaction, payment_total: f64) -> Result<Option<CoApproval>> {
+        let Some(account) = self.shared_account(transaction.user_id)? else {
+            return Ok(None);
+        };
+        if transaction.status == TransactionStatus::Rejected
+            || transaction.transaction_type == TransactionType::Deposit
+            || payment_total < account.approval_threshold
+        {
+            return Ok(None);
+        }
+        let released = transaction.status == TransactionStatus::Approved;
+        if released {
+            transaction.status = TransactionStatus::RequiresApproval;
+        }
+        Ok(Some(CoApproval { approvals: HashSet::new(), released }))
+    }
+
+    /// Reverse an approved transaction within the configured window by recording a compensating
+    /// transaction that refunds the amount to the sender (fees and levies are not refunded).
+    /// Recipients are not accounts in this ledger, so only the sender's balance moves.
+    pub fn reverse_transaction(&mut self, transaction_id: Uuid, reason: String) -> Result<Transaction> {
+        let mut original = self.get_transaction(transaction_id)?;
+
+        if original.status != TransactionStatus::Approved {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: format!("{:?}", original.status),
+            });
+        }
+        if original.reversed_by.is_some() {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: "Transaction already reversed".to_string(),
+            });
+        }
+        if original.reverses.is_some() {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: "Reversals cannot be reversed".to_string(),
+            });
+        }
+
+        let now = self.clock.now();
+        if now - original.timestamp > Duration::hours(self.config.reversal_window_hours as i64) {
+            return Err(SafeBankError::InvalidTransactionState {
+                current_state: "Reversal window has passed".to_string(),
+            });
+        }
+
+        let transaction_type = match original.transaction_type {
+            TransactionType::Deposit => TransactionType::Withdrawal,
+            _ => TransactionType::Deposit,
+        };
+        let mut reversal = Transaction {
+            transaction_id: Uuid::new_v4(),
+            transaction_type,
+            timestamp: now,
+            fraud_score: 0.0,
+            status: TransactionStatus::Approved,
+            fee: 0.0,
+            levy: 0.0,
+            fx_fee: 0.0,
+            group_id: None,
+            risk_factors: Vec::new(),
+            idempotency_key: None,
+            reverses: Some(original.transaction_id),
+            reversed_by: None,
+            status_history: vec![StatusChange {
+                from: TransactionStatus::Pending,
+                to: TransactionStatus::Approved,
+                at: now,
+                actor: SYSTEM_ACTOR.to_string(),
+                reason: Some(reason.clone()),
+            }],
+            balance_after: None,
+            memo: None,
+            ..original.clone()
+        };
+
+        self.check_funds(reversal.user_id, self.balance_delta(&reversal))?;
+        self.append_to_chain(&mut reversal)?;
+        self.stamp_balance(&mut reversal)?;
+        self.store_transaction(&mut reversal)?;
+        self.apply_balance(&reversal)?;
+
+        // The original stays approved; the timeline notes that it was reversed and why
+        original.reversed_by = Some(reversal.transaction_id);
+        original.change_status(TransactionStatus::Approved, now, SYSTEM_ACTOR, Some(format!("Reversed: {}", reason)));
+        self.store_transaction(&mut original)?;
+
+        Ok(reversal)
+    }
+
+    /// Reject a transaction, recording who rejected it and why
+    pub fn reject_transaction(&mut self, transaction_id: Uuid, actor: &str, reason: String) -> Result<Transaction> {
         let mut transaction = self.get_transaction(transaction_id)?;
         
         if transaction.status == TransactionStatus::Approved {
@@ -177,14 +1276,46 @@ impl TransactionManager {
             });
         }
 
-        transaction.status = TransactionStatus::Rejected;
-        self.transactions.insert(transaction_id, transaction.clone());
+        transaction.change_status(TransactionStatus::Rejected, self.clock.now(), actor, Some(reason));
+        self.store_transaction(&mut transaction)?;
+        self.storage.remove_record(&co_approval_key(transaction_id))?;
         
         Ok(transaction)
     }
 
-    /// Create offline transaction for areas with poor connectivity
-    pub fn create_offline_transaction(&self, transaction: &Transaction, secret_key: &str) -> Result<OfflineTransaction> {
+    /// Reject transactions held for review (`RequiresApproval` or `Flagged`) or for the customer's
+    /// step-up code (`RequiresStepUp`) for longer than `pending_expiry_hours` as of `now`, measured
+    /// from when they were held. Held transactions never moved money, so there is no balance to release.
+    pub fn expire_stale_transactions(&mut self, now: DateTime<Utc>) -> Result<Vec<Transaction>> {
+        let Some(hours) = self.config.pending_expiry_hours else {
+            return Ok(Vec::new());
+        };
+        let cutoff = now - Duration::hours(hours as i64);
+
+        let stale: Vec<Transaction> = self.storage.load_all_transactions()?
+            .into_iter()
+            .filter(|t| matches!(t.status, TransactionStatus::RequiresApproval | TransactionStatus::Flagged | TransactionStatus::RequiresStepUp))
+            .filter(|t| t.status_history.last().map_or(t.timestamp, |change| change.at) < cutoff)
+            .collect();
+
+        let mut expired = Vec::with_capacity(stale.len());
+        for mut transaction in stale {
+            let reason = if transaction.status == TransactionStatus::RequiresStepUp {
+                format!("Expired after {} hours without confirmation", hours)
+            } else {
+                format!("Expired after {} hours without review", hours)
+            };
+            transaction.change_status(TransactionStatus::Rejected, now, SYSTEM_ACTOR, Some(reason));
+            self.store_transaction(&mut transaction)?;
+            self.storage.remove_record(&co_approval_key(transaction.transaction_id))?;
+            logging::info_event!(user_id = %transaction.user_id, transaction_id = %transaction.transaction_id, "held transaction expired");
+            expired.push(transaction);
+        }
+        Ok(expired)
+    }
+
+    /// Create offline transaction for areas with poor connectivity, sealed under the keyring's current key
+    pub fn create_offline_transaction(&self, transaction: &Transaction, keyring: &SigningKeyring) -> Result<OfflineTransaction> {
         if transaction.amount > self.config.offline_transaction_limit {
             return Err(SafeBankError::TransactionLimitExceeded {
                 amount: transaction.amount,
@@ -198,131 +1329,334 @@ impl TransactionManager {
                 message: format!("Failed to serialize transaction: {}", e),
             })?;
 
-        // Encrypt transaction data (simplified encryption for demo)
-        let encrypted_data = self.encrypt_data(&transaction_data, secret_key)?;
+        // Encrypt transaction data with authenticated encryption
+        let secret_key = keyring.current_key();
+        let (nonce, encrypted_data) = self.encrypt_data(&transaction_data, secret_key)?;
         
         // Generate signature for integrity
         let signature = self.generate_signature(&transaction_data, secret_key);
 
         // Set expiration time
-        let expires_at = Utc::now() + Duration::hours(self.config.offline_cache_duration_hours as i64);
+        let expires_at = self.clock.now() + Duration::hours(self.config.offline_cache_duration_hours as i64);
 
         Ok(OfflineTransaction {
             transaction: transaction.clone(),
             encrypted_data,
+            nonce,
             signature,
             expires_at,
+            key_version: keyring.current_version(),
         })
     }
 
+    /// Create an offline transaction and queue it until connectivity returns.
+    /// Refuses new entries once the configured number of unsynced transactions is reached.
+    pub fn queue_offline_transaction(&mut self, transaction: &Transaction, keyring: &SigningKeyring) -> Result<OfflineTransaction> {
+        self.prune_expired_offline();
+        if self.pending_offline.len() >= self.config.max_pending_offline_transactions {
+            return Err(SafeBankError::ResourceLimitExceeded {
+                resource: format!(
+                    "offline transaction queue ({} pending)",
+                    self.pending_offline.len()
+                ),
+            });
+        }
+
+        let offline_tx = self.create_offline_transaction(transaction, keyring)?;
+        self.pending_offline.insert(transaction.transaction_id, offline_tx.clone());
+        Ok(offline_tx)
+    }
+
+    /// Number of queued offline transactions awaiting sync
+    pub fn pending_offline_count(&self) -> usize {
+        self.pending_offline.len()
+    }
+
+    /// Drop queued offline transactions past their expiry, which could never be applied;
+    /// returns how many were dropped
+    pub fn prune_expired_offline(&mut self) -> usize {
+        let now = self.clock.now();
+        let before = self.pending_offline.len();
+        self.pending_offline.retain(|_, offline_tx| offline_tx.expires_at >= now);
+        before - self.pending_offline.len()
+    }
+
     /// Process offline transaction when connectivity is restored
-    pub fn process_offline_transaction(&mut self, offline_tx: &OfflineTransaction, secret_key: &str) -> Result<Transaction> {
+    pub fn process_offline_transaction(&mut self, offline_tx: &OfflineTransaction, keyring: &SigningKeyring) -> Result<Transaction> {
+        let transaction = self.open_offline_transaction(offline_tx, keyring)?;
+        let transaction = self.process_transaction(transaction)?;
+        self.pending_offline.remove(&transaction.transaction_id);
+        Ok(transaction)
+    }
+
+    /// Apply a batch of offline transactions after reconnecting.
+    /// Transactions are evaluated in the order they were made, so daily limits apply
+    /// as of the day each was made rather than the day they arrive.
+    pub fn sync_offline_batch(&mut self, batch: Vec<OfflineTransaction>, keyring: &SigningKeyring) -> SyncReport {
+        self.sync_offline_batch_with(batch, keyring, |_| Ok(()))
+    }
+
+    /// `sync_offline_batch` that hands each authenticated transaction to `screen` before it is applied,
+    /// so the caller can score it and set its status; an error from `screen` rejects that transaction
+    pub fn sync_offline_batch_with(&mut self, mut batch: Vec<OfflineTransaction>, keyring: &SigningKeyring, mut screen: impl FnMut(&mut Transaction) -> Result<()>) -> SyncReport {
+        self.prune_expired_offline();
+        batch.sort_by_key(|offline_tx| offline_tx.transaction.timestamp);
+
+        let mut report = SyncReport::default();
+        let mut seen = HashSet::new();
+        for offline_tx in &batch {
+            let transaction_id = offline_tx.transaction.transaction_id;
+            let already_stored = matches!(self.storage.load_transaction(transaction_id), Ok(Some(_)));
+            if !seen.insert(transaction_id) || already_stored {
+                report.record(transaction_id, SyncStatus::Duplicate, None);
+                continue;
+            }
+
+            match self.apply_offline_transaction(offline_tx, keyring, &mut screen) {
+                Ok(_) => report.record(transaction_id, SyncStatus::Applied, None),
+                Err(SafeBankError::TimeoutError { operation }) => {
+                    report.record(transaction_id, SyncStatus::Expired, Some(operation));
+                }
+                Err(e) => report.record(transaction_id, SyncStatus::Rejected, Some(e.to_string())),
+            }
+            self.pending_offline.remove(&transaction_id);
+        }
+        report
+    }
+
+    /// Process one synced offline transaction, checking the daily limit for the day it was made
+    fn apply_offline_transaction(&mut self, offline_tx: &OfflineTransaction, keyring: &SigningKeyring, screen: &mut impl FnMut(&mut Transaction) -> Result<()>) -> Result<Transaction> {
+        let mut transaction = self.open_offline_transaction(offline_tx, keyring)?;
+        if let Some(key) = &transaction.idempotency_key {
+            if let Some(existing) = self.find_by_idempotency_key(transaction.user_id, key)? {
+                return Ok(existing);
+            }
+        }
+
+        self.prepare_transaction(&mut transaction)?;
+        screen(&mut transaction)?;
+
+        let day = self.config.banking_day(transaction.timestamp);
+        let usage = if day == self.config.banking_day(self.clock.now()) {
+            self.daily_limit_state(transaction.user_id)
+        } else {
+            self.usage_on(transaction.user_id, day, transaction.timestamp)?
+        };
+        self.check_daily_limit(&usage, &[&transaction])?;
+
+        if transaction.status == TransactionStatus::Approved {
+            self.check_funds(transaction.user_id, self.balance_delta(&transaction))?;
+        }
+        let payment_total = transaction.amount;
+        self.commit_transaction(transaction, payment_total)
+    }
+
+    /// Check expiry and integrity of an offline transaction and return its authenticated contents
+    fn open_offline_transaction(&self, offline_tx: &OfflineTransaction, keyring: &SigningKeyring) -> Result<Transaction> {
         // Check if transaction has expired
-        if Utc::now() > offline_tx.expires_at {
+        if self.clock.now() > offline_tx.expires_at {
             return Err(SafeBankError::TimeoutError {
                 operation: "Offline transaction expired".to_string(),
             });
         }
 
-        // Verify signature
+        // The stored nonce must be the one the ciphertext was sealed with
+        if !offline_tx.nonce.is_empty() && !offline_tx.encrypted_data.starts_with(&offline_tx.nonce) {
+            return Err(SafeBankError::CryptographyError {
+                message: "Nonce does not match encrypted data".to_string(),
+            });
+        }
+
+        // Decrypting verifies the GCM tag; the signature must cover both the decrypted
+        // copy and the plaintext body carried alongside it
+        let secret_key = keyring.key(offline_tx.key_version)?;
         let decrypted_data = self.decrypt_data(&offline_tx.encrypted_data, secret_key)?;
-        let expected_signature = self.generate_signature(&decrypted_data, secret_key);
-        
-        if offline_tx.signature != expected_signature {
+        let carried_data = serde_json::to_string(&offline_tx.transaction)
+            .map_err(|e| SafeBankError::SerializationError {
+                message: format!("Failed to serialize transaction: {}", e),
+            })?;
+
+        if !self.verify_signature(&decrypted_data, secret_key, &offline_tx.signature)
+            || !self.verify_signature(&carried_data, secret_key, &offline_tx.signature)
+        {
             return Err(SafeBankError::CryptographyError {
                 message: "Invalid transaction signature".to_string(),
             });
         }
 
-        // Process the transaction normally
-        self.process_transaction(offline_tx.transaction.clone())
+        // Process the authenticated copy, not the plaintext carried alongside it
+        serde_json::from_str(&decrypted_data)
+            .map_err(|e| SafeBankError::SerializationError {
+                message: format!("Failed to deserialize offline transaction: {}", e),
+            })
     }
 
     /// Get transaction statistics for monitoring
-    pub fn get_transaction_statistics(&self) -> HashMap<String, f64> {
+    /// Transaction counts and volumes from the running totals, without rescanning history
+    pub fn get_transaction_statistics(&self) -> Result<HashMap<String, f64>> {
+        let counters = self.system.with_transaction_stats(TransactionStats::default, |stats| stats.clone());
+        let total_volume = self.config.from_money(counters.volume);
         let mut stats = HashMap::new();
         
-        stats.insert("total_transactions".to_string(), self.transactions.len() as f64);
-        
-        let mut approved = 0;
-        let mut rejected = 0;
-        let mut flagged = 0;
-        let mut total_volume = 0.0;
-        
-        for transaction in self.transactions.values() {
-            match transaction.status {
-                TransactionStatus::Approved => approved += 1,
-                TransactionStatus::Rejected => rejected += 1,
-                TransactionStatus::Flagged | TransactionStatus::RequiresApproval => flagged += 1,
-                _ => {}
-            }
-            total_volume += transaction.amount;
-        }
-        
-        stats.insert("approved_count".to_string(), approved as f64);
-        stats.insert("rejected_count".to_string(), rejected as f64);
-        stats.insert("flagged_count".to_string(), flagged as f64);
+        stats.insert("total_transactions".to_string(), counters.total as f64);
+        stats.insert("approved_count".to_string(), counters.approved as f64);
+        stats.insert("rejected_count".to_string(), counters.rejected as f64);
+        stats.insert("flagged_count".to_string(), counters.flagged as f64);
         stats.insert("total_volume".to_string(), total_volume);
+
+        // Per-category breakdown, e.g. "category_volume.school_fees"
+        for (category, (count, volume)) in counters.categories.iter().filter(|(_, (count, _))| *count > 0) {
+            stats.insert(format!("category_volume.{}", category), self.config.from_money(*volume));
+            stats.insert(format!("category_count.{}", category), *count as f64);
+        }
         
-        if self.transactions.len() > 0 {
-            let approval_rate = (approved as f64) / (self.transactions.len() as f64) * 100.0;
+        if counters.total > 0 {
+            let approval_rate = (counters.approved as f64) / (counters.total as f64) * 100.0;
             stats.insert("approval_rate_percent".to_string(), approval_rate);
             
-            let average_amount = total_volume / (self.transactions.len() as f64);
+            let average_amount = total_volume / (counters.total as f64);
             stats.insert("average_transaction_amount".to_string(), average_amount);
         }
         
-        stats
+        Ok(stats)
     }
 
-    /// Check if user has exceeded daily transaction limits
-    fn check_daily_limit(&self, transaction: &Transaction) -> Result<()> {
-        if let Some(daily_limit) = self.daily_limits.get(&transaction.user_id) {
-            let today = Utc::now().date_naive();
-            let limit_date = daily_limit.date.date_naive();
-            
-            if today == limit_date {
-                let projected_total = daily_limit.total_amount + transaction.amount;
-                if projected_total > self.config.daily_transaction_limit {
-                    return Err(SafeBankError::TransactionLimitExceeded {
-                        amount: projected_total,
-                        limit: self.config.daily_transaction_limit,
-                    });
-                }
+    /// A user's daily limit: the configured limit, raised by up to `LIMIT_STEP` for each month the
+    /// account has been open and for each `CLEAN_TRANSACTIONS_PER_STEP` approved transactions, capped at
+    /// the trusted ceiling. Only accounts with a device the bank trusted qualify, and confirmed fraud restarts the count.
+    pub fn effective_daily_limit(&self, user_id: Uuid) -> f64 {
+        let base = self.config.daily_transaction_limit;
+        let Some(ceiling) = self.config.trusted_daily_limit_ceiling else {
+            return base;
+        };
+        // Unknown users and storage failures get the base limit, never a raised one
+        let Ok(Some(user)) = self.storage.load_user_by_id(user_id) else {
+            return base;
+        };
+        // Only trust the bank granted counts; the client's own `is_trusted` claim doesn't
+        if !user.devices.iter().any(|device| device.trusted_at.is_some()) {
+            return base;
+        }
+
+        let since = clean_history_since(&user);
+        let months = ((self.clock.now() - since).num_days().max(0) / 30).min(MAX_AGE_STEPS);
+        let clean = clean_transaction_count(self.storage.as_ref(), &user);
+        let history = (clean / CLEAN_TRANSACTIONS_PER_STEP).min(MAX_HISTORY_STEPS) as i64;
+
+        (base * (1.0 + LIMIT_STEP * (months + history) as f64)).min(ceiling)
+    }
+
+    /// Check whether additional transactions would exceed the daily amount, count or per-recipient caps
+    fn check_daily_limit(&self, usage: &DailyLimit, transactions: &[&Transaction]) -> Result<()> {
+        let added: Money = transactions.iter().map(|t| self.limit_amount(t)).sum();
+        let projected_total = usage.total_amount + added;
+        let limit = self.effective_daily_limit(usage.user_id);
+        if projected_total > self.config.to_money(limit) {
+            return Err(SafeBankError::TransactionLimitExceeded {
+                amount: self.config.from_money(projected_total),
+                limit,
+            });
+        }
+
+        if let Some(limit) = self.config.daily_transaction_count_limit {
+            let count = usage.transaction_count + transactions.len() as u32;
+            if count > limit {
+                return Err(SafeBankError::TransactionCountLimitExceeded { count, limit });
+            }
+        }
+
+        if let Some(limit) = self.config.daily_per_recipient_limit {
+            let mut projected = usage.recipient_totals.clone();
+            for transaction in transactions {
+                *projected.entry(transaction.recipient.clone()).or_insert(Money::ZERO) += self.config.to_money(transaction.amount);
+            }
+            if let Some(total) = projected.values().copied().find(|total| *total > self.config.to_money(limit)) {
+                return Err(SafeBankError::TransactionLimitExceeded {
+                    amount: self.config.from_money(total),
+                    limit,
+                });
             }
         }
+
         Ok(())
     }
 
-    /// Update daily transaction limits for user
-    fn update_daily_limit(&mut self, transaction: &Transaction) -> Result<()> {
-        let today = Utc::now().date_naive();
-        
-        if let Some(daily_limit) = self.daily_limits.get_mut(&transaction.user_id) {
-            let limit_date = daily_limit.date.date_naive();
-            
-            if today == limit_date {
-                // Same day, update existing limit
-                daily_limit.total_amount += transaction.amount;
-                daily_limit.transaction_count += 1;
-            } else {
-                // New day, reset limit
-                daily_limit.date = Utc::now();
-                daily_limit.total_amount = transaction.amount;
-                daily_limit.transaction_count = 1;
+    fn remember_idempotency_key(&mut self, transaction: &Transaction) {
+        if let Some(key) = &transaction.idempotency_key {
+            self.idempotency_keys.insert((transaction.user_id, key.clone()), transaction.transaction_id);
+        }
+    }
+
+    /// Signed balance movement for a transaction: deposits credit the net amount,
+    /// everything else debits the amount plus fee and levy
+    fn balance_delta(&self, transaction: &Transaction) -> Money {
+        let amount = self.config.to_money(transaction.amount);
+        let charges = self.config.to_money(transaction.fee) + self.config.to_money(transaction.levy);
+        match transaction.transaction_type {
+            TransactionType::Deposit => amount - charges,
+            TransactionType::Transfer | TransactionType::Payment | TransactionType::Withdrawal => {
+                -(amount + charges)
             }
-        } else {
-            // First transaction for this user
-            self.daily_limits.insert(transaction.user_id, DailyLimit {
-                user_id: transaction.user_id,
-                date: Utc::now(),
-                total_amount: transaction.amount,
-                transaction_count: 1,
+        }
+    }
+
+    /// Ensure a balance movement would not leave the account negative
+    fn check_funds(&self, user_id: Uuid, delta: Money) -> Result<()> {
+        let balance = self.balance_money(user_id)?;
+        if (balance + delta).is_negative() {
+            return Err(SafeBankError::InsufficientFunds {
+                balance: self.config.from_money(balance),
+                required: self.config.from_money(-delta),
             });
         }
         Ok(())
     }
 
+    /// Record on a transaction about to move money the balance it will leave
+    fn stamp_balance(&self, transaction: &mut Transaction) -> Result<()> {
+        let balance = self.balance_money(transaction.user_id)? + self.balance_delta(transaction);
+        transaction.balance_after = Some(self.config.from_money(balance));
+        Ok(())
+    }
+
+    /// Move the balance for a committed, approved transaction
+    fn apply_balance(&mut self, transaction: &Transaction) -> Result<()> {
+        let balance = self.balance_money(transaction.user_id)? + self.balance_delta(transaction);
+        self.storage.save_balance(transaction.user_id, self.config.from_money(balance))
+    }
+
+    /// Update daily transaction limits for user
+    fn update_daily_limit(&mut self, transaction: &Transaction) {
+        let now = self.clock.now();
+        let today = self.config.banking_day(now);
+        let amount = self.config.to_money(transaction.amount);
+        let counted = self.limit_amount(transaction);
+        
+        let daily_limit = self.daily_limits
+            .entry(transaction.user_id)
+            .or_insert_with(|| DailyLimit::empty(transaction.user_id, now));
+        if self.config.banking_day(daily_limit.date) != today {
+            // New day, reset limit
+            *daily_limit = DailyLimit::empty(transaction.user_id, now);
+        }
+        daily_limit.record(&transaction.recipient, amount, counted);
+    }
+
+    /// What a transaction counts toward the daily limit: its amount, plus the fee if so configured
+    fn limit_amount(&self, transaction: &Transaction) -> Money {
+        let amount = self.config.to_money(transaction.amount);
+        if self.config.fees_count_toward_daily_limit {
+            amount + self.config.to_money(transaction.fee)
+        } else {
+            amount
+        }
+    }
+
+    /// Add to the system-wide volume for today, resetting on a new day
+    fn record_system_volume(&self, amount: f64) {
+        self.system.add_daily_volume(self.config.banking_day(self.clock.now()), self.config.to_money(amount));
+    }
+
     /// Validate transaction status transitions
     fn validate_transaction_status(&self, transaction: &Transaction) -> Result<()> {
         // Basic validation - can be extended for more complex state machines
@@ -331,25 +1665,119 @@ impl TransactionManager {
                 // Terminal states - should not be changed
                 Ok(())
             }
-            TransactionStatus::Pending | TransactionStatus::Flagged | TransactionStatus::RequiresApproval => {
+            TransactionStatus::Pending | TransactionStatus::Flagged | TransactionStatus::RequiresApproval | TransactionStatus::RequiresStepUp => {
                 // Valid intermediate states
                 Ok(())
             }
         }
     }
 
-    /// Generate transaction hash for integrity verification
-    fn generate_transaction_hash(&self, transaction: &Transaction) -> String {
+    /// Hash over every field fixed at commit, linked to the previous hash.
+    /// Each field is length-prefixed so content can't shift from one field into the next.
+    fn generate_transaction_hash(&self, transaction: &Transaction, prev_hash: Option<&str>) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(transaction.transaction_id.as_bytes());
-        hasher.update(transaction.user_id.as_bytes());
-        hasher.update(transaction.amount.to_string().as_bytes());
-        hasher.update(transaction.recipient.as_bytes());
-        hasher.update(transaction.timestamp.timestamp().to_string().as_bytes());
-        
+        hash_optional(&mut hasher, prev_hash);
+        hash_field(&mut hasher, transaction.transaction_id.as_bytes());
+        hash_field(&mut hasher, transaction.user_id.as_bytes());
+        hash_field(&mut hasher, transaction.amount.to_string().as_bytes());
+        hash_field(&mut hasher, transaction.fee.to_string().as_bytes());
+        hash_field(&mut hasher, transaction.levy.to_string().as_bytes());
+        hash_field(&mut hasher, transaction.fx_fee.to_string().as_bytes());
+        hash_field(&mut hasher, transaction.transaction_type.as_str().as_bytes());
+        hash_field(&mut hasher, transaction.recipient.as_bytes());
+        hash_field(&mut hasher, transaction.timestamp.to_rfc3339().as_bytes());
+        hash_optional(&mut hasher, transaction.location.as_deref());
+        hash_optional(&mut hasher, transaction.geo_location.as_ref()
+            .map(|place| format!("{},{},{}", place.lat, place.lon, place.label)).as_deref());
+        hash_field(&mut hasher, transaction.device_id.as_bytes());
+        hash_field(&mut hasher, transaction.fraud_score.to_string().as_bytes());
+        hash_optional(&mut hasher, transaction.category.as_ref().map(|category| format!("{:?}", category)).as_deref());
+        hash_optional(&mut hasher, transaction.group_id.map(|id| id.to_string()).as_deref());
+        hash_optional(&mut hasher, transaction.reverses.map(|id| id.to_string()).as_deref());
+        hash_optional(&mut hasher, transaction.idempotency_key.as_deref());
+        hash_optional(&mut hasher, transaction.memo.as_deref());
+
+        hex::encode(hasher.finalize())
+    }
+
+    /// Hash over a chained transaction's hash and its mutable state: status, status history,
+    /// balance stamp and reversal link. Approval and reversal renew it; editing them in storage breaks it.
+    fn generate_state_hash(transaction: &Transaction) -> String {
+        let mut hasher = Sha256::new();
+        hash_optional(&mut hasher, transaction.hash.as_deref());
+        hash_field(&mut hasher, format!("{:?}", transaction.status).as_bytes());
+        hash_optional(&mut hasher, transaction.balance_after.map(|balance| balance.to_string()).as_deref());
+        hash_optional(&mut hasher, transaction.reversed_by.map(|id| id.to_string()).as_deref());
+        hash_field(&mut hasher, &(transaction.status_history.len() as u64).to_le_bytes());
+        for change in &transaction.status_history {
+            hash_field(&mut hasher, format!("{:?}", change.from).as_bytes());
+            hash_field(&mut hasher, format!("{:?}", change.to).as_bytes());
+            hash_field(&mut hasher, change.at.to_rfc3339().as_bytes());
+            hash_field(&mut hasher, change.actor.as_bytes());
+            hash_optional(&mut hasher, change.reason.as_deref());
+        }
+
         hex::encode(hasher.finalize())
     }
 
+    /// Link a transaction after the user's current chain head. The head only moves once the
+    /// transaction is stored, since it is always read back from storage.
+    fn append_to_chain(&self, transaction: &mut Transaction) -> Result<()> {
+        let prev_hash = self.chain_head(transaction.user_id)?;
+        transaction.hash = Some(self.generate_transaction_hash(transaction, prev_hash.as_deref()));
+        transaction.prev_hash = prev_hash;
+        Ok(())
+    }
+
+    /// Hash of the user's latest chained transaction: the stored entry nothing links to yet.
+    /// Read from storage every time so instances sharing a backend agree on it.
+    fn chain_head(&self, user_id: Uuid) -> Result<Option<String>> {
+        let transactions = self.storage.load_user_transactions(user_id)?;
+        let linked: HashSet<&str> = transactions.iter().filter_map(|t| t.prev_hash.as_deref()).collect();
+        Ok(transactions.iter()
+            .filter(|t| t.hash.as_deref().is_some_and(|hash| !linked.contains(hash)))
+            .max_by_key(|t| t.timestamp)
+            .and_then(|t| t.hash.clone()))
+    }
+
+    /// Walk the user's hash chain from its first entry, failing on any altered, removed,
+    /// reordered or forked entry. Records from before the chain existed carry no hash and are skipped.
+    pub fn verify_chain(&self, user_id: Uuid) -> Result<()> {
+        let chained: Vec<Transaction> = self.storage.load_user_transactions(user_id)?
+            .into_iter()
+            .filter(|t| t.hash.is_some())
+            .collect();
+        let broken = |transaction_id: Uuid, reason: &str| SafeBankError::CryptographyError {
+            message: format!("Transaction chain broken at {}: {}", transaction_id, reason),
+        };
+
+        let mut successors: HashMap<Option<&str>, &Transaction> = HashMap::new();
+        for transaction in &chained {
+            if successors.insert(transaction.prev_hash.as_deref(), transaction).is_some() {
+                return Err(broken(transaction.transaction_id, "two entries share a predecessor"));
+            }
+        }
+
+        let mut prev_hash: Option<&str> = None;
+        let mut visited = HashSet::new();
+        while let Some(transaction) = successors.get(&prev_hash) {
+            let expected = self.generate_transaction_hash(transaction, prev_hash);
+            if transaction.hash.as_deref() != Some(expected.as_str()) || !visited.insert(transaction.transaction_id) {
+                return Err(broken(transaction.transaction_id, "contents do not match hash"));
+            }
+            if transaction.state_hash.as_deref() != Some(Self::generate_state_hash(transaction).as_str()) {
+                return Err(broken(transaction.transaction_id, "status or balance does not match hash"));
+            }
+            prev_hash = transaction.hash.as_deref();
+        }
+
+        // Anything not reached from the first entry lost its predecessor
+        if let Some(unreached) = chained.iter().find(|t| !visited.contains(&t.transaction_id)) {
+            return Err(broken(unreached.transaction_id, "predecessor is missing or out of order"));
+        }
+        Ok(())
+    }
+
     /// Generate confirmation code for receipts
     fn generate_confirmation_code(&self, transaction: &Transaction) -> String {
         let mut hasher = Sha256::new();
@@ -361,76 +1789,138 @@ impl TransactionManager {
         hash[..8].to_uppercase()
     }
 
-    /// Simple encryption for offline transactions (demo purposes)
-    fn encrypt_data(&self, data: &str, key: &str) -> Result<String> {
-        // In a real implementation, use proper encryption like AES
-        // For demo, we'll use a simple XOR cipher with the key
-        let key_bytes = key.as_bytes();
-        let data_bytes = data.as_bytes();
-        
-        let encrypted: Vec<u8> = data_bytes
-            .iter()
-            .enumerate()
-            .map(|(i, &byte)| byte ^ key_bytes[i % key_bytes.len()])
-            .collect();
-        
-        Ok(hex::encode(encrypted))
+    /// Encrypt offline transaction data with AES-256-GCM under a fresh random nonce.
+    /// Returns the hex nonce and the hex nonce-prefixed ciphertext.
+    fn encrypt_data(&self, data: &str, key: &str) -> Result<(String, String)> {
+        let cipher = Aes256Gcm::new(&self.derive_offline_key(key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let ciphertext = cipher.encrypt(&nonce, data.as_bytes())
+            .map_err(|e| SafeBankError::CryptographyError {
+                message: format!("Failed to encrypt data: {}", e),
+            })?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok((hex::encode(nonce), hex::encode(sealed)))
     }
 
-    /// Simple decryption for offline transactions
+    /// Decrypt nonce-prefixed AES-256-GCM data, failing if the authentication tag doesn't verify
     fn decrypt_data(&self, encrypted_data: &str, key: &str) -> Result<String> {
-        let encrypted_bytes = hex::decode(encrypted_data)
+        let sealed = hex::decode(encrypted_data)
             .map_err(|e| SafeBankError::CryptographyError {
                 message: format!("Failed to decode encrypted data: {}", e),
             })?;
-        
-        let key_bytes = key.as_bytes();
-        
-        let decrypted: Vec<u8> = encrypted_bytes
-            .iter()
-            .enumerate()
-            .map(|(i, &byte)| byte ^ key_bytes[i % key_bytes.len()])
-            .collect();
-        
+
+        if sealed.len() < NONCE_LEN {
+            return Err(SafeBankError::CryptographyError {
+                message: "Encrypted data is too short".to_string(),
+            });
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+        let cipher = Aes256Gcm::new(&self.derive_offline_key(key));
+        let decrypted = cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SafeBankError::CryptographyError {
+                message: "Failed to authenticate encrypted data".to_string(),
+            })?;
+
         String::from_utf8(decrypted)
             .map_err(|e| SafeBankError::CryptographyError {
                 message: format!("Failed to decrypt data: {}", e),
             })
     }
 
-    /// Generate signature for data integrity
+    /// Derive a 256-bit encryption key from the offline secret with HKDF-SHA256
+    fn derive_offline_key(&self, secret_key: &str) -> Key<Aes256Gcm> {
+        let mut key = Key::<Aes256Gcm>::default();
+        Hkdf::<Sha256>::new(None, secret_key.as_bytes())
+            .expand(OFFLINE_KEY_INFO, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        key
+    }
+
+    /// Salted hash of a recipient identifier
+    fn hash_recipient(&self, recipient: &str) -> Result<String> {
+        let salt = self.recipient_salt.as_deref().ok_or_else(|| SafeBankError::ConfigError {
+            message: "Recipient hashing requires a recipient hash salt".to_string(),
+        })?;
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(salt.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(recipient.trim().as_bytes());
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// HMAC-SHA256 signature over the data, keyed by the secret (hex)
     fn generate_signature(&self, data: &str, secret: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(data.as_bytes());
-        hasher.update(secret.as_bytes());
-        hex::encode(hasher.finalize())
+        hex::encode(Self::signature_mac(data, secret).finalize().into_bytes())
+    }
+
+    /// Check a hex signature in constant time
+    fn verify_signature(&self, data: &str, secret: &str, signature: &str) -> bool {
+        hex::decode(signature)
+            .is_ok_and(|signature| Self::signature_mac(data, secret).verify_slice(&signature).is_ok())
+    }
+
+    fn signature_mac(data: &str, secret: &str) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(data.as_bytes());
+        mac
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{config::SafeBankConfig, TransactionType};
+    use std::sync::Arc;
+    use chrono::TimeZone;
+    use crate::{GeoLocation, TransactionCategory, clock::{Clock, MockClock}, config::{FeeSchedule, LevySchedule, RoundingMode, SafeBankConfig}};
+
+    const TEST_USER_ID: Uuid = Uuid::from_u128(1);
+
+    /// Manager whose test account has enough money that balance checks don't get in the way
+    fn funded_manager(config: &SafeBankConfig) -> TransactionManager {
+        let mut manager = TransactionManager::new(config);
+        manager.adjust_balance(TEST_USER_ID, 100_000.0).unwrap();
+        manager
+    }
 
     fn create_test_transaction() -> Transaction {
         Transaction {
             transaction_id: Uuid::new_v4(),
-            user_id: Uuid::new_v4(),
+            user_id: TEST_USER_ID,
             amount: 100.0,
             recipient: "Test Recipient".to_string(),
             transaction_type: TransactionType::Transfer,
             timestamp: Utc::now(),
             location: None,
+            geo_location: None,
             device_id: "test-device".to_string(),
             fraud_score: 0.3,
             status: TransactionStatus::Approved,
+            fee: 0.0,
+            levy: 0.0,
+            fx_fee: 0.0,
+            group_id: None,
+            risk_factors: Vec::new(),
+            idempotency_key: None,
+            reverses: None,
+            reversed_by: None,
+            category: None,
+            prev_hash: None,
+            hash: None,
+            state_hash: None,
+            status_history: Vec::new(),
+            balance_after: None,
+            memo: None,
         }
     }
 
     #[test]
     fn test_transaction_processing() {
         let config = SafeBankConfig::default();
-        let mut manager = TransactionManager::new(&config);
+        let mut manager = funded_manager(&config);
         
         let transaction = create_test_transaction();
         let result = manager.process_transaction(transaction.clone());
@@ -444,11 +1934,13 @@ mod tests {
 
     #[test]
     fn test_daily_limit_check() {
-        let mut config = SafeBankConfig::default();
-        config.daily_transaction_limit = 1000.0;
+        let config = SafeBankConfig {
+            daily_transaction_limit: 1000.0,
+            ..SafeBankConfig::default()
+        };
         
-        let mut manager = TransactionManager::new(&config);
-        let user_id = Uuid::new_v4();
+        let mut manager = funded_manager(&config);
+        let user_id = TEST_USER_ID;
         
         // First transaction
         let mut transaction1 = create_test_transaction();
@@ -471,7 +1963,7 @@ mod tests {
     #[test]
     fn test_transaction_receipt() {
         let config = SafeBankConfig::default();
-        let manager = TransactionManager::new(&config);
+        let manager = funded_manager(&config);
         
         let transaction = create_test_transaction();
         let receipt = manager.create_receipt(&transaction);
@@ -484,12 +1976,12 @@ mod tests {
     #[test]
     fn test_offline_transaction() {
         let config = SafeBankConfig::default();
-        let manager = TransactionManager::new(&config);
+        let manager = funded_manager(&config);
         
         let transaction = create_test_transaction();
-        let secret_key = "test_secret_key";
+        let keyring = SigningKeyring::new(1, "test_secret_key");
         
-        let offline_tx = manager.create_offline_transaction(&transaction, secret_key);
+        let offline_tx = manager.create_offline_transaction(&transaction, &keyring);
         assert!(offline_tx.is_ok());
         
         let offline_tx = offline_tx.unwrap();
@@ -498,9 +1990,519 @@ mod tests {
     }
 
     #[test]
-    fn test_transaction_statistics() {
+    fn test_offline_transaction_tamper_detection() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+        let transaction = create_test_transaction();
+        let keyring = SigningKeyring::new(1, "device-key");
+
+        let offline_tx = manager.create_offline_transaction(&transaction, &keyring).unwrap();
+        assert_eq!(offline_tx.nonce.len(), NONCE_LEN * 2);
+        assert!(offline_tx.encrypted_data.starts_with(&offline_tx.nonce));
+
+        // Nonces are fresh per encryption
+        let again = manager.create_offline_transaction(&transaction, &keyring).unwrap();
+        assert_ne!(offline_tx.nonce, again.nonce);
+
+        // Flipping a ciphertext byte breaks the GCM tag
+        let mut tampered = offline_tx.clone();
+        let mut bytes = hex::decode(&tampered.encrypted_data).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        tampered.encrypted_data = hex::encode(bytes);
+        assert!(matches!(
+            manager.process_offline_transaction(&tampered, &keyring),
+            Err(SafeBankError::CryptographyError { .. })
+        ));
+
+        // A wrong key fails the same way
+        assert!(matches!(
+            manager.process_offline_transaction(&offline_tx, &SigningKeyring::new(1, "other-key")),
+            Err(SafeBankError::CryptographyError { .. })
+        ));
+
+        // Altering the plaintext body or the signature fails signature verification
+        let mut altered = offline_tx.clone();
+        altered.transaction.amount = 9_999.0;
+        assert!(matches!(
+            manager.process_offline_transaction(&altered, &keyring),
+            Err(SafeBankError::CryptographyError { .. })
+        ));
+        let mut forged = offline_tx.clone();
+        forged.signature = manager.generate_signature("forged", "device-key");
+        assert!(matches!(
+            manager.process_offline_transaction(&forged, &keyring),
+            Err(SafeBankError::CryptographyError { .. })
+        ));
+
+        assert!(manager.process_offline_transaction(&offline_tx, &keyring).is_ok());
+    }
+
+    #[test]
+    fn test_offline_transactions_verify_across_key_rotation() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+
+        let mut keyring = SigningKeyring::new(0, "key-v0");
+        let signed_v0 = manager.create_offline_transaction(&create_test_transaction(), &keyring).unwrap();
+        keyring.rotate(1, "key-v1");
+        let signed_v1 = manager.create_offline_transaction(&create_test_transaction(), &keyring).unwrap();
+        assert_eq!((signed_v0.key_version, signed_v1.key_version), (0, 1));
+
+        // v2 becomes current and v0 is retired; v1 transactions still verify during the rotation window
+        keyring.rotate(2, "key-v2");
+        keyring.retire(0).unwrap();
+        assert!(keyring.retire(2).is_err());
+        assert_eq!(manager.create_offline_transaction(&create_test_transaction(), &keyring).unwrap().key_version, 2);
+        assert!(manager.process_offline_transaction(&signed_v1, &keyring).is_ok());
+        match manager.process_offline_transaction(&signed_v0, &keyring) {
+            Err(SafeBankError::CryptographyError { message }) => assert!(message.contains("v0")),
+            other => panic!("retired key accepted: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_signature_is_hmac() {
+        let manager = TransactionManager::new(&SafeBankConfig::default());
+        let signature = manager.generate_signature("payload", "secret");
+        assert_eq!(signature.len(), 64);
+        assert!(manager.verify_signature("payload", "secret", &signature));
+        assert!(!manager.verify_signature("payload", "other-secret", &signature));
+        assert!(!manager.verify_signature("payload!", "secret", &signature));
+        assert!(!manager.verify_signature("payload", "secret", "not-hex"));
+    }
+
+    #[test]
+    fn test_fee_and_levy_are_itemized() {
+        let config = SafeBankConfig {
+            levy_schedule: Some(LevySchedule {
+                rate: 0.015,
+                flat_amount: 0.0,
+                exempt_up_to: 100.0,
+                applies_to: vec![TransactionType::Transfer],
+            }),
+            ..SafeBankConfig::default()
+        };
+        let mut manager = funded_manager(&config);
+
+        let mut transaction = create_test_transaction();
+        transaction.amount = 1000.0;
+        let processed = manager.process_transaction(transaction).unwrap();
+
+        assert_eq!(processed.fee, 10.0); // 1% domestic transfer fee
+        assert_eq!(processed.levy, 15.0); // 1.5% levy
+
+        let receipt = manager.create_receipt(&processed);
+        assert_eq!(receipt.fee, 10.0);
+        assert_eq!(receipt.levy, 15.0);
+
+        // Exempt amounts carry a fee but no levy
+        let small = manager.process_transaction(create_test_transaction()).unwrap();
+        assert_eq!(small.levy, 0.0);
+        assert!(small.fee > 0.0);
+    }
+
+    #[test]
+    fn test_configured_fee_schedule_is_charged() {
+        let config = SafeBankConfig {
+            fee_schedule: FeeSchedule { transfer_bps: 250, min_fee: 1.0, max_fee: 20.0, ..FeeSchedule::default() },
+            fees_count_toward_daily_limit: true,
+            daily_transaction_limit: 1020.0,
+            ..SafeBankConfig::default()
+        };
+        let mut manager = funded_manager(&config);
+        let balance = manager.get_balance(TEST_USER_ID).unwrap();
+
+        let mut transaction = create_test_transaction();
+        transaction.amount = 400.0;
+        let processed = manager.process_transaction(transaction).unwrap();
+        assert_eq!(processed.fee, 10.0); // 2.5% of 400
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), balance - 410.0);
+        assert_eq!(manager.create_receipt(&processed).fee, 10.0);
+        assert_eq!(manager.daily_usage(TEST_USER_ID), 410.0);
+
+        // 1000 in amounts fits the limit, but the fees push it over
+        let mut transaction = create_test_transaction();
+        transaction.amount = 600.0;
+        assert!(matches!(
+            manager.process_transaction(transaction),
+            Err(SafeBankError::TransactionLimitExceeded { amount, .. }) if amount == 1025.0
+        ));
+    }
+
+    #[test]
+    fn test_amount_rounding_follows_configured_mode() {
+        let half_up = SafeBankConfig::default();
+        let bankers = SafeBankConfig {
+            rounding_mode: RoundingMode::HalfEven,
+            ..SafeBankConfig::default()
+        };
+
+        let mut transaction = create_test_transaction();
+        transaction.amount = 10.125;
+
+        let rounded_up = funded_manager(&half_up).process_transaction(transaction.clone()).unwrap();
+        let rounded_even = funded_manager(&bankers).process_transaction(transaction).unwrap();
+
+        assert_eq!(rounded_up.amount, 10.13);
+        assert_eq!(rounded_even.amount, 10.12);
+    }
+
+    #[test]
+    fn test_balance_moves_only_for_approved_transactions() {
         let config = SafeBankConfig::default();
         let mut manager = TransactionManager::new(&config);
+
+        // Nothing to spend yet
+        let result = manager.process_transaction(create_test_transaction());
+        assert!(matches!(result, Err(SafeBankError::InsufficientFunds { balance, .. }) if balance == 0.0));
+
+        let mut deposit = create_test_transaction();
+        deposit.transaction_type = TransactionType::Deposit;
+        deposit.amount = 500.0;
+        manager.process_transaction(deposit).unwrap();
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), 500.0); // deposits are free
+
+        let payment = manager.process_transaction(create_test_transaction()).unwrap();
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), 500.0 - payment.amount - payment.fee);
+
+        // Held transactions don't move money until approved
+        let mut held = create_test_transaction();
+        held.status = TransactionStatus::RequiresApproval;
+        let held = manager.process_transaction(held).unwrap();
+        let before = manager.get_balance(TEST_USER_ID).unwrap();
+        let mut rejected = create_test_transaction();
+        rejected.status = TransactionStatus::Rejected;
+        manager.process_transaction(rejected).unwrap();
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), before);
+
+        manager.approve_transaction(held.transaction_id, "reviewer").unwrap();
+        assert!(manager.get_balance(TEST_USER_ID).unwrap() < before);
+    }
+
+    #[test]
+    fn test_status_history_records_review_timeline() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+
+        let mut held = create_test_transaction();
+        held.status = TransactionStatus::RequiresApproval;
+        let held = manager.process_transaction(held).unwrap();
+        manager.approve_transaction(held.transaction_id, "reviewer-7").unwrap();
+
+        let history = manager.get_transaction(held.transaction_id).unwrap().status_history;
+        let steps: Vec<(TransactionStatus, TransactionStatus, &str)> = history.iter()
+            .map(|change| (change.from.clone(), change.to.clone(), change.actor.as_str()))
+            .collect();
+        assert_eq!(steps, vec![
+            (TransactionStatus::Pending, TransactionStatus::RequiresApproval, SYSTEM_ACTOR),
+            (TransactionStatus::RequiresApproval, TransactionStatus::Approved, "reviewer-7"),
+        ]);
+        assert!(history[0].at <= history[1].at);
+
+        let reversal = manager.reverse_transaction(held.transaction_id, "Customer dispute".to_string()).unwrap();
+        assert_eq!(reversal.status_history.len(), 1);
+        let original = manager.get_transaction(held.transaction_id).unwrap();
+        assert_eq!(original.status_history.last().unwrap().reason.as_deref(), Some("Reversed: Customer dispute"));
+    }
+
+    #[test]
+    fn test_step_up_hold_is_released_only_by_the_customer() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+
+        let mut held = create_test_transaction();
+        held.status = TransactionStatus::RequiresStepUp;
+        let held = manager.process_transaction(held).unwrap();
+        let before = manager.get_balance(TEST_USER_ID).unwrap();
+
+        assert!(matches!(
+            manager.approve_transaction(held.transaction_id, "reviewer-7"),
+            Err(SafeBankError::InvalidTransactionState { .. })
+        ));
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), before);
+
+        let confirmed = manager.confirm_step_up(held.transaction_id).unwrap();
+        assert_eq!(confirmed.status, TransactionStatus::Approved);
+        assert_eq!(confirmed.status_history.last().unwrap().actor, "customer");
+        assert!(manager.get_balance(TEST_USER_ID).unwrap() < before);
+        assert!(manager.confirm_step_up(held.transaction_id).is_err());
+    }
+
+    #[test]
+    fn test_statement_running_balance_matches_balance() {
+        let config = SafeBankConfig::default();
+        let clock = MockClock::new(Utc::now());
+        let mut manager = TransactionManager::with_clock(&config, Arc::new(clock.clone()));
+        manager.adjust_balance(TEST_USER_ID, 1000.0).unwrap();
+        let at = |clock: &MockClock, status: TransactionStatus| {
+            clock.advance(Duration::minutes(1));
+            Transaction { timestamp: clock.now(), status, ..create_test_transaction() }
+        };
+
+        let paid = manager.process_transaction(at(&clock, TransactionStatus::Approved)).unwrap();
+        let held = manager.process_transaction(at(&clock, TransactionStatus::RequiresApproval)).unwrap();
+        let rejected = manager.process_transaction(at(&clock, TransactionStatus::RequiresApproval)).unwrap();
+        manager.process_transaction(at(&clock, TransactionStatus::Approved)).unwrap();
+        clock.advance(Duration::minutes(1));
+        manager.reject_transaction(rejected.transaction_id, "reviewer", "Suspicious".to_string()).unwrap();
+        manager.approve_transaction(held.transaction_id, "reviewer").unwrap();
+        manager.reverse_transaction(paid.transaction_id, "Sent in error".to_string()).unwrap();
+
+        let statement = manager.get_statement(TEST_USER_ID).unwrap();
+        assert_eq!(statement.len(), 5);
+        assert!(statement.windows(2).all(|pair| pair[0].posted_at <= pair[1].posted_at));
+        // Held transactions post when approved, after later ones that were approved straight away
+        assert_eq!(statement[3].transaction_id, held.transaction_id);
+
+        let rejected_line = statement.iter().find(|line| line.transaction_id == rejected.transaction_id).unwrap();
+        assert_eq!(rejected_line.balance_change, 0.0);
+        let mut running = 1000.0;
+        for line in &statement {
+            running += line.balance_change;
+            assert!((line.balance_after - running).abs() < 1e-9);
+        }
+        assert_eq!(statement.last().unwrap().balance_after, manager.get_balance(TEST_USER_ID).unwrap());
+    }
+
+    #[test]
+    fn test_query_transactions_filters_and_pages() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+        let start = Utc::now();
+
+        for (minutes, amount, kind) in [
+            (0, 50.0, TransactionType::Payment),
+            (1, 150.0, TransactionType::Transfer),
+            (2, 250.0, TransactionType::Transfer),
+            (3, 350.0, TransactionType::Transfer),
+            (4, 450.0, TransactionType::Payment),
+        ] {
+            let mut transaction = create_test_transaction();
+            transaction.amount = amount;
+            transaction.transaction_type = kind;
+            transaction.timestamp = start + Duration::minutes(minutes);
+            manager.process_transaction(transaction).unwrap();
+        }
+
+        let transfers = TransactionFilter {
+            transaction_type: Some(TransactionType::Transfer),
+            min_amount: Some(100.0),
+            ..TransactionFilter::default()
+        };
+        let first = manager.query_transactions(transfers.clone(), 0, 2).unwrap();
+        assert_eq!(first.total_count, 3);
+        assert!(first.has_more);
+        assert_eq!(first.transactions.iter().map(|t| t.amount).collect::<Vec<_>>(), vec![350.0, 250.0]);
+
+        let second = manager.query_transactions(transfers, 1, 2).unwrap();
+        assert!(!second.has_more);
+        assert_eq!(second.transactions.len(), 1);
+
+        let window = TransactionFilter {
+            from: Some(start + Duration::minutes(1)),
+            to: Some(start + Duration::minutes(3)),
+            status: Some(TransactionStatus::Approved),
+            ..TransactionFilter::default()
+        };
+        assert_eq!(manager.query_transactions(window, 0, 10).unwrap().total_count, 3);
+    }
+
+    #[test]
+    fn test_reversal_refunds_once_within_window() {
+        let config = SafeBankConfig {
+            reversal_window_hours: 24,
+            ..SafeBankConfig::default()
+        };
+        let clock = MockClock::new(Utc::now());
+        let mut manager = TransactionManager::with_clock(&config, Arc::new(clock.clone()));
+        manager.adjust_balance(TEST_USER_ID, 1000.0).unwrap();
+
+        let mut transaction = create_test_transaction();
+        transaction.timestamp = clock.now();
+        let sent = manager.process_transaction(transaction).unwrap();
+        let after_send = manager.get_balance(TEST_USER_ID).unwrap();
+
+        let reversal = manager.reverse_transaction(sent.transaction_id, "Sent to wrong number".to_string()).unwrap();
+        assert_eq!(reversal.reverses, Some(sent.transaction_id));
+        assert_eq!(manager.get_transaction(sent.transaction_id).unwrap().reversed_by, Some(reversal.transaction_id));
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), after_send + sent.amount);
+
+        assert!(matches!(
+            manager.reverse_transaction(sent.transaction_id, "again".to_string()),
+            Err(SafeBankError::InvalidTransactionState { .. })
+        ));
+
+        // Outside the window nothing can be reversed
+        let mut late = create_test_transaction();
+        late.timestamp = clock.now();
+        let late = manager.process_transaction(late).unwrap();
+        clock.advance(Duration::hours(25));
+        assert!(matches!(
+            manager.reverse_transaction(late.transaction_id, "too late".to_string()),
+            Err(SafeBankError::InvalidTransactionState { .. })
+        ));
+    }
+
+    #[test]
+    fn test_offline_queue_cap() {
+        let config = SafeBankConfig {
+            max_pending_offline_transactions: 2,
+            ..SafeBankConfig::default()
+        };
+        let mut manager = funded_manager(&config);
+        let keyring = SigningKeyring::new(1, "test_secret_key");
+
+        // Staying under the cap succeeds
+        assert!(manager.queue_offline_transaction(&create_test_transaction(), &keyring).is_ok());
+        assert!(manager.queue_offline_transaction(&create_test_transaction(), &keyring).is_ok());
+        assert_eq!(manager.pending_offline_count(), 2);
+
+        // Queueing past the cap is refused
+        let result = manager.queue_offline_transaction(&create_test_transaction(), &keyring);
+        assert!(matches!(result, Err(SafeBankError::ResourceLimitExceeded { .. })));
+        assert_eq!(manager.pending_offline_count(), 2);
+    }
+
+    #[test]
+    fn test_expired_offline_transactions_free_the_queue() {
+        let config = SafeBankConfig {
+            max_pending_offline_transactions: 2,
+            ..SafeBankConfig::default()
+        };
+        let clock = MockClock::new(Utc::now());
+        let mut manager = TransactionManager::with_clock(&config, Arc::new(clock.clone()));
+        let keyring = SigningKeyring::new(1, "test_secret_key");
+        manager.queue_offline_transaction(&create_test_transaction(), &keyring).unwrap();
+        manager.queue_offline_transaction(&create_test_transaction(), &keyring).unwrap();
+
+        // Once the queued ones expire they no longer count against the cap
+        clock.advance(Duration::hours(config.offline_cache_duration_hours as i64 + 1));
+        assert!(manager.queue_offline_transaction(&create_test_transaction(), &keyring).is_ok());
+        assert_eq!(manager.pending_offline_count(), 1);
+        assert_eq!(manager.prune_expired_offline(), 0);
+    }
+
+    #[test]
+    fn test_daily_limit_resets_at_local_midnight() {
+        // Nairobi is UTC+3: 22:30 UTC is already 01:30 the next day locally
+        let config = SafeBankConfig {
+            daily_transaction_limit: 1000.0,
+            single_transaction_limit: 1000.0,
+            timezone_offset_minutes: 180,
+            ..SafeBankConfig::default()
+        };
+        let evening = Utc.with_ymd_and_hms(2024, 5, 1, 20, 0, 0).unwrap(); // 23:00 local, May 1
+        let clock = MockClock::new(evening);
+        let mut manager = TransactionManager::with_clock(&config, Arc::new(clock.clone()));
+        manager.adjust_balance(TEST_USER_ID, 100_000.0).unwrap();
+
+        let at = |timestamp: DateTime<Utc>| Transaction { amount: 800.0, timestamp, ..create_test_transaction() };
+        manager.process_transaction(at(clock.now())).unwrap();
+
+        // Still May 1 locally (23:50) though nearly an hour later: limit applies
+        clock.advance(Duration::minutes(50));
+        assert!(matches!(
+            manager.process_transaction(at(clock.now())),
+            Err(SafeBankError::TransactionLimitExceeded { .. })
+        ));
+
+        // 22:30 UTC is still May 1 in UTC, but May 2 locally: the limit has reset
+        clock.advance(Duration::minutes(100));
+        assert_eq!(clock.now().date_naive(), evening.date_naive());
+        assert_eq!(manager.daily_usage(TEST_USER_ID), 0.0);
+        assert!(manager.process_transaction(at(clock.now())).is_ok());
+    }
+
+    #[test]
+    fn test_sync_offline_batch() {
+        let config = SafeBankConfig {
+            daily_transaction_limit: 1000.0,
+            single_transaction_limit: 1000.0,
+            offline_transaction_limit: 1000.0,
+            ..SafeBankConfig::default()
+        };
+        let clock = MockClock::new(Utc::now());
+        let mut manager = TransactionManager::with_clock(&config, Arc::new(clock.clone()));
+        manager.adjust_balance(TEST_USER_ID, 100_000.0).unwrap();
+        let keyring = SigningKeyring::new(1, "device-key");
+
+        let offline = |amount: f64, timestamp: DateTime<Utc>| {
+            let transaction = Transaction { amount, timestamp, ..create_test_transaction() };
+            manager.create_offline_transaction(&transaction, &keyring).unwrap()
+        };
+        let yesterday = clock.now() - Duration::days(1);
+        let first_yesterday = offline(600.0, yesterday);
+        let second_yesterday = offline(600.0, yesterday + Duration::hours(1));
+        let today = offline(600.0, clock.now());
+        let mut expired = offline(50.0, clock.now());
+        expired.expires_at = clock.now() - Duration::hours(1);
+
+        // Arrival order differs from the order the transactions were made
+        let report = manager.sync_offline_batch(
+            vec![today.clone(), second_yesterday.clone(), first_yesterday.clone(), first_yesterday.clone(), expired.clone()],
+            &keyring,
+        );
+        assert_eq!((report.applied, report.rejected, report.expired, report.duplicate), (2, 1, 1, 1));
+
+        let status_of = |id: Uuid| report.outcomes.iter().find(|o| o.transaction_id == id).unwrap().status;
+        assert_eq!(status_of(first_yesterday.transaction.transaction_id), SyncStatus::Applied);
+        assert_eq!(status_of(second_yesterday.transaction.transaction_id), SyncStatus::Rejected);
+        assert_eq!(status_of(today.transaction.transaction_id), SyncStatus::Applied);
+        assert_eq!(status_of(expired.transaction.transaction_id), SyncStatus::Expired);
+        assert!(report.outcomes.iter()
+            .find(|o| o.status == SyncStatus::Rejected)
+            .is_some_and(|o| o.reason.is_some()));
+
+        // Yesterday's spending doesn't count against today's limit
+        assert_eq!(manager.daily_usage(TEST_USER_ID), 600.0);
+
+        // Syncing the same batch again only reports duplicates
+        let again = manager.sync_offline_batch(vec![first_yesterday, today], &keyring);
+        assert_eq!(again.duplicate, 2);
+    }
+
+    #[test]
+    fn test_recipient_hashing_at_rest() {
+        let config = SafeBankConfig {
+            hash_recipients: true,
+            recipient_hash_salt: Some("branch-salt".to_string()),
+            ..SafeBankConfig::default()
+        };
+        let backend = storage::in_memory_storage();
+        let mut manager = TransactionManager::with_storage(&config, backend.clone(), clock::system_clock());
+        manager.adjust_balance(TEST_USER_ID, 100_000.0).unwrap();
+        let user_id = TEST_USER_ID;
+
+        let mut transaction1 = create_test_transaction();
+        transaction1.user_id = user_id;
+        transaction1.recipient = "Jane Farmer".to_string();
+        let mut transaction2 = create_test_transaction();
+        transaction2.user_id = user_id;
+        transaction2.recipient = "Jane Farmer".to_string();
+
+        let stored1 = manager.process_transaction(transaction1).unwrap();
+        let stored2 = manager.process_transaction(transaction2).unwrap();
+
+        // Stored transactions carry a hash, not the plaintext recipient
+        assert_ne!(stored1.recipient, "Jane Farmer");
+        let history = manager.get_user_transactions(user_id).unwrap();
+        assert!(history.iter().all(|t| !t.recipient.contains("Jane")));
+
+        // Repeated payments to the same recipient match via the hash
+        assert_eq!(stored1.recipient, stored2.recipient);
+        assert_eq!(manager.reveal_recipient(&stored1.recipient), Some("Jane Farmer".to_string()));
+
+        // A restarted instance on the same storage hashes alike and still resolves the directory
+        let restarted = TransactionManager::with_storage(&config, backend, clock::system_clock());
+        assert_eq!(restarted.protected_recipient("Jane Farmer").unwrap(), stored1.recipient);
+        assert_eq!(restarted.reveal_recipient(&stored1.recipient), Some("Jane Farmer".to_string()));
+    }
+
+    #[test]
+    fn test_transaction_statistics() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
         
         // Add some test transactions
         let transaction1 = create_test_transaction();
@@ -511,8 +2513,488 @@ mod tests {
         let _ = manager.process_transaction(transaction1);
         let _ = manager.process_transaction(transaction2);
         
-        let stats = manager.get_transaction_statistics();
+        let stats = manager.get_transaction_statistics().unwrap();
         assert_eq!(stats["total_transactions"], 2.0);
         assert!(stats.contains_key("approval_rate_percent"));
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_statistics_break_down_volume_by_category() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+
+        let tagged = [
+            (120.0, Some(TransactionCategory::SchoolFees)),
+            (80.0, Some(TransactionCategory::SchoolFees)),
+            (15.0, Some(TransactionCategory::Airtime)),
+            (40.0, Some(TransactionCategory::Other("Church Offering".to_string()))),
+            (25.0, None),
+        ];
+        for (amount, category) in tagged {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.amount = amount;
+            transaction.category = category;
+            manager.process_transaction(transaction).unwrap();
+        }
+
+        let stats = manager.get_transaction_statistics().unwrap();
+        assert_eq!(stats["category_volume.school_fees"], 200.0);
+        assert_eq!(stats["category_count.school_fees"], 2.0);
+        assert_eq!(stats["category_volume.airtime"], 15.0);
+        assert_eq!(stats["category_volume.church_offering"], 40.0);
+        assert_eq!(stats["category_volume.uncategorized"], 25.0);
+
+        // Records from before categories existed load as uncategorized
+        let mut json = serde_json::to_value(create_test_transaction()).unwrap();
+        json.as_object_mut().unwrap().remove("category");
+        let legacy: Transaction = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy.category, None);
+    }
+
+    #[test]
+    fn test_small_amounts_accumulate_exactly() {
+        let config = SafeBankConfig { daily_transaction_count_limit: None, ..SafeBankConfig::default() };
+        let mut manager = funded_manager(&config);
+
+        for _ in 0..1000 {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.amount = 0.01;
+            manager.process_transaction(transaction).unwrap();
+        }
+
+        // Summing 0.01 a thousand times in f64 gives 9.999999999999831
+        assert_eq!(manager.daily_usage_money(TEST_USER_ID), Money::from_minor_units(1000));
+        assert_eq!(manager.daily_usage(TEST_USER_ID), 10.0);
+        // Each transfer also pays the 0.10 minimum fee
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), 100_000.0 - 110.0);
+    }
+
+    #[test]
+    fn test_export_transactions_as_csv_and_json() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+
+        let empty = manager.export_transactions(TEST_USER_ID, ExportFormat::Csv).unwrap();
+        assert_eq!(empty.lines().count(), 1);
+        assert_eq!(manager.export_transactions(TEST_USER_ID, ExportFormat::Json).unwrap(), "[]");
+
+        let mut committed = Vec::new();
+        for (amount, recipient) in [(1250.0, "Mama Mboga, Kibera"), (80.0, "Village Shop")] {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.amount = amount;
+            transaction.recipient = recipient.to_string();
+            committed.push(manager.process_transaction(transaction).unwrap());
+        }
+
+        let csv = manager.export_transactions(TEST_USER_ID, ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        // Quoted commas inside fields don't count as separators
+        let column_count = |line: &str| line.split('"').step_by(2).map(|part| part.matches(',').count()).sum::<usize>() + 1;
+        assert!(lines.iter().all(|line| column_count(line) == 10));
+        assert!(lines[1].contains("\"Mama Mboga, Kibera\""));
+        assert!(lines[1].contains("$1250.00"));
+        assert!(lines[1].ends_with(&manager.create_receipt(&committed[0]).confirmation_code));
+
+        let json = manager.export_transactions(TEST_USER_ID, ExportFormat::Json).unwrap();
+        let exported: Vec<ExportedTransaction> = serde_json::from_str(&json).unwrap();
+        let ids: Vec<Uuid> = exported.iter().map(|t| t.transaction_id).collect();
+        assert_eq!(ids, committed.iter().map(|t| t.transaction_id).collect::<Vec<_>>());
+        assert_eq!(exported[1].amount, 80.0);
+        assert!(json.contains("confirmation_code"));
+        // Scoring and device internals stay out of the statement
+        for internal in ["fraud_score", "device_id", "hash", "geo_location", "balance_after"] {
+            assert!(!json.contains(internal), "{} leaked into the export", internal);
+        }
+    }
+
+    #[test]
+    fn test_export_reveals_hashed_recipients_and_neutralises_formulas() {
+        let config = SafeBankConfig {
+            hash_recipients: true,
+            recipient_hash_salt: Some("statement-salt".to_string()),
+            ..SafeBankConfig::default()
+        };
+        let mut manager = funded_manager(&config);
+        let mut committed = Vec::new();
+        for recipient in ["Village Shop", "=cmd|' /C calc'!A0", "Forgotten Payee"] {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.recipient = recipient.to_string();
+            committed.push(manager.process_transaction(transaction).unwrap());
+        }
+
+        let csv = manager.export_transactions(TEST_USER_ID, ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[1].contains(",Village Shop,"));
+        assert!(lines[2].contains(",'=cmd|' /C calc'!A0,"));
+
+        // A hash the directory can't resolve is left blank rather than exported
+        manager.storage.remove_record(&TransactionManager::directory_key(&committed[2].recipient)).unwrap();
+        let json = manager.export_transactions(TEST_USER_ID, ExportFormat::Json).unwrap();
+        let exported: Vec<ExportedTransaction> = serde_json::from_str(&json).unwrap();
+        assert_eq!(exported[0].recipient, "Village Shop");
+        assert_eq!(exported[2].recipient, "");
+        assert!(!json.contains(&committed[2].recipient));
+    }
+
+    #[test]
+    fn test_invalid_reported_coordinates_are_rejected() {
+        let config = SafeBankConfig {
+            location_coordinates: [("Nakuru".to_string(), (-0.3031, 36.0800))].into_iter().collect(),
+            ..SafeBankConfig::default()
+        };
+        let mut manager = funded_manager(&config);
+
+        let at = |place: GeoLocation| {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.geo_location = Some(place);
+            transaction
+        };
+
+        assert!(manager.process_transaction(at(GeoLocation::new(-0.2900, 36.0700, "Nakuru"))).is_ok());
+        assert!(manager.process_transaction(at(GeoLocation::new(-1.2921, 36.8219, "Somewhere"))).is_ok());
+        for invalid in [
+            GeoLocation::new(f64::NAN, 36.0, "Somewhere"),
+            GeoLocation::new(-1.0, f64::INFINITY, "Somewhere"),
+            GeoLocation::new(91.0, 36.0, "Somewhere"),
+            GeoLocation::new(-1.0, -181.0, "Somewhere"),
+            // Labelled Nakuru but placed in Mombasa
+            GeoLocation::new(-4.0435, 39.6682, "Nakuru"),
+        ] {
+            assert!(matches!(manager.process_transaction(at(invalid)), Err(SafeBankError::ConfigError { .. })));
+        }
+        assert_eq!(manager.get_user_transactions(TEST_USER_ID).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_geofence_blocks_out_of_region_transactions() {
+        let config = SafeBankConfig {
+            allowed_regions: vec!["Rift Valley".to_string(), "Kisumu, Nyanza".to_string()],
+            ..SafeBankConfig::default()
+        };
+        let mut manager = funded_manager(&config);
+
+        let at = |location: Option<&str>| {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.location = location.map(str::to_string);
+            transaction
+        };
+
+        assert!(manager.process_transaction(at(Some("Nakuru, rift valley"))).is_ok());
+        assert!(manager.process_transaction(at(Some("Kisumu, Nyanza"))).is_ok());
+        assert!(matches!(
+            manager.process_transaction(at(Some("Mombasa, Coast"))),
+            Err(SafeBankError::RegionNotAllowed { location }) if location == "Mombasa, Coast"
+        ));
+        // Only the listed town is allowed from Nyanza
+        assert!(manager.process_transaction(at(Some("Siaya, Nyanza"))).is_err());
+        // The precheck ahead of fraud analysis applies the same gates as commit
+        assert!(manager.precheck(&at(Some("Nakuru, rift valley"))).is_ok());
+        assert!(matches!(manager.precheck(&at(Some("Mombasa, Coast"))), Err(SafeBankError::RegionNotAllowed { .. })));
+        let nothing = Transaction { amount: 0.0, ..at(Some("Nakuru, rift valley")) };
+        assert!(matches!(manager.precheck(&nothing), Err(SafeBankError::ConfigError { .. })));
+        assert_eq!(manager.get_user_transactions(TEST_USER_ID).unwrap().len(), 2);
+
+        // Missing locations pass unless the deployment requires one
+        assert!(manager.process_transaction(at(None)).is_ok());
+        let strict = SafeBankConfig { require_transaction_location: true, ..config };
+        assert!(matches!(
+            funded_manager(&strict).process_transaction(at(None)),
+            Err(SafeBankError::RegionNotAllowed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_chain_detects_tampering() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+
+        let mut committed = Vec::new();
+        for amount in [100.0, 250.0, 75.0] {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.amount = amount;
+            committed.push(manager.process_transaction(transaction).unwrap());
+        }
+        assert_eq!(committed[0].prev_hash, None);
+        assert_eq!(committed[1].prev_hash, committed[0].hash);
+        manager.verify_chain(TEST_USER_ID).unwrap();
+
+        // Reversal and approval updates keep the chain intact
+        manager.reverse_transaction(committed[2].transaction_id, "Sent in error".to_string()).unwrap();
+        manager.verify_chain(TEST_USER_ID).unwrap();
+
+        // Rewriting a stored amount directly in the backend is detected
+        let mut tampered = committed[1].clone();
+        tampered.amount = 25.0;
+        manager.storage.save_transaction(&tampered).unwrap();
+        let result = manager.verify_chain(TEST_USER_ID);
+        assert!(matches!(result, Err(SafeBankError::CryptographyError { ref message }) if message.contains(&tampered.transaction_id.to_string())));
+        manager.storage.save_transaction(&committed[1]).unwrap();
+        manager.verify_chain(TEST_USER_ID).unwrap();
+
+        // So is rewriting the status or balance stamp that changes after commit
+        let mut tampered = manager.get_transaction(committed[0].transaction_id).unwrap();
+        tampered.status = TransactionStatus::Rejected;
+        manager.storage.save_transaction(&tampered).unwrap();
+        assert!(manager.verify_chain(TEST_USER_ID).is_err());
+        tampered.status = committed[0].status.clone();
+        tampered.balance_after = Some(1_000_000.0);
+        manager.storage.save_transaction(&tampered).unwrap();
+        assert!(manager.verify_chain(TEST_USER_ID).is_err());
+    }
+
+    #[test]
+    fn test_chain_head_is_shared_through_storage() {
+        let config = SafeBankConfig::default();
+        let shared = storage::in_memory_storage();
+        let mut first = TransactionManager::with_storage(&config, shared.clone(), clock::system_clock());
+        let mut second = TransactionManager::with_storage(&config, shared, clock::system_clock());
+        first.adjust_balance(TEST_USER_ID, 100_000.0).unwrap();
+
+        // Two instances appending for the same user extend one chain instead of forking it
+        let mut previous = None;
+        for use_second in [false, true, false] {
+            let manager = if use_second { &mut second } else { &mut first };
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            let committed = manager.process_transaction(transaction).unwrap();
+            assert_eq!(committed.prev_hash, previous);
+            previous = committed.hash;
+        }
+        second.verify_chain(TEST_USER_ID).unwrap();
+    }
+
+    #[test]
+    fn test_daily_count_limit() {
+        let config = SafeBankConfig { daily_transaction_count_limit: Some(3), ..SafeBankConfig::default() };
+        let mut manager = funded_manager(&config);
+        let submit = |manager: &mut TransactionManager, recipient: &str| {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.amount = 10.0;
+            transaction.recipient = recipient.to_string();
+            manager.process_transaction(transaction)
+        };
+
+        for recipient in ["Shop A", "Shop B", "Shop C"] {
+            submit(&mut manager, recipient).unwrap();
+        }
+        // Far below the amount caps, but the fourth transaction of the day is refused
+        assert!(matches!(
+            submit(&mut manager, "Shop D"),
+            Err(SafeBankError::TransactionCountLimitExceeded { count: 4, limit: 3 })
+        ));
+        assert_eq!(manager.daily_transaction_count(TEST_USER_ID), 3);
+    }
+
+    #[test]
+    fn test_daily_per_recipient_limit() {
+        let config = SafeBankConfig { daily_per_recipient_limit: Some(500.0), ..SafeBankConfig::default() };
+        let mut manager = funded_manager(&config);
+        let submit = |manager: &mut TransactionManager, recipient: &str, amount: f64| {
+            let mut transaction = create_test_transaction();
+            transaction.transaction_id = Uuid::new_v4();
+            transaction.amount = amount;
+            transaction.recipient = recipient.to_string();
+            manager.process_transaction(transaction)
+        };
+
+        submit(&mut manager, "Jane Farmer", 300.0).unwrap();
+        assert!(matches!(
+            submit(&mut manager, "Jane Farmer", 250.0),
+            Err(SafeBankError::TransactionLimitExceeded { amount, limit }) if amount == 550.0 && limit == 500.0
+        ));
+        // Other recipients are unaffected and the daily total is well below its own cap
+        submit(&mut manager, "John Trader", 450.0).unwrap();
+        submit(&mut manager, "Jane Farmer", 200.0).unwrap();
+    }
+
+    #[test]
+    fn test_shared_account_transfers_need_quorum_of_members() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+        let (alice, bob) = (Uuid::from_u128(2), Uuid::from_u128(3));
+        manager.register_shared_account(SharedAccount {
+            account_id: TEST_USER_ID,
+            members: HashSet::from([alice, bob]),
+            quorum: 2,
+            approval_threshold: 1000.0,
+        }).unwrap();
+
+        // Small payments go straight through
+        assert_eq!(manager.process_transaction(create_test_transaction()).unwrap().status, TransactionStatus::Approved);
+
+        let payment = |status| Transaction { transaction_id: Uuid::new_v4(), amount: 1200.0, status, ..create_test_transaction() };
+        let held = manager.process_transaction(payment(TransactionStatus::Approved)).unwrap();
+        assert_eq!(held.status, TransactionStatus::RequiresApproval);
+        let balance = manager.get_balance(TEST_USER_ID).unwrap();
+
+        // Only members can release it: not a reviewer, not an outsider, and not one member twice
+        assert!(matches!(manager.approve_transaction(held.transaction_id, "reviewer-7"), Err(SafeBankError::InvalidTransactionState { .. })));
+        assert!(matches!(
+            manager.co_approve_transaction(held.transaction_id, Uuid::from_u128(9)),
+            Err(SafeBankError::AuthenticationFailed { .. })
+        ));
+        let pending = manager.co_approve_transaction(held.transaction_id, alice).unwrap();
+        assert_eq!(pending.status, TransactionStatus::RequiresApproval);
+        assert!(manager.co_approve_transaction(held.transaction_id, alice).is_err());
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), balance);
+
+        // The account and approvals so far survive a restart; the second member completes it
+        let mut manager = TransactionManager::with_storage(&config, manager.storage.clone(), clock::system_clock());
+        let approved = manager.co_approve_transaction(held.transaction_id, bob).unwrap();
+        assert_eq!(approved.status, TransactionStatus::Approved);
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), balance - (1200.0 + approved.fee + approved.levy));
+
+        // A payment also held for review needs both the reviewer and the members, in either order
+        let flagged = manager.process_transaction(payment(TransactionStatus::Flagged)).unwrap();
+        assert_eq!(flagged.status, TransactionStatus::Flagged);
+        manager.co_approve_transaction(flagged.transaction_id, alice).unwrap();
+        assert_eq!(manager.co_approve_transaction(flagged.transaction_id, bob).unwrap().status, TransactionStatus::Flagged);
+        assert_eq!(manager.approve_transaction(flagged.transaction_id, "reviewer-7").unwrap().status, TransactionStatus::Approved);
+
+        let flagged = manager.process_transaction(payment(TransactionStatus::Flagged)).unwrap();
+        assert_eq!(manager.approve_transaction(flagged.transaction_id, "reviewer-7").unwrap().status, TransactionStatus::RequiresApproval);
+        manager.co_approve_transaction(flagged.transaction_id, alice).unwrap();
+        assert_eq!(manager.co_approve_transaction(flagged.transaction_id, bob).unwrap().status, TransactionStatus::Approved);
+
+        // The customer's step-up confirmation leaves it waiting on the members
+        let step_up = manager.process_transaction(payment(TransactionStatus::RequiresStepUp)).unwrap();
+        assert_eq!(manager.confirm_step_up(step_up.transaction_id).unwrap().status, TransactionStatus::RequiresApproval);
+
+        // Splitting a payment doesn't take its legs under the threshold
+        let legs = ["Seed Supplier", "Fertilizer Depot"].map(|recipient| Transaction {
+            amount: 600.0,
+            recipient: recipient.to_string(),
+            ..payment(TransactionStatus::Approved)
+        });
+        let committed = manager.process_transaction_group(legs.to_vec()).unwrap();
+        assert!(committed.iter().all(|leg| leg.status == TransactionStatus::RequiresApproval));
+    }
+
+    #[test]
+    fn test_incremental_statistics_match_full_recount() {
+        let config = SafeBankConfig::default();
+        let mut manager = funded_manager(&config);
+        let mut approved = Vec::new();
+        for (amount, category) in [(120.0, None), (250.5, Some(TransactionCategory::SchoolFees)), (80.0, None)] {
+            let transaction = Transaction { transaction_id: Uuid::new_v4(), amount, category, ..create_test_transaction() };
+            approved.push(manager.process_transaction(transaction).unwrap());
+        }
+        let held = manager.process_transaction(Transaction {
+            transaction_id: Uuid::new_v4(),
+            status: TransactionStatus::RequiresApproval,
+            ..create_test_transaction()
+        }).unwrap();
+        let flagged = manager.process_transaction(Transaction {
+            transaction_id: Uuid::new_v4(),
+            status: TransactionStatus::Flagged,
+            ..create_test_transaction()
+        }).unwrap();
+
+        // Status changes move counts between buckets
+        manager.approve_transaction(held.transaction_id, "reviewer-7").unwrap();
+        manager.reject_transaction(flagged.transaction_id, "reviewer-7", "Confirmed fraud".to_string()).unwrap();
+        manager.reverse_transaction(approved[1].transaction_id, "Customer request".to_string()).unwrap();
+
+        let stats = manager.get_transaction_statistics().unwrap();
+        assert_eq!(stats["total_transactions"], 6.0);
+        assert_eq!(stats["approved_count"], 5.0);
+        assert_eq!(stats["rejected_count"], 1.0);
+        assert_eq!(stats["flagged_count"], 0.0);
+
+        // A manager opening the same storage counts everything from scratch
+        let recounted = TransactionManager::with_storage(&config, manager.storage.clone(), clock::system_clock())
+            .get_transaction_statistics()
+            .unwrap();
+        assert_eq!(stats.len(), recounted.len());
+        for (key, value) in &recounted {
+            assert!((stats[key] - value).abs() < 1e-9, "{}: {} != {}", key, stats[key], value);
+        }
+
+        // Instances sharing storage and system state, like shards, see each other's transactions
+        let mut shard = TransactionManager::with_system_state(&config, manager.storage.clone(), clock::system_clock(), manager.system.clone());
+        shard.process_transaction(Transaction { transaction_id: Uuid::new_v4(), ..create_test_transaction() }).unwrap();
+        assert_eq!(manager.get_transaction_statistics().unwrap()["total_transactions"], 7.0);
+        assert_eq!(shard.get_transaction_statistics().unwrap()["total_transactions"], 7.0);
+    }
+
+    #[test]
+    fn test_stale_held_transactions_expire() {
+        let clock = Arc::new(MockClock::new(Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap()));
+        let config = SafeBankConfig { pending_expiry_hours: Some(48), ..SafeBankConfig::default() };
+        let mut manager = TransactionManager::with_clock(&config, clock.clone());
+        let held = |clock: &MockClock, status| Transaction {
+            transaction_id: Uuid::new_v4(),
+            timestamp: clock.now(),
+            status,
+            ..create_test_transaction()
+        };
+
+        let stale = manager.process_transaction(held(&clock, TransactionStatus::RequiresApproval)).unwrap();
+        clock.advance(Duration::hours(40));
+        let fresh = manager.process_transaction(held(&clock, TransactionStatus::Flagged)).unwrap();
+        clock.advance(Duration::hours(10));
+
+        let expired = manager.expire_stale_transactions(clock.now()).unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].transaction_id, stale.transaction_id);
+
+
+        let stale = manager.get_transaction(stale.transaction_id).unwrap();
+        assert_eq!(stale.status, TransactionStatus::Rejected);
+        let change = stale.status_history.last().unwrap();
+        assert_eq!((&change.from, change.actor.as_str(), change.at), (&TransactionStatus::RequiresApproval, SYSTEM_ACTOR, clock.now()));
+        assert!(change.reason.as_deref().unwrap().contains("48 hours"));
+        assert_eq!(manager.get_transaction(fresh.transaction_id).unwrap().status, TransactionStatus::Flagged);
+
+        // Unconfirmed step-up holds expire the same way
+        let unconfirmed = manager.process_transaction(held(&clock, TransactionStatus::RequiresStepUp)).unwrap();
+        clock.advance(Duration::hours(49));
+        let expired = manager.expire_stale_transactions(clock.now()).unwrap();
+        assert_eq!(expired.len(), 2);
+        let unconfirmed = manager.get_transaction(unconfirmed.transaction_id).unwrap();
+        assert_eq!(unconfirmed.status, TransactionStatus::Rejected);
+        assert!(unconfirmed.status_history.last().unwrap().reason.as_deref().unwrap().contains("without confirmation"));
+    }
+
+    #[test]
+    fn test_split_transfer_rolls_back_when_last_leg_fails() {
+        let config = SafeBankConfig::default();
+        let backend = Arc::new(storage::TestStorage::default());
+        let mut manager = TransactionManager::with_storage(&config, backend.clone(), clock::system_clock());
+        manager.adjust_balance(TEST_USER_ID, 1000.0).unwrap();
+        backend.fail_transaction_save(2);
+        let legs: Vec<Transaction> = [("Day Laborer A", 100.0), ("Day Laborer B", 150.0), ("Day Laborer C", 200.0)]
+            .into_iter()
+            .map(|(recipient, amount)| Transaction {
+                transaction_id: Uuid::new_v4(),
+                recipient: recipient.to_string(),
+                amount,
+                ..create_test_transaction()
+            })
+            .collect();
+
+        // The third leg can't be stored
+        assert!(matches!(manager.process_transaction_group(legs), Err(SafeBankError::StorageError { .. })));
+
+        assert_eq!(manager.get_balance(TEST_USER_ID).unwrap(), 1000.0);
+        assert_eq!(manager.daily_usage(TEST_USER_ID), 0.0);
+        let stored = manager.get_user_transactions(TEST_USER_ID).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored.iter().all(|leg| leg.status == TransactionStatus::Rejected));
+        assert_eq!(manager.get_transaction_statistics().unwrap()["approved_count"], 0.0);
+        manager.verify_chain(TEST_USER_ID).unwrap();
+
+        // The account works normally afterwards
+        manager.process_transaction(create_test_transaction()).unwrap();
+        manager.verify_chain(TEST_USER_ID).unwrap();
+    }
+}
+
@@ -1,21 +1,212 @@
 //! Utility functions for SafeBank framework
 //! Provides helper functions for validation, formatting, and common operations
 
-use chrono::{DateTime, Utc, Duration, Timelike};
+use chrono::{DateTime, NaiveDate, Utc, Duration, Timelike};
 use std::collections::HashMap;
+use std::io::{Read, Write};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::{GeoLocation, config::{FactorBounds, FeeSchedule, RoundingMode}, errors::SafeBankError, money::Money};
+
+/// Where a currency symbol goes relative to the amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymbolPlacement {
+    /// "$100.50"
+    Prefix,
+    /// "KSh 100.50"
+    PrefixSpaced,
+    /// "1000 CFA"
+    SuffixSpaced,
+}
+
+/// How amounts in one currency are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrencyFormat {
+    pub symbol: &'static str,
+    pub decimal_places: u32,
+    /// Separator between groups of thousands; `None` prints digits ungrouped
+    pub thousands_separator: Option<char>,
+    pub placement: SymbolPlacement,
+}
+
+impl CurrencyFormat {
+    const fn new(symbol: &'static str, decimal_places: u32, placement: SymbolPlacement) -> Self {
+        Self { symbol, decimal_places, thousands_separator: None, placement }
+    }
+}
+
+/// Display metadata for the currencies SafeBank knows by code
+const CURRENCY_FORMATS: &[(&str, CurrencyFormat)] = &[
+    ("USD", CurrencyFormat::new("$", 2, SymbolPlacement::Prefix)),
+    ("EUR", CurrencyFormat::new("€", 2, SymbolPlacement::Prefix)),
+    ("KES", CurrencyFormat::new("KSh", 2, SymbolPlacement::PrefixSpaced)), // Kenyan Shilling
+    ("NGN", CurrencyFormat::new("₦", 2, SymbolPlacement::Prefix)),         // Nigerian Naira
+    ("INR", CurrencyFormat::new("₹", 2, SymbolPlacement::Prefix)),         // Indian Rupee
+    ("GHS", CurrencyFormat::new("₵", 2, SymbolPlacement::Prefix)),         // Ghanaian Cedi
+    ("UGX", CurrencyFormat::new("USh", 0, SymbolPlacement::PrefixSpaced)), // Ugandan Shilling
+    ("XOF", CurrencyFormat::new("CFA", 0, SymbolPlacement::SuffixSpaced)), // West African CFA franc
+    ("JPY", CurrencyFormat::new("¥", 0, SymbolPlacement::Prefix)),
+];
+
+/// Display metadata for a currency code, if it is in the table
+pub fn currency_format(currency: &str) -> Option<CurrencyFormat> {
+    let code = currency.to_uppercase();
+    CURRENCY_FORMATS.iter().find(|(known, _)| *known == code).map(|(_, format)| *format)
+}
+
+/// Per-call overrides of a currency's display metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub decimal_places: Option<u32>,
+    /// Group thousands with this separator, e.g. "KSh 1,234,567.00"
+    pub thousands_separator: Option<char>,
+    pub placement: Option<SymbolPlacement>,
+}
+
 /// Format currency amount for display in rural banking context
 pub fn format_currency(amount: f64, currency: &str) -> String {
-    match currency.to_uppercase().as_str() {
-        "USD" => format!("${:.2}", amount),
-        "EUR" => format!("€{:.2}", amount),
-        "KES" => format!("KSh {:.2}", amount), // Kenyan Shilling
-        "NGN" => format!("₦{:.2}", amount),   // Nigerian Naira
-        "INR" => format!("₹{:.2}", amount),   // Indian Rupee
-        "GHS" => format!("₵{:.2}", amount),   // Ghanaian Cedi
-        _ => format!("{} {:.2}", currency, amount),
+    format_currency_opts(amount, currency, &FormatOptions::default())
+}
+
+/// `format_currency` with the currency's decimal places, grouping or symbol placement overridden
+pub fn format_currency_opts(amount: f64, currency: &str, options: &FormatOptions) -> String {
+    let places = options.decimal_places.unwrap_or_else(|| currency_format(currency).map_or(2, |format| format.decimal_places));
+    render_amount(Money::from_decimal(amount, places, RoundingMode::HalfUp), places, currency, options)
+}
+
+/// Format an amount held in cents, without a round trip through floating point
+pub fn format_money(amount: Money, currency: &str) -> String {
+    let places = currency_format(currency).map_or(2, |format| format.decimal_places);
+    // Rescale from cents to the currency's own minor unit, rounding half away from zero
+    let amount = match places {
+        0..=2 => amount.mul_ratio(1, 10_i64.pow(2 - places)),
+        _ => amount.mul_ratio(10_i64.pow(places - 2), 1),
+    };
+    render_amount(amount, places, currency, &FormatOptions::default())
+}
+
+/// Lay out an amount already in minor units of `places` decimals, with symbol and grouping
+fn render_amount(amount: Money, places: u32, currency: &str, options: &FormatOptions) -> String {
+    let format = currency_format(currency);
+    let mut value = amount.to_decimal_string(places);
+    if let Some(separator) = options.thousands_separator.or(format.and_then(|format| format.thousands_separator)) {
+        value = group_thousands(&value, separator);
+    }
+
+    let symbol = format.map_or(currency, |format| format.symbol);
+    match options.placement.unwrap_or(format.map_or(SymbolPlacement::PrefixSpaced, |format| format.placement)) {
+        SymbolPlacement::Prefix => format!("{}{}", symbol, value),
+        SymbolPlacement::PrefixSpaced => format!("{} {}", symbol, value),
+        SymbolPlacement::SuffixSpaced => format!("{} {}", value, symbol),
+    }
+}
+
+/// Insert a separator between groups of three digits in the integer part of "-1234567.00"
+fn group_thousands(value: &str, separator: char) -> String {
+    let (sign, unsigned) = value.split_at(usize::from(value.starts_with('-')));
+    let (integer, fraction) = unsigned.split_at(unsigned.find('.').unwrap_or(unsigned.len()));
+    let mut grouped = String::with_capacity(value.len() + integer.len() / 3);
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break (RFC 4180).
+/// Fields a spreadsheet would run as a formula get a leading apostrophe so they stay text.
+pub fn escape_csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// Round a monetary amount to the given number of decimal places.
+/// Ties are detected with a small tolerance so binary artifacts (2.345 stored as 2.34499...) round as decimal values would.
+pub fn round_money(amount: f64, decimal_places: u32, mode: RoundingMode) -> f64 {
+    let scale = 10_f64.powi(decimal_places as i32);
+    let scaled = amount.abs() * scale;
+    let floor = scaled.floor();
+    let is_tie = (scaled - floor - 0.5).abs() < 1e-6;
+
+    let rounded = if is_tie {
+        match mode {
+            RoundingMode::HalfUp => floor + 1.0,
+            RoundingMode::HalfEven => if floor % 2.0 == 0.0 { floor } else { floor + 1.0 },
+        }
+    } else {
+        scaled.round()
+    };
+
+    (rounded / scale).copysign(amount)
+}
+
+/// Convert an amount at a mid-market rate, taking a percentage margin.
+/// Returns `(converted_amount, fx_fee)` where the fee is expressed in the target currency.
+pub fn convert_with_margin(amount: f64, mid_market_rate: f64, margin_percent: f64) -> (f64, f64) {
+    let mid_market_value = amount * mid_market_rate;
+    let fx_fee = mid_market_value * margin_percent / 100.0;
+    (mid_market_value - fx_fee, fx_fee)
+}
+
+/// Great-circle distance in kilometres between two (latitude, longitude) points
+pub fn haversine_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Great-circle distance in kilometres between two locations
+pub fn haversine_distance(from: &GeoLocation, to: &GeoLocation) -> f64 {
+    haversine_km(from.position(), to.position())
+}
+
+/// PINs common enough to be among an attacker's first guesses
+const COMMON_PINS: &[&str] = &[
+    "1234", "0000", "1111", "1212", "7777", "1004", "2000", "4444", "2222", "6969",
+    "9999", "3333", "5555", "6666", "1122", "1313", "8888", "4321", "2001", "1010",
+    "123456", "654321", "111111", "000000", "121212", "112233", "123123", "159753",
+];
+
+/// Why a PIN would be easy to guess, if it would be
+pub fn pin_weakness(pin: &str) -> Option<&'static str> {
+    let digits: Vec<u32> = pin.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() != pin.len() || digits.is_empty() {
+        return None; // Format is checked separately
+    }
+
+    if digits.iter().all(|digit| *digit == digits[0]) {
+        return Some("repeats a single digit");
+    }
+    if (1..=digits.len() / 2).any(|block| digits.len().is_multiple_of(block) && digits.chunks(block).all(|chunk| chunk == &digits[..block])) {
+        return Some("repeats a short pattern");
+    }
+    if digits.windows(2).all(|pair| pair[1] == pair[0] + 1) || digits.windows(2).all(|pair| pair[0] == pair[1] + 1) {
+        return Some("is a run of consecutive digits");
+    }
+    if COMMON_PINS.contains(&pin) {
+        return Some("is one of the most commonly chosen PINs");
     }
+    if pin.len() == 4 && pin.parse::<u32>().is_ok_and(|year| (1940..=2030).contains(&year)) {
+        return Some("looks like a year");
+    }
+    None
 }
 
 /// Validate phone number format for different regions
@@ -41,31 +232,130 @@ pub fn validate_phone_number(phone: &str, region: Option<&str>) -> bool {
     }
 }
 
-/// Generate a simple OTP (One-Time Password) for rural users
+/// International calling code for a region (ISO 3166 alpha-2), if supported
+pub fn calling_code(region: &str) -> Option<&'static str> {
+    match region.to_uppercase().as_str() {
+        "KE" => Some("254"),
+        "UG" => Some("256"),
+        "TZ" => Some("255"),
+        "RW" => Some("250"),
+        "NG" => Some("234"),
+        "GH" => Some("233"),
+        "ZA" => Some("27"),
+        "IN" => Some("91"),
+        "US" | "CA" => Some("1"),
+        "GB" => Some("44"),
+        _ => None,
+    }
+}
+
+/// Canonicalize a phone number to E.164 (`+254712345678`).
+/// Accepts `+`/`00` international forms, national numbers with a trunk `0`, and numbers
+/// already carrying the region's calling code; spaces, dashes, dots and parentheses are ignored.
+pub fn normalize_phone_number(raw: &str, default_region: &str) -> Result<String, SafeBankError> {
+    let invalid = || SafeBankError::AuthenticationFailed {
+        message: "Invalid phone number format".to_string(),
+    };
+
+    let trimmed = raw.trim();
+    let cleaned: String = trimmed.chars().filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')')).collect();
+    let (international, digits) = if let Some(rest) = cleaned.strip_prefix('+') {
+        (true, rest)
+    } else if let Some(rest) = cleaned.strip_prefix("00") {
+        (true, rest)
+    } else {
+        (false, cleaned.as_str())
+    };
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let e164_digits = if international {
+        digits.to_string()
+    } else {
+        let code = calling_code(default_region).ok_or_else(invalid)?;
+        if let Some(national) = digits.strip_prefix('0') {
+            format!("{}{}", code, national)
+        } else if digits.starts_with(code) && digits.len() >= 11 {
+            digits.to_string()
+        } else {
+            format!("{}{}", code, digits)
+        }
+    };
+
+    // E.164 allows at most 15 digits; anything under 8 cannot be a subscriber number
+    if !(8..=15).contains(&e164_digits.len()) || e164_digits.starts_with('0') {
+        return Err(invalid());
+    }
+
+    Ok(format!("+{}", e164_digits))
+}
+
+/// Validate the Luhn (mod 10) checksum of a card or account number; spaces and dashes are ignored
+pub fn luhn_check(number: &str) -> bool {
+    let digits: Vec<u32> = match number.chars()
+        .filter(|c| !matches!(c, ' ' | '-'))
+        .map(|c| c.to_digit(10))
+        .collect::<Option<Vec<u32>>>()
+    {
+        Some(digits) if digits.len() >= 2 => digits,
+        _ => return false,
+    };
+
+    let sum: u32 = digits.iter().rev().enumerate().map(|(index, &digit)| {
+        if index % 2 == 1 {
+            let doubled = digit * 2;
+            if doubled > 9 { doubled - 9 } else { doubled }
+        } else {
+            digit
+        }
+    }).sum();
+
+    sum.is_multiple_of(10)
+}
+
+/// Check an account or card number is well formed: 12-19 digits (separators allowed) with a valid Luhn checksum
+pub fn validate_account_number(number: &str) -> bool {
+    let digit_count = number.chars().filter(|c| c.is_ascii_digit()).count();
+    (12..=19).contains(&digit_count) && luhn_check(number)
+}
+
+/// Generate a numeric OTP (One-Time Password) for rural users from the OS random number generator
 pub fn generate_simple_otp(length: usize) -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    // Simple OTP generation based on timestamp
-    let otp_num = timestamp % (10_u64.pow(length as u32));
-    format!("{:0width$}", otp_num, width = length)
+    use rand_core::{OsRng, RngCore};
+
+    let mut otp = String::with_capacity(length);
+    while otp.len() < length {
+        let mut byte = [0u8; 1];
+        OsRng.fill_bytes(&mut byte);
+        // Reject the top of the range so every digit is equally likely
+        if byte[0] < 250 {
+            otp.push(char::from(b'0' + byte[0] % 10));
+        }
+    }
+    otp
+}
+
+/// Compare two byte strings without short-circuiting on the first difference
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Generate a random hex string from the OS random number generator (used for salts)
+pub fn random_hex(num_bytes: usize) -> String {
+    use rand_core::{OsRng, RngCore};
+
+    let mut bytes = vec![0u8; num_bytes];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
 }
 
-/// Check network connectivity status (simplified)
+/// Check network connectivity with a short TCP connect to the default probe target.
+/// Use `connectivity::TcpProbe` to probe a configured host or inject a probe in tests.
 pub fn check_connectivity() -> ConnectivityStatus {
-    // In a real implementation, this would check actual network status
-    // For demo purposes, we'll simulate based on system time
-    let now = Utc::now().timestamp() % 10;
-    
-    match now {
-        0..=7 => ConnectivityStatus::Online,
-        8 => ConnectivityStatus::Limited,
-        _ => ConnectivityStatus::Offline,
-    }
+    use crate::connectivity::ConnectivityProbe;
+    crate::connectivity::TcpProbe::default().check()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -75,35 +365,43 @@ pub enum ConnectivityStatus {
     Offline,
 }
 
-/// Data compression utilities for low-bandwidth environments
+/// Data compression utilities for low-bandwidth environments (gzip)
 pub fn compress_transaction_data(data: &str) -> Result<Vec<u8>, String> {
-    // Simple compression - in real implementation use proper compression
-    let compressed = data.as_bytes().to_vec();
-    Ok(compressed)
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data.as_bytes())
+        .map_err(|e| format!("Compression failed: {}", e))?;
+    encoder.finish()
+        .map_err(|e| format!("Compression failed: {}", e))
 }
 
 pub fn decompress_transaction_data(data: &[u8]) -> Result<String, String> {
-    String::from_utf8(data.to_vec())
-        .map_err(|e| format!("Decompression failed: {}", e))
+    let mut decompressed = String::new();
+    GzDecoder::new(data)
+        .read_to_string(&mut decompressed)
+        .map_err(|e| format!("Decompression failed: {}", e))?;
+    Ok(decompressed)
 }
 
-/// Calculate fraud risk based on multiple factors
+/// Calculate fraud risk based on multiple factors.
+/// Each factor contributes its score times its weight, so a weak factor never dilutes a strong one;
+/// factors without a weight are ignored and the sum is clamped to 0-1.
 pub fn calculate_composite_risk_score(factors: &HashMap<String, f64>, weights: &HashMap<String, f64>) -> f64 {
-    let mut total_score = 0.0;
-    let mut total_weight = 0.0;
-    
-    for (factor, score) in factors {
-        if let Some(weight) = weights.get(factor) {
-            total_score += score * weight;
-            total_weight += weight;
-        }
-    }
-    
-    if total_weight > 0.0 {
-        (total_score / total_weight).min(1.0).max(0.0)
-    } else {
-        0.0
-    }
+    calculate_bounded_risk_score(factors, weights, &HashMap::new())
+}
+
+/// Composite risk score with each present factor's weighted contribution held within its bounds
+pub fn calculate_bounded_risk_score(factors: &HashMap<String, f64>, weights: &HashMap<String, f64>, bounds: &HashMap<String, FactorBounds>) -> f64 {
+    let total_score: f64 = factors
+        .iter()
+        .filter_map(|(factor, score)| {
+            let contribution = score * weights.get(factor)?;
+            Some(match bounds.get(factor) {
+                Some(bounds) if *score > 0.0 => contribution.clamp(bounds.min, bounds.max),
+                _ => contribution,
+            })
+        })
+        .sum();
+    total_score.clamp(0.0, 1.0)
 }
 
 /// Time zone utilities for rural banking
@@ -112,6 +410,12 @@ pub fn get_local_time_hour(utc_time: DateTime<Utc>, timezone_offset_hours: i32)
     local_time.hour()
 }
 
+/// Calendar date in a local timezone given as minutes east of UTC
+pub fn get_local_date(utc_time: DateTime<Utc>, timezone_offset_minutes: i32) -> NaiveDate {
+    let local_time = utc_time + Duration::minutes(timezone_offset_minutes as i64);
+    local_time.date_naive()
+}
+
 /// Device capability assessment
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceCapabilities {
@@ -143,11 +447,11 @@ impl DeviceCapabilities {
     }
 }
 
-/// SMS formatting for rural banking notifications
-pub fn format_transaction_sms(amount: f64, recipient: &str, status: &str, confirmation: &str, currency: &str) -> String {
+/// SMS formatting for rural banking notifications; a memo is quoted at the end
+pub fn format_transaction_sms(amount: f64, recipient: &str, status: &str, confirmation: &str, currency: &str, memo: Option<&str>) -> String {
     let formatted_amount = format_currency(amount, currency);
     
-    match status.to_lowercase().as_str() {
+    let message = match status.to_lowercase().as_str() {
         "approved" => format!(
             "SafeBank: Transaction APPROVED. Sent {} to {}. Ref: {}. Keep this SMS for your records.",
             formatted_amount, recipient, confirmation
@@ -164,48 +468,120 @@ pub fn format_transaction_sms(amount: f64, recipient: &str, status: &str, confir
             "SafeBank: Transaction {} - {} to {}. Ref: {}",
             status, formatted_amount, recipient, confirmation
         ),
+    };
+    match memo {
+        Some(memo) => format!("{} Memo: \"{}\"", message, memo),
+        None => message,
     }
 }
 
-/// Calculate transaction fee for rural banking (simplified)
-pub fn calculate_transaction_fee(amount: f64, transaction_type: &str, is_domestic: bool) -> f64 {
-    let base_fee = match transaction_type.to_lowercase().as_str() {
-        "transfer" => if is_domestic { 0.01 } else { 0.03 },
-        "payment" => 0.005,
-        "withdrawal" => 0.02,
-        "deposit" => 0.0,
-        _ => 0.01,
+/// Characters that could turn a memo into markup wherever it is displayed
+const MEMO_MARKUP_CHARS: [char; 4] = ['<', '>', '{', '}'];
+
+/// Leading characters spreadsheets treat as the start of a formula
+const FORMULA_PREFIXES: [char; 4] = ['=', '+', '-', '@'];
+
+/// Unicode format (Cf) characters as inclusive ranges: invisible characters such as bidi overrides,
+/// zero-width joiners and tags that make a memo display differently from what it contains
+const FORMAT_CHAR_RANGES: [(char, char); 21] = [
+    ('\u{00AD}', '\u{00AD}'),
+    ('\u{0600}', '\u{0605}'),
+    ('\u{061C}', '\u{061C}'),
+    ('\u{06DD}', '\u{06DD}'),
+    ('\u{070F}', '\u{070F}'),
+    ('\u{0890}', '\u{0891}'),
+    ('\u{08E2}', '\u{08E2}'),
+    ('\u{180E}', '\u{180E}'),
+    ('\u{200B}', '\u{200F}'),
+    ('\u{202A}', '\u{202E}'),
+    ('\u{2060}', '\u{2064}'),
+    ('\u{2066}', '\u{206F}'),
+    ('\u{FEFF}', '\u{FEFF}'),
+    ('\u{FFF9}', '\u{FFFB}'),
+    ('\u{110BD}', '\u{110BD}'),
+    ('\u{110CD}', '\u{110CD}'),
+    ('\u{13430}', '\u{1343F}'),
+    ('\u{1BCA0}', '\u{1BCA3}'),
+    ('\u{1D173}', '\u{1D17A}'),
+    ('\u{E0001}', '\u{E0001}'),
+    ('\u{E0020}', '\u{E007F}'),
+];
+
+fn is_format_char(c: char) -> bool {
+    FORMAT_CHAR_RANGES.iter().any(|(start, end)| (*start..=*end).contains(&c))
+}
+
+/// Check a transaction memo: surrounding whitespace is trimmed and an empty memo is dropped.
+/// Memos longer than `max_length` characters, or containing control or invisible format characters,
+/// markup or a leading formula character, are rejected rather than altered, so the user sees what was refused.
+pub fn clean_memo(memo: &str, max_length: usize) -> Result<Option<String>, SafeBankError> {
+    let memo = memo.trim();
+    if memo.is_empty() {
+        return Ok(None);
+    }
+
+    let invalid = |reason: &str| SafeBankError::ConfigError {
+        message: format!("Memo {}", reason),
     };
+    if memo.chars().count() > max_length {
+        return Err(invalid(&format!("is longer than {} characters", max_length)));
+    }
+    if memo.chars().any(|c| c.is_control() || is_format_char(c)) {
+        return Err(invalid("contains control or invisible formatting characters"));
+    }
+    if memo.contains(MEMO_MARKUP_CHARS) || memo.starts_with(FORMULA_PREFIXES) {
+        return Err(invalid("contains characters that are not allowed"));
+    }
+    Ok(Some(memo.to_string()))
+}
+
+/// Calculate transaction fee for rural banking under the default fee schedule.
+/// The fee is computed on whole cents so it never carries sub-cent residue into balances.
+pub fn calculate_transaction_fee(amount: f64, transaction_type: &str, is_domestic: bool) -> f64 {
+    calculate_scheduled_fee(amount, transaction_type, is_domestic, &FeeSchedule::default())
+}
+
+/// `calculate_transaction_fee` under a configured fee schedule
+pub fn calculate_scheduled_fee(amount: f64, transaction_type: &str, is_domestic: bool, schedule: &FeeSchedule) -> f64 {
+    let amount = Money::from_decimal(amount, 2, RoundingMode::HalfUp);
+    calculate_transaction_fee_money(amount, transaction_type, is_domestic, schedule).to_decimal(2)
+}
+
+/// `calculate_scheduled_fee` on an amount in cents
+pub fn calculate_transaction_fee_money(amount: Money, transaction_type: &str, is_domestic: bool, schedule: &FeeSchedule) -> Money {
+    // Rates in basis points (1/100 of a percent)
+    let rate_bps = schedule.rate_bps(transaction_type, is_domestic);
+    let fee = amount.mul_ratio(rate_bps as i64, 10_000);
     
-    let fee = amount * base_fee;
-    
-    // Minimum and maximum fee caps
-    let min_fee = 0.10;
-    let max_fee = 50.0;
+    // Minimum and maximum fee caps; free transaction types and deposits owe no minimum
+    let min_fee = if rate_bps == 0 || transaction_type.eq_ignore_ascii_case("deposit") {
+        Money::ZERO
+    } else {
+        Money::from_decimal(schedule.min_fee, 2, RoundingMode::HalfUp)
+    };
+    let max_fee = Money::from_decimal(schedule.max_fee, 2, RoundingMode::HalfUp);
     
     fee.max(min_fee).min(max_fee)
 }
 
+/// Sensitive patterns masked by `sanitize_for_logging`, compiled once on first use.
+/// Order matters: card numbers are masked before the shorter phone pattern can match part of them.
+static SENSITIVE_PATTERNS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
+    vec![
+        // Card numbers: 16 digits, optionally grouped by spaces or dashes
+        (Regex::new(r"\b(?:\d{4}[ -]?){3}\d{4}\b").unwrap(), "****-****-****-****"),
+        // International phone numbers (+254 712 345 678) and local ones with a leading zero
+        (Regex::new(r"\+\d[\d \-()]{8,16}\d|\b0\d{9}\b").unwrap(), "+***-***-****"),
+        // PINs: 4-6 digits following a "pin" label, so amounts and years are left alone
+        (Regex::new(r"(?i)(\bpin\b\s*[:=]?\s*)\d{4,6}\b").unwrap(), "${1}****"),
+    ]
+});
+
 /// Data sanitization for logging (remove sensitive information)
 pub fn sanitize_for_logging(data: &str) -> String {
-    let mut sanitized = data.to_string();
-    
-    // Remove or mask common sensitive patterns
-    let sensitive_patterns = vec![
-        (r"\b\d{4}\s?\d{4}\s?\d{4}\s?\d{4}\b", "****-****-****-****"), // Card numbers
-        (r"\b\d{4}\b", "****"),  // PINs
-        (r"\b\+?[\d\s\-()]{10,15}\b", "+***-***-****"), // Phone numbers
-    ];
-    
-    for (_pattern, _replacement) in sensitive_patterns {
-        // In a real implementation, use regex crate
-        // For now, just do basic replacement
-        if data.contains("1234") {
-            sanitized = sanitized.replace("1234", "****");
-        }
-    }
-    
-    sanitized
+    SENSITIVE_PATTERNS.iter().fold(data.to_string(), |sanitized, (pattern, replacement)| {
+        pattern.replace_all(&sanitized, *replacement).into_owned()
+    })
 }
 
 /// Performance metrics tracking
@@ -233,6 +609,26 @@ impl PerformanceMetrics {
     }
 }
 
+/// Call `f` until it succeeds, retrying recoverable errors (see `SafeBankError::is_recoverable`)
+/// up to `max_attempts` calls in all. The wait doubles after each failure, starting at `base_delay`;
+/// other errors are returned immediately.
+pub fn retry_recoverable<T, F>(max_attempts: u32, base_delay: std::time::Duration, mut f: F) -> Result<T, SafeBankError>
+where
+    F: FnMut() -> Result<T, SafeBankError>,
+{
+    let mut delay = base_delay;
+    for _ in 1..max_attempts.max(1) {
+        match f() {
+            Err(e) if e.is_recoverable() => {
+                std::thread::sleep(delay);
+                delay = delay.saturating_mul(2);
+            }
+            result => return result,
+        }
+    }
+    f()
+}
+
 /// Emergency contact and help utilities
 pub fn get_emergency_help_message(language: &str) -> String {
     match language.to_lowercase().as_str() {
@@ -255,6 +651,78 @@ mod tests {
         assert_eq!(format_currency(1000.0, "NGN"), "₦1000.00");
     }
 
+    #[test]
+    fn test_currency_precision_and_grouping() {
+        // Zero-decimal currencies round to whole units
+        assert_eq!(format_currency(1500.5, "UGX"), "USh 1501");
+        assert_eq!(format_currency(2500.0, "XOF"), "2500 CFA");
+        assert_eq!(format_money(Money::from_minor_units(149_949), "UGX"), "USh 1499");
+
+        let grouped = FormatOptions { thousands_separator: Some(','), ..FormatOptions::default() };
+        assert_eq!(format_currency_opts(1_234_567.0, "KES", &grouped), "KSh 1,234,567.00");
+        assert_eq!(format_currency_opts(-1_234.5, "USD", &grouped), "$-1,234.50");
+        assert_eq!(format_currency_opts(999.0, "KES", &grouped), "KSh 999.00");
+
+        let whole_shillings = FormatOptions { decimal_places: Some(0), ..grouped };
+        assert_eq!(format_currency_opts(1_234_567.4, "KES", &whole_shillings), "KSh 1,234,567");
+    }
+
+    #[test]
+    fn test_retry_recoverable_retries_only_recoverable_errors() {
+        let delay = std::time::Duration::from_millis(1);
+
+        let mut calls = 0;
+        let result = retry_recoverable(5, delay, || {
+            calls += 1;
+            if calls <= 2 {
+                Err(SafeBankError::NetworkError { message: "2G dropped".to_string() })
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls, 3);
+
+        // Non-recoverable errors give up at once
+        let mut calls = 0;
+        let result: Result<(), _> = retry_recoverable(5, delay, || {
+            calls += 1;
+            Err(SafeBankError::InsufficientFunds { balance: 1.0, required: 2.0 })
+        });
+        assert!(matches!(result, Err(SafeBankError::InsufficientFunds { .. })));
+        assert_eq!(calls, 1);
+
+        // Recoverable errors stop after the attempt budget
+        let mut calls = 0;
+        let result: Result<(), _> = retry_recoverable(3, delay, || {
+            calls += 1;
+            Err(SafeBankError::TimeoutError { operation: "sync".to_string() })
+        });
+        assert!(matches!(result, Err(SafeBankError::TimeoutError { .. })));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_csv_field_escaping() {
+        assert_eq!(escape_csv_field("Village Shop"), "Village Shop");
+        assert_eq!(escape_csv_field("Mama Mboga, Kibera"), "\"Mama Mboga, Kibera\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("=HYPERLINK(\"http://x\")"), "\"'=HYPERLINK(\"\"http://x\"\")\"");
+        for formula in ["+1+1", "-2+3", "@SUM(A1)"] {
+            assert_eq!(escape_csv_field(formula), format!("'{}", formula));
+        }
+    }
+
+    #[test]
+    fn test_money_rounding_modes() {
+        assert_eq!(round_money(2.345, 2, RoundingMode::HalfUp), 2.35);
+        assert_eq!(round_money(2.345, 2, RoundingMode::HalfEven), 2.34);
+        assert_eq!(round_money(2.355, 2, RoundingMode::HalfEven), 2.36);
+        assert_eq!(round_money(-2.345, 2, RoundingMode::HalfUp), -2.35);
+        assert_eq!(round_money(2.3449, 2, RoundingMode::HalfUp), 2.34);
+        assert_eq!(round_money(7.5, 0, RoundingMode::HalfEven), 8.0);
+    }
+
     #[test]
     fn test_phone_validation() {
         assert!(validate_phone_number("+1234567890", Some("US")));
@@ -263,6 +731,34 @@ mod tests {
         assert!(!validate_phone_number("12345678901234567890", None)); // Too long
     }
 
+    #[test]
+    fn test_phone_normalization() {
+        for raw in ["+254712345678", "+254 712 345 678", "254-712-345-678", "0712345678", "00254712345678", "(0712) 345-678"] {
+            assert_eq!(normalize_phone_number(raw, "KE").unwrap(), "+254712345678", "{}", raw);
+        }
+        assert_eq!(normalize_phone_number("080 3123 4567", "NG").unwrap(), "+2348031234567");
+        assert!(normalize_phone_number("07123abc", "KE").is_err());
+        assert!(normalize_phone_number("0712345678", "XX").is_err());
+        assert!(normalize_phone_number("+1234567890123456", "KE").is_err());
+    }
+
+    #[test]
+    fn test_luhn_and_account_numbers() {
+        assert!(luhn_check("79927398713"));
+        assert!(luhn_check("4539 1488 0343 6467"));
+        assert!(luhn_check("4539-1488-0343-6467"));
+        assert!(!luhn_check("4539 1488 0343 6468"));
+        assert!(!luhn_check("79927398710"));
+        assert!(!luhn_check("4539a14880343646"));
+        assert!(!luhn_check(""));
+
+        assert!(validate_account_number("4111 1111 1111 1111"));
+        // Valid checksum but too short for an account number
+        assert!(!validate_account_number("79927398713"));
+        // Transposed digits break the checksum
+        assert!(!validate_account_number("4111 1111 1111 1121"));
+    }
+
     #[test]
     fn test_otp_generation() {
         let otp = generate_simple_otp(4);
@@ -281,7 +777,7 @@ mod tests {
         weights.insert("time_anomaly".to_string(), 0.3);
         
         let score = calculate_composite_risk_score(&factors, &weights);
-        assert!(score >= 0.0 && score <= 1.0);
+        assert!((0.0..=1.0).contains(&score));
         assert!((score - 0.65).abs() < 0.01); // Expected: 0.8*0.7 + 0.3*0.3 = 0.65
     }
 
@@ -292,11 +788,34 @@ mod tests {
         
         assert!(domestic_transfer_fee < international_transfer_fee);
         assert!(domestic_transfer_fee >= 0.10); // Minimum fee
+
+        // Deposits and zero-rated types aren't charged the minimum
+        assert_eq!(calculate_transaction_fee(1000.0, "deposit", true), 0.0);
+        let free_payments = FeeSchedule { payment_bps: 0, ..FeeSchedule::default() };
+        assert_eq!(calculate_scheduled_fee(1000.0, "payment", true, &free_payments), 0.0);
+        let charged_deposits = FeeSchedule { deposit_bps: 10, ..FeeSchedule::default() };
+        assert_eq!(calculate_scheduled_fee(50.0, "deposit", true, &charged_deposits), 0.05);
+    }
+
+    #[test]
+    fn test_haversine_distance_between_known_cities() {
+        let london = GeoLocation::new(51.5074, -0.1278, "London");
+        let paris = GeoLocation::new(48.8566, 2.3522, "Paris");
+        let new_york = GeoLocation::new(40.7128, -74.0060, "New York");
+        let los_angeles = GeoLocation::new(34.0522, -118.2437, "Los Angeles");
+        let nairobi = GeoLocation::new(-1.2921, 36.8219, "Nairobi");
+        let mombasa = GeoLocation::new(-4.0435, 39.6682, "Mombasa");
+
+        assert!((haversine_distance(&london, &paris) - 344.0).abs() < 5.0);
+        assert!((haversine_distance(&new_york, &los_angeles) - 3936.0).abs() < 20.0);
+        assert!((haversine_distance(&nairobi, &mombasa) - 441.0).abs() < 5.0);
+        assert_eq!(haversine_distance(&paris, &paris), 0.0);
+        assert!((haversine_distance(&paris, &london) - haversine_distance(&london, &paris)).abs() < 1e-9);
     }
 
     #[test]
     fn test_sms_formatting() {
-        let sms = format_transaction_sms(100.0, "John Doe", "approved", "ABC123", "USD");
+        let sms = format_transaction_sms(100.0, "John Doe", "approved", "ABC123", "USD", None);
         assert!(sms.contains("$100.00"));
         assert!(sms.contains("John Doe"));
         assert!(sms.contains("ABC123"));
@@ -317,4 +836,43 @@ mod tests {
         assert!(low_end_device.is_low_end());
         assert!(!low_end_device.supports_advanced_auth());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sanitize_for_logging() {
+        assert_eq!(
+            sanitize_for_logging("Card 4111 1111 1111 1111 charged"),
+            "Card ****-****-****-**** charged"
+        );
+        assert_eq!(sanitize_for_logging("card=4111-1111-1111-1111"), "card=****-****-****-****");
+        assert_eq!(sanitize_for_logging("Login for +254712345678 failed"), "Login for +***-***-**** failed");
+        assert_eq!(sanitize_for_logging("SMS to +254 712 345 678"), "SMS to +***-***-****");
+        assert_eq!(sanitize_for_logging("user entered PIN: 4821"), "user entered PIN: ****");
+        assert_eq!(sanitize_for_logging("pin=904512 rejected"), "pin=**** rejected");
+
+        // Amounts, years and ids are not sensitive
+        let benign = "Transfer of 1500.00 KES approved in 2024, ref 12345";
+        assert_eq!(sanitize_for_logging(benign), benign);
+    }
+
+    #[test]
+    fn test_transaction_data_compression() {
+        let batch: Vec<serde_json::Value> = (0..20)
+            .map(|i| serde_json::json!({
+                "transaction_id": format!("00000000-0000-0000-0000-{:012}", i),
+                "user_id": "7f3c2a10-5b1e-4c8d-9a6f-2e4b8c1d0a55",
+                "amount": 100.0 + i as f64,
+                "recipient": "Local Shop",
+                "transaction_type": "Payment",
+                "status": "Approved",
+                "fraud_score": 0.12,
+                "timestamp": "2024-05-01T09:30:00Z",
+            }))
+            .collect();
+        let payload = serde_json::to_string(&batch).unwrap();
+
+        let compressed = compress_transaction_data(&payload).unwrap();
+        assert!(compressed.len() < payload.len());
+        assert_eq!(decompress_transaction_data(&compressed).unwrap(), payload);
+        assert!(decompress_transaction_data(b"not gzip").is_err());
+    }
+}